@@ -0,0 +1,194 @@
+//! Structured access logging
+//!
+//! One log event per request with method, path, status, response size and
+//! duration. Request and response bodies are never logged.
+//!
+//! Each request runs in a `request` span carrying its package context: the
+//! repo, arch and filename of package and database downloads, and the
+//! authenticated user once the request's auth has been checked.
+
+use axum::{body::HttpBody, extract::Request, http::header, middleware::Next, response::Response};
+use std::time::Instant;
+use tracing::{Instrument, field::Empty};
+
+/// Axum middleware that emits an access log event for each request.
+pub async fn access_log_layer(request: Request, next: Next) -> Response {
+    let method = request.method().clone();
+    let path = request.uri().path().to_owned();
+    let start = Instant::now();
+
+    let span = tracing::info_span!(
+        "request",
+        user = Empty,
+        repo = Empty,
+        arch = Empty,
+        filename = Empty
+    );
+    if let Some((repo, arch, filename)) = package_route(&path) {
+        span.record("repo", repo);
+        span.record("arch", arch);
+        span.record("filename", filename);
+    }
+
+    let response = next.run(request).instrument(span.clone()).await;
+
+    let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let status = response.status().as_u16();
+    let bytes = response_size(&response);
+
+    span.in_scope(|| {
+        tracing::info!(
+            %method,
+            %path,
+            status,
+            bytes,
+            duration_ms,
+            "access"
+        );
+    });
+
+    response
+}
+
+/// Record the authenticated user on the current request's span
+///
+/// A no-op outside [`access_log_layer`], e.g. with access logging off.
+pub fn record_user(username: &str) {
+    tracing::Span::current().record("user", username);
+}
+
+/// Repo, arch and filename of a pacman download path,
+/// `/{repo}/os/{arch}/{filename}`
+fn package_route(path: &str) -> Option<(&str, &str, &str)> {
+    let mut segments = path.strip_prefix('/')?.split('/');
+    match (
+        segments.next(),
+        segments.next(),
+        segments.next(),
+        segments.next(),
+        segments.next(),
+    ) {
+        (Some(repo), Some("os"), Some(arch), Some(filename), None) => Some((repo, arch, filename)),
+        _ => None,
+    }
+}
+
+/// Response body size, from the body itself or the Content-Length header.
+/// `None` for streamed bodies of unknown length.
+fn response_size(response: &Response) -> Option<u64> {
+    response.body().size_hint().exact().or_else(|| {
+        response
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, body::Body, middleware, routing::get};
+    use tower::util::ServiceExt;
+
+    #[derive(Clone, Default)]
+    struct LogCapture(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogCapture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn access_log_records_status_size_and_duration() {
+        let capture = LogCapture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let app = Router::new()
+            .route("/hello", get(|| async { "secret-body" }))
+            .layer(middleware::from_fn(access_log_layer));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/hello")
+                    .body(Body::from("request-body"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let logs = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("access"), "{logs}");
+        assert!(logs.contains("method=GET"), "{logs}");
+        assert!(logs.contains("path=/hello"), "{logs}");
+        assert!(logs.contains("status=200"), "{logs}");
+        assert!(logs.contains("bytes=11"), "{logs}");
+        assert!(logs.contains("duration_ms="), "{logs}");
+        assert!(!logs.contains("secret-body"), "{logs}");
+        assert!(!logs.contains("request-body"), "{logs}");
+    }
+
+    #[tokio::test]
+    async fn access_log_records_package_and_user() {
+        let capture = LogCapture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let app = Router::new()
+            .route(
+                "/{repo}/os/{arch}/{filename}",
+                get(|| async {
+                    record_user("alice");
+                    "package"
+                }),
+            )
+            .layer(middleware::from_fn(access_log_layer));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/sw1nn/os/x86_64/foo-1.0.0-1-x86_64.pkg.tar.zst")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let logs = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("user=\"alice\""), "{logs}");
+        assert!(logs.contains("repo=\"sw1nn\""), "{logs}");
+        assert!(logs.contains("arch=\"x86_64\""), "{logs}");
+        assert!(
+            logs.contains("filename=\"foo-1.0.0-1-x86_64.pkg.tar.zst\""),
+            "{logs}"
+        );
+    }
+
+    #[test]
+    fn package_route_only_matches_pacman_paths() {
+        assert_eq!(
+            package_route("/sw1nn/os/x86_64/sw1nn.db"),
+            Some(("sw1nn", "x86_64", "sw1nn.db"))
+        );
+        assert_eq!(package_route("/api/packages"), None);
+        assert_eq!(package_route("/api/repos/sw1nn/os/x86_64/manifest"), None);
+    }
+}
@@ -0,0 +1,324 @@
+//! Admission control
+//!
+//! Caps on the number of requests in flight, overall and per client IP.
+//! Requests over a cap are answered with 503 straight away instead of
+//! queueing, so one client opening many connections can't starve the rest.
+//! A request stays in flight until its response body has been sent, which
+//! for a download is long after the handler returned.
+//!
+//! Writes are also rate limited per client, with a token bucket per
+//! authenticated user or anonymous IP; requests over the rate get 429.
+
+use crate::config::{AuthConfig, ServerConfig};
+use axum::{
+    body::{Body, Bytes},
+    extract::{ConnectInfo, Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http_body::{Frame, SizeHint};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+/// Shared in-flight request counters
+#[derive(Debug, Clone, Default)]
+pub struct ConcurrencyLimits {
+    requests: Option<Arc<Semaphore>>,
+    max_per_ip: Option<usize>,
+    per_ip: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+impl ConcurrencyLimits {
+    pub fn new(max_requests: Option<usize>, max_per_ip: Option<usize>) -> Self {
+        Self {
+            requests: max_requests.map(|max| Arc::new(Semaphore::new(max))),
+            max_per_ip,
+            per_ip: Arc::default(),
+        }
+    }
+
+    pub fn from_config(config: &ServerConfig) -> Self {
+        Self::new(
+            config.max_concurrent_requests,
+            config.max_concurrent_requests_per_ip,
+        )
+    }
+
+    /// Count a request from `ip`, or `None` if it already has `max` in flight
+    fn acquire_ip(&self, ip: IpAddr, max: usize) -> Option<IpGuard> {
+        let mut per_ip = self.per_ip.lock().expect("per-ip limit lock poisoned");
+        let in_flight = per_ip.entry(ip).or_default();
+        if *in_flight >= max {
+            return None;
+        }
+        *in_flight += 1;
+        Some(IpGuard {
+            per_ip: Arc::clone(&self.per_ip),
+            ip,
+        })
+    }
+}
+
+/// Releases a per-IP slot when the request finishes
+struct IpGuard {
+    per_ip: Arc<Mutex<HashMap<IpAddr, usize>>>,
+    ip: IpAddr,
+}
+
+impl Drop for IpGuard {
+    fn drop(&mut self) {
+        let mut per_ip = self.per_ip.lock().expect("per-ip limit lock poisoned");
+        if let Some(in_flight) = per_ip.get_mut(&self.ip) {
+            *in_flight -= 1;
+            if *in_flight == 0 {
+                per_ip.remove(&self.ip);
+            }
+        }
+    }
+}
+
+/// Response body holding an admission slot until the body is dropped, i.e.
+/// sent in full or abandoned by the client
+struct GuardedBody<G> {
+    body: Body,
+    _guard: G,
+}
+
+impl<G: Send + Unpin + 'static> http_body::Body for GuardedBody<G> {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        Pin::new(&mut self.get_mut().body).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+}
+
+/// Keep `guard` alive until `response`'s body is done with
+fn hold_until_sent<G: Send + Unpin + 'static>(response: Response, guard: G) -> Response {
+    response.map(|body| {
+        Body::new(GuardedBody {
+            body,
+            _guard: guard,
+        })
+    })
+}
+
+fn overloaded(message: &'static str) -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(header::RETRY_AFTER, "1")],
+        message,
+    )
+        .into_response()
+}
+
+/// Axum middleware enforcing `max_concurrent_requests` across all requests
+pub async fn concurrency_limit_layer(
+    State(limits): State<ConcurrencyLimits>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(requests) = &limits.requests else {
+        return next.run(request).await;
+    };
+    let Ok(permit) = Arc::clone(requests).try_acquire_owned() else {
+        crate::metrics::record_request_shed("global");
+        return overloaded("Server is overloaded, retry shortly");
+    };
+
+    hold_until_sent(next.run(request).await, permit)
+}
+
+/// Axum middleware enforcing `max_concurrent_requests_per_ip`
+///
+/// The client is identified by the peer address of the connection, so
+/// behind a reverse proxy all clients share the proxy's allowance.
+pub async fn per_ip_limit_layer(
+    State(limits): State<ConcurrencyLimits>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(max) = limits.max_per_ip else {
+        return next.run(request).await;
+    };
+    let Some(ip) = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip())
+    else {
+        return next.run(request).await;
+    };
+
+    let Some(guard) = limits.acquire_ip(ip, max) else {
+        crate::metrics::record_request_shed("per_ip");
+        return overloaded("Too many concurrent requests from this client, retry shortly");
+    };
+
+    hold_until_sent(next.run(request).await, guard)
+}
+
+/// Client buckets kept before those that have refilled are dropped
+const MAX_RATE_LIMIT_BUCKETS: usize = 1024;
+
+/// Per-client token buckets for `upload_rate_limit`
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    per_minute: Option<u32>,
+    buckets: Arc<Mutex<HashMap<String, TokenBucket>>>,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Tokens available at `now`, with those refilled since the last update
+    fn tokens_at(&self, now: Instant, capacity: f64) -> f64 {
+        let refilled = now.saturating_duration_since(self.updated).as_secs_f64() * capacity / 60.0;
+        (self.tokens + refilled).min(capacity)
+    }
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: Option<u32>) -> Self {
+        Self {
+            per_minute,
+            buckets: Arc::default(),
+        }
+    }
+
+    pub fn from_config(config: &ServerConfig) -> Self {
+        Self::new(config.upload_rate_limit)
+    }
+
+    /// Take a token from `client`'s bucket, or return how long until one is
+    /// available
+    ///
+    /// A bucket holds a minute's worth of requests and refills continuously,
+    /// so a client may burst up to the limit and then carries on at the
+    /// limit's pace.
+    pub fn acquire(&self, client: &str) -> Result<(), Duration> {
+        self.acquire_at(client, Instant::now())
+    }
+
+    fn acquire_at(&self, client: &str, now: Instant) -> Result<(), Duration> {
+        let Some(per_minute) = self.per_minute else {
+            return Ok(());
+        };
+        let capacity = f64::from(per_minute);
+
+        let mut buckets = self.buckets.lock().expect("rate limit lock poisoned");
+        if buckets.len() >= MAX_RATE_LIMIT_BUCKETS {
+            buckets.retain(|_, bucket| bucket.tokens_at(now, capacity) < capacity);
+        }
+
+        let bucket = buckets
+            .entry(client.to_owned())
+            .or_insert_with(|| TokenBucket {
+                tokens: capacity,
+                updated: now,
+            });
+        bucket.tokens = bucket.tokens_at(now, capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) * 60.0 / capacity,
+            ))
+        }
+    }
+}
+
+/// The client a request is rate limited as: its authenticated user, or
+/// else the peer IP of its connection
+pub fn rate_limit_client(auth_config: Option<&AuthConfig>, request: &Request) -> String {
+    if let Some(username) =
+        auth_config.and_then(|auth| crate::auth::request_username(auth, request.headers()))
+    {
+        return format!("user:{username}");
+    }
+
+    match request.extensions().get::<ConnectInfo<SocketAddr>>() {
+        Some(info) => format!("ip:{}", info.0.ip()),
+        None => "unknown".to_owned(),
+    }
+}
+
+/// 429 response telling the client when to retry
+pub fn rate_limited(retry_after: Duration) -> Response {
+    let secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, secs.to_string())],
+        "Too many requests from this client, retry later",
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limit_buckets_refill_over_time() {
+        let limiter = RateLimiter::new(Some(2));
+        let start = Instant::now();
+
+        assert!(limiter.acquire_at("alice", start).is_ok());
+        assert!(limiter.acquire_at("alice", start).is_ok());
+        let retry_after = limiter.acquire_at("alice", start).unwrap_err();
+        assert_eq!(retry_after, Duration::from_secs(30));
+
+        // Other clients have their own bucket
+        assert!(limiter.acquire_at("bob", start).is_ok());
+
+        // One token back every 30s
+        let later = start + Duration::from_secs(30);
+        assert!(limiter.acquire_at("alice", later).is_ok());
+        assert!(limiter.acquire_at("alice", later).is_err());
+    }
+
+    #[test]
+    fn rate_limit_unset_allows_everything() {
+        let limiter = RateLimiter::new(None);
+        for _ in 0..1000 {
+            assert!(limiter.acquire("alice").is_ok());
+        }
+    }
+
+    #[test]
+    fn per_ip_slots_are_released() {
+        let limits = ConcurrencyLimits::new(None, Some(1));
+        let ip: IpAddr = [10, 0, 0, 1].into();
+        let other: IpAddr = [10, 0, 0, 2].into();
+
+        let guard = limits.acquire_ip(ip, 1).unwrap();
+        assert!(limits.acquire_ip(ip, 1).is_none());
+        assert!(limits.acquire_ip(other, 1).is_some());
+
+        drop(guard);
+        assert!(limits.acquire_ip(ip, 1).is_some());
+    }
+}
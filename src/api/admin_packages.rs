@@ -0,0 +1,158 @@
+use crate::AppState;
+use crate::api::compare_versions;
+use crate::error::Result;
+use crate::models::Package;
+use axum::{
+    Json,
+    extract::{Query, State},
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AdminPackagesQuery {
+    /// Filter by package name (substring)
+    pub name: Option<String>,
+    /// Filter by repository
+    pub repo: Option<String>,
+    /// Filter by architecture
+    pub arch: Option<String>,
+    /// Only packages whose file is (or isn't) on disk
+    pub file_present: Option<bool>,
+    /// Only packages with (or without) a detached signature on disk
+    pub signed: Option<bool>,
+    /// Only packages that are (or aren't) in their repo's current db
+    pub in_current_db: Option<bool>,
+    /// Number of matching packages to skip (default 0)
+    pub offset: Option<usize>,
+    /// Maximum number of results (capped by the server's `max_list_results`)
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminPackageEntry {
+    pub package: Package,
+    /// The package file exists in storage
+    pub file_present: bool,
+    /// A `.sig` file exists next to the package file
+    pub signed: bool,
+    /// The package is the version its repo/arch db lists, and that db has
+    /// no pending regeneration
+    pub in_current_db: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminPackagesResponse {
+    /// Number of packages matching the filters, before pagination
+    pub total: usize,
+    pub offset: usize,
+    pub packages: Vec<AdminPackageEntry>,
+}
+
+/// List every stored package with its on-disk and db status
+#[utoipa::path(
+    get,
+    path = "/admin/packages",
+    params(AdminPackagesQuery),
+    responses(
+        (status = 200, description = "One page of packages with status flags", body = AdminPackagesResponse),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "admin"
+)]
+pub async fn list_admin_packages(
+    _user: crate::auth::AuthenticatedUser,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AdminPackagesQuery>,
+) -> Result<impl IntoResponse> {
+    let mut packages = match &query.repo {
+        Some(repo) => state.storage.list_packages(repo).await?,
+        None => state.storage.list_all_packages().await?,
+    };
+
+    if let Some(name_filter) = &query.name {
+        packages.retain(|p| p.name.contains(name_filter.as_str()));
+    }
+    if let Some(arch) = &query.arch {
+        let arch = state.config.storage.canonical_arch(arch);
+        packages.retain(|p| p.arch == arch);
+    }
+
+    // The newest version per repo, db arch and name is what the db lists.
+    // "any" packages are published through the default arch's db.
+    let db_arch = |p: &Package| {
+        if p.arch == "any" {
+            state.config.storage.default_arch.clone()
+        } else {
+            p.arch.clone()
+        }
+    };
+    let mut newest: HashMap<(String, String, String), String> = HashMap::new();
+    for p in &packages {
+        newest
+            .entry((p.repo.clone(), db_arch(p), p.name.clone()))
+            .and_modify(|version| {
+                if compare_versions(&p.version, version).is_gt() {
+                    version.clone_from(&p.version);
+                }
+            })
+            .or_insert_with(|| p.version.clone());
+    }
+
+    let mut entries = Vec::with_capacity(packages.len());
+    for package in packages {
+        let package_path = state
+            .storage
+            .package_path(&package.repo, &package.filename)?;
+        let sig_path = state
+            .storage
+            .package_path(&package.repo, &format!("{}.sig", package.filename))?;
+        let arch = db_arch(&package);
+        let is_newest = newest
+            .get(&(package.repo.clone(), arch.clone(), package.name.clone()))
+            .is_some_and(|version| *version == package.version);
+        let in_current_db = is_newest
+            && state
+                .db_update
+                .generation(&package.repo, &arch)
+                .is_current();
+
+        entries.push(AdminPackageEntry {
+            file_present: tokio::fs::try_exists(&package_path).await.unwrap_or(false),
+            signed: tokio::fs::try_exists(&sig_path).await.unwrap_or(false),
+            in_current_db,
+            package,
+        });
+    }
+
+    entries.retain(|e| {
+        query.file_present.is_none_or(|want| e.file_present == want)
+            && query.signed.is_none_or(|want| e.signed == want)
+            && query
+                .in_current_db
+                .is_none_or(|want| e.in_current_db == want)
+    });
+
+    // Same order as the public list so pages are stable
+    entries.sort_by(|a, b| {
+        let (a, b) = (&a.package, &b.package);
+        (&a.repo, &a.name, &a.arch)
+            .cmp(&(&b.repo, &b.name, &b.arch))
+            .then_with(|| compare_versions(&a.version, &b.version))
+    });
+
+    let total = entries.len();
+    let offset = query.offset.unwrap_or(0);
+    let max_results = state.config.server.max_list_results;
+    let limit = query.limit.map_or(max_results, |l| l.min(max_results));
+    let packages = entries.into_iter().skip(offset).take(limit).collect();
+
+    Ok(Json(AdminPackagesResponse {
+        total,
+        offset,
+        packages,
+    }))
+}
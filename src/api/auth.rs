@@ -3,7 +3,7 @@ use crate::auth;
 use crate::error::Error;
 use axum::Json;
 use axum::extract::State;
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, StatusCode, header};
 use axum::response::IntoResponse;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -38,8 +38,10 @@ pub struct DeviceTokenApiResponse {
 pub async fn device_code(State(state): State<Arc<AppState>>) -> Result<impl IntoResponse, Error> {
     let auth_config = state.config.auth.as_ref().ok_or(Error::AuthNotConfigured)?;
 
-    let response =
-        auth::request_device_code(&state.http_client, &auth_config.github_client_id).await?;
+    let response = state
+        .github
+        .request_device_code(&auth_config.github_client_id)
+        .await?;
 
     Ok(Json(DeviceCodeApiResponse {
         device_code: response.device_code,
@@ -57,33 +59,31 @@ pub async fn device_token(
 ) -> Result<impl IntoResponse, Error> {
     let auth_config = state.config.auth.as_ref().ok_or(Error::AuthNotConfigured)?;
 
-    let github_token = auth::poll_device_token(
-        &state.http_client,
-        &auth_config.github_client_id,
-        &req.device_code,
-    )
-    .await?;
-
-    let github_token = match github_token {
-        Some(token) => token,
-        None => {
-            return Ok((
-                StatusCode::ACCEPTED,
-                Json(serde_json::json!({"status": "pending"})),
-            )
-                .into_response());
+    let poll = state
+        .github
+        .poll_device_token(&auth_config.github_client_id, &req.device_code)
+        .await?;
+
+    let github_token = match poll {
+        auth::DevicePoll::Authorized(token) => token,
+        auth::DevicePoll::Pending { interval } => {
+            // Pass GitHub's slow_down interval on so the client backs off
+            let mut body = serde_json::json!({"status": "pending"});
+            if let Some(interval) = interval {
+                body["interval"] = interval.into();
+            }
+            return Ok((StatusCode::ACCEPTED, Json(body)).into_response());
         }
     };
 
     // Get GitHub username
-    let github_user = auth::get_github_user(&state.http_client, &github_token.access_token).await?;
+    let github_user = state
+        .github
+        .get_github_user(&github_token.access_token)
+        .await?;
 
     // Check allowlist
-    if !auth_config
-        .allowed_users
-        .iter()
-        .any(|u| u == &github_user.login)
-    {
+    if !state.allowlist.contains(&github_user.login) {
         return Err(Error::Forbidden {
             reason: format!(
                 "user '{}' is not in the allowed users list",
@@ -103,3 +103,36 @@ pub async fn device_token(
     })
     .into_response())
 }
+
+/// Exchange a still-valid JWT for a fresh one with a new expiry
+///
+/// The user must still be allowed, and the token keeps its type. An expired
+/// token is rejected, so the user has to log in again via the device flow.
+pub async fn refresh_token(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, Error> {
+    let auth_config = state.config.auth.as_ref().ok_or(Error::AuthNotConfigured)?;
+
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or(Error::Unauthorized)?;
+    let claims = auth::validate_jwt(auth_config, token)?;
+
+    if !state.allowlist.contains(&claims.sub) {
+        return Err(Error::Forbidden {
+            reason: format!("user '{}' is not in the allowed users list", claims.sub),
+        });
+    }
+
+    let jwt = auth::create_jwt(auth_config, &claims.sub, &claims.token_type)?;
+    let refreshed = auth::validate_jwt(auth_config, &jwt)?;
+
+    Ok(Json(DeviceTokenApiResponse {
+        token: jwt,
+        username: refreshed.sub,
+        expires_at: refreshed.exp,
+    }))
+}
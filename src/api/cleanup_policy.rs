@@ -1,9 +1,11 @@
 use crate::AppState;
+use crate::config::CleanupPolicy;
 use crate::error::Result;
+use crate::events::{EventKind, RepoEvent};
 use crate::models::Package;
 use axum::{Json, extract::State, response::IntoResponse};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use utoipa::ToSchema;
 
@@ -17,6 +19,10 @@ pub struct CleanupPolicyRequest {
     pub repo: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub arch: Option<String>,
+    /// Versions to keep, instead of the configured `cleanup_policy`; fields
+    /// left out take their defaults
+    #[serde(default)]
+    pub policy: Option<CleanupPolicy>,
 }
 
 fn default_pattern() -> String {
@@ -33,7 +39,7 @@ pub struct CleanupPolicyResponse {
     pub details: Vec<PackageCleanupDetail>,
 }
 
-#[derive(Debug, Serialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct PackageCleanupDetail {
     pub package_name: String,
     pub versions_deleted: usize,
@@ -52,7 +58,7 @@ pub struct PackageCleanupDetail {
     tag = "packages"
 )]
 pub async fn apply_cleanup_policy(
-    _user: crate::auth::AuthenticatedUser,
+    user: crate::auth::AuthenticatedUser,
     State(state): State<Arc<AppState>>,
     Json(request): Json<CleanupPolicyRequest>,
 ) -> Result<impl IntoResponse> {
@@ -63,8 +69,216 @@ pub async fn apply_cleanup_policy(
         .arch
         .unwrap_or_else(|| state.config.storage.default_arch.clone());
 
+    let pattern = parse_pattern(&request.package_pattern)?;
+    let policy = request
+        .policy
+        .unwrap_or_else(|| state.config.storage.cleanup_policy.clone());
+    let response = cleanup_repo_arch(
+        &state,
+        &repo,
+        &arch,
+        &pattern,
+        &policy,
+        false,
+        &mut HashSet::new(),
+    )
+    .await?;
+    let total_deleted = response.versions_deleted;
+
+    // Request database update (debounced, coalesced with other updates)
+    if total_deleted > 0 {
+        crate::metrics::record_cleanup_versions_deleted(&repo, total_deleted as u64);
+        record_cleanup_events(&state, &repo, &arch, &response.details, &user.username);
+        state.db_update.request_update(&repo, &arch).await;
+    }
+
+    tracing::info!(
+        pattern = %request.package_pattern,
+        repo = %repo,
+        arch = %arch,
+        packages_processed = response.packages_processed,
+        versions_deleted = response.versions_deleted,
+        "Cleanup policy completed"
+    );
+
+    Ok(Json(response))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CleanupAllRequest {
+    /// Package name pattern (glob-style). Use "*" for all packages.
+    #[serde(default = "default_pattern")]
+    pub package_pattern: String,
+    /// Report what would be deleted without deleting anything
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Versions to keep, instead of the configured `cleanup_policy`; fields
+    /// left out take their defaults
+    #[serde(default)]
+    pub policy: Option<CleanupPolicy>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CleanupAllResponse {
+    pub dry_run: bool,
+    /// Totals across every repo/arch
+    pub total: CleanupPolicyResponse,
+    /// Breakdown per repo/arch
+    pub repos: Vec<RepoArchCleanup>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RepoArchCleanup {
+    pub repo: String,
+    pub arch: String,
+    #[serde(flatten)]
+    pub result: CleanupPolicyResponse,
+}
+
+/// Apply cleanup policy to every repo/arch found in storage
+#[utoipa::path(
+    post,
+    path = "/admin/cleanup-all",
+    request_body = CleanupAllRequest,
+    responses(
+        (status = 200, description = "Cleanup policy applied to all repositories", body = CleanupAllResponse),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "packages"
+)]
+pub async fn apply_cleanup_all(
+    user: crate::auth::AuthenticatedUser,
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<CleanupAllRequest>,
+) -> Result<impl IntoResponse> {
+    let pattern = parse_pattern(&request.package_pattern)?;
+    let policy = request
+        .policy
+        .unwrap_or_else(|| state.config.storage.cleanup_policy.clone());
+
+    let mut repos = Vec::new();
+    let mut total = CleanupPolicyResponse {
+        packages_processed: 0,
+        versions_deleted: 0,
+        details: Vec::new(),
+    };
+
+    for repo in state.storage.list_repos().await? {
+        // "any" packages are cleaned as part of each concrete arch; a repo
+        // holding only "any" packages is cleaned via the default arch
+        let mut archs: Vec<String> = state
+            .storage
+            .list_archs_in_repo(&repo)
+            .await?
+            .into_iter()
+            .filter(|arch| arch != "any")
+            .collect();
+        if archs.is_empty() {
+            archs.push(state.config.storage.default_arch.clone());
+        }
+
+        // "any" packages show up under every arch; only the first counts
+        let mut evaluated = HashSet::new();
+        let mut repo_deleted = 0;
+        for arch in &archs {
+            let result = cleanup_repo_arch(
+                &state,
+                &repo,
+                arch,
+                &pattern,
+                &policy,
+                request.dry_run,
+                &mut evaluated,
+            )
+            .await?;
+            total.packages_processed += result.packages_processed;
+            if result.versions_deleted == 0 {
+                continue;
+            }
+
+            if !request.dry_run {
+                record_cleanup_events(&state, &repo, arch, &result.details, &user.username);
+            }
+            repo_deleted += result.versions_deleted;
+            total.versions_deleted += result.versions_deleted;
+            total.details.extend(result.details.iter().cloned());
+            repos.push(RepoArchCleanup {
+                repo: repo.clone(),
+                arch: arch.clone(),
+                result,
+            });
+        }
+
+        // Deleted "any" packages affect every arch db in the repo, so refresh
+        // them all (debounced, coalesced with other updates)
+        if repo_deleted > 0 && !request.dry_run {
+            crate::metrics::record_cleanup_versions_deleted(&repo, repo_deleted as u64);
+            for arch in &archs {
+                state.db_update.request_update(&repo, arch).await;
+            }
+        }
+    }
+
+    tracing::info!(
+        pattern = %request.package_pattern,
+        dry_run = request.dry_run,
+        packages_processed = total.packages_processed,
+        versions_deleted = total.versions_deleted,
+        "Cleanup policy completed for all repositories"
+    );
+
+    Ok(Json(CleanupAllResponse {
+        dry_run: request.dry_run,
+        total,
+        repos,
+    }))
+}
+
+/// Record one cleanup event per package that lost versions
+fn record_cleanup_events(
+    state: &AppState,
+    repo: &str,
+    arch: &str,
+    details: &[PackageCleanupDetail],
+    user: &str,
+) {
+    for detail in details {
+        state.events.record(
+            RepoEvent::new(EventKind::Cleanup, repo)
+                .arch(arch)
+                .package(&detail.package_name)
+                .user(user)
+                .detail(format!(
+                    "deleted versions: {}",
+                    detail.deleted_versions.join(", ")
+                )),
+        );
+    }
+}
+
+fn parse_pattern(pattern: &str) -> Result<glob::Pattern> {
+    glob::Pattern::new(pattern).map_err(|e| crate::error::Error::InvalidPackage {
+        pkgname: format!("Invalid pattern: {}", e),
+    })
+}
+
+/// Apply the retention policy to packages matching `pattern` in one repo/arch.
+/// With `dry_run`, report what would be deleted without deleting it.
+///
+/// `evaluated` holds the filenames of packages already evaluated in this repo
+/// by an earlier arch. Packages whose files are all in it are skipped, and a
+/// dry run doesn't report files in it as deleted again.
+async fn cleanup_repo_arch(
+    state: &AppState,
+    repo: &str,
+    arch: &str,
+    pattern: &glob::Pattern,
+    policy: &CleanupPolicy,
+    dry_run: bool,
+    evaluated: &mut HashSet<String>,
+) -> Result<CleanupPolicyResponse> {
     // Get all packages for this repo/arch (includes "any" packages)
-    let all_packages = state.storage.list_packages_for_arch(&repo, &arch).await?;
+    let all_packages = state.storage.list_packages_for_arch(repo, arch).await?;
 
     // Group packages by name
     let mut packages_by_name: HashMap<String, Vec<Package>> = HashMap::new();
@@ -76,12 +290,6 @@ pub async fn apply_cleanup_policy(
     }
 
     // Filter package names by pattern
-    let pattern = glob::Pattern::new(&request.package_pattern).map_err(|e| {
-        crate::error::Error::InvalidPackage {
-            pkgname: format!("Invalid pattern: {}", e),
-        }
-    })?;
-
     let matching_packages: Vec<String> = packages_by_name
         .keys()
         .filter(|name| pattern.matches(name))
@@ -89,9 +297,10 @@ pub async fn apply_cleanup_policy(
         .collect();
 
     tracing::info!(
-        pattern = %request.package_pattern,
+        pattern = %pattern,
         repo = %repo,
         arch = %arch,
+        dry_run,
         matching_count = matching_packages.len(),
         "Applying cleanup policy to packages"
     );
@@ -99,11 +308,43 @@ pub async fn apply_cleanup_policy(
     // Apply cleanup to each matching package
     let mut details = Vec::new();
     let mut total_deleted = 0;
+    let mut processed = 0;
 
     for package_name in matching_packages {
-        let deleted =
-            crate::storage::cleanup_old_versions(&state.storage, &package_name, &repo, &arch)
-                .await?;
+        let fresh: HashSet<String> = packages_by_name[&package_name]
+            .iter()
+            .filter(|pkg| evaluated.insert(pkg.filename.clone()))
+            .map(|pkg| pkg.filename.clone())
+            .collect();
+        if fresh.is_empty() {
+            continue;
+        }
+        processed += 1;
+
+        let deleted = if dry_run {
+            crate::storage::find_old_versions(
+                state.storage.as_ref(),
+                &package_name,
+                repo,
+                arch,
+                policy,
+            )
+            .await?
+        } else {
+            crate::storage::cleanup_old_versions(
+                state.storage.as_ref(),
+                &package_name,
+                repo,
+                arch,
+                policy,
+            )
+            .await?
+        };
+        // A dry run leaves files in place, so "any" ones would come up again
+        let deleted: Vec<Package> = deleted
+            .into_iter()
+            .filter(|pkg| !dry_run || fresh.contains(&pkg.filename))
+            .collect();
 
         if !deleted.is_empty() {
             let deleted_versions: Vec<String> = deleted.iter().map(|p| p.version.clone()).collect();
@@ -113,6 +354,7 @@ pub async fn apply_cleanup_policy(
                 package = %package_name,
                 repo = %repo,
                 arch = %arch,
+                dry_run,
                 deleted_count = count,
                 "Applied cleanup policy to package"
             );
@@ -127,26 +369,9 @@ pub async fn apply_cleanup_policy(
         }
     }
 
-    // Request database update (debounced, coalesced with other updates)
-    if total_deleted > 0 {
-        crate::metrics::record_cleanup_versions_deleted(&repo, total_deleted as u64);
-        state.db_update.request_update(&repo, &arch).await;
-    }
-
-    let response = CleanupPolicyResponse {
-        packages_processed: details.len(),
+    Ok(CleanupPolicyResponse {
+        packages_processed: processed,
         versions_deleted: total_deleted,
         details,
-    };
-
-    tracing::info!(
-        pattern = %request.package_pattern,
-        repo = %repo,
-        arch = %arch,
-        packages_processed = response.packages_processed,
-        versions_deleted = response.versions_deleted,
-        "Cleanup policy completed"
-    );
-
-    Ok(Json(response))
+    })
 }
@@ -1,9 +1,10 @@
 use crate::AppState;
 use crate::error::{Error, Result};
-use crate::models::Package;
+use crate::events::{EventKind, RepoEvent};
+use crate::models::{Package, PackageQuery};
 use axum::{
     Json,
-    extract::{Path as AxumPath, State},
+    extract::{Path as AxumPath, Query, State},
     response::IntoResponse,
 };
 use serde::{Deserialize, Serialize};
@@ -12,8 +13,11 @@ use utoipa::ToSchema;
 
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct DeleteVersionsRequest {
-    /// List of version specifications - can be exact versions (e.g., "1.5.3-1")
-    /// or semver ranges (e.g., "^1.0.0", ">=1.0.0, <2.0.0")
+    /// List of version specifications - can be exact versions (e.g., "1.5.3-1",
+    /// or "1.5.3" for every pkgrel of it, compared as pacman's vercmp does)
+    /// or semver ranges (e.g., "^1.0.0", ">=1.0.0, <2.0.0"). Ranges never
+    /// match stored versions whose pkgver isn't semver (e.g. date-based
+    /// `20250115-1`); exact versions still do.
     pub versions: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub repo: Option<String>,
@@ -27,35 +31,10 @@ pub struct DeleteVersionsResponse {
     pub deleted_count: usize,
     /// List of deleted version strings
     pub deleted_versions: Vec<String>,
-}
-
-/// Parse Arch Linux version (epoch:pkgver-pkgrel) to semver
-fn parse_arch_version_to_semver(version_str: &str) -> Result<semver::Version> {
-    // Remove epoch if present
-    let without_epoch = if let Some(colon_pos) = version_str.find(':') {
-        &version_str[colon_pos + 1..]
-    } else {
-        version_str
-    };
-
-    // Remove pkgrel (everything after last '-')
-    let last_dash = without_epoch
-        .rfind('-')
-        .ok_or_else(|| Error::InvalidPackage {
-            pkgname: format!("Invalid version format: {}", version_str),
-        })?;
-    let pkgver = &without_epoch[..last_dash];
-
-    // Parse as semver
-    semver::Version::parse(pkgver).map_err(|_| Error::InvalidPackage {
-        pkgname: format!("Invalid semver format: {}", pkgver),
-    })
-}
-
-/// Check if a version matches a semver range requirement
-fn version_matches_range(version_str: &str, range: &semver::VersionReq) -> Result<bool> {
-    let version = parse_arch_version_to_semver(version_str)?;
-    Ok(range.matches(&version))
+    /// Stored versions that range specs couldn't be evaluated against
+    /// because they aren't semver; only exact specs can delete them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unevaluated_versions: Vec<String>,
 }
 
 /// Delete package versions
@@ -75,7 +54,7 @@ fn version_matches_range(version_str: &str, range: &semver::VersionReq) -> Resul
     tag = "packages"
 )]
 pub async fn delete_versions(
-    _user: crate::auth::AuthenticatedUser,
+    user: crate::auth::AuthenticatedUser,
     State(state): State<Arc<AppState>>,
     AxumPath(name): AxumPath<String>,
     Json(request): Json<DeleteVersionsRequest>,
@@ -103,35 +82,21 @@ pub async fn delete_versions(
 
     // Determine which versions to delete by processing each version spec
     // Each spec can be either an exact version match or a semver range
-    use std::collections::HashSet;
+    use std::collections::{BTreeSet, HashSet};
     let mut to_delete_set: HashSet<String> = HashSet::new();
+    let mut unevaluated: BTreeSet<String> = BTreeSet::new();
 
     for version_spec in &request.versions {
-        // Check if this looks like a semver range (contains range operators)
-        let has_range_operators = version_spec.contains('^')
-            || version_spec.contains('~')
-            || version_spec.contains('>')
-            || version_spec.contains('<')
-            || version_spec.contains('=')
-            || version_spec.contains('*')
-            || version_spec.contains(',');
-
-        if has_range_operators {
-            // Try parsing as semver range
-            if let Ok(version_req) = semver::VersionReq::parse(version_spec) {
-                // It's a semver range - match all packages against it
-                for pkg in &packages {
-                    if version_matches_range(&pkg.version, &version_req).unwrap_or(false) {
-                        to_delete_set.insert(pkg.version.clone());
-                    }
+        for pkg in &packages {
+            match crate::version::matches_constraint(&pkg.version, version_spec) {
+                Some(true) => {
+                    to_delete_set.insert(pkg.version.clone());
+                }
+                Some(false) => {}
+                None => {
+                    unevaluated.insert(pkg.version.clone());
                 }
-            } else {
-                // Invalid range syntax - treat as exact version match
-                to_delete_set.insert(version_spec.clone());
             }
-        } else {
-            // No range operators - treat as exact version match
-            to_delete_set.insert(version_spec.clone());
         }
     }
 
@@ -141,11 +106,29 @@ pub async fn delete_versions(
         .filter(|p| to_delete_set.contains(&p.version))
         .collect();
 
+    // Versions an exact spec deleted anyway don't need a warning
+    let unevaluated_versions: Vec<String> = unevaluated
+        .into_iter()
+        .filter(|v| !to_delete_set.contains(v))
+        .collect();
+    if !unevaluated_versions.is_empty() {
+        tracing::warn!(
+            package = %name,
+            versions = ?unevaluated_versions,
+            "Versions are not semver and were skipped by range specs"
+        );
+    }
+
     // Check if any versions matched
     if to_delete.is_empty() {
-        return Err(Error::PackageNotFound {
-            pkgname: format!("No matching versions found for package: {}", name),
-        });
+        let mut pkgname = format!("No matching versions found for package: {}", name);
+        if !unevaluated_versions.is_empty() {
+            pkgname.push_str(&format!(
+                " (not semver, so not matched by ranges: {})",
+                unevaluated_versions.join(", ")
+            ));
+        }
+        return Err(Error::PackageNotFound { pkgname });
     }
 
     // Collect deleted versions for response
@@ -162,6 +145,9 @@ pub async fn delete_versions(
             arch = %package.arch,
             "Deleted package version"
         );
+        state
+            .events
+            .record(RepoEvent::for_package(EventKind::Delete, package).user(user.username.clone()));
     }
 
     crate::metrics::record_package_deleted(&repo, deleted_count as u64);
@@ -180,5 +166,87 @@ pub async fn delete_versions(
     Ok(Json(DeleteVersionsResponse {
         deleted_count,
         deleted_versions,
+        unevaluated_versions,
+    }))
+}
+
+/// Delete every version of a package
+#[utoipa::path(
+    delete,
+    path = "/packages/{name}/all",
+    params(
+        ("name" = String, Path, description = "Package name"),
+        ("repo" = Option<String>, Query, description = "Repository name"),
+        ("arch" = Option<String>, Query, description = "Architecture")
+    ),
+    responses(
+        (status = 200, description = "All versions deleted", body = DeleteVersionsResponse),
+        (status = 404, description = "Package has no versions"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "packages"
+)]
+pub async fn delete_all_versions(
+    user: crate::auth::AuthenticatedUser,
+    State(state): State<Arc<AppState>>,
+    AxumPath(name): AxumPath<String>,
+    Query(query): Query<PackageQuery>,
+) -> Result<impl IntoResponse> {
+    let repo = query
+        .repo
+        .unwrap_or_else(|| state.config.storage.default_repo.clone());
+    let arch = query
+        .arch
+        .unwrap_or_else(|| state.config.storage.default_arch.clone());
+
+    // Includes "any" packages, as delete_versions does
+    let mut to_delete: Vec<Package> = state
+        .storage
+        .list_packages_for_arch(&repo, &arch)
+        .await?
+        .into_iter()
+        .filter(|p| p.name == name)
+        .collect();
+
+    if to_delete.is_empty() {
+        return Err(Error::PackageNotFound {
+            pkgname: name.clone(),
+        });
+    }
+
+    to_delete.sort_by(|a, b| super::compare_versions(&a.version, &b.version));
+    let deleted_versions: Vec<String> = to_delete.iter().map(|p| p.version.clone()).collect();
+    let deleted_count = to_delete.len();
+
+    for package in &to_delete {
+        state.storage.delete_package(package).await?;
+        tracing::info!(
+            package = %package.name,
+            version = %package.version,
+            repo = %package.repo,
+            arch = %package.arch,
+            "Deleted package version"
+        );
+        state
+            .events
+            .record(RepoEvent::for_package(EventKind::Delete, package).user(user.username.clone()));
+    }
+
+    crate::metrics::record_package_deleted(&repo, deleted_count as u64);
+
+    state.db_update.request_update(&repo, &arch).await;
+
+    tracing::info!(
+        package = %name,
+        repo = %repo,
+        arch = %arch,
+        deleted_count,
+        "Deleted all package versions"
+    );
+
+    Ok(Json(DeleteVersionsResponse {
+        deleted_count,
+        deleted_versions,
+        unevaluated_versions: Vec::new(),
     }))
 }
@@ -0,0 +1,81 @@
+use crate::AppState;
+use crate::events::RepoEvent;
+use axum::{
+    Json,
+    extract::{Query, State},
+    response::{
+        IntoResponse,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use utoipa::IntoParams;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct EventsQuery {
+    /// Maximum number of events to return, newest last (default 100)
+    pub limit: Option<usize>,
+}
+
+const DEFAULT_EVENTS_LIMIT: usize = 100;
+
+/// List recent repository events
+#[utoipa::path(
+    get,
+    path = "/admin/events",
+    params(EventsQuery),
+    responses(
+        (status = 200, description = "Recent events, oldest first", body = Vec<RepoEvent>)
+    ),
+    tag = "admin"
+)]
+pub async fn list_events(
+    _user: crate::auth::AuthenticatedUser,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<EventsQuery>,
+) -> impl IntoResponse {
+    Json(
+        state
+            .events
+            .recent(query.limit.unwrap_or(DEFAULT_EVENTS_LIMIT)),
+    )
+}
+
+/// Stream repository events as they happen (server-sent events)
+#[utoipa::path(
+    get,
+    path = "/admin/events/stream",
+    responses(
+        (status = 200, description = "Stream of events, one JSON object per `data:` line", content_type = "text/event-stream")
+    ),
+    tag = "admin"
+)]
+pub async fn stream_events(
+    _user: crate::auth::AuthenticatedUser,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let receiver = state.events.subscribe();
+    let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let sse = Event::default()
+                        .event("repo_event")
+                        .json_data(&event)
+                        .unwrap_or_else(|_| Event::default().comment("unserializable event"));
+                    return Some((Ok::<_, Infallible>(sse), receiver));
+                }
+                // A slow client misses events rather than holding up others
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "Event stream client lagged, events dropped");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
@@ -1,61 +1,99 @@
+mod admin_packages;
 pub mod auth;
 pub mod cleanup_policy;
 pub mod delete_versions;
+mod events;
+mod stats;
 mod upload;
 
 use crate::config::Config;
 use crate::db_actor::DbUpdateHandle;
 use crate::error::{Result, ResultIoExt};
-use crate::metadata::{extract_pkginfo, generate_files_db, generate_repo_db};
-use crate::models::{Package, PackageQuery};
-use crate::storage::Storage;
+use crate::events::{EventKind, EventLog, RepoEvent};
+use crate::metadata::{
+    extract_pkginfo_and_files, generate_files_db, generate_json_index, generate_repo_db,
+    load_pkginfo_cache, remove_repo_dbs, store_pkginfo_cache,
+};
+use crate::models::{Dependency, Package, PackageQuery, PkgInfo};
+use crate::storage::PackageStore;
 use crate::upload::UploadSessionStore;
 use axum::{
     Json,
-    extract::{Path as AxumPath, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{Path as AxumPath, Query, Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
 };
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
-use utoipa::OpenApi;
+use std::sync::atomic::{AtomicBool, Ordering};
+use utoipa::{OpenApi, ToSchema};
 use utoipa_axum::router::OpenApiRouter;
 use utoipa_axum::routes;
 
 pub struct AppState {
-    pub storage: Arc<Storage>,
+    pub storage: Arc<dyn PackageStore>,
     pub config: Config,
     pub upload_store: UploadSessionStore,
     pub db_update: DbUpdateHandle,
-    pub http_client: reqwest::Client,
+    pub github: crate::auth::GitHubClient,
+    pub allowlist: crate::auth::Allowlist,
+    /// Set once the startup database rebuild has finished
+    pub ready: Arc<AtomicBool>,
+    /// Recent uploads, deletes and cleanups for the admin events endpoints
+    pub events: EventLog,
+    /// Counters for the request concurrency caps
+    pub limits: crate::admission::ConcurrencyLimits,
+    /// Per-client token buckets for the write rate limit
+    pub rate_limiter: crate::admission::RateLimiter,
+    /// Per-upload receipts under `data/.receipts/`
+    pub receipts: crate::receipts::ReceiptStore,
+    /// Per-package download counts under `data/.downloads/`
+    pub downloads: crate::downloads::DownloadCounter,
 }
 
-/// List packages with optional filtering
+/// Page size of the package listing when the client doesn't give a `limit`
+pub const DEFAULT_LIST_LIMIT: usize = 100;
+
+/// One page of the package listing
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PackageListResponse {
+    /// Number of packages matching the filters, across all pages
+    pub total: usize,
+    pub items: Vec<Package>,
+}
+
+/// List packages with optional filtering, one page at a time
 #[utoipa::path(
     get,
     path = "/packages",
     params(
         ("name" = Option<String>, Query, description = "Filter by package name"),
         ("repo" = Option<String>, Query, description = "Filter by repository"),
-        ("arch" = Option<String>, Query, description = "Filter by architecture")
+        ("arch" = Option<String>, Query, description = "Filter by architecture (includes \"any\" packages)"),
+        ("license" = Option<String>, Query, description = "Filter by declared license, e.g. GPL-3.0-or-later (case-insensitive)"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 100, capped by server config)"),
+        ("offset" = Option<usize>, Query, description = "Number of matching packages to skip (default 0)")
     ),
     responses(
-        (status = 200, description = "List of packages", body = Vec<Package>),
+        (status = 200, description = "One page of packages and the total number of matches", body = PackageListResponse),
         (status = 500, description = "Internal server error")
     ),
     tag = "packages"
 )]
 pub async fn list_packages(
     State(state): State<Arc<AppState>>,
-    Query(query): Query<PackageQuery>,
-) -> Result<Json<Vec<Package>>> {
-    // List packages from specified repo or all repos
+    Query(mut query): Query<PackageQuery>,
+) -> Result<impl IntoResponse> {
+    query.arch = query
+        .arch
+        .map(|arch| state.config.storage.canonical_arch(&arch).to_owned());
+
+    // Each of repo and arch only narrows the listing when given: a missing
+    // one spans every repo or arch rather than falling back to the default
     let mut packages = if let Some(ref repo) = query.repo {
-        // If arch filter is specified, use list_packages_for_arch
-        if let Some(ref arch) = query.arch {
-            state.storage.list_packages_for_arch(repo, arch).await?
-        } else {
-            state.storage.list_packages(repo).await?
-        }
+        state.storage.list_packages(repo).await?
     } else {
         state.storage.list_all_packages().await?
     };
@@ -65,15 +103,367 @@ pub async fn list_packages(
         packages.retain(|p| p.name.contains(name_filter));
     }
 
-    if let Some(ref repo_filter) = query.repo {
-        packages.retain(|p| &p.repo == repo_filter);
+    // "any" packages install on every arch, so they belong to each arch's
+    // listing just as they do to its db
+    if let Some(ref arch_filter) = query.arch {
+        packages.retain(|p| &p.arch == arch_filter || p.arch == "any");
+    }
+
+    if let Some(ref license_filter) = query.license {
+        packages.retain(|p| {
+            p.license
+                .iter()
+                .any(|license| license.eq_ignore_ascii_case(license_filter))
+        });
     }
 
-    if let Some(ref arch_filter) = query.arch {
-        packages.retain(|p| &p.arch == arch_filter);
+    // Stable order so pages are deterministic
+    packages.sort_by(|a, b| {
+        (&a.repo, &a.name, &a.arch)
+            .cmp(&(&b.repo, &b.name, &b.arch))
+            .then_with(|| compare_versions(&a.version, &b.version))
+    });
+
+    let total = packages.len();
+    let max_results = state.config.server.max_list_results;
+    let limit = query.limit.unwrap_or(DEFAULT_LIST_LIMIT).min(max_results);
+    let items = packages
+        .into_iter()
+        .skip(query.offset.unwrap_or(0))
+        .take(limit)
+        .collect();
+
+    Ok(Json(PackageListResponse { total, items }))
+}
+
+/// Find the newest version of a package in a repo, optionally for one arch
+async fn find_latest(state: &AppState, name: String, query: PackageQuery) -> Result<Package> {
+    let repo = query
+        .repo
+        .unwrap_or_else(|| state.config.storage.default_repo.clone());
+
+    let packages = if let Some(ref arch) = query.arch {
+        let arch = state.config.storage.canonical_arch(arch);
+        state.storage.list_packages_for_arch(&repo, arch).await?
+    } else {
+        state.storage.list_packages(&repo).await?
+    };
+
+    packages
+        .into_iter()
+        .filter(|p| p.name == name)
+        .max_by(|a, b| compare_versions(&a.version, &b.version))
+        .ok_or(crate::error::Error::PackageNotFound { pkgname: name })
+}
+
+/// Get the newest version of a package
+///
+/// Versions are ordered like pacman's `vercmp`, so epoch and pkgrel count.
+#[utoipa::path(
+    get,
+    path = "/packages/{name}/latest",
+    params(
+        ("name" = String, Path, description = "Package name"),
+        ("repo" = Option<String>, Query, description = "Repository name (defaults to the configured default repo)"),
+        ("arch" = Option<String>, Query, description = "Architecture (includes \"any\" packages)")
+    ),
+    responses(
+        (status = 200, description = "Newest version of the package", body = Package),
+        (status = 404, description = "Package not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "packages"
+)]
+pub async fn latest_package(
+    State(state): State<Arc<AppState>>,
+    AxumPath(name): AxumPath<String>,
+    Query(query): Query<PackageQuery>,
+) -> Result<Json<Package>> {
+    Ok(Json(find_latest(&state, name, query).await?))
+}
+
+/// The `.PKGINFO` of a stored package, from the pkginfo cache while the
+/// package file is unchanged, or `None` if its file is missing
+async fn stored_pkginfo(
+    storage: &dyn PackageStore,
+    package: &Package,
+    lossy_pkginfo: bool,
+) -> Result<Option<PkgInfo>> {
+    let pkg_path = storage.package_path(&package.repo, &package.filename)?;
+    let file = match tokio::fs::metadata(&pkg_path).await {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).map_io_err(&pkg_path),
+    };
+
+    let cache_path = storage.pkginfo_cache_path(
+        &package.repo,
+        package.filename.trim_end_matches(".pkg.tar.zst"),
+    )?;
+    if let Some((pkginfo, _)) = load_pkginfo_cache(&cache_path, package, &file).await {
+        return Ok(Some(pkginfo));
+    }
+
+    let data = tokio::fs::read(&pkg_path).await.map_io_err(&pkg_path)?;
+    let (pkginfo, files) =
+        tokio::task::spawn_blocking(move || extract_pkginfo_and_files(&data, lossy_pkginfo))
+            .await
+            .map_err(|e| std::io::Error::other(format!("Task join error: {e}")))??;
+
+    if let Err(e) = store_pkginfo_cache(&cache_path, package, &file, &pkginfo, &files).await {
+        tracing::warn!(
+            path = %cache_path.display(),
+            package = %package.name,
+            error = %e,
+            "Failed to cache package info"
+        );
+    }
+    Ok(Some(pkginfo))
+}
+
+/// Record the licenses of packages stored before licenses were, reading
+/// them from each package's `.PKGINFO`. Run at startup, before any upload
+/// can replace a package; returns how many packages were updated.
+pub async fn backfill_licenses(storage: &dyn PackageStore, lossy_pkginfo: bool) -> Result<usize> {
+    let mut updated = 0;
+    for package in storage.list_all_packages().await? {
+        if !package.license.is_empty() {
+            continue;
+        }
+        let license = match stored_pkginfo(storage, &package, lossy_pkginfo).await {
+            Ok(Some(pkginfo)) if !pkginfo.license.is_empty() => pkginfo.license,
+            Ok(_) => continue,
+            Err(e) => {
+                tracing::warn!(
+                    package = %package.filename,
+                    repo = %package.repo,
+                    error = %e,
+                    "Failed to read package info for its licenses"
+                );
+                continue;
+            }
+        };
+
+        // Only add to the metadata the package was read from, never to a
+        // newer upload of the same filename
+        let stem = package.filename.trim_end_matches(".pkg.tar.zst");
+        let current = storage.load_package(&package.repo, stem).await?;
+        if current.sha256 != package.sha256 || !current.license.is_empty() {
+            continue;
+        }
+        storage
+            .reindex_package(&Package { license, ..current })
+            .await?;
+        updated += 1;
+    }
+    Ok(updated)
+}
+
+/// Get the newest version of a package as plain text
+#[utoipa::path(
+    get,
+    path = "/packages/{name}/latest-version",
+    params(
+        ("name" = String, Path, description = "Package name"),
+        ("repo" = Option<String>, Query, description = "Repository name (defaults to the configured default repo)"),
+        ("arch" = Option<String>, Query, description = "Architecture (includes \"any\" packages)")
+    ),
+    responses(
+        (status = 200, description = "Newest version string", body = String, content_type = "text/plain"),
+        (status = 404, description = "Package not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "packages"
+)]
+pub async fn latest_version(
+    State(state): State<Arc<AppState>>,
+    AxumPath(name): AxumPath<String>,
+    Query(query): Query<PackageQuery>,
+) -> Result<impl IntoResponse> {
+    let latest = find_latest(&state, name, query).await?;
+
+    Ok((
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; charset=utf-8",
+        )],
+        latest.version,
+    ))
+}
+
+/// Query parameters for fetching one package
+#[derive(Debug, Deserialize)]
+pub struct GetPackageQuery {
+    pub repo: Option<String>,
+    pub arch: Option<String>,
+    pub version: Option<String>,
+}
+
+/// A package with the runtime dependencies declared in its `.PKGINFO`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PackageDetail {
+    #[serde(flatten)]
+    pub package: Package,
+    /// Parsed `depends` entries
+    pub depends: Vec<Dependency>,
+}
+
+impl PackageDetail {
+    /// Read the package's dependencies from its `.PKGINFO`; a package whose
+    /// file can't be read is returned without any
+    async fn load(state: &AppState, package: Package) -> Self {
+        let lossy_pkginfo = state.config.storage.lossy_pkginfo;
+        let depends = match stored_pkginfo(state.storage.as_ref(), &package, lossy_pkginfo).await {
+            Ok(pkginfo) => pkginfo.map(|p| p.parsed_depends()).unwrap_or_default(),
+            Err(e) => {
+                tracing::warn!(
+                    package = %package.filename,
+                    error = %e,
+                    "Failed to read package dependencies"
+                );
+                Vec::new()
+            }
+        };
+        Self { package, depends }
+    }
+}
+
+/// A single matching package, or every match when there are several
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum PackageLookup {
+    One(PackageDetail),
+    Many(Vec<PackageDetail>),
+}
+
+/// Get a package's metadata by name
+///
+/// Returns the package when exactly one stored package matches, otherwise
+/// every match (e.g. all versions of the name), oldest first. Each comes
+/// with its parsed runtime dependencies.
+#[utoipa::path(
+    get,
+    path = "/packages/{name}",
+    params(
+        ("name" = String, Path, description = "Package name"),
+        ("repo" = Option<String>, Query, description = "Repository name (defaults to the configured default repo)"),
+        ("arch" = Option<String>, Query, description = "Architecture (includes \"any\" packages)"),
+        ("version" = Option<String>, Query, description = "Exact version, e.g. 1.0.0-1")
+    ),
+    responses(
+        (status = 200, description = "The matching package, or a list when several match", body = PackageLookup),
+        (status = 404, description = "Package not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "packages"
+)]
+pub async fn get_package(
+    State(state): State<Arc<AppState>>,
+    AxumPath(name): AxumPath<String>,
+    Query(query): Query<GetPackageQuery>,
+) -> Result<Json<PackageLookup>> {
+    let repo = query
+        .repo
+        .unwrap_or_else(|| state.config.storage.default_repo.clone());
+
+    let packages = if let Some(ref arch) = query.arch {
+        let arch = state.config.storage.canonical_arch(arch);
+        state.storage.list_packages_for_arch(&repo, arch).await?
+    } else {
+        state.storage.list_packages(&repo).await?
+    };
+
+    let mut matches: Vec<Package> = packages
+        .into_iter()
+        .filter(|p| p.name == name)
+        .filter(|p| query.version.as_ref().is_none_or(|v| &p.version == v))
+        .collect();
+    matches
+        .sort_by(|a, b| compare_versions(&a.version, &b.version).then_with(|| a.arch.cmp(&b.arch)));
+
+    match matches.len() {
+        0 => Err(crate::error::Error::PackageNotFound {
+            pkgname: match query.version {
+                Some(version) => format!("{name} {version}"),
+                None => name,
+            },
+        }),
+        1 => Ok(Json(PackageLookup::One(
+            PackageDetail::load(&state, matches.remove(0)).await,
+        ))),
+        _ => {
+            let mut details = Vec::with_capacity(matches.len());
+            for package in matches {
+                details.push(PackageDetail::load(&state, package).await);
+            }
+            Ok(Json(PackageLookup::Many(details)))
+        }
+    }
+}
+
+/// Request body for looking up several packages at once
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchInfoRequest {
+    /// Package names to look up
+    pub names: Vec<String>,
+    /// Repository name (defaults to the configured default repo)
+    pub repo: Option<String>,
+    /// Architecture (includes "any" packages)
+    pub arch: Option<String>,
+}
+
+/// Get the newest version's metadata for several packages in one request
+#[utoipa::path(
+    post,
+    path = "/packages/batch-info",
+    request_body = BatchInfoRequest,
+    responses(
+        (status = 200, description = "Map of package name to metadata, `null` when not found",
+            body = BTreeMap<String, Package>),
+        (status = 400, description = "Too many names in the batch"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "packages"
+)]
+pub async fn batch_info(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<BatchInfoRequest>,
+) -> Result<Json<BTreeMap<String, Option<Package>>>> {
+    let max_batch_size = state.config.server.max_batch_size;
+    if request.names.len() > max_batch_size {
+        return Err(crate::error::Error::InvalidPackage {
+            pkgname: format!(
+                "Too many names in batch: {} (max {max_batch_size})",
+                request.names.len()
+            ),
+        });
+    }
+
+    let repo = request
+        .repo
+        .unwrap_or_else(|| state.config.storage.default_repo.clone());
+
+    let packages = if let Some(ref arch) = request.arch {
+        let arch = state.config.storage.canonical_arch(arch);
+        state.storage.list_packages_for_arch(&repo, arch).await?
+    } else {
+        state.storage.list_packages(&repo).await?
+    };
+
+    let mut result: BTreeMap<String, Option<Package>> =
+        request.names.into_iter().map(|name| (name, None)).collect();
+    for pkg in packages {
+        if let Some(slot) = result.get_mut(&pkg.name) {
+            let newer = slot
+                .as_ref()
+                .is_none_or(|existing| compare_versions(&pkg.version, &existing.version).is_gt());
+            if newer {
+                *slot = Some(pkg);
+            }
+        }
     }
 
-    Ok(Json(packages))
+    Ok(Json(result))
 }
 
 /// Delete a package
@@ -93,7 +483,7 @@ pub async fn list_packages(
     tag = "packages"
 )]
 pub async fn delete_package(
-    _user: crate::auth::AuthenticatedUser,
+    user: crate::auth::AuthenticatedUser,
     State(state): State<Arc<AppState>>,
     AxumPath(name): AxumPath<String>,
     Query(query): Query<PackageQuery>,
@@ -112,6 +502,9 @@ pub async fn delete_package(
     state.storage.delete_package(&package).await?;
 
     crate::metrics::record_package_deleted(&repo, 1);
+    state
+        .events
+        .record(RepoEvent::for_package(EventKind::Delete, &package).user(user.username));
 
     // Request database update for affected architectures
     // If package arch is "any", update the default arch database
@@ -128,6 +521,15 @@ pub async fn delete_package(
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Response to a queued database rebuild
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RebuildResponse {
+    pub repo: String,
+    pub arch: String,
+    /// Always `"queued"`; poll db-status to see the rebuild applied
+    pub status: &'static str,
+}
+
 /// Force rebuild of repository database
 #[utoipa::path(
     post,
@@ -137,7 +539,9 @@ pub async fn delete_package(
         ("arch" = String, Path, description = "Architecture")
     ),
     responses(
-        (status = 202, description = "Database rebuild initiated"),
+        (status = 202, description = "Database rebuild queued", body = RebuildResponse),
+        (status = 400, description = "Invalid repo or arch name"),
+        (status = 404, description = "No database or packages for this repo/arch"),
         (status = 500, description = "Internal server error")
     ),
     tag = "packages"
@@ -147,25 +551,355 @@ pub async fn rebuild_db(
     State(state): State<Arc<AppState>>,
     AxumPath((repo, arch)): AxumPath<(String, String)>,
 ) -> Result<impl IntoResponse> {
+    let arch = state.config.storage.canonical_arch(&arch).to_owned();
+
+    // Rejects names that aren't a single safe path component
+    let db_dir = state.storage.db_dir(&repo, &arch)?;
+
+    // A repo/arch with neither a db nor packages to build one from is unknown
+    if !tokio::fs::try_exists(&db_dir).await.unwrap_or(false)
+        && state
+            .storage
+            .list_packages_for_arch(&repo, &arch)
+            .await?
+            .is_empty()
+    {
+        return Err(crate::error::Error::PackageNotFound {
+            pkgname: format!("repository {repo}/{arch}"),
+        });
+    }
+
     tracing::info!(repo = %repo, arch = %arch, "Force rebuild requested via API");
 
     // Force immediate rebuild (bypass debounce)
     state.db_update.force_rebuild(&repo, &arch).await;
 
-    Ok(StatusCode::ACCEPTED)
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(RebuildResponse {
+            repo,
+            arch,
+            status: "queued",
+        }),
+    ))
+}
+
+/// Whether a repo/arch database reflects the latest change
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DbStatusResponse {
+    pub repo: String,
+    pub arch: String,
+    /// Incremented on every requested database update
+    pub requested_generation: u64,
+    /// Generation the current database was built from
+    pub applied_generation: u64,
+    /// `true` once the database includes every requested change
+    pub current: bool,
+    /// Successful regenerations since startup; bursts of updates within
+    /// the debounce window share one
+    pub regenerations: u64,
+}
+
+/// Report whether the repository database is up to date
+#[utoipa::path(
+    get,
+    path = "/repos/{repo}/os/{arch}/db-status",
+    params(
+        ("repo" = String, Path, description = "Repository name"),
+        ("arch" = String, Path, description = "Architecture")
+    ),
+    responses(
+        (status = 200, description = "Database status", body = DbStatusResponse)
+    ),
+    tag = "packages"
+)]
+pub async fn db_status(
+    State(state): State<Arc<AppState>>,
+    AxumPath((repo, arch)): AxumPath<(String, String)>,
+) -> Json<DbStatusResponse> {
+    let arch = state.config.storage.canonical_arch(&arch).to_owned();
+    let generation = state.db_update.generation(&repo, &arch);
+
+    Json(DbStatusResponse {
+        repo,
+        arch,
+        requested_generation: generation.requested,
+        applied_generation: generation.applied,
+        current: generation.is_current(),
+        regenerations: generation.regenerations,
+    })
+}
+
+/// One package file in a repo/arch manifest
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ManifestEntry {
+    pub filename: String,
+    pub sha256: String,
+    pub size: u64,
+}
+
+/// Every package file served for a repo/arch, for mirrors to verify against
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ManifestResponse {
+    pub repo: String,
+    pub arch: String,
+    /// SHA256 over one `{filename} {sha256} {size}\n` line per entry, in
+    /// entry order
+    pub checksum: String,
+    /// Sorted by filename
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// List the package files of a repo/arch with a checksum over the list
+///
+/// A mirror builds the same lines from its local files and compares the
+/// checksum to confirm it holds exactly the same packages.
+#[utoipa::path(
+    get,
+    path = "/repos/{repo}/os/{arch}/manifest",
+    params(
+        ("repo" = String, Path, description = "Repository name"),
+        ("arch" = String, Path, description = "Architecture")
+    ),
+    responses(
+        (status = 200, description = "Repository manifest", body = ManifestResponse),
+        (status = 400, description = "Invalid repository or architecture")
+    ),
+    tag = "packages"
+)]
+pub async fn manifest(
+    State(state): State<Arc<AppState>>,
+    AxumPath((repo, arch)): AxumPath<(String, String)>,
+) -> Result<Json<ManifestResponse>> {
+    let arch = state.config.storage.canonical_arch(&arch).to_owned();
+
+    let mut entries: Vec<ManifestEntry> = state
+        .storage
+        .list_packages_for_arch(&repo, &arch)
+        .await?
+        .into_iter()
+        .map(|pkg| ManifestEntry {
+            filename: pkg.filename,
+            sha256: pkg.sha256,
+            size: pkg.size,
+        })
+        .collect();
+    entries.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+    let lines: String = entries
+        .iter()
+        .map(|e| format!("{} {} {}\n", e.filename, e.sha256, e.size))
+        .collect();
+
+    Ok(Json(ManifestResponse {
+        repo,
+        arch,
+        checksum: crate::metadata::calculate_sha256(lines.as_bytes()),
+        entries,
+    }))
+}
+
+/// Report whether the server has finished warming up
+#[utoipa::path(
+    get,
+    path = "/ready",
+    responses(
+        (status = 200, description = "Startup database rebuild finished"),
+        (status = 503, description = "Still warming up")
+    ),
+    tag = "health"
+)]
+pub async fn ready(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    if state.ready.load(Ordering::Acquire) {
+        (StatusCode::OK, "ready")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "warming up")
+    }
+}
+
+/// Health of the server, as reported by [`health`]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HealthResponse {
+    /// `ok`, or `unavailable` when the storage can't be written
+    pub status: &'static str,
+    pub version: &'static str,
+    /// Why the storage check failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Report whether the storage is usable, for load balancers and service
+/// managers
+///
+/// Served at `/health`, outside `/api` and without auth. Unlike [`ready`] it
+/// doesn't wait for warm-up: it probes the data directory by writing to it.
+#[utoipa::path(
+    get,
+    path = "/health",
+    responses(
+        (status = 200, description = "Storage is writable", body = HealthResponse),
+        (status = 503, description = "Storage check failed", body = HealthResponse)
+    ),
+    tag = "health"
+)]
+pub async fn health(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let version = env!("CARGO_PKG_VERSION");
+    match state.storage.check_writable().await {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(HealthResponse {
+                status: "ok",
+                version,
+                error: None,
+            }),
+        ),
+        Err(e) => {
+            tracing::warn!(error = %e, "Health check failed");
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(HealthResponse {
+                    status: "unavailable",
+                    version,
+                    error: Some(e.to_string()),
+                }),
+            )
+        }
+    }
+}
+
+/// Middleware answering write requests with 503 during warm-up, when
+/// `reject_writes_until_ready` is set
+async fn require_ready_for_writes(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let is_write = !matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+    if is_write
+        && state.config.server.reject_writes_until_ready
+        && !state.ready.load(Ordering::Acquire)
+    {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(axum::http::header::RETRY_AFTER, "5")],
+            "Server is warming up, retry shortly",
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+/// Apply the per-IP concurrency cap to uploads and other writes
+async fn per_ip_limit_for_writes(
+    limits: State<crate::admission::ConcurrencyLimits>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) {
+        return next.run(request).await;
+    }
+    crate::admission::per_ip_limit_layer(limits, request, next).await
+}
+
+/// Whether a request to the API router counts against the write rate limit
+///
+/// Reads don't, including the POST of batch-info. Neither do the auth
+/// endpoints: device-flow clients poll for their token by design.
+fn is_rate_limited(method: &Method, path: &str) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+        && path != "/packages/batch-info"
+        && !path.starts_with("/auth/")
+}
+
+/// Apply the per-client rate limit to uploads and other writes
+async fn rate_limit_writes(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !is_rate_limited(request.method(), request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let client = crate::admission::rate_limit_client(state.config.auth.as_ref(), &request);
+    if let Err(retry_after) = state.rate_limiter.acquire(&client) {
+        crate::metrics::record_request_shed("rate_limit");
+        return crate::admission::rate_limited(retry_after);
+    }
+    next.run(request).await
+}
+
+/// How repository databases are generated, from the storage config
+#[derive(Debug, Clone, Copy)]
+pub struct DbOptions {
+    /// Remove the db files of an emptied repo/arch instead of publishing an
+    /// empty db
+    pub remove_empty_db: bool,
+    /// Decode a `.PKGINFO` that isn't valid UTF-8 lossily
+    pub lossy_pkginfo: bool,
+    /// Compression of the db archives
+    pub compression_format: crate::config::DbCompressionFormat,
+    /// Gzip level (0-9)
+    pub compression_level: u32,
+    /// Also write `index.json` next to the db
+    pub generate_json_index: bool,
+}
+
+impl From<&crate::config::StorageConfig> for DbOptions {
+    fn from(config: &crate::config::StorageConfig) -> Self {
+        Self {
+            remove_empty_db: config.remove_empty_db,
+            lossy_pkginfo: config.lossy_pkginfo,
+            compression_format: config.db_compression_format,
+            compression_level: config.db_compression_level,
+            generate_json_index: config.generate_json_index,
+        }
+    }
+}
+
+impl Default for DbOptions {
+    fn default() -> Self {
+        Self::from(&crate::config::StorageConfig::default())
+    }
 }
 
 /// Regenerate repository database for a given repo/arch
-pub(crate) async fn regenerate_repo_db(storage: &Storage, repo: &str, arch: &str) -> Result<()> {
+pub(crate) async fn regenerate_repo_db(
+    storage: &dyn PackageStore,
+    options: &DbOptions,
+    repo: &str,
+    arch: &str,
+) -> Result<()> {
+    // Only one regeneration per repo/arch at a time
+    let _db_lock = storage.lock_db(repo, arch).await;
+
     // List packages for this arch (includes "any" architecture packages)
     let packages = storage.list_packages_for_arch(repo, arch).await?;
 
     // Database files go in os/{arch}/ for URL compatibility
     let db_dir = storage.db_dir(repo, arch)?;
+    tokio::fs::create_dir_all(&db_dir)
+        .await
+        .map_io_err(&db_dir)?;
 
-    // Group packages by name and keep only the latest version of each
+    // Group packages by name and keep only the latest version of each. "any"
+    // packages are folded in above, so a name present as both "any" and this
+    // arch still yields a single entry: the higher version wins.
     let latest_packages = select_latest_versions(packages);
 
+    // An emptied repo/arch gets a valid empty db (pacman accepts one), or no
+    // db at all if so configured
+    if latest_packages.is_empty() && options.remove_empty_db {
+        tracing::info!(repo, arch, "No packages left, removing databases");
+        return remove_repo_dbs(&db_dir, repo).await;
+    }
+
     tracing::info!(
         repo,
         arch,
@@ -173,13 +907,40 @@ pub(crate) async fn regenerate_repo_db(storage: &Storage, repo: &str, arch: &str
         "Regenerating database with latest package versions"
     );
 
-    // Load pkginfo for each package
+    // Load pkginfo for each package, from the cache while the package file
+    // is unchanged. A package that can't be read or parsed is skipped so it
+    // can't take the rest of the repo index down with it.
     let mut pkg_data = Vec::new();
+    let mut signatures = Vec::new();
+    let mut file_lists = Vec::new();
+    let mut skipped = 0usize;
     for pkg in latest_packages {
         // Package files are in flat storage (no arch in path)
         let pkg_path = storage.package_path(repo, &pkg.filename)?;
 
-        // Read package file, skipping if missing (orphaned metadata)
+        // Skip packages whose file is missing (orphaned metadata)
+        let file = match tokio::fs::metadata(&pkg_path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::warn!(
+                    path = %pkg_path.display(),
+                    package = %pkg.name,
+                    "Orphaned metadata - package file missing, skipping"
+                );
+                continue;
+            }
+            Err(e) => return Err(e).map_io_err(&pkg_path),
+        };
+
+        let cache_path =
+            storage.pkginfo_cache_path(repo, pkg.filename.trim_end_matches(".pkg.tar.zst"))?;
+        if let Some((pkginfo, files)) = load_pkginfo_cache(&cache_path, &pkg, &file).await {
+            signatures.push(db_signature(&pkg, &pkg_path).await);
+            pkg_data.push((pkg, pkginfo));
+            file_lists.push(files);
+            continue;
+        }
+
         let data = match tokio::fs::read(&pkg_path).await {
             Ok(data) => data,
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
@@ -193,21 +954,109 @@ pub(crate) async fn regenerate_repo_db(storage: &Storage, repo: &str, arch: &str
             Err(e) => return Err(e).map_io_err(&pkg_path),
         };
 
-        // Extract pkginfo in blocking task (CPU-intensive decompression)
-        let pkginfo = tokio::task::spawn_blocking(move || extract_pkginfo(&data))
-            .await
-            .map_err(|e| std::io::Error::other(format!("Task join error: {e}")))??;
+        // Extract pkginfo and the file listing in one blocking task
+        // (CPU-intensive decompression)
+        let lossy_pkginfo = options.lossy_pkginfo;
+        let (pkginfo, files) = match tokio::task::spawn_blocking(move || {
+            extract_pkginfo_and_files(&data, lossy_pkginfo)
+        })
+        .await
+        .map_err(|e| std::io::Error::other(format!("Task join error: {e}")))?
+        {
+            Ok(extracted) => extracted,
+            Err(e) => {
+                tracing::error!(
+                    path = %pkg_path.display(),
+                    package = %pkg.name,
+                    version = %pkg.version,
+                    error = %e,
+                    "Failed to extract package info, skipping package in database"
+                );
+                skipped += 1;
+                continue;
+            }
+        };
 
+        if let Err(e) = store_pkginfo_cache(&cache_path, &pkg, &file, &pkginfo, &files).await {
+            tracing::warn!(
+                path = %cache_path.display(),
+                package = %pkg.name,
+                error = %e,
+                "Failed to cache package info"
+            );
+        }
+
+        signatures.push(db_signature(&pkg, &pkg_path).await);
         pkg_data.push((pkg, pkginfo));
+        file_lists.push(files);
+    }
+
+    if skipped > 0 {
+        crate::metrics::record_db_packages_skipped(repo, arch, skipped as u64);
+        tracing::warn!(
+            repo,
+            arch,
+            skipped,
+            "Skipped unreadable packages while regenerating database"
+        );
     }
 
     // Generate databases
-    generate_repo_db(&db_dir, repo, &pkg_data).await?;
-    generate_files_db(&db_dir, repo, &pkg_data).await?;
+    let format = options.compression_format;
+    let level = options.compression_level;
+    generate_repo_db(&db_dir, repo, &pkg_data, &signatures, format, level).await?;
+    generate_files_db(
+        &db_dir,
+        repo,
+        &pkg_data,
+        &signatures,
+        &file_lists,
+        format,
+        level,
+    )
+    .await?;
+    if options.generate_json_index {
+        generate_json_index(&db_dir, &pkg_data).await?;
+    }
 
     Ok(())
 }
 
+/// The base64 detached signature of a package to embed in the db as
+/// `%PGPSIG%`
+///
+/// Only signatures verified against the keyring are embedded: pacman
+/// rejects a package outright over a bad embedded signature, so unverified
+/// ones are left to the `.sig` file served next to the package.
+async fn db_signature(pkg: &Package, pkg_path: &std::path::Path) -> Option<String> {
+    use base64::Engine;
+
+    if !pkg.signed {
+        return None;
+    }
+    if !pkg.signature_verified {
+        tracing::warn!(
+            package = %pkg.filename,
+            "Not embedding unverified signature in database"
+        );
+        return None;
+    }
+
+    let sig_path = std::path::PathBuf::from(format!("{}.sig", pkg_path.display()));
+    match tokio::fs::read(&sig_path).await {
+        Ok(sig) => Some(base64::engine::general_purpose::STANDARD.encode(sig)),
+        Err(e) => {
+            tracing::warn!(
+                path = %sig_path.display(),
+                package = %pkg.filename,
+                error = %e,
+                "Failed to read signature, not embedding it in database"
+            );
+            None
+        }
+    }
+}
+
 /// Select only the latest version of each package
 fn select_latest_versions(packages: Vec<Package>) -> Vec<Package> {
     use std::collections::HashMap;
@@ -228,30 +1077,33 @@ fn select_latest_versions(packages: Vec<Package>) -> Vec<Package> {
 }
 
 /// Compare two Arch Linux package versions using the pacman vercmp algorithm
-/// (rpmvercmp), as implemented by [`alpm_types::FullVersion`].
+/// (see [`crate::version::vercmp`]).
 ///
 /// Handles the full `[epoch:]pkgver-pkgrel` form, including AUR-style
 /// pkgvers like `0.15.0.r166.gae5dbc9` that aren't valid semver.
-///
-/// Falls back to plain string comparison only if both inputs fail to parse
-/// as an alpm-package-version.
 fn compare_versions(v1: &str, v2: &str) -> std::cmp::Ordering {
-    use std::str::FromStr;
-    match (
-        alpm_types::FullVersion::from_str(v1),
-        alpm_types::FullVersion::from_str(v2),
-    ) {
-        (Ok(a), Ok(b)) => a.cmp(&b),
-        _ => v1.cmp(v2),
-    }
+    crate::version::vercmp(v1, v2)
 }
 
 #[derive(OpenApi)]
 #[openapi(
+    // Served outside `/api`, so not registered through the api router
+    paths(health),
     components(
         schemas(
             Package,
             PackageQuery,
+            PackageLookup,
+            PackageDetail,
+            Dependency,
+            crate::models::VersionOp,
+            PackageListResponse,
+            DbStatusResponse,
+            RebuildResponse,
+            HealthResponse,
+            ManifestEntry,
+            ManifestResponse,
+            BatchInfoRequest,
             upload::InitiateUploadRequest,
             upload::InitiateUploadResponse,
             upload::UploadChunkResponse,
@@ -259,40 +1111,90 @@ fn compare_versions(v1: &str, v2: &str) -> std::cmp::Ordering {
             upload::CompleteUploadRequest,
             upload::ChunkInfo,
             upload::AbortUploadResponse,
+            upload::QueuedUploadResponse,
+            upload::UploadSessionDetails,
+            upload::UploadStatusResponse,
+            upload::RangeUploadResponse,
             delete_versions::DeleteVersionsRequest,
             delete_versions::DeleteVersionsResponse,
             cleanup_policy::CleanupPolicyRequest,
             cleanup_policy::CleanupPolicyResponse,
-            cleanup_policy::PackageCleanupDetail
+            cleanup_policy::PackageCleanupDetail,
+            cleanup_policy::CleanupAllRequest,
+            cleanup_policy::CleanupAllResponse,
+            cleanup_policy::RepoArchCleanup,
+            crate::config::CleanupPolicy,
+            crate::events::RepoEvent,
+            crate::events::EventKind,
+            crate::receipts::UploadReceipt,
+            admin_packages::AdminPackageEntry,
+            admin_packages::AdminPackagesResponse,
+            crate::downloads::PackageDownloads
         )
     ),
     tags(
         (name = "packages", description = "Package management endpoints"),
-        (name = "chunked-uploads", description = "Chunked upload endpoints")
+        (name = "chunked-uploads", description = "Chunked upload endpoints"),
+        (name = "health", description = "Service health endpoints"),
+        (name = "admin", description = "Administration endpoints")
     )
 )]
 pub struct ApiDoc;
 
 /// Create the API router with all routes
 pub fn create_api_router(state: Arc<AppState>) -> OpenApiRouter {
+    use axum::extract::DefaultBodyLimit;
     use axum::routing::post;
 
+    // Chunk and range upload bodies may carry up to a whole package, well
+    // past axum's 2 MB default
+    let body_limit =
+        usize::try_from(state.config.server.max_payload_size.as_u64()).unwrap_or(usize::MAX);
+
     OpenApiRouter::with_openapi(ApiDoc::openapi())
-        .routes(routes!(list_packages))
-        .routes(routes!(delete_package))
+        .routes(routes!(list_packages, upload::legacy_upload))
+        .routes(routes!(get_package, delete_package, upload::range_upload))
+        .routes(routes!(latest_package))
+        .routes(routes!(latest_version))
+        .routes(routes!(batch_info))
+        .routes(routes!(stats::popular_packages))
         .routes(routes!(rebuild_db))
+        .routes(routes!(db_status))
+        .routes(routes!(manifest))
+        .routes(routes!(ready))
         .route(
             "/packages/{name}/versions/delete",
             post(delete_versions::delete_versions),
         )
+        .routes(routes!(delete_versions::delete_all_versions))
         .routes(routes!(cleanup_policy::apply_cleanup_policy))
+        .routes(routes!(cleanup_policy::apply_cleanup_all))
+        .routes(routes!(events::list_events))
+        .routes(routes!(events::stream_events))
+        .routes(routes!(upload::get_receipt))
+        .routes(routes!(admin_packages::list_admin_packages))
         .routes(routes!(upload::initiate_upload))
         .routes(routes!(upload::upload_chunk))
         .routes(routes!(upload::upload_signature))
         .routes(routes!(upload::complete_upload))
-        .routes(routes!(upload::abort_upload))
+        .routes(routes!(upload::get_upload_session, upload::abort_upload))
+        .routes(routes!(upload::get_upload_status))
         .route("/auth/device/code", post(auth::device_code))
         .route("/auth/device/token", post(auth::device_token))
+        .route("/auth/refresh", post(auth::refresh_token))
+        .layer(DefaultBodyLimit::max(body_limit))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            require_ready_for_writes,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.limits.clone(),
+            per_ip_limit_for_writes,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit_writes,
+        ))
         .with_state(state)
 }
 
@@ -316,6 +1218,9 @@ mod tests {
             sha256: String::new(),
             size: 0,
             created_at: Utc::now(),
+            signed: false,
+            signature_verified: false,
+            license: Vec::new(),
         }
     }
 
@@ -357,7 +1262,7 @@ mod tests {
     #[test]
     fn compare_versions_falls_back_for_unparseable_input() {
         // Neither side is a valid alpm-package-version (missing pkgrel).
-        // Should not panic; string compare is the documented fallback.
+        // Should not panic; alpha segments compare lexically.
         assert_eq!(compare_versions("garbage", "garbage"), Ordering::Equal);
         assert_eq!(compare_versions("aaa", "bbb"), Ordering::Less);
     }
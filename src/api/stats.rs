@@ -0,0 +1,56 @@
+use crate::AppState;
+use crate::downloads::PackageDownloads;
+use crate::error::Result;
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use utoipa::IntoParams;
+
+/// Number of packages in the popular list when the client doesn't give a
+/// `limit`
+pub const DEFAULT_POPULAR_LIMIT: usize = 10;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PopularQuery {
+    /// Repository name (defaults to the configured default repo)
+    pub repo: Option<String>,
+    /// Architecture (defaults to the configured default arch)
+    pub arch: Option<String>,
+    /// Number of packages to return (default 10, capped by the server's
+    /// `max_list_results`)
+    pub limit: Option<usize>,
+}
+
+/// List the most downloaded packages of a repo/arch
+#[utoipa::path(
+    get,
+    path = "/packages/stats/popular",
+    params(PopularQuery),
+    responses(
+        (status = 200, description = "Packages by download count, most downloaded first", body = Vec<PackageDownloads>),
+        (status = 400, description = "Invalid repo or arch"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "packages"
+)]
+pub async fn popular_packages(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<PopularQuery>,
+) -> Result<Json<Vec<PackageDownloads>>> {
+    let repo = query
+        .repo
+        .unwrap_or_else(|| state.config.storage.default_repo.clone());
+    let arch = query
+        .arch
+        .unwrap_or_else(|| state.config.storage.default_arch.clone());
+    let arch = state.config.storage.canonical_arch(&arch);
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_POPULAR_LIMIT)
+        .min(state.config.server.max_list_results);
+
+    Ok(Json(state.downloads.popular(&repo, arch, limit).await?))
+}
@@ -1,17 +1,24 @@
 use crate::api::AppState;
 use crate::error::{Error, Result, ResultIoExt};
-use crate::metadata::{calculate_sha256, extract_pkginfo};
-use crate::models::Package;
-use crate::upload::{DEFAULT_CHUNK_SIZE, UploadSession};
+use crate::events::{EventKind, RepoEvent};
+use crate::metadata::{
+    Provenance, calculate_sha256, extract_pkginfo, extract_provenance, verify_buildinfo,
+    verify_compression,
+};
+use crate::models::{Package, PackageQuery};
+use crate::receipts::UploadReceipt;
+use crate::signing::verify_detached;
+use crate::upload::{DEFAULT_CHUNK_SIZE, DEFAULT_SESSION_EXPIRATION_SECS, UploadSession};
 use axum::{
     Json,
     body::Bytes,
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
 };
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::sync::Arc;
 use utoipa::ToSchema;
 
@@ -37,6 +44,10 @@ pub struct InitiateUploadRequest {
     /// Whether a signature file will be uploaded
     #[serde(default)]
     pub has_signature: bool,
+    /// Session lifetime in seconds (optional, defaults to 24 hours, capped by
+    /// server config)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration_secs: Option<i64>,
 }
 
 /// Response from initiating a chunked upload
@@ -88,6 +99,81 @@ pub struct ChunkInfo {
     pub checksum: String,
 }
 
+/// Response from completing an upload with `async_db_update` enabled
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QueuedUploadResponse {
+    /// The stored package
+    #[serde(flatten)]
+    pub package: Package,
+    /// Whether the repository database update is still queued
+    pub db_update_pending: bool,
+}
+
+/// Server-side state of an upload session
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UploadSessionDetails {
+    /// Upload session ID
+    pub upload_id: String,
+    /// Package filename given at initiation
+    pub filename: String,
+    /// Total file size in bytes
+    pub size: u64,
+    /// Pre-calculated SHA256 hash, if one was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Repository name
+    pub repo: String,
+    /// Architecture
+    pub arch: String,
+    /// Chunk size in bytes
+    pub chunk_size: usize,
+    /// Total number of chunks
+    pub total_chunks: u32,
+    /// Chunk numbers received so far, ascending
+    pub uploaded_chunks: Vec<u32>,
+    /// Whether a signature file will be uploaded
+    pub has_signature: bool,
+    /// Session creation timestamp
+    pub created_at: String,
+    /// Session expiration timestamp
+    pub expires_at: String,
+    /// Whether the session has expired
+    pub expired: bool,
+}
+
+/// What a client needs to resume a chunked upload
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UploadStatusResponse {
+    /// Upload session ID
+    pub upload_id: String,
+    /// Total file size in bytes
+    pub size: u64,
+    /// Pre-calculated SHA256 hash, if one was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Chunk size in bytes
+    pub chunk_size: usize,
+    /// Total number of chunks
+    pub total_chunks: u32,
+    /// Chunk numbers received so far, ascending
+    pub uploaded_chunks: Vec<u32>,
+    /// Chunk numbers still to be sent, ascending
+    pub missing_chunks: Vec<u32>,
+    /// Session expiration timestamp
+    pub expires_at: String,
+}
+
+/// Response to a `Content-Range` upload that is still missing bytes
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RangeUploadResponse {
+    /// Upload session ID
+    pub upload_id: String,
+    /// Distinct bytes received so far
+    pub received_bytes: u64,
+    /// Total file size in bytes
+    pub size: u64,
+}
+
 /// Response from aborting an upload
 #[derive(Debug, Serialize, ToSchema)]
 pub struct AbortUploadResponse {
@@ -100,11 +186,16 @@ pub struct AbortUploadResponse {
 }
 
 /// Initiate a chunked upload session
+///
+/// When the request carries a SHA256 and an identical package is already
+/// stored under that filename, no session is created and the existing
+/// package is returned with 200, so the client can skip the transfer.
 #[utoipa::path(
     post,
     path = "/packages/upload/initiate",
     request_body = InitiateUploadRequest,
     responses(
+        (status = 200, description = "Identical package already present, nothing to upload", body = Package),
         (status = 201, description = "Upload session created", body = InitiateUploadResponse),
         (status = 400, description = "Invalid request"),
         (status = 500, description = "Internal server error")
@@ -115,7 +206,7 @@ pub async fn initiate_upload(
     _user: crate::auth::AuthenticatedUser,
     State(state): State<Arc<AppState>>,
     Json(req): Json<InitiateUploadRequest>,
-) -> Result<impl IntoResponse> {
+) -> Result<Response> {
     // Validate filename
     if !req.filename.ends_with(".pkg.tar.zst") {
         return Err(Error::InvalidPackage {
@@ -147,18 +238,53 @@ pub async fn initiate_upload(
     let repo = req
         .repo
         .unwrap_or_else(|| state.config.storage.default_repo.clone());
-    let arch = req
-        .arch
-        .unwrap_or_else(|| state.config.storage.default_arch.clone());
+    let arch = req.arch.map_or_else(
+        || state.config.storage.default_arch.clone(),
+        |arch| state.config.storage.canonical_arch(&arch).to_owned(),
+    );
+    check_arch_allowed(&state, &arch)?;
     let chunk_size = req.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
 
-    // Validate chunk size (must be at least 1 byte, at most file size)
-    if chunk_size == 0 || chunk_size as u64 > req.size {
+    // Validate chunk size (must be at least 1 byte). A chunk size larger than
+    // the file means the whole file is sent as a single chunk.
+    if chunk_size == 0 {
         return Err(Error::InvalidPackage {
             pkgname: format!("Invalid chunk size: {}", chunk_size),
         });
     }
 
+    let max_expiration_secs = state.config.server.max_upload_expiration_secs;
+    let expiration_secs = req
+        .expiration_secs
+        .unwrap_or(DEFAULT_SESSION_EXPIRATION_SECS.min(max_expiration_secs));
+    if !(1..=max_expiration_secs).contains(&expiration_secs) {
+        return Err(Error::InvalidPackage {
+            pkgname: format!(
+                "Invalid expiration: {} seconds (must be between 1 and {})",
+                expiration_secs, max_expiration_secs
+            ),
+        });
+    }
+
+    // Skip the transfer entirely if this exact package is already stored,
+    // signed or unsigned as this upload would be. Only a full digest is
+    // compared: a stored package without one never matches.
+    if let Some(sha256) = req.sha256.as_deref().filter(|s| is_sha256_digest(s)) {
+        let metadata_name = req.filename.trim_end_matches(".pkg.tar.zst");
+        if let Ok(existing) = state.storage.load_package(&repo, metadata_name).await
+            && existing.sha256.eq_ignore_ascii_case(sha256)
+            && existing.signed == req.has_signature
+        {
+            tracing::info!(
+                package = %existing.name,
+                version = %existing.version,
+                repo = %existing.repo,
+                "Identical package already present, no upload session created"
+            );
+            return Ok((StatusCode::OK, Json(existing)).into_response());
+        }
+    }
+
     // Create upload session
     let mut builder = UploadSession::builder()
         .filename(req.filename)
@@ -166,7 +292,8 @@ pub async fn initiate_upload(
         .repo(repo)
         .arch(arch)
         .chunk_size(chunk_size)
-        .has_signature(req.has_signature);
+        .has_signature(req.has_signature)
+        .expiration_secs(expiration_secs);
 
     if let Some(sha256) = req.sha256 {
         builder = builder.sha256(sha256);
@@ -183,7 +310,7 @@ pub async fn initiate_upload(
 
     state.upload_store.create_session(session).await?;
 
-    Ok((StatusCode::CREATED, Json(response)))
+    Ok((StatusCode::CREATED, Json(response)).into_response())
 }
 
 /// Upload a single chunk
@@ -286,7 +413,9 @@ pub async fn upload_signature(
     ),
     request_body = CompleteUploadRequest,
     responses(
+        (status = 200, description = "Identical package already stored, nothing changed", body = Package),
         (status = 201, description = "Package uploaded successfully", body = Package),
+        (status = 202, description = "Package uploaded, database update queued (async_db_update)", body = QueuedUploadResponse),
         (status = 400, description = "Invalid upload or missing chunks"),
         (status = 404, description = "Upload session not found"),
         (status = 409, description = "Package already exists"),
@@ -295,7 +424,7 @@ pub async fn upload_signature(
     tag = "chunked-uploads"
 )]
 pub async fn complete_upload(
-    _user: crate::auth::AuthenticatedUser,
+    user: crate::auth::AuthenticatedUser,
     State(state): State<Arc<AppState>>,
     Path(upload_id): Path<String>,
     Json(req): Json<CompleteUploadRequest>,
@@ -335,15 +464,148 @@ pub async fn complete_upload(
     // Assemble chunks to disk
     let assembled_path = state.upload_store.assemble_chunks(&upload_id).await?;
 
+    finalize_upload(&state, &session, assembled_path, &user.username).await
+}
+
+/// Turn a fully received upload into a stored package: extract and verify
+/// it, store it with its signature and sidecars, and queue the db update.
+/// Failures are recorded in the event log.
+///
+/// On failure the session and its chunks are kept so the client can retry
+/// `complete` (e.g. after a conflicting package was deleted) until the
+/// session expires and the cleanup task removes it. A package rejected as
+/// invalid or with a bad signature won't get any better on retry, so its
+/// session is discarded at once unless `keep_invalid_uploads` is set.
+async fn finalize_upload(
+    state: &AppState,
+    session: &UploadSession,
+    assembled_path: PathBuf,
+    user: &str,
+) -> Result<Response> {
+    let result = store_upload(state, session, assembled_path, user).await;
+
+    if let Err(e) = &result {
+        crate::metrics::record_upload_failed(&session.repo);
+        state.events.record(
+            RepoEvent::new(EventKind::UploadFailed, &session.repo)
+                .arch(&session.arch)
+                .user(user)
+                .detail(format!("{}: {}", session.filename, e)),
+        );
+
+        if matches!(
+            e,
+            Error::InvalidPackage { .. } | Error::SignatureInvalid { .. }
+        ) && !state.config.server.keep_invalid_uploads
+        {
+            let upload_id = &session.upload_id;
+            if let Err(e) = state.upload_store.delete_session(upload_id).await {
+                tracing::warn!("Failed to cleanup upload session {}: {}", upload_id, e);
+            }
+        }
+    }
+
+    result
+}
+
+/// Whether `s` is a full SHA256 digest: 64 hex digits
+fn is_sha256_digest(s: &str) -> bool {
+    s.len() == 64 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Reject an upload for an arch outside `storage.allowed_archs`
+fn check_arch_allowed(state: &AppState, arch: &str) -> Result<()> {
+    if state.config.storage.arch_allowed(arch) {
+        return Ok(());
+    }
+    Err(Error::InvalidPackage {
+        pkgname: format!(
+            "Architecture {} is not allowed (allowed: {})",
+            arch,
+            state.config.storage.allowed_archs.join(", ")
+        ),
+    })
+}
+
+/// The uploaded bytes failed to decompress or unpack: that's a bad package
+/// (400), not a server-side IO failure
+fn corrupt_archive_error(e: Error) -> Error {
+    match e {
+        Error::Io { error, .. } => Error::InvalidPackage {
+            pkgname: format!("Corrupt package archive: {}", error),
+        },
+        e => e,
+    }
+}
+
+async fn store_upload(
+    state: &AppState,
+    session: &UploadSession,
+    assembled_path: PathBuf,
+    user: &str,
+) -> Result<Response> {
+    let upload_id = &session.upload_id;
+
+    let signature = if session.has_signature {
+        let signature = state.upload_store.get_signature(upload_id).await?;
+        if signature.is_none() {
+            tracing::warn!(upload_id, "Session indicated signature but none found");
+        }
+        signature
+    } else {
+        None
+    };
+    let keyring = if state.config.storage.requires_signature(&session.repo) {
+        if signature.is_none() {
+            return Err(Error::SignatureInvalid {
+                reason: "this repository requires signed packages, but no signature was uploaded"
+                    .to_string(),
+            });
+        }
+        let keyring = state
+            .config
+            .storage
+            .keyring_for(&session.repo)
+            .ok_or_else(|| Error::Config {
+                msg: format!("No keyring to verify signatures for repo {}", session.repo),
+            })?;
+        Some(keyring.clone())
+    } else {
+        None
+    };
+    let verifying = keyring.is_some();
+
     // Read assembled file for processing (extract PKGINFO and calculate SHA256)
     // This is done in a blocking task to avoid blocking the async runtime
     let assembled_path_clone = assembled_path.clone();
-    let (pkginfo, sha256, size) = tokio::task::spawn_blocking(move || {
+    let extract_provenance_enabled = state.config.storage.extract_provenance;
+    let strict_provenance = state.config.storage.strict_provenance;
+    let verify_compression_enabled = state.config.storage.verify_compression;
+    let lossy_pkginfo = state.config.storage.lossy_pkginfo;
+    let filename = session.filename.clone();
+    let sig_data = signature.clone();
+    let (pkginfo, sha256, size, provenance) = tokio::task::spawn_blocking(move || {
         let package_data = std::fs::read(&assembled_path_clone)?;
-        let pkginfo = extract_pkginfo(&package_data)?;
+        if let (Some(keyring), Some(sig)) = (&keyring, &sig_data) {
+            let fingerprint = verify_detached(&package_data, sig, keyring)?;
+            tracing::info!(filename, fingerprint, "Package signature verified");
+        }
+        if verify_compression_enabled {
+            verify_compression(&filename, &package_data)?;
+        }
+        let pkginfo =
+            extract_pkginfo(&package_data, lossy_pkginfo).map_err(corrupt_archive_error)?;
         let sha256 = calculate_sha256(&package_data);
         let size = package_data.len() as u64;
-        Ok::<_, Error>((pkginfo, sha256, size))
+        let provenance = if extract_provenance_enabled {
+            extract_provenance(&package_data).map_err(corrupt_archive_error)?
+        } else {
+            Provenance::default()
+        };
+        if strict_provenance && let Some(buildinfo) = &provenance.buildinfo {
+            verify_buildinfo(buildinfo, &pkginfo)?;
+        }
+        Ok::<_, Error>((pkginfo, sha256, size, provenance))
     })
     .await
     .map_err(|e| std::io::Error::other(format!("Task join error: {}", e)))??;
@@ -354,6 +616,30 @@ pub async fn complete_upload(
         pkginfo.pkgname, pkginfo.pkgver, pkginfo.arch
     );
 
+    if filename != session.filename {
+        if state.config.storage.verify_filename {
+            tracing::warn!(
+                declared = %session.filename,
+                pkginfo = %filename,
+                "Rejecting upload whose filename disagrees with its .PKGINFO"
+            );
+            return Err(Error::InvalidPackage {
+                pkgname: format!(
+                    "Filename {} does not match package contents ({})",
+                    session.filename, filename
+                ),
+            });
+        }
+        tracing::warn!(
+            declared = %session.filename,
+            pkginfo = %filename,
+            "Uploaded filename disagrees with its .PKGINFO, storing as the latter"
+        );
+    }
+
+    // The .PKGINFO arch is what gets stored, whatever arch was requested
+    check_arch_allowed(state, &pkginfo.arch)?;
+
     // Create package record
     let package = Package {
         name: pkginfo.pkgname,
@@ -364,39 +650,100 @@ pub async fn complete_upload(
         sha256,
         size,
         created_at: Utc::now(),
+        signed: signature.is_some(),
+        // Only checked against the keyring when the repo requires signatures;
+        // otherwise signatures are stored as uploaded
+        signature_verified: signature.is_some() && verifying,
+        license: pkginfo.license,
     };
 
+    // Re-uploading identical bytes (e.g. an idempotent CI re-run) is a no-op:
+    // skip the store and the db update and report the existing package. A
+    // signature added or dropped is a change, and conflicts like any other.
+    let metadata_name = package.filename.trim_end_matches(".pkg.tar.zst");
+    if let Ok(existing) = state
+        .storage
+        .load_package(&package.repo, metadata_name)
+        .await
+        && existing.sha256 == package.sha256
+        && existing.signed == package.signed
+    {
+        tracing::info!(
+            package = %existing.name,
+            version = %existing.version,
+            repo = %existing.repo,
+            "Uploaded package is identical to stored package, nothing to do"
+        );
+
+        save_receipt(state, session, &existing, user).await;
+        if let Err(e) = state.upload_store.delete_session(upload_id).await {
+            tracing::warn!("Failed to cleanup upload session {}: {}", upload_id, e);
+        }
+
+        return Ok((StatusCode::OK, Json(existing)).into_response());
+    }
+
     // Move assembled file to permanent storage (without loading into memory)
     state
         .storage
         .store_package_from_path(&package, &assembled_path)
         .await?;
 
+    tracing::info!(
+        user,
+        package = %package.name,
+        version = %package.version,
+        repo = %package.repo,
+        arch = %package.arch,
+        filename = %package.filename,
+        size = package.size,
+        signed = package.signed,
+        "Package uploaded"
+    );
+
     // Record upload metrics
     crate::metrics::record_upload_completed(&package.repo);
     crate::metrics::record_upload_size(&package.repo, package.size);
+    state
+        .events
+        .record(RepoEvent::for_package(EventKind::Upload, &package).user(user));
+    save_receipt(state, session, &package, user).await;
 
     // Store signature if present
-    if session.has_signature {
-        if let Some(sig_data) = state.upload_store.get_signature(&upload_id).await? {
-            let sig_filename = format!("{}.sig", package.filename);
-            let sig_path = state.storage.package_path(&package.repo, &sig_filename)?;
+    if let Some(sig_data) = signature {
+        let sig_filename = format!("{}.sig", package.filename);
+        let sig_path = state.storage.package_path(&package.repo, &sig_filename)?;
+
+        tokio::fs::write(&sig_path, &sig_data)
+            .await
+            .map_io_err(&sig_path)?;
+    }
 
-            tokio::fs::write(&sig_path, &sig_data)
+    // Store provenance sidecars if extracted
+    for (suffix, content) in [
+        ("BUILDINFO", &provenance.buildinfo),
+        ("MTREE", &provenance.mtree),
+    ] {
+        if let Some(content) = content {
+            let sidecar_filename = format!("{}.{}", package.filename, suffix);
+            let sidecar_path = state
+                .storage
+                .package_path(&package.repo, &sidecar_filename)?;
+
+            tokio::fs::write(&sidecar_path, content)
                 .await
-                .map_io_err(&sig_path)?;
-        } else {
-            tracing::warn!(upload_id, "Session indicated signature but none found");
+                .map_io_err(&sidecar_path)?;
         }
     }
 
     // Auto-cleanup old versions if enabled
     if state.config.storage.auto_cleanup_enabled {
         let deleted = crate::storage::cleanup_old_versions(
-            &state.storage,
+            state.storage.as_ref(),
             &package.name,
             &package.repo,
             &package.arch,
+            &state.config.storage.cleanup_policy,
         )
         .await
         .inspect_err(|e| {
@@ -412,6 +759,20 @@ pub async fn complete_upload(
 
         if !deleted.is_empty() {
             crate::metrics::record_cleanup_versions_deleted(&package.repo, deleted.len() as u64);
+            state.events.record(
+                RepoEvent::new(EventKind::Cleanup, &package.repo)
+                    .arch(&package.arch)
+                    .package(&package.name)
+                    .user(user)
+                    .detail(format!(
+                        "deleted versions: {}",
+                        deleted
+                            .iter()
+                            .map(|p| p.version.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )),
+            );
             tracing::info!(
                 package = %package.name,
                 repo = %package.repo,
@@ -436,11 +797,310 @@ pub async fn complete_upload(
         .await;
 
     // Cleanup upload session
-    if let Err(e) = state.upload_store.delete_session(&upload_id).await {
+    if let Err(e) = state.upload_store.delete_session(upload_id).await {
         tracing::warn!("Failed to cleanup upload session {}: {}", upload_id, e);
     }
 
-    Ok((StatusCode::CREATED, Json(package)))
+    if state.config.storage.async_db_update {
+        let response = QueuedUploadResponse {
+            db_update_pending: !state
+                .db_update
+                .generation(&package.repo, update_arch)
+                .is_current(),
+            package,
+        };
+        return Ok((StatusCode::ACCEPTED, Json(response)).into_response());
+    }
+
+    Ok((StatusCode::CREATED, Json(package)).into_response())
+}
+
+/// Persist the receipt of a completed upload. The package is already stored
+/// by now, so a failure here is logged rather than failing the upload.
+async fn save_receipt(state: &AppState, session: &UploadSession, package: &Package, user: &str) {
+    let receipt = UploadReceipt::new(session, package, user);
+    if let Err(e) = state.receipts.save(&receipt).await {
+        tracing::warn!(
+            upload_id = %session.upload_id,
+            error = %e,
+            "Failed to write upload receipt"
+        );
+    }
+}
+
+/// Get the receipt of a completed upload
+#[utoipa::path(
+    get,
+    path = "/admin/receipts/{upload_id}",
+    params(
+        ("upload_id" = String, Path, description = "Upload session ID")
+    ),
+    responses(
+        (status = 200, description = "Upload receipt", body = UploadReceipt),
+        (status = 400, description = "Invalid upload ID"),
+        (status = 404, description = "No receipt for this upload")
+    ),
+    tag = "admin"
+)]
+pub async fn get_receipt(
+    _user: crate::auth::AuthenticatedUser,
+    State(state): State<Arc<AppState>>,
+    Path(upload_id): Path<String>,
+) -> Result<Json<UploadReceipt>> {
+    Ok(Json(state.receipts.load(&upload_id).await?))
+}
+
+/// Get the details of an upload session
+#[utoipa::path(
+    get,
+    path = "/packages/upload/{upload_id}",
+    params(
+        ("upload_id" = String, Path, description = "Upload session ID")
+    ),
+    responses(
+        (status = 200, description = "Upload session details, including expired sessions", body = UploadSessionDetails),
+        (status = 404, description = "Upload session not found")
+    ),
+    tag = "chunked-uploads"
+)]
+pub async fn get_upload_session(
+    _user: crate::auth::AuthenticatedUser,
+    State(state): State<Arc<AppState>>,
+    Path(upload_id): Path<String>,
+) -> Result<impl IntoResponse> {
+    let session = state
+        .upload_store
+        .get_session(&upload_id)
+        .await
+        .map_err(|_| Error::PackageNotFound {
+            pkgname: format!("upload session {}", upload_id),
+        })?;
+
+    let mut uploaded_chunks: Vec<u32> = session.uploaded_chunks.iter().copied().collect();
+    uploaded_chunks.sort_unstable();
+
+    Ok(Json(UploadSessionDetails {
+        expired: session.is_expired(),
+        upload_id: session.upload_id,
+        filename: session.filename,
+        size: session.file_size,
+        sha256: session.sha256,
+        repo: session.repo,
+        arch: session.arch,
+        chunk_size: session.chunk_size,
+        total_chunks: session.total_chunks,
+        uploaded_chunks,
+        has_signature: session.has_signature,
+        created_at: session.created_at.to_rfc3339(),
+        expires_at: session.expires_at.to_rfc3339(),
+    }))
+}
+
+/// Get the chunks an upload session still needs, for resuming it
+#[utoipa::path(
+    get,
+    path = "/packages/upload/{upload_id}/status",
+    params(
+        ("upload_id" = String, Path, description = "Upload session ID")
+    ),
+    responses(
+        (status = 200, description = "Received and missing chunks", body = UploadStatusResponse),
+        (status = 404, description = "Upload session not found"),
+        (status = 410, description = "Upload session has expired")
+    ),
+    tag = "chunked-uploads"
+)]
+pub async fn get_upload_status(
+    _user: crate::auth::AuthenticatedUser,
+    State(state): State<Arc<AppState>>,
+    Path(upload_id): Path<String>,
+) -> Result<Json<UploadStatusResponse>> {
+    let session = state
+        .upload_store
+        .get_session(&upload_id)
+        .await
+        .map_err(|_| Error::PackageNotFound {
+            pkgname: format!("upload session {}", upload_id),
+        })?;
+
+    if session.is_expired() {
+        return Err(Error::Gone {
+            msg: format!(
+                "Upload session {} has expired; start a new upload",
+                upload_id
+            ),
+        });
+    }
+
+    let mut uploaded_chunks: Vec<u32> = session.uploaded_chunks.iter().copied().collect();
+    uploaded_chunks.sort_unstable();
+
+    Ok(Json(UploadStatusResponse {
+        missing_chunks: session.missing_chunks(),
+        upload_id: session.upload_id,
+        size: session.file_size,
+        sha256: session.sha256,
+        chunk_size: session.chunk_size,
+        total_chunks: session.total_chunks,
+        uploaded_chunks,
+        expires_at: session.expires_at.to_rfc3339(),
+    }))
+}
+
+/// Parse `Content-Range: bytes start-end/total` into a half-open
+/// `(start, end, total)`
+fn parse_content_range(value: &str) -> Option<(u64, u64, u64)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let start: u64 = start.trim().parse().ok()?;
+    let end: u64 = end.trim().parse().ok()?;
+    let total: u64 = total.trim().parse().ok()?;
+
+    (start <= end && end < total).then_some((start, end + 1, total))
+}
+
+/// Upload a package in pieces with `PUT` and `Content-Range`
+///
+/// Each request carries one byte range of the file. Ranges may arrive in any
+/// order and be re-sent; once the whole file is covered it is processed like
+/// a completed chunked upload.
+#[utoipa::path(
+    put,
+    path = "/packages/{name}",
+    params(
+        ("name" = String, Path, description = "Package filename (e.g., \"package-1.0.0-1-x86_64.pkg.tar.zst\")"),
+        ("repo" = Option<String>, Query, description = "Repository name (defaults from config)"),
+        ("arch" = Option<String>, Query, description = "Architecture (defaults from config)"),
+        ("Content-Range" = String, Header, description = "Byte range in this request: `bytes start-end/total`")
+    ),
+    request_body(content = Vec<u8>, content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "Identical package already stored, nothing changed", body = Package),
+        (status = 201, description = "Final range received, package uploaded", body = Package),
+        (status = 202, description = "Package uploaded, database update queued (async_db_update)", body = QueuedUploadResponse),
+        (status = 308, description = "Range stored, more bytes expected", body = RangeUploadResponse,
+            headers(
+                ("Range" = String, description = "Contiguous bytes received from the start, `bytes=0-N`")
+            )
+        ),
+        (status = 400, description = "Invalid range or package"),
+        (status = 409, description = "Package already exists"),
+        (status = 413, description = "File too large")
+    ),
+    tag = "chunked-uploads"
+)]
+pub async fn range_upload(
+    user: crate::auth::AuthenticatedUser,
+    State(state): State<Arc<AppState>>,
+    Path(filename): Path<String>,
+    Query(query): Query<PackageQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response> {
+    let (start, end, total) = headers
+        .get(header::CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_content_range)
+        .ok_or_else(|| Error::InvalidPackage {
+            pkgname: "Missing or invalid Content-Range (expected bytes start-end/total)"
+                .to_string(),
+        })?;
+
+    if body.len() as u64 != end - start {
+        return Err(Error::InvalidPackage {
+            pkgname: format!(
+                "Body length {} does not match Content-Range {}-{}",
+                body.len(),
+                start,
+                end - 1
+            ),
+        });
+    }
+
+    if !filename.ends_with(".pkg.tar.zst") {
+        return Err(Error::InvalidPackage {
+            pkgname: format!("Not a .pkg.tar.zst package: {}", filename),
+        });
+    }
+
+    let max_size = state.config.server.max_payload_size.as_u64();
+    if total > max_size {
+        return Err(Error::PayloadTooLarge {
+            msg: format!(
+                "File size {} exceeds maximum allowed size of {}",
+                byte_unit::Byte::from_u64(total),
+                state.config.server.max_payload_size
+            ),
+        });
+    }
+
+    let repo = query
+        .repo
+        .unwrap_or_else(|| state.config.storage.default_repo.clone());
+
+    let session = state
+        .upload_store
+        .find_or_create_range_session(&repo, &filename, || {
+            let arch = query.arch.map_or_else(
+                || state.config.storage.default_arch.clone(),
+                |arch| state.config.storage.canonical_arch(&arch).to_owned(),
+            );
+            check_arch_allowed(&state, &arch)?;
+            let max_expiration_secs = state.config.server.max_upload_expiration_secs;
+            let mut session = UploadSession::builder()
+                .filename(filename.clone())
+                .file_size(total)
+                .repo(repo.clone())
+                .arch(arch)
+                .expiration_secs(DEFAULT_SESSION_EXPIRATION_SECS.min(max_expiration_secs))
+                .build();
+            session.received_ranges = Some(Vec::new());
+            Ok(session)
+        })
+        .await?;
+    if session.file_size != total {
+        return Err(Error::InvalidPackage {
+            pkgname: format!(
+                "Content-Range total {} does not match upload size {}",
+                total, session.file_size
+            ),
+        });
+    }
+
+    let session = state
+        .upload_store
+        .store_range(&session.upload_id, start, &body)
+        .await?;
+
+    if !session.is_complete() {
+        let received_prefix = session
+            .received_ranges
+            .iter()
+            .flatten()
+            .find(|(start, _)| *start == 0)
+            .map(|(_, end)| *end);
+
+        let mut response = (
+            StatusCode::PERMANENT_REDIRECT,
+            Json(RangeUploadResponse {
+                upload_id: session.upload_id.clone(),
+                received_bytes: session.received_bytes(),
+                size: session.file_size,
+            }),
+        )
+            .into_response();
+        if let Some(end) = received_prefix {
+            response.headers_mut().insert(
+                header::RANGE,
+                HeaderValue::from_str(&format!("bytes=0-{}", end - 1))
+                    .expect("range header is ASCII"),
+            );
+        }
+        return Ok(response);
+    }
+
+    let part_path = state.upload_store.range_upload_path(&session.upload_id)?;
+    finalize_upload(&state, &session, part_path, &user.username).await
 }
 
 /// Abort a chunked upload
@@ -474,3 +1134,24 @@ pub async fn abort_upload(
 
     Ok(Json(response))
 }
+
+/// Legacy single-request upload
+///
+/// The multipart `POST /api/packages` upload used by `sw1nn-pkg-upload` has
+/// been replaced by the chunked upload API. Old clients get a clear 410
+/// instead of a bare 405.
+#[utoipa::path(
+    post,
+    path = "/packages",
+    responses(
+        (status = 410, description = "Legacy upload removed; use the chunked upload API")
+    ),
+    tag = "packages"
+)]
+pub async fn legacy_upload() -> Error {
+    Error::Gone {
+        msg: "Single-request uploads are no longer supported. Use the chunked upload API \
+              (POST /api/packages/upload/initiate) or sw1nn-pkg-ctl upload"
+            .to_string(),
+    }
+}
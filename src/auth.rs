@@ -1,11 +1,14 @@
 use crate::api::AppState;
-use crate::config::AuthConfig;
+use crate::config::{ApiKey, ApiKeyScope, AuthConfig, GitHubApiConfig};
 use crate::error::Error;
 use axum::extract::FromRequestParts;
+use axum::http::HeaderMap;
 use axum::http::request::Parts;
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
 /// JWT claims
 #[derive(Debug, Serialize, Deserialize)]
@@ -22,7 +25,7 @@ pub struct Claims {
     pub token_type: String,
 }
 
-/// Authenticated user extracted from JWT
+/// Authenticated user extracted from a JWT or API key
 #[derive(Debug, Clone)]
 pub struct AuthenticatedUser {
     pub username: String,
@@ -31,6 +34,128 @@ pub struct AuthenticatedUser {
 
 const ISSUER: &str = "sw1nn-pkg-repo";
 
+/// Usernames allowed to authenticate
+///
+/// Taken from inline `allowed_users`, or read from `allowed_users_file` and
+/// kept current by [`Allowlist::spawn_reload_task`].
+#[derive(Debug, Clone, Default)]
+pub struct Allowlist {
+    users: Arc<RwLock<Vec<String>>>,
+    file: Option<PathBuf>,
+}
+
+impl Allowlist {
+    pub fn from_config(auth_config: Option<&AuthConfig>) -> Self {
+        let Some(auth_config) = auth_config else {
+            return Self::default();
+        };
+
+        let Some(file) = auth_config.allowed_users_file.clone() else {
+            return Self {
+                users: Arc::new(RwLock::new(auth_config.allowed_users.clone())),
+                file: None,
+            };
+        };
+
+        let users = match std::fs::read_to_string(&file) {
+            Ok(content) => parse_allowed_users(&content),
+            Err(e) => {
+                tracing::error!(
+                    path = %file.display(),
+                    error = %e,
+                    "Failed to read allowed users file, no users allowed until it can be read"
+                );
+                Vec::new()
+            }
+        };
+        tracing::info!(path = %file.display(), count = users.len(), "Loaded allowed users");
+
+        Self {
+            users: Arc::new(RwLock::new(users)),
+            file: Some(file),
+        }
+    }
+
+    pub fn contains(&self, username: &str) -> bool {
+        self.users
+            .read()
+            .expect("allowlist lock poisoned")
+            .iter()
+            .any(|u| u == username)
+    }
+
+    /// Re-read the allowed users file. Keeps the last known list if the file
+    /// can't be read.
+    pub async fn reload(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+
+        match tokio::fs::read_to_string(file).await {
+            Ok(content) => {
+                let users = parse_allowed_users(&content);
+                let mut current = self.users.write().expect("allowlist lock poisoned");
+                if *current != users {
+                    tracing::info!(path = %file.display(), count = users.len(), "Reloaded allowed users");
+                    *current = users;
+                }
+            }
+            Err(e) => {
+                tracing::warn!(
+                    path = %file.display(),
+                    error = %e,
+                    "Failed to reload allowed users file, keeping last known list"
+                );
+            }
+        }
+    }
+
+    /// Spawn a background task re-reading the allowed users file every
+    /// `interval_secs` and on SIGHUP. Does nothing for an inline list.
+    pub fn spawn_reload_task(&self, interval_secs: u64) {
+        if self.file.is_none() {
+            return;
+        }
+
+        let allowlist = self.clone();
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(std::time::Duration::from_secs(interval_secs.max(1)));
+            interval.tick().await; // First tick completes immediately
+
+            #[cfg(unix)]
+            let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+                .expect("failed to install SIGHUP handler");
+
+            loop {
+                #[cfg(unix)]
+                tokio::select! {
+                    _ = interval.tick() => {},
+                    _ = hangup.recv() => {
+                        tracing::info!("SIGHUP received, reloading allowed users");
+                    },
+                }
+
+                #[cfg(not(unix))]
+                interval.tick().await;
+
+                allowlist.reload().await;
+            }
+        });
+    }
+}
+
+/// Parse an allowed users file: one username per line, blank lines and `#`
+/// comments ignored
+fn parse_allowed_users(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
 /// Create a JWT for the given username
 pub fn create_jwt(
     auth_config: &AuthConfig,
@@ -72,6 +197,46 @@ pub fn validate_jwt(auth_config: &AuthConfig, token: &str) -> Result<Claims, Err
     })
 }
 
+/// The user a request's API key or JWT authenticates as, without checking
+/// the allowlist; `None` without valid credentials
+pub fn request_username(auth_config: &AuthConfig, headers: &HeaderMap) -> Option<String> {
+    if let Some(key) = headers.get("X-API-Key") {
+        let key = key.to_str().ok()?;
+        return find_api_key(auth_config, key).map(|api_key| api_key.username.clone());
+    }
+
+    let token = headers
+        .get("Authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+    validate_jwt(auth_config, token)
+        .ok()
+        .map(|claims| claims.sub)
+}
+
+/// Look up the API key presented in an `X-API-Key` header
+///
+/// Keys are compared by SHA256 in constant time, and every configured key is
+/// checked, so the response time doesn't reveal how close a guess was.
+pub fn find_api_key<'a>(auth_config: &'a AuthConfig, key: &str) -> Option<&'a ApiKey> {
+    use sha2::Digest;
+
+    let digest = format!("{:x}", sha2::Sha256::digest(key.as_bytes()));
+    auth_config.api_keys.iter().fold(None, |found, api_key| {
+        let matches = constant_time_eq(
+            digest.as_bytes(),
+            api_key.key_sha256.to_ascii_lowercase().as_bytes(),
+        );
+        found.or(matches.then_some(api_key))
+    })
+}
+
+/// Compare two byte strings without an early exit on the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
 // -- GitHub Device Flow --
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -95,120 +260,242 @@ pub struct GitHubUser {
     pub login: String,
 }
 
-/// Request a device code from GitHub
-pub async fn request_device_code(
-    http_client: &reqwest::Client,
-    client_id: &str,
-) -> Result<DeviceCodeResponse, Error> {
-    let response = http_client
-        .post("https://github.com/login/device/code")
-        .header("Accept", "application/json")
-        .form(&[("client_id", client_id), ("scope", "read:user")])
-        .send()
-        .await
-        .map_err(|e| Error::GitHubApi {
-            msg: format!("failed to request device code: {e}"),
-        })?;
-
-    if !response.status().is_success() {
-        let body = response.text().await.unwrap_or_default();
-        return Err(Error::GitHubApi {
-            msg: format!("device code request failed: {body}"),
-        });
-    }
-
-    response
-        .json::<DeviceCodeResponse>()
-        .await
-        .map_err(|e| Error::GitHubApi {
-            msg: format!("failed to parse device code response: {e}"),
-        })
+/// Outcome of one device token poll
+#[derive(Debug)]
+pub enum DevicePoll {
+    /// The user hasn't authorized yet. `interval` is set when GitHub asked
+    /// to poll more slowly, to the new minimum seconds between polls.
+    Pending {
+        interval: Option<u64>,
+    },
+    Authorized(GitHubAccessToken),
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
 enum PollResponse {
     Success(GitHubAccessToken),
-    Error { error: String },
+    Error {
+        error: String,
+        #[serde(default)]
+        interval: Option<u64>,
+    },
 }
 
-/// Poll GitHub for the access token (returns Ok(None) if still pending)
-pub async fn poll_device_token(
-    http_client: &reqwest::Client,
-    client_id: &str,
-    device_code: &str,
-) -> Result<Option<GitHubAccessToken>, Error> {
-    let response = http_client
-        .post("https://github.com/login/oauth/access_token")
-        .header("Accept", "application/json")
-        .form(&[
-            ("client_id", client_id),
-            ("device_code", device_code),
-            ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
-        ])
-        .send()
-        .await
-        .map_err(|e| Error::GitHubApi {
-            msg: format!("failed to poll for token: {e}"),
-        })?;
+#[derive(Debug, Default)]
+struct CircuitBreaker {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
 
-    if !response.status().is_success() {
-        let body = response.text().await.unwrap_or_default();
-        return Err(Error::GitHubApi {
-            msg: format!("token poll failed: {body}"),
-        });
+/// Client for the GitHub calls of the device flow
+///
+/// Idempotent GETs are retried with exponential backoff after network
+/// errors, 5xx and 429 responses. Once `breaker_threshold` calls in a row
+/// have failed that way, every call fails fast with
+/// [`Error::GitHubUnavailable`] until `breaker_cooldown_secs` has passed;
+/// the first call after that decides whether it closes again.
+#[derive(Debug, Clone)]
+pub struct GitHubClient {
+    http: reqwest::Client,
+    config: GitHubApiConfig,
+    breaker: Arc<Mutex<CircuitBreaker>>,
+}
+
+impl GitHubClient {
+    pub fn new(config: GitHubApiConfig) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            config,
+            breaker: Arc::default(),
+        }
     }
 
-    let poll_response: PollResponse = response.json().await.map_err(|e| Error::GitHubApi {
-        msg: format!("failed to parse poll response: {e}"),
-    })?;
+    pub fn from_config(auth_config: Option<&AuthConfig>) -> Self {
+        Self::new(auth_config.map(|c| c.github.clone()).unwrap_or_default())
+    }
 
-    match poll_response {
-        PollResponse::Success(token) => Ok(Some(token)),
-        PollResponse::Error { error } => match error.as_str() {
-            "authorization_pending" | "slow_down" => Ok(None),
-            "expired_token" => Err(Error::GitHubApi {
-                msg: "device code expired, please restart login".to_string(),
-            }),
-            "access_denied" => Err(Error::Forbidden {
-                reason: "user denied the authorization request".to_string(),
+    fn check_breaker(&self) -> Result<(), Error> {
+        let breaker = self.breaker.lock().expect("circuit breaker lock poisoned");
+        match breaker.open_until {
+            Some(until) if until > Instant::now() => Err(Error::GitHubUnavailable {
+                retry_after_secs: (until - Instant::now()).as_secs() + 1,
             }),
-            _ => Err(Error::GitHubApi {
-                msg: format!("unexpected error from GitHub: {error}"),
-            }),
-        },
+            _ => Ok(()),
+        }
     }
-}
 
-/// Get the authenticated GitHub user's login name
-pub async fn get_github_user(
-    http_client: &reqwest::Client,
-    access_token: &str,
-) -> Result<GitHubUser, Error> {
-    let response = http_client
-        .get("https://api.github.com/user")
-        .header("Authorization", format!("Bearer {access_token}"))
-        .header("User-Agent", "sw1nn-pkg-repo")
-        .header("Accept", "application/json")
-        .send()
-        .await
-        .map_err(|e| Error::GitHubApi {
-            msg: format!("failed to get GitHub user: {e}"),
+    fn record_outcome(&self, reachable: bool) {
+        let mut breaker = self.breaker.lock().expect("circuit breaker lock poisoned");
+        if reachable {
+            *breaker = CircuitBreaker::default();
+            return;
+        }
+
+        breaker.consecutive_failures += 1;
+        if breaker.consecutive_failures >= self.config.breaker_threshold {
+            tracing::warn!(
+                failures = breaker.consecutive_failures,
+                cooldown_secs = self.config.breaker_cooldown_secs,
+                "GitHub unreachable, failing GitHub calls fast"
+            );
+            breaker.open_until =
+                Some(Instant::now() + Duration::from_secs(self.config.breaker_cooldown_secs));
+        }
+    }
+
+    /// Send a request through the circuit breaker, retrying transient
+    /// failures when `idempotent`
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        idempotent: bool,
+        context: &str,
+    ) -> Result<reqwest::Response, Error> {
+        let retries = if idempotent {
+            self.config.max_retries
+        } else {
+            0
+        };
+        let mut attempt = 0;
+
+        loop {
+            self.check_breaker()?;
+
+            let this_request = request.try_clone().ok_or_else(|| Error::GitHubApi {
+                msg: format!("{context}: request can't be retried"),
+            })?;
+            let error = match this_request.send().await {
+                Ok(response)
+                    if response.status().is_server_error()
+                        || response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS =>
+                {
+                    format!("{context}: HTTP {}", response.status())
+                }
+                Ok(response) => {
+                    self.record_outcome(true);
+                    return Ok(response);
+                }
+                Err(e) => format!("{context}: {e}"),
+            };
+            self.record_outcome(false);
+
+            if attempt >= retries {
+                return Err(Error::GitHubApi { msg: error });
+            }
+
+            let backoff = Duration::from_millis(self.config.retry_backoff_ms << attempt.min(16));
+            tracing::warn!(
+                error = %error,
+                attempt = attempt + 1,
+                backoff_ms = backoff.as_millis() as u64,
+                "GitHub call failed, retrying"
+            );
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+        }
+    }
+
+    /// Request a device code from GitHub
+    pub async fn request_device_code(&self, client_id: &str) -> Result<DeviceCodeResponse, Error> {
+        let request = self
+            .http
+            .post(format!("{}/login/device/code", self.config.url))
+            .header("Accept", "application/json")
+            .form(&[("client_id", client_id), ("scope", "read:user")]);
+        let response = self
+            .send(request, false, "failed to request device code")
+            .await?;
+
+        if !response.status().is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::GitHubApi {
+                msg: format!("device code request failed: {body}"),
+            });
+        }
+
+        response
+            .json::<DeviceCodeResponse>()
+            .await
+            .map_err(|e| Error::GitHubApi {
+                msg: format!("failed to parse device code response: {e}"),
+            })
+    }
+
+    /// Poll GitHub for the access token
+    pub async fn poll_device_token(
+        &self,
+        client_id: &str,
+        device_code: &str,
+    ) -> Result<DevicePoll, Error> {
+        let request = self
+            .http
+            .post(format!("{}/login/oauth/access_token", self.config.url))
+            .header("Accept", "application/json")
+            .form(&[
+                ("client_id", client_id),
+                ("device_code", device_code),
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+            ]);
+        let response = self
+            .send(request, false, "failed to poll for token")
+            .await?;
+
+        if !response.status().is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::GitHubApi {
+                msg: format!("token poll failed: {body}"),
+            });
+        }
+
+        let poll_response: PollResponse = response.json().await.map_err(|e| Error::GitHubApi {
+            msg: format!("failed to parse poll response: {e}"),
         })?;
 
-    if !response.status().is_success() {
-        let body = response.text().await.unwrap_or_default();
-        return Err(Error::GitHubApi {
-            msg: format!("GitHub user API returned error: {body}"),
-        });
+        match poll_response {
+            PollResponse::Success(token) => Ok(DevicePoll::Authorized(token)),
+            PollResponse::Error { error, interval } => match error.as_str() {
+                "authorization_pending" => Ok(DevicePoll::Pending { interval: None }),
+                "slow_down" => Ok(DevicePoll::Pending { interval }),
+                "expired_token" => Err(Error::GitHubApi {
+                    msg: "device code expired, please restart login".to_string(),
+                }),
+                "access_denied" => Err(Error::Forbidden {
+                    reason: "user denied the authorization request".to_string(),
+                }),
+                _ => Err(Error::GitHubApi {
+                    msg: format!("unexpected error from GitHub: {error}"),
+                }),
+            },
+        }
     }
 
-    response
-        .json::<GitHubUser>()
-        .await
-        .map_err(|e| Error::GitHubApi {
-            msg: format!("failed to parse GitHub user response: {e}"),
-        })
+    /// Get the authenticated GitHub user's login name
+    pub async fn get_github_user(&self, access_token: &str) -> Result<GitHubUser, Error> {
+        let request = self
+            .http
+            .get(format!("{}/user", self.config.api_url))
+            .header("Authorization", format!("Bearer {access_token}"))
+            .header("User-Agent", "sw1nn-pkg-repo")
+            .header("Accept", "application/json");
+        let response = self
+            .send(request, true, "failed to get GitHub user")
+            .await?;
+
+        if !response.status().is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::GitHubApi {
+                msg: format!("GitHub user API returned error: {body}"),
+            });
+        }
+
+        response
+            .json::<GitHubUser>()
+            .await
+            .map_err(|e| Error::GitHubApi {
+                msg: format!("failed to parse GitHub user response: {e}"),
+            })
+    }
 }
 
 // -- Axum Extractor --
@@ -231,6 +518,41 @@ impl FromRequestParts<Arc<AppState>> for AuthenticatedUser {
             }
         };
 
+        if let Some(key) = parts.headers.get("X-API-Key") {
+            let api_key = key
+                .to_str()
+                .ok()
+                .filter(|key| !key.is_empty())
+                .and_then(|key| find_api_key(auth_config, key))
+                .ok_or(Error::Unauthorized)?;
+
+            if !state.allowlist.contains(&api_key.username) {
+                return Err(Error::Forbidden {
+                    reason: format!(
+                        "user '{}' is not in the allowed users list",
+                        api_key.username
+                    ),
+                });
+            }
+
+            if api_key.scope == ApiKeyScope::ReadOnly
+                && !matches!(
+                    parts.method,
+                    axum::http::Method::GET | axum::http::Method::HEAD
+                )
+            {
+                return Err(Error::Forbidden {
+                    reason: format!("api key for '{}' is read-only", api_key.username),
+                });
+            }
+
+            crate::access_log::record_user(&api_key.username);
+            return Ok(AuthenticatedUser {
+                username: api_key.username.clone(),
+                token_type: "api_key".to_string(),
+            });
+        }
+
         let auth_header = parts
             .headers
             .get("Authorization")
@@ -244,15 +566,34 @@ impl FromRequestParts<Arc<AppState>> for AuthenticatedUser {
         let claims = validate_jwt(auth_config, token)?;
 
         // Check allowlist
-        if !auth_config.allowed_users.iter().any(|u| u == &claims.sub) {
+        if !state.allowlist.contains(&claims.sub) {
             return Err(Error::Forbidden {
                 reason: format!("user '{}' is not in the allowed users list", claims.sub),
             });
         }
 
+        crate::access_log::record_user(&claims.sub);
         Ok(AuthenticatedUser {
             username: claims.sub,
             token_type: claims.token_type,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_time_eq_compares_whole_strings() {
+        assert!(constant_time_eq(b"abcd", b"abcd"));
+        assert!(!constant_time_eq(b"abcd", b"abce"));
+        assert!(!constant_time_eq(b"abcd", b"abc"));
+    }
+
+    #[test]
+    fn parse_allowed_users_skips_blanks_and_comments() {
+        let content = "# maintainers\nalice\n\n  bob  # CI\n#carol\n";
+        assert_eq!(parse_allowed_users(content), vec!["alice", "bob"]);
+    }
+}
@@ -6,11 +6,13 @@ use glob::Pattern;
 use indicatif::{ProgressBar, ProgressStyle};
 use serde::{Deserialize, Serialize};
 use sha2::Digest;
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashSet};
+use std::io::SeekFrom;
 use std::path::{Path, PathBuf};
 use std::process;
 use tokio::fs::File;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 // Re-use the Package struct from the lib
@@ -60,6 +62,14 @@ enum Commands {
         /// Path(s) to package file(s) (.pkg.tar.zst)
         #[arg(value_hint = ValueHint::FilePath)]
         package_files: Vec<String>,
+        /// Print a JSON summary of per-file results to stdout instead of the
+        /// human-readable output (logs go to stderr)
+        #[arg(long)]
+        json_summary: bool,
+        /// Resume an interrupted upload session, sending only the chunks the
+        /// server is still missing (takes a single package file)
+        #[arg(long, value_name = "UPLOAD_ID")]
+        resume: Option<String>,
     },
     /// Delete package version(s) from the repository
     Delete {
@@ -93,7 +103,8 @@ enum Commands {
         /// Filter by repository name
         #[arg(short = 'R', long)]
         repo: Option<String>,
-        /// Filter by architecture
+        /// Filter by architecture; a concrete arch also matches `any`
+        /// packages, as pacman would install them
         #[arg(short = 'a', long)]
         arch: Option<String>,
         /// Output as JSON instead of table
@@ -108,6 +119,28 @@ enum Commands {
         /// Reverse sort order
         #[arg(short = 'r', long)]
         reverse: bool,
+        /// Show creation times in the local timezone instead of UTC
+        #[arg(short = 'l', long)]
+        local: bool,
+        /// strftime format for creation times (e.g. "%Y-%m-%dT%H:%M:%S%:z")
+        #[arg(short = 't', long, default_value = DEFAULT_TIME_FORMAT)]
+        time_format: String,
+    },
+    /// Upload packages present in an upstream repository but missing here
+    Sync {
+        /// Base URL of the upstream repository server
+        #[arg(value_hint = ValueHint::Url)]
+        upstream: String,
+        /// Only sync packages in this repository
+        #[arg(short, long)]
+        repo: Option<String>,
+        /// Only sync packages of this architecture; a concrete arch also
+        /// matches `any` packages
+        #[arg(short, long)]
+        arch: Option<String>,
+        /// Only print the packages that would be synced
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Log in to the repository via GitHub
     Login,
@@ -165,6 +198,17 @@ struct InitiateUploadResponse {
     total_chunks: u32,
 }
 
+/// Server-side state of an upload session, as reported by its status endpoint
+#[derive(Debug, Deserialize)]
+struct UploadStatusResponse {
+    upload_id: String,
+    size: u64,
+    sha256: Option<String>,
+    chunk_size: usize,
+    total_chunks: u32,
+    missing_chunks: Vec<u32>,
+}
+
 #[derive(Debug, Deserialize)]
 struct UploadChunkResponse {
     chunk_number: u32,
@@ -173,6 +217,12 @@ struct UploadChunkResponse {
     received_size: usize,
 }
 
+#[derive(Debug, Deserialize)]
+struct PackageListResponse {
+    total: usize,
+    items: Vec<Package>,
+}
+
 #[derive(Debug, Serialize)]
 struct CompleteUploadRequest {
     chunks: Vec<ChunkInfo>,
@@ -201,18 +251,32 @@ struct DeleteVersionsResponse {
 
 #[tokio::main]
 async fn main() {
+    let args = Args::parse();
+
+    // Keep stdout clean for machine-readable output
+    let json_output = matches!(
+        args.command,
+        Some(Commands::Upload {
+            json_summary: true,
+            ..
+        })
+    );
+    let log_writer = if json_output {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| format!("{BIN_NAME}=info").into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().with_writer(log_writer))
         .init();
 
     tracing::info!("{BIN_NAME} version {VERSION}");
 
-    let args = Args::parse();
-
     // Configure color output
     configure_colors(args.color);
 
@@ -223,8 +287,12 @@ async fn main() {
 
     // Handle subcommands or backwards-compatible positional args
     match args.command {
-        Some(Commands::Upload { package_files }) => {
-            run_upload(&client, &base_url, package_files).await;
+        Some(Commands::Upload {
+            package_files,
+            json_summary,
+            resume,
+        }) => {
+            run_upload(&client, &base_url, package_files, json_summary, resume).await;
         }
         Some(Commands::Replace { package_file, repo }) => {
             run_replace(&client, &base_url, &package_file, repo).await;
@@ -245,12 +313,35 @@ async fn main() {
             unit,
             sort,
             reverse,
+            local,
+            time_format,
         }) => {
+            let time_display = TimeDisplay::new(local, time_format).unwrap_or_else(|e| {
+                tracing::error!(error = %e, "Invalid --time-format");
+                process::exit(1);
+            });
             run_list(
-                &client, &base_url, name, repo, arch, json, unit, sort, reverse,
+                &client,
+                &base_url,
+                name,
+                repo,
+                arch,
+                json,
+                unit,
+                sort,
+                reverse,
+                &time_display,
             )
             .await;
         }
+        Some(Commands::Sync {
+            upstream,
+            repo,
+            arch,
+            dry_run,
+        }) => {
+            run_sync(&client, &base_url, &upstream, repo, arch, dry_run).await;
+        }
         Some(Commands::Login) => {
             run_login(&base_url).await;
         }
@@ -272,82 +363,147 @@ async fn main() {
             // Backwards compatibility: treat positional args as upload
             if args.package_files.is_empty() {
                 tracing::error!(
-                    "No command specified. Use 'upload', 'delete', 'replace', 'list', 'login', 'logout', or 'status' subcommand, or provide package files directly."
+                    "No command specified. Use 'upload', 'delete', 'replace', 'list', 'sync', 'login', 'logout', or 'status' subcommand, or provide package files directly."
                 );
                 process::exit(1);
             }
-            run_upload(&client, &base_url, args.package_files).await;
+            run_upload(&client, &base_url, args.package_files, false, None).await;
+        }
+    }
+}
+
+/// Outcome of uploading one file
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum UploadStatus {
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+struct UploadResult {
+    file: String,
+    status: UploadStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Machine-readable summary printed by `upload --json-summary`
+#[derive(Debug, Serialize)]
+struct UploadSummary {
+    total: usize,
+    succeeded: usize,
+    failed: usize,
+    results: Vec<UploadResult>,
+}
+
+impl UploadSummary {
+    fn new(results: Vec<UploadResult>) -> Self {
+        let succeeded = results
+            .iter()
+            .filter(|r| matches!(r.status, UploadStatus::Succeeded))
+            .count();
+
+        Self {
+            total: results.len(),
+            succeeded,
+            failed: results.len() - succeeded,
+            results,
         }
     }
 }
 
-async fn run_upload(client: &reqwest::Client, base_url: &str, package_files: Vec<String>) {
+async fn run_upload(
+    client: &reqwest::Client,
+    base_url: &str,
+    package_files: Vec<String>,
+    json_summary: bool,
+    resume: Option<String>,
+) {
     if package_files.is_empty() {
         tracing::error!("No package files specified");
         process::exit(1);
     }
 
+    if resume.is_some() && package_files.len() != 1 {
+        tracing::error!("--resume takes exactly one package file");
+        process::exit(1);
+    }
+
     let total_files = package_files.len();
-    let mut successful_uploads = 0;
-    let mut failed_uploads = 0;
+    let mut results = Vec::with_capacity(total_files);
 
     tracing::info!("Uploading {total_files} package(s) to {base_url}");
 
     for (index, pkg_file) in package_files.iter().enumerate() {
         let path = Path::new(pkg_file);
 
-        if !path.exists() {
-            tracing::error!(
-                "[{}/{}] File '{}' does not exist",
+        let error = if !path.exists() {
+            Some(format!("File '{pkg_file}' does not exist"))
+        } else if !pkg_file.ends_with(".pkg.tar.zst") {
+            Some(format!("File '{pkg_file}' must be a .pkg.tar.zst package"))
+        } else {
+            tracing::info!("[{}/{}] Uploading {}", index + 1, total_files, pkg_file);
+
+            // Always use chunked upload
+            match upload_chunked(
+                client,
+                base_url,
+                path,
+                None,
+                resume.as_deref(),
                 index + 1,
                 total_files,
-                pkg_file
-            );
-            failed_uploads += 1;
-            continue;
-        }
+            )
+            .await
+            {
+                Ok(package) => {
+                    if !json_summary {
+                        print_upload_success(&package, index + 1, total_files);
+                    }
+                    None
+                }
+                Err(e) => Some(format!("Upload failed: {e}")),
+            }
+        };
 
-        if !pkg_file.ends_with(".pkg.tar.zst") {
-            tracing::error!(
-                "[{}/{}] File '{}' must be a .pkg.tar.zst package",
-                index + 1,
-                total_files,
-                pkg_file
-            );
-            failed_uploads += 1;
-            continue;
+        if let Some(ref error) = error {
+            tracing::error!("[{}/{}] {}", index + 1, total_files, error);
         }
 
-        tracing::info!("[{}/{}] Uploading {}", index + 1, total_files, pkg_file);
+        results.push(UploadResult {
+            file: pkg_file.clone(),
+            status: if error.is_some() {
+                UploadStatus::Failed
+            } else {
+                UploadStatus::Succeeded
+            },
+            error,
+        });
+    }
 
-        // Always use chunked upload
-        let result = upload_chunked(client, base_url, path, index + 1, total_files).await;
+    let summary = UploadSummary::new(results);
 
-        match result {
-            Ok(package) => {
-                print_upload_success(&package, index + 1, total_files);
-                successful_uploads += 1;
-            }
-            Err(e) => {
-                tracing::error!("[{}/{}] Upload failed: {}", index + 1, total_files, e);
-                failed_uploads += 1;
-            }
-        }
+    if json_summary {
+        println!(
+            "{}",
+            serde_json::to_string(&summary).expect("upload summary is serializable")
+        );
+    } else {
+        println!("\n{}", "=".repeat(50));
+        println!("{}", "Upload Summary".bold());
+        println!("{}", "=".repeat(50));
+        println!("  Total files:       {}", summary.total);
+        println!(
+            "  Successful:        {}",
+            summary.succeeded.to_string().green()
+        );
+        println!("  Failed:            {}", summary.failed.to_string().red());
+        println!("{}", "=".repeat(50));
+        println!();
     }
 
-    println!("\n{}", "=".repeat(50));
-    println!("{}", "Upload Summary".bold());
-    println!("{}", "=".repeat(50));
-    println!("  Total files:       {total_files}");
-    println!(
-        "  Successful:        {}",
-        successful_uploads.to_string().green()
-    );
-    println!("  Failed:            {}", failed_uploads.to_string().red());
-    println!("{}", "=".repeat(50));
-    println!();
-
-    if failed_uploads > 0 {
+    if summary.failed > 0 {
         process::exit(1);
     }
 }
@@ -522,12 +678,10 @@ async fn run_replace(
 
     // Compute SHA256 of the new file
     tracing::info!("Calculating SHA256 of replacement file...");
-    let new_file_data = tokio::fs::read(path).await.unwrap_or_else(|e| {
+    let (new_sha256, new_size) = sha256_file(path).await.unwrap_or_else(|e| {
         tracing::error!(error = %e, "Failed to read file");
         process::exit(1);
     });
-    let new_sha256 = format!("{:x}", sha2::Sha256::digest(&new_file_data));
-    let new_size = new_file_data.len() as u64;
 
     // Query existing packages
     let packages = list_packages(client, base_url).await.unwrap_or_else(|e| {
@@ -647,7 +801,7 @@ async fn run_replace(
 
     // Upload replacement
     tracing::info!("Uploading replacement package...");
-    let upload_result = upload_chunked(client, base_url, path, 1, 1).await;
+    let upload_result = upload_chunked(client, base_url, path, None, None, 1, 1).await;
 
     match upload_result {
         Ok(package) => {
@@ -715,6 +869,12 @@ fn configure_colors(mode: ColorMode) {
     }
 }
 
+/// Whether a package of `package_arch` is installable on `filter`: its own
+/// arch, or `any`
+fn arch_matches(package_arch: &str, filter: &str) -> bool {
+    package_arch == filter || package_arch == "any"
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn run_list(
     client: &reqwest::Client,
@@ -726,6 +886,7 @@ async fn run_list(
     size_unit: SizeUnit,
     sort_field: SortField,
     reverse: bool,
+    time_display: &TimeDisplay,
 ) {
     let result = list_packages(client, base_url).await;
 
@@ -740,7 +901,7 @@ async fn run_list(
                 packages.retain(|p| p.repo == *repo);
             }
             if let Some(ref arch) = arch_filter {
-                packages.retain(|p| p.arch == *arch);
+                packages.retain(|p| arch_matches(&p.arch, arch));
             }
 
             // Sort packages
@@ -764,7 +925,7 @@ async fn run_list(
             if json_output {
                 print_packages_json(&packages);
             } else {
-                print_packages_table(&packages, size_unit);
+                print_packages_table(&packages, size_unit, time_display);
             }
         }
         Err(e) => {
@@ -774,20 +935,238 @@ async fn run_list(
     }
 }
 
-async fn list_packages(
+/// Upstream packages with no local package of the same name, version, arch
+/// and SHA256, after applying the repo and arch filters
+fn missing_packages<'a>(
+    upstream: &'a [Package],
+    local: &[Package],
+    repo_filter: Option<&str>,
+    arch_filter: Option<&str>,
+) -> Vec<&'a Package> {
+    let local: HashSet<(&str, &str, &str, &str)> = local
+        .iter()
+        .map(|p| (&*p.name, &*p.version, &*p.arch, &*p.sha256))
+        .collect();
+
+    let mut missing: Vec<&Package> = upstream
+        .iter()
+        .filter(|p| repo_filter.is_none_or(|repo| p.repo == repo))
+        .filter(|p| arch_filter.is_none_or(|arch| arch_matches(&p.arch, arch)))
+        .filter(|p| !local.contains(&(&*p.name, &*p.version, &*p.arch, &*p.sha256)))
+        .collect();
+    missing.sort_by(|a, b| {
+        a.repo
+            .cmp(&b.repo)
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| sw1nn_pkg_repo::version::vercmp(&a.version, &b.version))
+            .then_with(|| a.arch.cmp(&b.arch))
+    });
+    missing
+}
+
+async fn run_sync(
     client: &reqwest::Client,
     base_url: &str,
-) -> Result<Vec<Package>, Box<dyn std::error::Error>> {
-    let url = format!("{base_url}/api/packages");
-    let response = client.get(&url).send().await?;
+    upstream_url: &str,
+    repo_filter: Option<String>,
+    arch_filter: Option<String>,
+    dry_run: bool,
+) {
+    let upstream_url = upstream_url.trim_end_matches('/');
+    // The stored login token is for our server, never send it upstream
+    let upstream_client = reqwest::Client::new();
+
+    let upstream = list_packages(&upstream_client, upstream_url)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!(upstream = upstream_url, error = %e, "Failed to list upstream packages");
+            process::exit(1);
+        });
+    let local = list_packages(client, base_url).await.unwrap_or_else(|e| {
+        tracing::error!(error = %e, "Failed to list packages");
+        process::exit(1);
+    });
+
+    let missing = missing_packages(
+        &upstream,
+        &local,
+        repo_filter.as_deref(),
+        arch_filter.as_deref(),
+    );
+
+    if missing.is_empty() {
+        println!("{}", "Already in sync with upstream.".green().bold());
+        return;
+    }
+
+    println!();
+    println!(
+        "{} package(s) in {} missing from {}:",
+        missing.len().to_string().yellow(),
+        upstream_url.cyan(),
+        base_url.cyan()
+    );
+    for package in &missing {
+        println!(
+            "  + {} {} {} ({})",
+            package.name.green(),
+            package.version.yellow(),
+            package.arch,
+            package.repo
+        );
+    }
+    println!();
+
+    if dry_run {
+        return;
+    }
+
+    let staging = std::env::temp_dir().join(format!("sw1nn-pkg-sync-{}", uuid::Uuid::new_v4()));
+    if let Err(e) = tokio::fs::create_dir_all(&staging).await {
+        tracing::error!(path = %staging.display(), error = %e, "Failed to create staging directory");
+        process::exit(1);
+    }
+
+    let total = missing.len();
+    let mut synced = 0usize;
+    let mut failed = 0usize;
+
+    for (index, package) in missing.iter().enumerate() {
+        tracing::info!("[{}/{}] Syncing {}", index + 1, total, package.filename);
+
+        let result = async {
+            let path = download_upstream(&upstream_client, upstream_url, package, &staging).await?;
+            let uploaded = upload_chunked(
+                client,
+                base_url,
+                &path,
+                Some(&package.repo),
+                None,
+                index + 1,
+                total,
+            )
+            .await;
+            let _ = tokio::fs::remove_file(&path).await;
+            let _ = tokio::fs::remove_file(format!("{}.sig", path.display())).await;
+            uploaded
+        }
+        .await;
+
+        match result {
+            Ok(uploaded) => {
+                print_upload_success(&uploaded, index + 1, total);
+                synced += 1;
+            }
+            Err(e) => {
+                tracing::error!(
+                    "[{}/{}] Sync of {} failed: {}",
+                    index + 1,
+                    total,
+                    package.filename,
+                    e
+                );
+                failed += 1;
+            }
+        }
+    }
+
+    let _ = tokio::fs::remove_dir_all(&staging).await;
 
+    println!("{}", "=".repeat(50));
+    println!("{}", "Sync Summary".bold());
+    println!("{}", "=".repeat(50));
+    println!("  Missing packages:  {}", total.to_string().yellow());
+    println!("  Synced:            {}", synced.to_string().green());
+    println!("  Failed:            {}", failed.to_string().red());
+    println!("{}", "=".repeat(50));
+    println!();
+
+    if failed > 0 {
+        process::exit(1);
+    }
+}
+
+/// Download an upstream package, and its signature when it has one, into
+/// `dir`, checking the package against its upstream SHA256
+async fn download_upstream(
+    client: &reqwest::Client,
+    upstream_url: &str,
+    package: &Package,
+    dir: &Path,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let url = format!(
+        "{upstream_url}/{}/os/{}/{}",
+        package.repo, package.arch, package.filename
+    );
+    let path = dir.join(&package.filename);
+    let sha256 = download_file(client, &url, &path).await?;
+
+    if !sha256.eq_ignore_ascii_case(&package.sha256) {
+        let _ = tokio::fs::remove_file(&path).await;
+        return Err(format!(
+            "SHA256 mismatch for {}: upstream lists {} but downloaded {}",
+            package.filename, package.sha256, sha256
+        )
+        .into());
+    }
+
+    if package.signed {
+        let sig_path = PathBuf::from(format!("{}.sig", path.display()));
+        download_file(client, &format!("{url}.sig"), &sig_path).await?;
+    }
+
+    Ok(path)
+}
+
+/// Stream `url` to `path`, returning the hex SHA256 of what was written
+async fn download_file(
+    client: &reqwest::Client,
+    url: &str,
+    path: &Path,
+) -> Result<String, Box<dyn std::error::Error>> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut response = client.get(url).send().await?;
     if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        return Err(format!("Failed to list packages - HTTP {status}: {body}").into());
+        let error = describe_error(response).await;
+        return Err(format!("Failed to download {url} - {error}").into());
+    }
+
+    let mut file = File::create(path).await?;
+    let mut hasher = sha2::Sha256::new();
+    while let Some(chunk) = response.chunk().await? {
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
     }
+    file.flush().await?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+async fn list_packages(
+    client: &reqwest::Client,
+    base_url: &str,
+) -> Result<Vec<Package>, Box<dyn std::error::Error>> {
+    // The listing is paginated; fetch pages until every package is in
+    let mut packages = Vec::new();
+    loop {
+        let url = format!("{base_url}/api/packages?offset={}", packages.len());
+        let response = client.get(&url).send().await?;
+
+        if !response.status().is_success() {
+            let error = describe_error(response).await;
+            return Err(format!("Failed to list packages - {error}").into());
+        }
 
-    let packages = response.json::<Vec<Package>>().await?;
+        let page = response.json::<PackageListResponse>().await?;
+        if page.items.is_empty() {
+            break;
+        }
+        packages.extend(page.items);
+        if packages.len() >= page.total {
+            break;
+        }
+    }
     Ok(packages)
 }
 
@@ -801,7 +1180,7 @@ fn print_packages_json(packages: &[Package]) {
     }
 }
 
-fn print_packages_table(packages: &[Package], size_unit: SizeUnit) {
+fn print_packages_table(packages: &[Package], size_unit: SizeUnit, time_display: &TimeDisplay) {
     if packages.is_empty() {
         println!("{}", "No packages found.".yellow());
         return;
@@ -828,6 +1207,16 @@ fn print_packages_table(packages: &[Package], size_unit: SizeUnit) {
         .max()
         .unwrap_or(4)
         .max(4);
+    let created: Vec<String> = packages
+        .iter()
+        .map(|p| time_display.format(&p.created_at))
+        .collect();
+    let created_width = created
+        .iter()
+        .map(|c| c.chars().count())
+        .max()
+        .unwrap_or(7)
+        .max(7);
 
     // Print header
     println!(
@@ -841,14 +1230,13 @@ fn print_packages_table(packages: &[Package], size_unit: SizeUnit) {
     );
     println!(
         "{}",
-        "-".repeat(name_width + version_width + arch_width + repo_width + 10 + 20 + 12)
+        "-".repeat(name_width + version_width + arch_width + repo_width + 10 + created_width + 10)
             .bright_black()
     );
 
     // Print rows
-    for pkg in packages {
+    for (pkg, created_str) in packages.iter().zip(created) {
         let size_str = format_size(pkg.size, size_unit);
-        let created_str = pkg.created_at.format("%Y-%m-%d %H:%M").to_string();
 
         let version_str = format_version(&pkg.version, version_width);
         println!(
@@ -870,6 +1258,38 @@ fn print_packages_table(packages: &[Package], size_unit: SizeUnit) {
     );
 }
 
+/// Default `created_at` format for `list`: short UTC form
+const DEFAULT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// How `list` renders package creation times
+struct TimeDisplay {
+    local: bool,
+    format: String,
+}
+
+impl TimeDisplay {
+    /// Validates the strftime `format` up front so rendering can't fail
+    fn new(local: bool, format: String) -> Result<Self, String> {
+        use std::fmt::Write;
+
+        let mut probe = String::new();
+        write!(probe, "{}", chrono::Utc::now().format(&format))
+            .map_err(|_| format!("invalid strftime format: {format}"))?;
+        Ok(Self { local, format })
+    }
+
+    fn format(&self, created_at: &chrono::DateTime<chrono::Utc>) -> String {
+        if self.local {
+            created_at
+                .with_timezone(&chrono::Local)
+                .format(&self.format)
+                .to_string()
+        } else {
+            created_at.format(&self.format).to_string()
+        }
+    }
+}
+
 fn format_size(bytes: u64, unit: SizeUnit) -> String {
     let byte = Byte::from_u64(bytes);
     match unit {
@@ -897,11 +1317,24 @@ fn format_version(version: &str, width: usize) -> String {
     format!("{colored}{:padding$}", "")
 }
 
-/// Upload a package using the simple (non-chunked) API
+/// Where a chunked upload stands before any chunks are sent
+struct UploadPlan {
+    upload_id: String,
+    chunk_size: usize,
+    total_chunks: u32,
+    /// Chunks the server doesn't have yet
+    missing_chunks: BTreeSet<u32>,
+}
+
+/// Upload a package using the chunked API, into `repo` or the server's
+/// default repo, or resume the upload session `resume` by sending only the
+/// chunks the server is missing
 async fn upload_chunked(
     client: &reqwest::Client,
     base_url: &str,
     path: &Path,
+    repo: Option<&str>,
+    resume: Option<&str>,
     index: usize,
     total: usize,
 ) -> Result<Package, Box<dyn std::error::Error>> {
@@ -914,44 +1347,50 @@ async fn upload_chunked(
 
     // Calculate SHA256 (optional but recommended)
     tracing::info!("[{}/{}] Calculating SHA256...", index, total);
-    let file_data = tokio::fs::read(path).await?;
-    let sha256 = format!("{:x}", sha2::Sha256::digest(&file_data));
+    let (sha256, _) = sha256_file(path).await?;
 
-    // Initiate upload
-    tracing::info!("[{}/{}] Initiating chunked upload...", index, total);
-    // Cap chunk size to file size to avoid server validation errors
-    let chunk_size = std::cmp::min(DEFAULT_CHUNK_SIZE, file_size as usize);
-    let init_req = InitiateUploadRequest {
-        filename: filename.clone(),
-        size: file_size,
-        sha256: Some(sha256),
-        repo: None,
-        arch: None,
-        chunk_size: Some(chunk_size),
-        has_signature,
+    let plan = match resume {
+        Some(upload_id) => {
+            tracing::info!("[{}/{}] Resuming upload {}...", index, total, upload_id);
+            resume_upload(client, base_url, upload_id, file_size, &sha256).await?
+        }
+        None => {
+            tracing::info!("[{}/{}] Initiating chunked upload...", index, total);
+            match initiate_upload(
+                client,
+                base_url,
+                filename,
+                repo,
+                file_size,
+                sha256,
+                has_signature,
+            )
+            .await?
+            {
+                Initiated::Session(plan) => plan,
+                Initiated::AlreadyPresent(package) => {
+                    tracing::info!(
+                        "[{}/{}] Identical package already present, nothing to upload",
+                        index,
+                        total
+                    );
+                    return Ok(package);
+                }
+            }
+        }
     };
-
-    let init_url = format!("{}/api/packages/upload/initiate", base_url);
-    let response = client.post(&init_url).json(&init_req).send().await?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        return Err(format!("Failed to initiate upload - HTTP {}: {}", status, body).into());
-    }
-
-    let init_resp: InitiateUploadResponse = response.json().await?;
-    let upload_id = init_resp.upload_id;
-    let chunk_size = init_resp.chunk_size;
-    let total_chunks = init_resp.total_chunks;
+    let upload_id = plan.upload_id;
+    let chunk_size = plan.chunk_size;
+    let total_chunks = plan.total_chunks;
 
     tracing::info!(
-        "[{}/{}] Upload ID: {}, {} chunks of {} bytes",
+        "[{}/{}] Upload ID: {}, {} chunks of {} bytes, {} to send",
         index,
         total,
         upload_id,
         total_chunks,
-        chunk_size
+        chunk_size,
+        plan.missing_chunks.len()
     );
 
     // Create progress bar
@@ -964,22 +1403,30 @@ async fn upload_chunked(
             .progress_chars("#>-"),
     );
 
-    // Upload chunks
+    // Upload chunks. Chunks the server already has are only read to
+    // checksum them for the completion request.
     let mut file = File::open(path).await?;
     let mut chunk_infos = Vec::new();
 
     for chunk_num in 1..=total_chunks {
-        let mut chunk_data = vec![0u8; chunk_size];
-        let bytes_read = file.read(&mut chunk_data).await?;
-        chunk_data.truncate(bytes_read);
-
-        // Upload chunk with retry
-        let chunk_info =
-            upload_chunk_with_retry(client, base_url, &upload_id, chunk_num, &chunk_data, 3)
-                .await?;
+        let offset = u64::from(chunk_num - 1) * chunk_size as u64;
+        let len = std::cmp::min(chunk_size as u64, file_size.saturating_sub(offset)) as usize;
+        let mut chunk_data = vec![0u8; len];
+        file.seek(SeekFrom::Start(offset)).await?;
+        file.read_exact(&mut chunk_data).await?;
+
+        let chunk_info = if plan.missing_chunks.contains(&chunk_num) {
+            // Upload chunk with retry
+            upload_chunk_with_retry(client, base_url, &upload_id, chunk_num, &chunk_data, 3).await?
+        } else {
+            ChunkInfo {
+                chunk_number: chunk_num,
+                checksum: format!("{:x}", md5::compute(&chunk_data)),
+            }
+        };
 
         chunk_infos.push(chunk_info);
-        progress.inc(bytes_read as u64);
+        progress.inc(len as u64);
     }
 
     progress.finish_with_message("Upload complete");
@@ -998,9 +1445,8 @@ async fn upload_chunked(
             .await?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            tracing::warn!("Failed to upload signature - HTTP {}: {}", status, body);
+            let error = describe_error(response).await;
+            tracing::warn!("Failed to upload signature - {error}");
         }
     }
 
@@ -1018,15 +1464,189 @@ async fn upload_chunked(
         .await?;
 
     if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        return Err(format!("Failed to complete upload - HTTP {}: {}", status, body).into());
+        let error = describe_error(response).await;
+        return Err(format!("Failed to complete upload - {error}").into());
     }
 
     let package = response.json::<Package>().await?;
     Ok(package)
 }
 
+/// Outcome of initiating an upload
+enum Initiated {
+    /// A new session to send the chunks to
+    Session(UploadPlan),
+    /// The server already stores a package with this SHA256 (200 instead of
+    /// 201), so there is nothing to send
+    AlreadyPresent(Package),
+}
+
+/// Start a new upload session for the whole file
+async fn initiate_upload(
+    client: &reqwest::Client,
+    base_url: &str,
+    filename: String,
+    repo: Option<&str>,
+    file_size: u64,
+    sha256: String,
+    has_signature: bool,
+) -> Result<Initiated, Box<dyn std::error::Error>> {
+    // Cap chunk size to file size to avoid server validation errors
+    let chunk_size = std::cmp::min(DEFAULT_CHUNK_SIZE, file_size as usize);
+    let init_req = InitiateUploadRequest {
+        filename,
+        size: file_size,
+        sha256: Some(sha256),
+        repo: repo.map(str::to_owned),
+        arch: None,
+        chunk_size: Some(chunk_size),
+        has_signature,
+    };
+
+    let init_url = format!("{}/api/packages/upload/initiate", base_url);
+    let response = client.post(&init_url).json(&init_req).send().await?;
+
+    if !response.status().is_success() {
+        let error = describe_error(response).await;
+        return Err(format!("Failed to initiate upload - {error}").into());
+    }
+
+    if response.status() == reqwest::StatusCode::OK {
+        return Ok(Initiated::AlreadyPresent(response.json().await?));
+    }
+
+    let init_resp: InitiateUploadResponse = response.json().await?;
+    Ok(Initiated::Session(UploadPlan {
+        upload_id: init_resp.upload_id,
+        chunk_size: init_resp.chunk_size,
+        total_chunks: init_resp.total_chunks,
+        missing_chunks: (1..=init_resp.total_chunks).collect(),
+    }))
+}
+
+/// Look up an existing upload session and check it is for the same file
+async fn resume_upload(
+    client: &reqwest::Client,
+    base_url: &str,
+    upload_id: &str,
+    file_size: u64,
+    sha256: &str,
+) -> Result<UploadPlan, Box<dyn std::error::Error>> {
+    let status_url = format!("{}/api/packages/upload/{}/status", base_url, upload_id);
+    let response = client.get(&status_url).send().await?;
+
+    match response.status() {
+        reqwest::StatusCode::NOT_FOUND => {
+            return Err(format!(
+                "Upload session {} not found (it may have expired and been cleaned up); \
+                 run without --resume to start a fresh upload",
+                upload_id
+            )
+            .into());
+        }
+        reqwest::StatusCode::GONE => {
+            return Err(format!(
+                "Upload session {} has expired; run without --resume to start a fresh upload",
+                upload_id
+            )
+            .into());
+        }
+        status if !status.is_success() => {
+            let error = describe_error(response).await;
+            return Err(format!("Failed to get upload status - {error}").into());
+        }
+        _ => {}
+    }
+
+    let status: UploadStatusResponse = response.json().await?;
+    check_resumable(&status, file_size, sha256)?;
+
+    Ok(UploadPlan {
+        upload_id: status.upload_id,
+        chunk_size: status.chunk_size,
+        total_chunks: status.total_chunks,
+        missing_chunks: status.missing_chunks.into_iter().collect(),
+    })
+}
+
+/// Refuse to resume a session with a different file than it was started with
+fn check_resumable(
+    status: &UploadStatusResponse,
+    file_size: u64,
+    sha256: &str,
+) -> Result<(), String> {
+    if status.size != file_size {
+        return Err(format!(
+            "Upload session {} is for a {} byte file but this file is {} bytes; \
+             run without --resume to start a fresh upload",
+            status.upload_id, status.size, file_size
+        ));
+    }
+
+    match &status.sha256 {
+        Some(expected) if !expected.eq_ignore_ascii_case(sha256) => Err(format!(
+            "Upload session {} is for a file with SHA256 {} but this file's is {}; \
+             run without --resume to start a fresh upload",
+            status.upload_id, expected, sha256
+        )),
+        Some(_) => Ok(()),
+        None => {
+            tracing::warn!(
+                "Upload session {} has no SHA256 to check this file against",
+                status.upload_id
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Error body of a failed API request
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    error: String,
+    /// Machine-readable error kind, e.g. `package_already_exists`
+    code: Option<String>,
+}
+
+/// Describe a failed API response by its status and, when the body is an
+/// API error, its code and message
+async fn describe_error(response: reqwest::Response) -> String {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    format_api_error(status, &body)
+}
+
+fn format_api_error(status: reqwest::StatusCode, body: &str) -> String {
+    match serde_json::from_str::<ApiError>(body) {
+        Ok(ApiError {
+            error,
+            code: Some(code),
+        }) => format!("HTTP {status} [{code}]: {error}"),
+        Ok(ApiError { error, code: None }) => format!("HTTP {status}: {error}"),
+        Err(_) => format!("HTTP {status}: {body}"),
+    }
+}
+
+/// Hash a file incrementally, returning its hex SHA256 and size, without
+/// loading it into memory
+async fn sha256_file(path: &Path) -> std::io::Result<(String, u64)> {
+    let mut file = File::open(path).await?;
+    let mut hasher = sha2::Sha256::new();
+    let mut buffer = vec![0u8; DEFAULT_CHUNK_SIZE];
+    let mut size = 0u64;
+
+    loop {
+        let bytes_read = file.read(&mut buffer).await?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+        size += bytes_read as u64;
+    }
+
+    Ok((format!("{:x}", hasher.finalize()), size))
+}
+
 /// Upload a chunk with retry logic
 async fn upload_chunk_with_retry(
     client: &reqwest::Client,
@@ -1060,16 +1680,15 @@ async fn upload_chunk_with_retry(
                 });
             }
             Ok(resp) => {
-                let status = resp.status();
-                let body = resp.text().await.unwrap_or_default();
+                let error = describe_error(resp).await;
 
                 if retries < max_retries {
                     retries += 1;
                     let delay = std::time::Duration::from_millis(1000 * retries as u64);
                     tracing::warn!(
-                        "Chunk {} upload failed (HTTP {}), retrying in {:?}... ({}/{})",
+                        "Chunk {} upload failed ({}), retrying in {:?}... ({}/{})",
                         chunk_number,
-                        status,
+                        error,
                         delay,
                         retries,
                         max_retries
@@ -1078,8 +1697,8 @@ async fn upload_chunk_with_retry(
                     continue;
                 } else {
                     return Err(format!(
-                        "Chunk {} upload failed after {} retries: HTTP {}: {}",
-                        chunk_number, max_retries, status, body
+                        "Chunk {} upload failed after {} retries: {}",
+                        chunk_number, max_retries, error
                     )
                     .into());
                 }
@@ -1130,9 +1749,8 @@ async fn delete_versions(
     let response = client.post(&url).json(&request).send().await?;
 
     if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        return Err(format!("Failed to delete versions - HTTP {status}: {body}").into());
+        let error = describe_error(response).await;
+        return Err(format!("Failed to delete versions - {error}").into());
     }
 
     let delete_response = response.json::<DeleteVersionsResponse>().await?;
@@ -1212,10 +1830,21 @@ fn delete_token() -> bool {
     }
 }
 
+/// Client authenticating with the `SW1NN_API_KEY` API key if set (e.g. in
+/// CI), otherwise with the stored login token
 fn build_authenticated_client() -> reqwest::Client {
     let mut builder = reqwest::Client::builder();
 
-    if let Some(token) = load_token() {
+    if let Ok(key) = std::env::var("SW1NN_API_KEY").map(|key| key.trim().to_string())
+        && !key.is_empty()
+    {
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Ok(mut value) = reqwest::header::HeaderValue::from_str(&key) {
+            value.set_sensitive(true);
+            headers.insert("X-API-Key", value);
+        }
+        builder = builder.default_headers(headers);
+    } else if let Some(token) = load_token() {
         let mut headers = reqwest::header::HeaderMap::new();
         if let Ok(value) = reqwest::header::HeaderValue::from_str(&format!("Bearer {token}")) {
             headers.insert(reqwest::header::AUTHORIZATION, value);
@@ -1274,9 +1903,8 @@ async fn run_login(base_url: &str) {
     }
 
     if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        tracing::error!("Failed to start login - HTTP {status}: {body}");
+        let error = describe_error(response).await;
+        tracing::error!("Failed to start login - {error}");
         process::exit(1);
     }
 
@@ -1296,7 +1924,7 @@ async fn run_login(base_url: &str) {
 
     // Poll for token
     let poll_url = format!("{base_url}/api/auth/device/token");
-    let interval = std::time::Duration::from_secs(device_code.interval.max(5));
+    let mut interval = std::time::Duration::from_secs(device_code.interval.max(5));
 
     loop {
         tokio::time::sleep(interval).await;
@@ -1318,7 +1946,11 @@ async fn run_login(base_url: &str) {
         let status = response.status();
 
         if status == reqwest::StatusCode::ACCEPTED {
-            // Still pending
+            // Still pending; the server passes on GitHub's slow_down interval
+            let body: serde_json::Value = response.json().await.unwrap_or_default();
+            if let Some(secs) = body["interval"].as_u64() {
+                interval = interval.max(std::time::Duration::from_secs(secs));
+            }
             continue;
         }
 
@@ -1329,8 +1961,8 @@ async fn run_login(base_url: &str) {
         }
 
         if !status.is_success() {
-            let body = response.text().await.unwrap_or_default();
-            tracing::error!("Login failed - HTTP {status}: {body}");
+            let error = describe_error(response).await;
+            tracing::error!("Login failed - {error}");
             process::exit(1);
         }
 
@@ -1414,3 +2046,193 @@ fn print_upload_success(package: &Package, index: usize, total: usize) {
     );
     println!();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Local, TimeZone, Utc};
+
+    fn timestamp() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 15, 9, 5, 30).unwrap()
+    }
+
+    #[test]
+    fn arch_filter_includes_any_packages() {
+        let archs = ["x86_64", "any", "aarch64"];
+
+        let x86: Vec<_> = archs.iter().filter(|a| arch_matches(a, "x86_64")).collect();
+        assert_eq!(x86, [&"x86_64", &"any"]);
+
+        let any: Vec<_> = archs.iter().filter(|a| arch_matches(a, "any")).collect();
+        assert_eq!(any, [&"any"]);
+    }
+
+    fn sync_package(repo: &str, name: &str, version: &str, arch: &str, sha256: &str) -> Package {
+        Package {
+            name: name.to_owned(),
+            version: version.to_owned(),
+            arch: arch.to_owned(),
+            repo: repo.to_owned(),
+            filename: format!("{name}-{version}-{arch}.pkg.tar.zst"),
+            sha256: sha256.to_owned(),
+            size: 0,
+            created_at: timestamp(),
+            signed: false,
+            signature_verified: false,
+            license: vec![],
+        }
+    }
+
+    #[test]
+    fn sync_diff_lists_upstream_packages_missing_locally() {
+        let upstream = [
+            sync_package("sw1nn", "foo", "1.0.0-1", "x86_64", "aa"),
+            sync_package("sw1nn", "foo", "1.1.0-1", "x86_64", "bb"),
+            sync_package("sw1nn", "bar", "2.0.0-1", "any", "cc"),
+            sync_package("sw1nn", "baz", "1.0.0-1", "aarch64", "dd"),
+            sync_package("extra", "qux", "1.0.0-1", "x86_64", "ee"),
+        ];
+        let local = [
+            sync_package("sw1nn", "foo", "1.0.0-1", "x86_64", "aa"),
+            // Same version rebuilt: a different file, so still missing
+            sync_package("sw1nn", "bar", "2.0.0-1", "any", "ff"),
+        ];
+
+        let names = |missing: Vec<&Package>| -> Vec<String> {
+            missing
+                .iter()
+                .map(|p| format!("{}/{}-{}-{}", p.repo, p.name, p.version, p.arch))
+                .collect()
+        };
+
+        assert_eq!(
+            names(missing_packages(&upstream, &local, None, None)),
+            [
+                "extra/qux-1.0.0-1-x86_64",
+                "sw1nn/bar-2.0.0-1-any",
+                "sw1nn/baz-1.0.0-1-aarch64",
+                "sw1nn/foo-1.1.0-1-x86_64",
+            ]
+        );
+        assert_eq!(
+            names(missing_packages(
+                &upstream,
+                &local,
+                Some("sw1nn"),
+                Some("x86_64")
+            )),
+            ["sw1nn/bar-2.0.0-1-any", "sw1nn/foo-1.1.0-1-x86_64"]
+        );
+        assert!(missing_packages(&upstream, &upstream, None, None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn sha256_file_matches_one_shot_hash() {
+        // Several buffers plus a partial one
+        let data: Vec<u8> = (0..3 * DEFAULT_CHUNK_SIZE + 12345)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), &data).unwrap();
+
+        let (sha256, size) = sha256_file(file.path()).await.unwrap();
+        assert_eq!(sha256, format!("{:x}", sha2::Sha256::digest(&data)));
+        assert_eq!(size, data.len() as u64);
+    }
+
+    #[test]
+    fn resume_rejects_a_different_file() {
+        let status = UploadStatusResponse {
+            upload_id: "abc".to_owned(),
+            size: 100,
+            sha256: Some("AABB".to_owned()),
+            chunk_size: 50,
+            total_chunks: 2,
+            missing_chunks: vec![2],
+        };
+
+        assert!(check_resumable(&status, 100, "aabb").is_ok());
+
+        let err = check_resumable(&status, 99, "aabb").unwrap_err();
+        assert!(err.contains("100 byte file"), "{err}");
+
+        let err = check_resumable(&status, 100, "ccdd").unwrap_err();
+        assert!(err.contains("SHA256"), "{err}");
+        assert!(err.contains("without --resume"), "{err}");
+    }
+
+    #[test]
+    fn api_errors_show_their_code() {
+        assert_eq!(
+            format_api_error(
+                reqwest::StatusCode::CONFLICT,
+                r#"{"error": "Package already exists: foo", "code": "package_already_exists"}"#
+            ),
+            "HTTP 409 Conflict [package_already_exists]: Package already exists: foo"
+        );
+        assert_eq!(
+            format_api_error(reqwest::StatusCode::BAD_GATEWAY, "upstream down"),
+            "HTTP 502 Bad Gateway: upstream down"
+        );
+    }
+
+    #[test]
+    fn upload_summary_serializes_mixed_results() {
+        let summary = UploadSummary::new(vec![
+            UploadResult {
+                file: "foo-1.0.0-1-x86_64.pkg.tar.zst".to_owned(),
+                status: UploadStatus::Succeeded,
+                error: None,
+            },
+            UploadResult {
+                file: "bar.tar.gz".to_owned(),
+                status: UploadStatus::Failed,
+                error: Some("File 'bar.tar.gz' must be a .pkg.tar.zst package".to_owned()),
+            },
+        ]);
+
+        assert_eq!(
+            serde_json::to_value(&summary).unwrap(),
+            serde_json::json!({
+                "total": 2,
+                "succeeded": 1,
+                "failed": 1,
+                "results": [
+                    {"file": "foo-1.0.0-1-x86_64.pkg.tar.zst", "status": "succeeded"},
+                    {
+                        "file": "bar.tar.gz",
+                        "status": "failed",
+                        "error": "File 'bar.tar.gz' must be a .pkg.tar.zst package"
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn time_display_defaults_to_short_utc() {
+        let display = TimeDisplay::new(false, DEFAULT_TIME_FORMAT.to_owned()).unwrap();
+        assert_eq!(display.format(&timestamp()), "2025-01-15 09:05");
+    }
+
+    #[test]
+    fn time_display_uses_custom_format() {
+        let display = TimeDisplay::new(false, "%Y-%m-%dT%H:%M:%S%:z".to_owned()).unwrap();
+        assert_eq!(display.format(&timestamp()), "2025-01-15T09:05:30+00:00");
+    }
+
+    #[test]
+    fn time_display_converts_to_local() {
+        let display = TimeDisplay::new(true, "%Y-%m-%d %H:%M %z".to_owned()).unwrap();
+        let expected = timestamp()
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M %z")
+            .to_string();
+        assert_eq!(display.format(&timestamp()), expected);
+    }
+
+    #[test]
+    fn time_display_rejects_invalid_format() {
+        assert!(TimeDisplay::new(false, "%Q".to_owned()).is_err());
+    }
+}
@@ -14,6 +14,11 @@ struct Args {
     #[arg(short, long, value_name = "FILE", global = true)]
     config: Option<String>,
 
+    /// Load and validate the configuration, print the effective settings and
+    /// exit without starting the server
+    #[arg(long)]
+    check_config: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -36,6 +41,17 @@ enum Commands {
         #[command(subcommand)]
         action: TokenCommands,
     },
+    /// Print a signed, expiring download URL for a repository file
+    SignUrl {
+        /// Path of the file on the server, e.g. /sw1nn/os/x86_64/sw1nn.db
+        path: String,
+        /// How long the URL stays valid, in seconds
+        #[arg(short, long, default_value = "3600")]
+        expires_in_secs: i64,
+        /// Server URL to prefix the signed path with, e.g. https://repo.example.com
+        #[arg(short, long)]
+        base_url: Option<String>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -55,15 +71,40 @@ enum TokenCommands {
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
+    if args.check_config {
+        check_config(args.config.as_deref());
+    }
+
     match args.command {
         Some(Commands::Migrate { data_path, dry_run }) => {
             run_migration(args.config.as_deref(), data_path, dry_run).await
         }
         Some(Commands::Token { action }) => run_token_command(args.config.as_deref(), action),
+        Some(Commands::SignUrl {
+            path,
+            expires_in_secs,
+            base_url,
+        }) => run_sign_url(args.config.as_deref(), &path, expires_in_secs, base_url),
         Some(Commands::Serve) | None => run_service(args.config.as_deref()).await,
     }
 }
 
+/// Validate the configuration and print the effective settings, exiting
+/// non-zero on failure. Secrets are redacted by the config `Debug` impls.
+fn check_config(config_path: Option<&str>) -> ! {
+    match sw1nn_pkg_repo::config::Config::load(config_path) {
+        Ok(config) => {
+            println!("Configuration OK");
+            println!("{config:#?}");
+            std::process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("Invalid configuration: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
 /// Run the storage migration from old structure to new flat structure
 async fn run_migration(
     config_path: Option<&str>,
@@ -256,3 +297,33 @@ fn run_token_command(
 
     Ok(())
 }
+
+fn run_sign_url(
+    config_path: Option<&str>,
+    path: &str,
+    expires_in_secs: i64,
+    base_url: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let config = sw1nn_pkg_repo::config::Config::load(config_path)?;
+
+    let secret = config
+        .server
+        .download_signing_secret
+        .as_deref()
+        .ok_or("No download_signing_secret configured in the [server] section")?;
+
+    let path = if path.starts_with('/') {
+        path.to_owned()
+    } else {
+        format!("/{path}")
+    };
+    let expires_at = chrono::Utc::now() + chrono::Duration::seconds(expires_in_secs);
+    let signed = sw1nn_pkg_repo::signed_url::signed_path(secret, &path, expires_at);
+
+    match base_url {
+        Some(base_url) => println!("{}{signed}", base_url.trim_end_matches('/')),
+        None => println!("{signed}"),
+    }
+
+    Ok(())
+}
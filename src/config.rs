@@ -1,7 +1,9 @@
 use crate::error::{Error, Result};
 use byte_unit::Byte;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
+use utoipa::ToSchema;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
@@ -10,13 +12,119 @@ pub struct Config {
     pub auth: Option<AuthConfig>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Deserialize, Clone)]
 pub struct AuthConfig {
     pub github_client_id: String,
+    #[serde(default)]
     pub allowed_users: Vec<String>,
+    /// File with one allowed username per line, re-read periodically and on
+    /// SIGHUP. Mutually exclusive with `allowed_users`.
+    #[serde(default)]
+    pub allowed_users_file: Option<PathBuf>,
+    #[serde(default = "default_allowed_users_reload_secs")]
+    pub allowed_users_reload_secs: u64,
     pub jwt_secret: String,
     #[serde(default = "default_jwt_expiration_secs")]
     pub jwt_expiration_secs: i64,
+    /// Endpoints, retries and circuit breaker for GitHub API calls
+    #[serde(default)]
+    pub github: GitHubApiConfig,
+    /// Static keys accepted in the `X-API-Key` header, for clients such as
+    /// CI that can't go through the device flow
+    #[serde(default)]
+    pub api_keys: Vec<ApiKey>,
+}
+
+/// A static API key, stored as its SHA256 so the config never holds the key
+#[derive(Deserialize, Clone)]
+pub struct ApiKey {
+    /// User requests with the key act as; must be allowed like any other user
+    pub username: String,
+    /// Hex SHA256 of the key
+    pub key_sha256: String,
+    /// What requests made with the key may do
+    #[serde(default)]
+    pub scope: ApiKeyScope,
+}
+
+/// What an API key may do on behalf of its user
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ApiKeyScope {
+    /// Everything the user may do
+    #[default]
+    #[serde(alias = "api_key")]
+    Write,
+    /// Only GET and HEAD requests; writes are refused with 403
+    ReadOnly,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GitHubApiConfig {
+    /// Base URL of the GitHub web endpoints used by the device flow
+    #[serde(default = "default_github_url")]
+    pub url: String,
+
+    /// Base URL of the GitHub REST API
+    #[serde(default = "default_github_api_url")]
+    pub api_url: String,
+
+    /// Retries of an idempotent GET after a network error, 5xx or 429
+    #[serde(default = "default_github_max_retries")]
+    pub max_retries: u32,
+
+    /// Delay before the first retry, doubled for each further retry
+    #[serde(default = "default_github_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+
+    /// Consecutive failed calls after which GitHub calls fail fast
+    #[serde(default = "default_github_breaker_threshold")]
+    pub breaker_threshold: u32,
+
+    /// How long calls fail fast once the breaker has opened
+    #[serde(default = "default_github_breaker_cooldown_secs")]
+    pub breaker_cooldown_secs: u64,
+}
+
+impl Default for GitHubApiConfig {
+    fn default() -> Self {
+        Self {
+            url: default_github_url(),
+            api_url: default_github_api_url(),
+            max_retries: default_github_max_retries(),
+            retry_backoff_ms: default_github_retry_backoff_ms(),
+            breaker_threshold: default_github_breaker_threshold(),
+            breaker_cooldown_secs: default_github_breaker_cooldown_secs(),
+        }
+    }
+}
+
+fn default_github_url() -> String {
+    "https://github.com".to_string()
+}
+
+fn default_github_api_url() -> String {
+    "https://api.github.com".to_string()
+}
+
+fn default_github_max_retries() -> u32 {
+    3
+}
+
+fn default_github_retry_backoff_ms() -> u64 {
+    500
+}
+
+fn default_github_breaker_threshold() -> u32 {
+    5
+}
+
+fn default_github_breaker_cooldown_secs() -> u64 {
+    60
+}
+
+fn default_allowed_users_reload_secs() -> u64 {
+    60
 }
 
 fn default_jwt_expiration_secs() -> i64 {
@@ -31,8 +139,109 @@ pub struct ServerConfig {
     #[serde(default = "default_port")]
     pub port: u16,
 
+    /// Largest package accepted, and the largest single request body the
+    /// API reads
     #[serde(default = "default_max_payload_size")]
     pub max_payload_size: Byte,
+
+    /// Maximum combined size of all uploads in progress; new uploads beyond
+    /// it are refused with 507. Unlimited when unset.
+    #[serde(default)]
+    pub max_total_inflight_bytes: Option<Byte>,
+
+    /// Maximum number of requests handled at once; further requests get 503.
+    /// Unlimited when unset.
+    #[serde(default)]
+    pub max_concurrent_requests: Option<usize>,
+
+    /// Maximum number of uploads, other writes and package downloads in
+    /// flight from one client IP; further requests get 503. Unlimited when
+    /// unset.
+    #[serde(default)]
+    pub max_concurrent_requests_per_ip: Option<usize>,
+
+    /// Maximum uploads and other write requests per minute from one client:
+    /// the authenticated user, or the IP of an anonymous one. Further
+    /// requests get 429. Unlimited when unset.
+    #[serde(default)]
+    pub upload_rate_limit: Option<u32>,
+
+    /// Maximum number of packages returned by a single list request
+    #[serde(default = "default_max_list_results")]
+    pub max_list_results: usize,
+
+    /// Longest upload session expiration a client may request, in seconds
+    #[serde(default = "default_max_upload_expiration_secs")]
+    pub max_upload_expiration_secs: i64,
+
+    /// Keep the chunks of an upload whose package was rejected as invalid,
+    /// like those of any other failed completion, instead of discarding the
+    /// session at once
+    #[serde(default)]
+    pub keep_invalid_uploads: bool,
+
+    /// Maximum number of package names accepted by one batch-info request
+    #[serde(default = "default_max_batch_size")]
+    pub max_batch_size: usize,
+
+    /// `Cache-Control` max-age for package files and their sidecars, which
+    /// are immutable once published
+    #[serde(default = "default_package_cache_max_age_secs")]
+    pub package_cache_max_age_secs: u64,
+
+    /// `Cache-Control` max-age for db files; 0 sends `no-cache`
+    #[serde(default)]
+    pub db_cache_max_age_secs: u64,
+
+    /// Answer write requests with 503 until the startup database rebuild has
+    /// finished
+    #[serde(default)]
+    pub reject_writes_until_ready: bool,
+
+    /// Number of recent events kept for `GET /api/admin/events`
+    #[serde(default = "default_event_log_capacity")]
+    pub event_log_capacity: usize,
+
+    /// Log method, path, status, response size and duration for every request
+    #[serde(default)]
+    pub access_log: bool,
+
+    /// Only serve repository files to requests carrying a valid, unexpired
+    /// `exp`/`sig` pair signed with `download_signing_secret`
+    #[serde(default)]
+    pub require_signed_downloads: bool,
+
+    /// HMAC secret for signed download URLs (at least 32 characters)
+    #[serde(default)]
+    pub download_signing_secret: Option<String>,
+
+    /// Which browser origins may call the server. Any origin may when unset.
+    #[serde(default)]
+    pub cors: Option<CorsConfig>,
+}
+
+/// CORS policy for browser clients
+#[derive(Debug, Deserialize, Clone)]
+pub struct CorsConfig {
+    /// Origins allowed to make requests, e.g. `https://repo-ui.example.com`,
+    /// or `"*"` for any origin
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+
+    /// Methods allowed in cross-origin requests
+    #[serde(default = "default_cors_allowed_methods")]
+    pub allowed_methods: Vec<String>,
+
+    /// Let browsers send cookies and `Authorization` headers; not allowed
+    /// with the `"*"` origin
+    #[serde(default)]
+    pub allow_credentials: bool,
+}
+
+fn default_cors_allowed_methods() -> Vec<String> {
+    ["GET", "HEAD", "POST", "PUT", "DELETE"]
+        .map(str::to_string)
+        .to_vec()
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -40,6 +249,10 @@ pub struct StorageConfig {
     #[serde(default = "default_data_path")]
     pub data_path: PathBuf,
 
+    /// Create `data_path` at startup if it doesn't exist
+    #[serde(default = "default_create_data_path")]
+    pub create_data_path: bool,
+
     #[serde(default = "default_repo_name")]
     pub default_repo: String,
 
@@ -48,6 +261,212 @@ pub struct StorageConfig {
 
     #[serde(default = "default_auto_cleanup_enabled")]
     pub auto_cleanup_enabled: bool,
+
+    /// Versions kept by cleanup, both automatic after an upload and through
+    /// the cleanup endpoints when their request doesn't give a policy
+    #[serde(default)]
+    pub cleanup_policy: CleanupPolicy,
+
+    /// Extract `.BUILDINFO` and `.MTREE` from uploaded packages and store them
+    /// as sidecar files next to the package
+    #[serde(default)]
+    pub extract_provenance: bool,
+
+    /// With `extract_provenance`, reject packages whose `.BUILDINFO` name,
+    /// version or arch disagree with `.PKGINFO`
+    #[serde(default)]
+    pub strict_provenance: bool,
+
+    /// Reject uploads whose data isn't compressed the way the filename
+    /// suffix says (`.zst` zstd, `.gz` gzip, `.xz` xz)
+    #[serde(default = "default_verify_compression")]
+    pub verify_compression: bool,
+
+    /// Reject uploads whose declared filename differs from the
+    /// `{pkgname}-{pkgver}-{arch}.pkg.tar.zst` of their `.PKGINFO`; when off
+    /// the mismatch is only logged and the `.PKGINFO` name is stored
+    #[serde(default = "default_verify_filename")]
+    pub verify_filename: bool,
+
+    /// Accept a `.PKGINFO` that isn't valid UTF-8 by replacing the invalid
+    /// sequences, instead of rejecting the package
+    #[serde(default)]
+    pub lossy_pkginfo: bool,
+
+    /// Verify the detached signature of every upload against
+    /// `trusted_keyring`, rejecting unsigned packages and bad signatures
+    #[serde(default)]
+    pub verify_signatures: bool,
+
+    /// Keyring (`gpg --export` output or a keybox) holding the keys trusted
+    /// to sign packages
+    #[serde(default)]
+    pub trusted_keyring: Option<PathBuf>,
+
+    /// Per-repo signature policies, overriding `verify_signatures` and
+    /// `trusted_keyring` for the repos listed
+    #[serde(default)]
+    pub repos: HashMap<String, RepoPolicy>,
+
+    /// Maximum length in bytes of a repo, arch or file name on disk
+    #[serde(default = "default_max_filename_length")]
+    pub max_filename_length: usize,
+
+    /// Also link every stored package into `data/.pool/{sha256[..2]}/`
+    #[serde(default)]
+    pub maintain_pool: bool,
+
+    /// Keep a `{name}-latest-{arch}.pkg.tar.zst` link next to the packages of
+    /// every name/arch, pointing at its newest version
+    #[serde(default)]
+    pub maintain_latest_symlink: bool,
+
+    /// Reject a package whose SHA256 matches another stored version of the
+    /// same name, instead of only logging a warning
+    #[serde(default)]
+    pub reject_duplicate_content: bool,
+
+    /// Remove the db files of a repo/arch once its last package is gone,
+    /// instead of publishing an empty db
+    #[serde(default)]
+    pub remove_empty_db: bool,
+
+    /// Also write `index.json`, a JSON list of the packages in the db, next
+    /// to the db of every repo/arch for clients that don't read pacman dbs
+    #[serde(default)]
+    pub generate_json_index: bool,
+
+    /// How package metadata is stored: one JSON file per package, or one
+    /// zstd-compressed index per repo that is faster to scan for repos with
+    /// very many packages
+    #[serde(default)]
+    pub metadata_store: MetadataStore,
+
+    /// Compression of the repository and files databases. The `{repo}.db`
+    /// and `{repo}.files` links point at the archives in this format.
+    /// Also accepted as `db_format`.
+    #[serde(default, alias = "db_format")]
+    pub db_compression_format: DbCompressionFormat,
+
+    /// Gzip level (0-9) of the databases: lower regenerates faster, higher
+    /// makes smaller dbs. zstd dbs use zstd's default level.
+    #[serde(default = "default_db_compression_level")]
+    pub db_compression_level: u32,
+
+    /// Answer completed uploads with 202 and `db_update_pending` instead of
+    /// 201; clients poll the db-status endpoint to see the db catch up
+    #[serde(default)]
+    pub async_db_update: bool,
+
+    /// Reject storage paths running through a symlink below `data_path`
+    #[serde(default)]
+    pub reject_symlinks: bool,
+
+    /// Alternative arch names mapped to the arch packages are stored under,
+    /// e.g. `armv7h = "armv7l"`
+    #[serde(default)]
+    pub arch_aliases: HashMap<String, String>,
+
+    /// Archs packages may be uploaded for, after alias resolution; empty
+    /// allows any. `default_arch` is always allowed.
+    #[serde(default = "default_allowed_archs")]
+    pub allowed_archs: Vec<String>,
+
+    /// On startup, move packages found in the legacy `data/{repo}/{arch}/`
+    /// layout into the current one
+    #[serde(default)]
+    pub migrate_layout: bool,
+
+    /// Days to keep upload receipts in `data/.receipts/` before pruning them
+    #[serde(default = "default_receipt_retention_days")]
+    pub receipt_retention_days: u64,
+
+    /// Seconds between sweeps removing expired upload sessions
+    #[serde(default = "default_session_cleanup_interval_secs")]
+    pub session_cleanup_interval_secs: u64,
+}
+
+/// Signature policy of one repo
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RepoPolicy {
+    /// Verify the detached signature of every upload to this repo,
+    /// rejecting unsigned packages and bad signatures
+    #[serde(default)]
+    pub require_signature: bool,
+
+    /// Keyring trusted to sign this repo's packages, instead of
+    /// `trusted_keyring`
+    #[serde(default)]
+    pub keyring_path: Option<PathBuf>,
+}
+
+/// On-disk layout of package metadata within `data/{repo}/metadata/`
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataStore {
+    /// One `{package-file-stem}.json` per package
+    #[default]
+    PerPackage,
+    /// A single `metadata.json.zst` holding every package of the repo
+    Bundled,
+}
+
+/// Which versions of a package cleanup keeps
+///
+/// Versions differing only in pkgrel count as one, of which only the newest
+/// pkgrel is kept. The newest version is always kept.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, ToSchema)]
+#[serde(default)]
+pub struct CleanupPolicy {
+    /// Keep the N newest versions, whatever their minor version
+    pub keep_latest: usize,
+    /// Keep the N newest versions of the newest version's minor
+    pub keep_per_minor: usize,
+    /// Keep the newest version of each of the N minors before the newest
+    /// version's (within its epoch and major)
+    pub keep_previous_minors: usize,
+}
+
+impl Default for CleanupPolicy {
+    /// Keep the current version, the latest other version of its minor and
+    /// the latest of the previous minor
+    fn default() -> Self {
+        Self {
+            keep_latest: 1,
+            keep_per_minor: 2,
+            keep_previous_minors: 1,
+        }
+    }
+}
+
+/// Compression of the generated repository databases
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DbCompressionFormat {
+    /// `{repo}.db.tar.gz`, readable by every pacman version
+    #[default]
+    #[serde(alias = "gz")]
+    Gzip,
+    /// `{repo}.db.tar.zst`, smaller and faster to read; needs pacman 5.2+
+    #[serde(alias = "zst")]
+    Zstd,
+}
+
+impl DbCompressionFormat {
+    /// Archive suffix after `.tar`, e.g. `gz`
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Gzip => "gz",
+            Self::Zstd => "zst",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Gzip => "application/gzip",
+            Self::Zstd => "application/zstd",
+        }
+    }
 }
 
 fn default_host() -> String {
@@ -62,6 +481,51 @@ fn default_max_payload_size() -> Byte {
     Byte::from_u64_with_unit(512, byte_unit::Unit::MiB).unwrap()
 }
 
+fn default_verify_compression() -> bool {
+    true
+}
+
+fn default_verify_filename() -> bool {
+    true
+}
+
+/// flate2's default gzip level
+fn default_db_compression_level() -> u32 {
+    6
+}
+
+fn default_receipt_retention_days() -> u64 {
+    30
+}
+
+fn default_session_cleanup_interval_secs() -> u64 {
+    crate::upload::DEFAULT_CLEANUP_INTERVAL_SECS
+}
+
+fn default_create_data_path() -> bool {
+    true
+}
+
+fn default_max_list_results() -> usize {
+    1000
+}
+
+fn default_max_upload_expiration_secs() -> i64 {
+    604800 // 7 days
+}
+
+fn default_package_cache_max_age_secs() -> u64 {
+    365 * 24 * 60 * 60
+}
+
+fn default_event_log_capacity() -> usize {
+    1000
+}
+
+fn default_max_batch_size() -> usize {
+    100
+}
+
 fn default_data_path() -> PathBuf {
     PathBuf::from("data")
 }
@@ -74,10 +538,85 @@ fn default_arch() -> String {
     "x86_64".to_string()
 }
 
+fn default_allowed_archs() -> Vec<String> {
+    ["x86_64", "aarch64", "any"].map(str::to_string).to_vec()
+}
+
 fn default_auto_cleanup_enabled() -> bool {
     true
 }
 
+fn default_max_filename_length() -> usize {
+    crate::storage::DEFAULT_MAX_COMPONENT_LEN
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            data_path: default_data_path(),
+            create_data_path: default_create_data_path(),
+            default_repo: default_repo_name(),
+            default_arch: default_arch(),
+            auto_cleanup_enabled: default_auto_cleanup_enabled(),
+            cleanup_policy: CleanupPolicy::default(),
+            extract_provenance: false,
+            strict_provenance: false,
+            verify_compression: default_verify_compression(),
+            verify_filename: default_verify_filename(),
+            lossy_pkginfo: false,
+            verify_signatures: false,
+            trusted_keyring: None,
+            repos: HashMap::new(),
+            max_filename_length: default_max_filename_length(),
+            maintain_pool: false,
+            maintain_latest_symlink: false,
+            reject_duplicate_content: false,
+            remove_empty_db: false,
+            generate_json_index: false,
+            metadata_store: MetadataStore::default(),
+            db_compression_format: DbCompressionFormat::default(),
+            db_compression_level: default_db_compression_level(),
+            async_db_update: false,
+            reject_symlinks: false,
+            migrate_layout: false,
+            receipt_retention_days: default_receipt_retention_days(),
+            session_cleanup_interval_secs: default_session_cleanup_interval_secs(),
+            arch_aliases: HashMap::new(),
+            allowed_archs: default_allowed_archs(),
+        }
+    }
+}
+
+impl StorageConfig {
+    /// Resolve an arch alias to the canonical arch name
+    pub fn canonical_arch<'a>(&'a self, arch: &'a str) -> &'a str {
+        self.arch_aliases.get(arch).map_or(arch, String::as_str)
+    }
+
+    /// Whether packages may be uploaded for `arch`
+    pub fn arch_allowed(&self, arch: &str) -> bool {
+        self.allowed_archs.is_empty()
+            || arch == self.canonical_arch(&self.default_arch)
+            || self.allowed_archs.iter().any(|a| a == arch)
+    }
+
+    /// Whether uploads to `repo` must carry a signature that verifies, per
+    /// the repo's policy or else `verify_signatures`
+    pub fn requires_signature(&self, repo: &str) -> bool {
+        self.repos
+            .get(repo)
+            .map_or(self.verify_signatures, |policy| policy.require_signature)
+    }
+
+    /// Keyring signatures on `repo`'s packages are verified against
+    pub fn keyring_for(&self, repo: &str) -> Option<&PathBuf> {
+        self.repos
+            .get(repo)
+            .and_then(|policy| policy.keyring_path.as_ref())
+            .or(self.trusted_keyring.as_ref())
+    }
+}
+
 impl Config {
     pub fn load(config_path: Option<&str>) -> Result<Self> {
         let mut builder = config::Config::builder();
@@ -133,6 +672,84 @@ impl Config {
             config.storage.data_path = canonical;
         }
 
+        if let Some(secret) = &config.server.download_signing_secret
+            && secret.len() < 32
+        {
+            return Err(Error::Config {
+                msg: "download_signing_secret must be at least 32 characters".to_string(),
+            });
+        }
+        if config.server.require_signed_downloads && config.server.download_signing_secret.is_none()
+        {
+            return Err(Error::Config {
+                msg: "require_signed_downloads needs a download_signing_secret".to_string(),
+            });
+        }
+
+        if let Some(cors) = &config.server.cors {
+            let any_origin = cors.allowed_origins.iter().any(|origin| origin == "*");
+            if any_origin && cors.allow_credentials {
+                return Err(Error::Config {
+                    msg: "cors allow_credentials can't be used with the \"*\" origin".to_string(),
+                });
+            }
+            if let Some(origin) = cors
+                .allowed_origins
+                .iter()
+                .find(|origin| *origin != "*" && axum::http::HeaderValue::from_str(origin).is_err())
+            {
+                return Err(Error::Config {
+                    msg: format!("invalid cors origin: {origin}"),
+                });
+            }
+            if let Some(method) = cors
+                .allowed_methods
+                .iter()
+                .find(|method| axum::http::Method::from_bytes(method.as_bytes()).is_err())
+            {
+                return Err(Error::Config {
+                    msg: format!("invalid cors method: {method}"),
+                });
+            }
+        }
+
+        if config.server.max_upload_expiration_secs < 1 {
+            return Err(Error::Config {
+                msg: "max_upload_expiration_secs must be at least 1 second".to_string(),
+            });
+        }
+
+        if config.server.upload_rate_limit == Some(0) {
+            return Err(Error::Config {
+                msg: "upload_rate_limit must be at least 1 request per minute".to_string(),
+            });
+        }
+
+        if config.storage.db_compression_level > 9 {
+            return Err(Error::Config {
+                msg: format!(
+                    "db_compression_level must be 0-9, got {}",
+                    config.storage.db_compression_level
+                ),
+            });
+        }
+
+        if config.storage.verify_signatures && config.storage.trusted_keyring.is_none() {
+            return Err(Error::Config {
+                msg: "verify_signatures needs a trusted_keyring".to_string(),
+            });
+        }
+        for (repo, policy) in &config.storage.repos {
+            if policy.require_signature && config.storage.keyring_for(repo).is_none() {
+                return Err(Error::Config {
+                    msg: format!(
+                        "repo {} requires signatures but has no keyring_path or trusted_keyring",
+                        repo
+                    ),
+                });
+            }
+        }
+
         // Validate auth config if present
         if let Some(ref auth) = config.auth {
             if auth.jwt_secret.len() < 32 {
@@ -140,10 +757,31 @@ impl Config {
                     msg: "jwt_secret must be at least 32 characters".to_string(),
                 });
             }
-            if auth.allowed_users.is_empty() {
-                return Err(Error::Config {
-                    msg: "allowed_users must not be empty when auth is configured".to_string(),
-                });
+            match (&auth.allowed_users_file, auth.allowed_users.is_empty()) {
+                (Some(_), false) => {
+                    return Err(Error::Config {
+                        msg: "allowed_users and allowed_users_file are mutually exclusive"
+                            .to_string(),
+                    });
+                }
+                (None, true) => {
+                    return Err(Error::Config {
+                        msg: "allowed_users must not be empty when auth is configured".to_string(),
+                    });
+                }
+                _ => {}
+            }
+            for key in &auth.api_keys {
+                if key.key_sha256.len() != 64
+                    || !key.key_sha256.bytes().all(|b| b.is_ascii_hexdigit())
+                {
+                    return Err(Error::Config {
+                        msg: format!(
+                            "api key for '{}' must have a key_sha256 of 64 hex digits",
+                            key.username
+                        ),
+                    });
+                }
             }
         }
 
@@ -173,18 +811,54 @@ impl Default for Config {
                 host: default_host(),
                 port: default_port(),
                 max_payload_size: default_max_payload_size(),
+                max_total_inflight_bytes: None,
+                max_concurrent_requests: None,
+                max_concurrent_requests_per_ip: None,
+                upload_rate_limit: None,
+                max_list_results: default_max_list_results(),
+                max_upload_expiration_secs: default_max_upload_expiration_secs(),
+                keep_invalid_uploads: false,
+                max_batch_size: default_max_batch_size(),
+                package_cache_max_age_secs: default_package_cache_max_age_secs(),
+                db_cache_max_age_secs: 0,
+                reject_writes_until_ready: false,
+                event_log_capacity: default_event_log_capacity(),
+                access_log: false,
+                require_signed_downloads: false,
+                download_signing_secret: None,
+                cors: None,
             },
             storage: StorageConfig {
                 data_path,
-                default_repo: default_repo_name(),
-                default_arch: default_arch(),
-                auto_cleanup_enabled: default_auto_cleanup_enabled(),
+                ..StorageConfig::default()
             },
             auth: None,
         }
     }
 }
 
+impl std::fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("github_client_id", &self.github_client_id)
+            .field("allowed_users", &self.allowed_users)
+            .field("allowed_users_file", &self.allowed_users_file)
+            .field("allowed_users_reload_secs", &self.allowed_users_reload_secs)
+            .field("jwt_secret", &"<redacted>")
+            .field("jwt_expiration_secs", &self.jwt_expiration_secs)
+            .field("github", &self.github)
+            .field(
+                "api_keys",
+                &self
+                    .api_keys
+                    .iter()
+                    .map(|key| &key.username)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
 impl std::fmt::Debug for ServerConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ServerConfig")
@@ -198,6 +872,39 @@ impl std::fmt::Debug for ServerConfig {
                         .get_appropriate_unit(byte_unit::UnitType::Binary)
                 ),
             )
+            .field(
+                "max_total_inflight_bytes",
+                &self
+                    .max_total_inflight_bytes
+                    .map(|b| format!("{}", b.get_appropriate_unit(byte_unit::UnitType::Binary))),
+            )
+            .field("max_concurrent_requests", &self.max_concurrent_requests)
+            .field(
+                "max_concurrent_requests_per_ip",
+                &self.max_concurrent_requests_per_ip,
+            )
+            .field("upload_rate_limit", &self.upload_rate_limit)
+            .field("max_list_results", &self.max_list_results)
+            .field(
+                "max_upload_expiration_secs",
+                &self.max_upload_expiration_secs,
+            )
+            .field("keep_invalid_uploads", &self.keep_invalid_uploads)
+            .field("max_batch_size", &self.max_batch_size)
+            .field(
+                "package_cache_max_age_secs",
+                &self.package_cache_max_age_secs,
+            )
+            .field("db_cache_max_age_secs", &self.db_cache_max_age_secs)
+            .field("reject_writes_until_ready", &self.reject_writes_until_ready)
+            .field("event_log_capacity", &self.event_log_capacity)
+            .field("access_log", &self.access_log)
+            .field("require_signed_downloads", &self.require_signed_downloads)
+            .field(
+                "download_signing_secret",
+                &self.download_signing_secret.as_ref().map(|_| "<redacted>"),
+            )
+            .field("cors", &self.cors)
             .finish()
     }
 }
@@ -254,6 +961,259 @@ data_path = "./my_data"
         );
     }
 
+    #[test]
+    fn test_repo_policy_overrides_global_signature_settings() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        fs::write(
+            &config_path,
+            format!(
+                r#"
+[server]
+
+[storage]
+data_path = "{}"
+trusted_keyring = "/etc/trusted.gpg"
+
+[storage.repos.stable]
+require_signature = true
+keyring_path = "/etc/stable.gpg"
+
+[storage.repos.testing]
+require_signature = true
+"#,
+                temp_dir.path().display()
+            ),
+        )
+        .unwrap();
+
+        let storage = Config::load(Some(config_path.to_str().unwrap()))
+            .unwrap()
+            .storage;
+        assert!(storage.requires_signature("stable"));
+        assert!(!storage.requires_signature("other"));
+        assert_eq!(
+            storage.keyring_for("stable"),
+            Some(&PathBuf::from("/etc/stable.gpg"))
+        );
+        // Falls back to the global keyring
+        assert_eq!(
+            storage.keyring_for("testing"),
+            Some(&PathBuf::from("/etc/trusted.gpg"))
+        );
+    }
+
+    #[test]
+    fn test_repo_requiring_signatures_needs_a_keyring() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        fs::write(
+            &config_path,
+            format!(
+                r#"
+[server]
+
+[storage]
+data_path = "{}"
+
+[storage.repos.stable]
+require_signature = true
+"#,
+                temp_dir.path().display()
+            ),
+        )
+        .unwrap();
+
+        let err = Config::load(Some(config_path.to_str().unwrap())).unwrap_err();
+        assert!(err.to_string().contains("repo stable"), "{err}");
+    }
+
+    #[test]
+    fn test_db_format_accepts_archive_suffixes() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        for (format, expected) in [
+            ("zst", DbCompressionFormat::Zstd),
+            ("gz", DbCompressionFormat::Gzip),
+        ] {
+            fs::write(
+                &config_path,
+                format!(
+                    r#"
+[server]
+
+[storage]
+data_path = "{}"
+db_format = "{}"
+"#,
+                    temp_dir.path().display(),
+                    format
+                ),
+            )
+            .unwrap();
+
+            let config = Config::load(Some(config_path.to_str().unwrap())).unwrap();
+            assert_eq!(config.storage.db_compression_format, expected);
+        }
+    }
+
+    #[test]
+    fn test_db_compression_level_out_of_range_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        fs::write(
+            &config_path,
+            format!(
+                r#"
+[server]
+
+[storage]
+data_path = "{}"
+db_compression_level = 10
+"#,
+                temp_dir.path().display()
+            ),
+        )
+        .unwrap();
+
+        let err = Config::load(Some(config_path.to_str().unwrap())).unwrap_err();
+        assert!(err.to_string().contains("db_compression_level"), "{err}");
+    }
+
+    #[test]
+    fn test_api_key_needs_a_sha256() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        fs::write(
+            &config_path,
+            format!(
+                r#"
+[server]
+
+[storage]
+data_path = "{}"
+
+[auth]
+github_client_id = "client"
+allowed_users = ["ci"]
+jwt_secret = "0123456789abcdef0123456789abcdef"
+
+[[auth.api_keys]]
+username = "ci"
+key_sha256 = "not-a-hash"
+"#,
+                temp_dir.path().display()
+            ),
+        )
+        .unwrap();
+
+        let err = Config::load(Some(config_path.to_str().unwrap())).unwrap_err();
+        assert!(err.to_string().contains("key_sha256"), "{err}");
+    }
+
+    #[test]
+    fn test_default_arch_always_allowed() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        fs::write(
+            &config_path,
+            format!(
+                r#"
+[server]
+
+[storage]
+data_path = "{}"
+default_arch = "armv7h"
+"#,
+                temp_dir.path().display()
+            ),
+        )
+        .unwrap();
+
+        let config = Config::load(Some(config_path.to_str().unwrap())).unwrap();
+        assert!(config.storage.arch_allowed("armv7h"));
+        assert!(config.storage.arch_allowed("any"));
+        assert!(!config.storage.arch_allowed("riscv64"));
+    }
+
+    #[test]
+    fn test_zero_upload_expiration_ceiling_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        fs::write(
+            &config_path,
+            format!(
+                r#"
+[server]
+max_upload_expiration_secs = 0
+
+[storage]
+data_path = "{}"
+"#,
+                temp_dir.path().display()
+            ),
+        )
+        .unwrap();
+
+        let err = Config::load(Some(config_path.to_str().unwrap())).unwrap_err();
+        assert!(
+            err.to_string().contains("max_upload_expiration_secs"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_cors_any_origin_rejects_credentials() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        fs::write(
+            &config_path,
+            format!(
+                r#"
+[server.cors]
+allowed_origins = ["*"]
+allow_credentials = true
+
+[storage]
+data_path = "{}"
+"#,
+                temp_dir.path().display()
+            ),
+        )
+        .unwrap();
+
+        let err = Config::load(Some(config_path.to_str().unwrap())).unwrap_err();
+        assert!(err.to_string().contains("allow_credentials"), "{err}");
+    }
+
+    #[test]
+    fn test_max_payload_size_parses_human_readable_sizes() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        fs::write(
+            &config_path,
+            format!(
+                r#"
+[server]
+max_payload_size = "10MiB"
+
+[storage]
+data_path = "{}"
+"#,
+                temp_dir.path().join("data").display()
+            ),
+        )
+        .unwrap();
+
+        let config = Config::load(Some(config_path.to_str().unwrap())).unwrap();
+        assert_eq!(config.server.max_payload_size.as_u64(), 10 * 1024 * 1024);
+
+        assert_eq!(
+            Config::default().server.max_payload_size.as_u64(),
+            512 * 1024 * 1024
+        );
+    }
+
     #[test]
     fn test_default_relative_path_converted_to_absolute() {
         let config = Config::default();
@@ -3,10 +3,10 @@
 //! Serializes and coalesces repository database updates to prevent corruption
 //! from concurrent regeneration and improve efficiency.
 
-use crate::api::regenerate_repo_db;
-use crate::storage::Storage;
+use crate::api::{DbOptions, regenerate_repo_db};
+use crate::storage::PackageStore;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
 use tokio::time::{Duration, Instant};
 
@@ -30,6 +30,31 @@ impl RepoArchKey {
     }
 }
 
+/// Generation counters for one repo/arch database
+///
+/// `requested` is bumped on every update request; `applied` is set to the
+/// `requested` value a successful regeneration started from. The db reflects
+/// every change made so far when the two are equal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DbGeneration {
+    pub requested: u64,
+    pub applied: u64,
+    /// The `requested` value the last regeneration started from, whether it
+    /// succeeded or not
+    pub attempted: u64,
+    /// Successful regenerations so far; requests arriving within the
+    /// debounce window share one
+    pub regenerations: u64,
+}
+
+impl DbGeneration {
+    pub fn is_current(&self) -> bool {
+        self.applied >= self.requested
+    }
+}
+
+type Generations = Arc<Mutex<HashMap<RepoArchKey, DbGeneration>>>;
+
 /// Message sent to the actor
 #[derive(Debug)]
 pub enum DbUpdateMessage {
@@ -45,9 +70,36 @@ pub enum DbUpdateMessage {
 #[derive(Clone)]
 pub struct DbUpdateHandle {
     tx: mpsc::Sender<DbUpdateMessage>,
+    generations: Generations,
 }
 
 impl DbUpdateHandle {
+    /// Current generation counters for the given repo/arch
+    pub fn generation(&self, repo: &str, arch: &str) -> DbGeneration {
+        self.generations
+            .lock()
+            .expect("generation lock poisoned")
+            .get(&RepoArchKey::new(repo, arch))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Whether every database includes all changes requested so far
+    pub fn all_current(&self) -> bool {
+        self.generations
+            .lock()
+            .expect("generation lock poisoned")
+            .values()
+            .all(DbGeneration::is_current)
+    }
+
+    fn bump_requested(&self, key: &RepoArchKey) -> u64 {
+        let mut generations = self.generations.lock().expect("generation lock poisoned");
+        let entry = generations.entry(key.clone()).or_default();
+        entry.requested += 1;
+        entry.requested
+    }
+
     /// Request a database update for the given repo/arch.
     /// This is fire-and-forget - updates are coalesced with debounce.
     pub async fn request_update<R, A>(&self, repo: R, arch: A)
@@ -56,6 +108,7 @@ impl DbUpdateHandle {
         A: Into<String>,
     {
         let key = RepoArchKey::new(repo, arch);
+        self.bump_requested(&key);
         if let Err(e) = self.tx.send(DbUpdateMessage::RequestUpdate(key)).await {
             tracing::error!(error = %e, "Failed to send database update request");
         }
@@ -63,15 +116,20 @@ impl DbUpdateHandle {
 
     /// Force an immediate database rebuild, bypassing the debounce.
     /// This is fire-and-forget - the rebuild will happen as soon as possible.
-    pub async fn force_rebuild<R, A>(&self, repo: R, arch: A)
+    ///
+    /// Returns the generation requested; the rebuild has run once
+    /// `attempted` reaches it.
+    pub async fn force_rebuild<R, A>(&self, repo: R, arch: A) -> u64
     where
         R: Into<String>,
         A: Into<String>,
     {
         let key = RepoArchKey::new(repo, arch);
+        let requested = self.bump_requested(&key);
         if let Err(e) = self.tx.send(DbUpdateMessage::ForceRebuild(key)).await {
             tracing::error!(error = %e, "Failed to send force rebuild request");
         }
+        requested
     }
 
     /// Request graceful shutdown of the actor
@@ -93,9 +151,11 @@ struct PendingUpdate {
 /// Database update actor - serializes and coalesces DB regeneration
 pub struct DbUpdateActor {
     rx: mpsc::Receiver<DbUpdateMessage>,
-    storage: Arc<Storage>,
+    storage: Arc<dyn PackageStore>,
+    db_options: DbOptions,
     pending: HashMap<RepoArchKey, PendingUpdate>,
     debounce_duration: Duration,
+    generations: Generations,
 }
 
 impl DbUpdateActor {
@@ -106,29 +166,39 @@ impl DbUpdateActor {
     const CHANNEL_CAPACITY: usize = 100;
 
     /// Create a new actor and its handle
-    pub fn new(storage: Arc<Storage>) -> (Self, DbUpdateHandle) {
+    pub fn new(storage: Arc<dyn PackageStore>) -> (Self, DbUpdateHandle) {
         Self::with_debounce(storage, Duration::from_secs(Self::DEFAULT_DEBOUNCE_SECS))
     }
 
     /// Create with custom debounce duration (useful for testing)
     pub fn with_debounce(
-        storage: Arc<Storage>,
+        storage: Arc<dyn PackageStore>,
         debounce_duration: Duration,
     ) -> (Self, DbUpdateHandle) {
         let (tx, rx) = mpsc::channel(Self::CHANNEL_CAPACITY);
+        let generations = Generations::default();
 
         let actor = Self {
             rx,
             storage,
+            db_options: DbOptions::default(),
             pending: HashMap::new(),
             debounce_duration,
+            generations: Arc::clone(&generations),
         };
 
-        let handle = DbUpdateHandle { tx };
+        let handle = DbUpdateHandle { tx, generations };
 
         (actor, handle)
     }
 
+    /// Generate databases with `options` rather than the default storage
+    /// config's
+    pub fn with_db_options(mut self, options: DbOptions) -> Self {
+        self.db_options = options;
+        self
+    }
+
     /// Run the actor loop
     pub async fn run(mut self) {
         tracing::info!(
@@ -292,7 +362,28 @@ impl DbUpdateActor {
     async fn regenerate_db(&self, key: &RepoArchKey) {
         let _timer = crate::metrics::ScopedTimer::db_rebuild(key.repo.clone(), key.arch.clone());
 
-        if let Err(e) = regenerate_repo_db(&self.storage, &key.repo, &key.arch).await {
+        // Changes requested from here on may not be picked up by this run
+        let generation = self
+            .generations
+            .lock()
+            .expect("generation lock poisoned")
+            .get(key)
+            .map_or(0, |g| g.requested);
+
+        let result = regenerate_repo_db(
+            self.storage.as_ref(),
+            &self.db_options,
+            &key.repo,
+            &key.arch,
+        )
+        .await;
+        {
+            let mut generations = self.generations.lock().expect("generation lock poisoned");
+            let entry = generations.entry(key.clone()).or_default();
+            entry.attempted = entry.attempted.max(generation);
+        }
+
+        if let Err(e) = result {
             crate::metrics::record_db_rebuild(&key.repo, &key.arch, "error");
             tracing::error!(
                 repo = %key.repo,
@@ -301,6 +392,12 @@ impl DbUpdateActor {
                 "Failed to regenerate repository database"
             );
         } else {
+            let mut generations = self.generations.lock().expect("generation lock poisoned");
+            let entry = generations.entry(key.clone()).or_default();
+            entry.applied = entry.applied.max(generation);
+            entry.regenerations += 1;
+            drop(generations);
+
             crate::metrics::record_db_rebuild(&key.repo, &key.arch, "success");
             tracing::info!(
                 repo = %key.repo,
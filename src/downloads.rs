@@ -0,0 +1,247 @@
+//! Per-package download counts
+//!
+//! Every package file served is counted in memory, per repo/arch, and the
+//! counts are written to `data/.downloads/{repo}/{arch}.json` by a
+//! background task rather than on every download. A repo/arch's counts are
+//! read from disk the first time it is counted or queried.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::fs;
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+
+use crate::db_actor::RepoArchKey;
+use crate::error::{Error, Result, ResultIoExt};
+use crate::models::Package;
+
+/// How often changed download counts are written to disk: 10 seconds
+pub const DEFAULT_FLUSH_INTERVAL_SECS: u64 = 10;
+
+/// Download count of one package version
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PackageDownloads {
+    pub name: String,
+    pub version: String,
+    pub downloads: u64,
+}
+
+/// Counts of one repo/arch, keyed by package filename
+#[derive(Debug, Default)]
+struct RepoArchCounts {
+    packages: BTreeMap<String, PackageDownloads>,
+    /// Changed since last written to disk
+    dirty: bool,
+}
+
+/// Download counts kept in memory and flushed to JSON files under
+/// `data/.downloads/`
+#[derive(Debug, Clone)]
+pub struct DownloadCounter {
+    dir: PathBuf,
+    counts: Arc<Mutex<HashMap<RepoArchKey, RepoArchCounts>>>,
+}
+
+impl DownloadCounter {
+    pub fn new(data_path: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: data_path.into().join(".downloads"),
+            counts: Arc::default(),
+        }
+    }
+
+    fn counts_path(&self, repo: &str, arch: &str) -> Result<PathBuf> {
+        for component in [repo, arch] {
+            if component.is_empty()
+                || component == "."
+                || component == ".."
+                || component.contains(['/', '\\', '\0'])
+            {
+                return Err(Error::InvalidPackage {
+                    pkgname: format!("Invalid path component: '{component}'"),
+                });
+            }
+        }
+        Ok(self.dir.join(repo).join(format!("{arch}.json")))
+    }
+
+    /// Read a repo/arch's counts from disk; missing or unreadable files
+    /// start from zero
+    async fn load(&self, repo: &str, arch: &str) -> Result<RepoArchCounts> {
+        let path = self.counts_path(repo, arch)?;
+        let packages = match fs::read(&path).await {
+            Ok(json) => serde_json::from_slice(&json).unwrap_or_else(|e| {
+                tracing::warn!(
+                    path = %path.display(),
+                    error = %e,
+                    "Ignoring unparseable download counts"
+                );
+                BTreeMap::new()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).map_io_err(&path),
+        };
+        Ok(RepoArchCounts {
+            packages,
+            dirty: false,
+        })
+    }
+
+    /// Count one download of `package` through the `repo`/`arch` URL
+    pub async fn record(&self, repo: &str, arch: &str, package: &Package) -> Result<()> {
+        let key = RepoArchKey::new(repo, arch);
+        let mut counts = self.counts.lock().await;
+        if !counts.contains_key(&key) {
+            let loaded = self.load(repo, arch).await?;
+            counts.insert(key.clone(), loaded);
+        }
+
+        let entry = counts.get_mut(&key).expect("counts loaded above");
+        entry
+            .packages
+            .entry(package.filename.clone())
+            .or_insert_with(|| PackageDownloads {
+                name: package.name.clone(),
+                version: package.version.clone(),
+                downloads: 0,
+            })
+            .downloads += 1;
+        entry.dirty = true;
+        Ok(())
+    }
+
+    /// The `limit` most downloaded packages of a repo/arch, most downloaded
+    /// first
+    pub async fn popular(
+        &self,
+        repo: &str,
+        arch: &str,
+        limit: usize,
+    ) -> Result<Vec<PackageDownloads>> {
+        let key = RepoArchKey::new(repo, arch);
+        let mut counts = self.counts.lock().await;
+        if !counts.contains_key(&key) {
+            let loaded = self.load(repo, arch).await?;
+            counts.insert(key.clone(), loaded);
+        }
+
+        let mut popular: Vec<PackageDownloads> = counts[&key].packages.values().cloned().collect();
+        popular.sort_by(|a, b| {
+            b.downloads
+                .cmp(&a.downloads)
+                .then_with(|| a.name.cmp(&b.name))
+                .then_with(|| crate::version::vercmp(&b.version, &a.version))
+        });
+        popular.truncate(limit);
+        Ok(popular)
+    }
+
+    /// Write every repo/arch whose counts changed since the last flush,
+    /// returning how many files were written
+    pub async fn flush(&self) -> Result<usize> {
+        let mut counts = self.counts.lock().await;
+        let mut written = 0;
+
+        for (key, entry) in counts.iter_mut().filter(|(_, entry)| entry.dirty) {
+            let path = self.counts_path(&key.repo, &key.arch)?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).await.map_io_err(parent)?;
+            }
+
+            let json = serde_json::to_vec_pretty(&entry.packages).map_err(std::io::Error::other)?;
+            let tmp_path = path.with_extension("json.tmp");
+            fs::write(&tmp_path, json).await.map_io_err(&tmp_path)?;
+            fs::rename(&tmp_path, &path).await.map_io_err(&path)?;
+
+            entry.dirty = false;
+            written += 1;
+        }
+
+        Ok(written)
+    }
+}
+
+/// Spawn a background task that periodically writes changed download counts
+pub fn spawn_flush_task(counter: DownloadCounter, interval_secs: u64) {
+    tokio::spawn(async move {
+        let interval = std::time::Duration::from_secs(interval_secs);
+
+        loop {
+            tokio::time::sleep(interval).await;
+
+            if let Err(e) = counter.flush().await {
+                tracing::error!(error = %e, "Failed to write download counts");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn test_package(name: &str, version: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            arch: "x86_64".to_string(),
+            repo: "test".to_string(),
+            filename: format!("{name}-{version}-x86_64.pkg.tar.zst"),
+            sha256: String::new(),
+            size: 0,
+            created_at: chrono::Utc::now(),
+            signed: false,
+            signature_verified: false,
+            license: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_counts_survive_flush_and_reload() {
+        let temp_dir = TempDir::new().unwrap();
+        let counter = DownloadCounter::new(temp_dir.path());
+        let foo = test_package("foo", "1.0.0-1");
+        let bar = test_package("bar", "2.0.0-1");
+
+        counter.record("test", "x86_64", &foo).await.unwrap();
+        counter.record("test", "x86_64", &bar).await.unwrap();
+        counter.record("test", "x86_64", &bar).await.unwrap();
+
+        assert_eq!(counter.flush().await.unwrap(), 1);
+        // Nothing changed since
+        assert_eq!(counter.flush().await.unwrap(), 0);
+
+        let reloaded = DownloadCounter::new(temp_dir.path());
+        let popular = reloaded.popular("test", "x86_64", 10).await.unwrap();
+        let counts: Vec<_> = popular
+            .iter()
+            .map(|p| (p.name.as_str(), p.downloads))
+            .collect();
+        assert_eq!(counts, [("bar", 2), ("foo", 1)]);
+
+        assert_eq!(
+            reloaded.popular("test", "x86_64", 1).await.unwrap().len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_traversal_in_repo_and_arch() {
+        let temp_dir = TempDir::new().unwrap();
+        let counter = DownloadCounter::new(temp_dir.path());
+
+        assert!(matches!(
+            counter.popular("..", "x86_64", 10).await,
+            Err(Error::InvalidPackage { .. })
+        ));
+        assert!(matches!(
+            counter
+                .record("test", "../x86_64", &test_package("foo", "1.0.0-1"))
+                .await,
+            Err(Error::InvalidPackage { .. })
+        ));
+    }
+}
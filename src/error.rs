@@ -16,9 +16,15 @@ pub enum Error {
     #[display("Package already exists: {pkgname}")]
     PackageAlreadyExists { pkgname: String },
 
+    #[display("Package {pkgname} has the same content as existing package {existing}")]
+    DuplicateContent { pkgname: String, existing: String },
+
     #[display("Payload too large: {msg}")]
     PayloadTooLarge { msg: String },
 
+    #[display("Insufficient storage: {msg}")]
+    InsufficientStorage { msg: String },
+
     #[display("Metadata generation failed: {msg}")]
     MetadataGeneration { msg: String },
 
@@ -37,15 +43,51 @@ pub enum Error {
     #[display("GitHub API error: {msg}")]
     GitHubApi { msg: String },
 
+    #[display("GitHub unreachable, failing fast for {retry_after_secs}s")]
+    GitHubUnavailable { retry_after_secs: u64 },
+
     #[display("JWT error: {msg}")]
     Jwt { msg: String },
 
     #[display("Authentication not configured")]
     AuthNotConfigured,
+
+    #[display("Gone: {msg}")]
+    Gone { msg: String },
+
+    #[display("Invalid signature: {reason}")]
+    SignatureInvalid { reason: String },
 }
 
 impl std::error::Error for Error {}
 
+impl Error {
+    /// Stable, machine-readable name of the error kind, sent to clients
+    /// alongside the human-readable message
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Io { .. } => "io_error",
+            Error::PackageNotFound { .. } => "package_not_found",
+            Error::InvalidPackage { .. } => "invalid_package",
+            Error::PackageAlreadyExists { .. } => "package_already_exists",
+            Error::DuplicateContent { .. } => "duplicate_content",
+            Error::PayloadTooLarge { .. } => "payload_too_large",
+            Error::InsufficientStorage { .. } => "insufficient_storage",
+            Error::MetadataGeneration { .. } => "metadata_generation_failed",
+            Error::Config { .. } => "config_error",
+            Error::PermissionDenied { .. } => "permission_denied",
+            Error::Unauthorized => "unauthorized",
+            Error::Forbidden { .. } => "forbidden",
+            Error::GitHubApi { .. } => "github_api_error",
+            Error::GitHubUnavailable { .. } => "github_unavailable",
+            Error::Jwt { .. } => "invalid_token",
+            Error::AuthNotConfigured => "auth_not_configured",
+            Error::Gone { .. } => "gone",
+            Error::SignatureInvalid { .. } => "signature_invalid",
+        }
+    }
+}
+
 // Implement From<std::io::Error> for cases where path context is not available
 impl From<std::io::Error> for Error {
     fn from(error: std::io::Error) -> Self {
@@ -90,10 +132,24 @@ impl axum::response::IntoResponse for Error {
                     format!("Package already exists: {}", pkgname),
                 )
             }
+            Error::DuplicateContent { pkgname, existing } => {
+                // Safe to expose - just the filenames
+                (
+                    axum::http::StatusCode::CONFLICT,
+                    format!(
+                        "Package {} has the same content as existing package {}",
+                        pkgname, existing
+                    ),
+                )
+            }
             Error::PayloadTooLarge { msg } => {
                 // Safe to expose - contains size limits we configured
                 (axum::http::StatusCode::PAYLOAD_TOO_LARGE, msg.clone())
             }
+            Error::InsufficientStorage { msg } => {
+                // Safe to expose - contains size limits we configured
+                (axum::http::StatusCode::INSUFFICIENT_STORAGE, msg.clone())
+            }
             Error::Io { error, path } => {
                 // Log full error with path internally for debugging
                 tracing::error!("IO error at path {}: {}", path, error);
@@ -148,6 +204,13 @@ impl axum::response::IntoResponse for Error {
                     "GitHub API error".to_string(),
                 )
             }
+            Error::GitHubUnavailable { retry_after_secs } => {
+                // Safe to expose - only the remaining breaker cooldown
+                (
+                    axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                    format!("GitHub has been unreachable repeatedly, retry in {retry_after_secs}s"),
+                )
+            }
             Error::Jwt { msg } => {
                 tracing::warn!("JWT error: {msg}");
                 (
@@ -159,10 +222,22 @@ impl axum::response::IntoResponse for Error {
                 axum::http::StatusCode::NOT_IMPLEMENTED,
                 "Authentication is not configured on this server".to_string(),
             ),
+            Error::Gone { msg } => {
+                // Safe to expose - fixed message saying what to do instead
+                (axum::http::StatusCode::GONE, msg.clone())
+            }
+            Error::SignatureInvalid { reason } => {
+                // Safe to expose - reasons are fixed summaries, not gpgv output
+                (
+                    axum::http::StatusCode::BAD_REQUEST,
+                    format!("Invalid signature: {reason}"),
+                )
+            }
         };
 
         let body = axum::Json(serde_json::json!({
             "error": message,
+            "code": self.code(),
         }));
 
         (status, body).into_response()
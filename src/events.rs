@@ -0,0 +1,161 @@
+//! In-memory log of recent repository events
+//!
+//! Uploads, deletes, cleanups and failed uploads are recorded into a bounded
+//! ring buffer so a web UI can show recent activity without tailing the
+//! journal. New events are also broadcast to live subscribers.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+use crate::models::Package;
+
+/// Events buffered per live subscriber before it starts missing some
+const BROADCAST_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Upload,
+    UploadFailed,
+    Delete,
+    Cleanup,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RepoEvent {
+    pub timestamp: DateTime<Utc>,
+    pub kind: EventKind,
+    pub repo: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl RepoEvent {
+    pub fn new(kind: EventKind, repo: impl Into<String>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            kind,
+            repo: repo.into(),
+            arch: None,
+            package: None,
+            version: None,
+            user: None,
+            detail: None,
+        }
+    }
+
+    /// Event about a single stored package
+    pub fn for_package(kind: EventKind, package: &Package) -> Self {
+        Self {
+            arch: Some(package.arch.clone()),
+            package: Some(package.name.clone()),
+            version: Some(package.version.clone()),
+            ..Self::new(kind, &package.repo)
+        }
+    }
+
+    pub fn arch(mut self, arch: impl Into<String>) -> Self {
+        self.arch = Some(arch.into());
+        self
+    }
+
+    pub fn package(mut self, name: impl Into<String>) -> Self {
+        self.package = Some(name.into());
+        self
+    }
+
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// Bounded log of recent events, cheap to clone and share
+#[derive(Clone)]
+pub struct EventLog {
+    events: Arc<Mutex<VecDeque<RepoEvent>>>,
+    capacity: usize,
+    sender: broadcast::Sender<RepoEvent>,
+}
+
+impl EventLog {
+    /// Create a log keeping the last `capacity` events; 0 keeps none but
+    /// still notifies live subscribers
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(BROADCAST_CAPACITY);
+        Self {
+            events: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+            sender,
+        }
+    }
+
+    pub fn record(&self, event: RepoEvent) {
+        if self.capacity > 0 {
+            let mut events = self.events.lock().expect("event log lock poisoned");
+            if events.len() == self.capacity {
+                events.pop_front();
+            }
+            events.push_back(event.clone());
+        }
+        // No subscribers is not an error
+        let _ = self.sender.send(event);
+    }
+
+    /// The newest `limit` events, oldest first
+    pub fn recent(&self, limit: usize) -> Vec<RepoEvent> {
+        let events = self.events.lock().expect("event log lock poisoned");
+        let skip = events.len().saturating_sub(limit);
+        events.iter().skip(skip).cloned().collect()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<RepoEvent> {
+        self.sender.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_only_the_newest_events() {
+        let log = EventLog::new(2);
+        for repo in ["a", "b", "c"] {
+            log.record(RepoEvent::new(EventKind::Delete, repo));
+        }
+
+        let repos: Vec<_> = log.recent(10).into_iter().map(|e| e.repo).collect();
+        assert_eq!(repos, ["b", "c"]);
+
+        let repos: Vec<_> = log.recent(1).into_iter().map(|e| e.repo).collect();
+        assert_eq!(repos, ["c"]);
+    }
+
+    #[test]
+    fn zero_capacity_stores_nothing_but_broadcasts() {
+        let log = EventLog::new(0);
+        let mut rx = log.subscribe();
+        log.record(RepoEvent::new(EventKind::Upload, "sw1nn"));
+
+        assert!(log.recent(10).is_empty());
+        assert_eq!(rx.try_recv().unwrap().repo, "sw1nn");
+    }
+}
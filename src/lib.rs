@@ -1,14 +1,22 @@
+pub mod access_log;
+pub mod admission;
 pub mod api;
 pub mod auth;
 pub mod config;
 pub mod db_actor;
+pub mod downloads;
 pub mod error;
+pub mod events;
 pub mod metadata;
 pub mod metrics;
 pub mod models;
+pub mod receipts;
 pub mod repo;
+pub mod signed_url;
+pub mod signing;
 pub mod storage;
 pub mod upload;
+pub mod version;
 
 use api::{AppState, create_api_router};
 use axum::{Router, middleware, routing::get};
@@ -17,8 +25,9 @@ use db_actor::{DbUpdateActor, DbUpdateHandle};
 use repo::serve_file;
 use std::io::IsTerminal;
 use std::sync::Arc;
-use storage::Storage;
-use tower_http::cors::CorsLayer;
+use std::sync::atomic::{AtomicBool, Ordering};
+use storage::{FsStore, PackageStore};
+use tower_http::cors::{AllowHeaders, AllowOrigin, CorsLayer};
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use utoipa_rapidoc::RapiDoc;
@@ -41,6 +50,35 @@ pub fn init_tracing() {
     }
 }
 
+/// Build the CORS layer from the `[server.cors]` config, permissive if unset
+pub fn cors_layer(cors: Option<&config::CorsConfig>) -> CorsLayer {
+    let Some(cors) = cors else {
+        tracing::warn!("No [server.cors] configured, allowing requests from any origin");
+        return CorsLayer::permissive();
+    };
+
+    let allow_origin = if cors.allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            cors.allowed_origins
+                .iter()
+                .filter_map(|origin| origin.parse().ok()),
+        )
+    };
+    let methods: Vec<axum::http::Method> = cors
+        .allowed_methods
+        .iter()
+        .filter_map(|method| axum::http::Method::from_bytes(method.as_bytes()).ok())
+        .collect();
+
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods(methods)
+        .allow_headers(AllowHeaders::mirror_request())
+        .allow_credentials(cors.allow_credentials)
+}
+
 /// Run the package repository service
 pub async fn run_service(config_path: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
     // Initialize tracing
@@ -60,42 +98,139 @@ pub async fn run_service(config_path: Option<&str>) -> Result<(), Box<dyn std::e
 
     tracing::info!("Starting server with config: {:?}", config);
 
-    // Create storage (wrapped in Arc for sharing with actor)
-    let storage = Arc::new(Storage::new(&config.storage.data_path));
+    // Create storage
+    let storage = FsStore::from_config(&config.storage);
+
+    // Fail fast on an unusable data directory rather than on the first upload
+    if let Err(e) = storage.preflight(config.storage.create_data_path).await {
+        tracing::error!(error = %e, "Storage preflight failed");
+        return Err(e.into());
+    }
+
+    // Refuse to share the data directory with another running instance. The
+    // lock is held until run_service returns.
+    let _data_lock = match storage.lock_data_dir() {
+        Ok(lock) => lock,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to lock data directory");
+            return Err(e.into());
+        }
+    };
+
+    // Bring packages left in the legacy layout into view before the startup
+    // db rebuild picks up what's stored
+    if config.storage.migrate_layout {
+        match storage.migrate_legacy_layout().await {
+            Ok(0) => tracing::info!("No legacy-layout packages to migrate"),
+            Ok(count) => tracing::info!(count, "Migrated legacy-layout packages"),
+            Err(e) => {
+                tracing::error!(error = %e, "Legacy layout migration failed");
+                return Err(e.into());
+            }
+        }
+    }
+
+    // Wrapped in Arc for sharing with actor
+    let storage: Arc<dyn PackageStore> = Arc::new(storage);
+
+    // Packages stored before licenses were recorded get theirs from their
+    // .PKGINFO, while nothing can upload over them yet
+    match api::backfill_licenses(storage.as_ref(), config.storage.lossy_pkginfo).await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!(count, "Recorded licenses of existing packages"),
+        Err(e) => tracing::error!(error = %e, "Failed to backfill package licenses"),
+    }
 
     // Create upload session store
-    let upload_store = upload::UploadSessionStore::new(config.storage.data_path.clone());
+    let upload_store = upload::UploadSessionStore::new(config.storage.data_path.clone())
+        .with_max_inflight_bytes(config.server.max_total_inflight_bytes.map(|b| b.as_u64()));
+
+    // Pick up uploads that were in progress when the service last stopped
+    match upload_store.rehydrate().await {
+        Ok(count) if count > 0 => {
+            tracing::info!(count, "Resumable upload sessions loaded from disk");
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to load upload sessions from disk");
+        }
+        _ => {}
+    }
+
+    // Spawn background task to clean up expired/orphaned upload sessions,
+    // stopped once the server has shut down
+    let (cleanup_shutdown, cleanup_shutdown_rx) = tokio::sync::watch::channel(false);
+    let cleanup_task = upload::spawn_cleanup_task(
+        upload_store.clone(),
+        config.storage.session_cleanup_interval_secs,
+        cleanup_shutdown_rx,
+    );
+
+    // Spawn background task to prune upload receipts past their retention
+    let receipts = receipts::ReceiptStore::new(config.storage.data_path.clone());
+    receipts::spawn_prune_task(
+        receipts.clone(),
+        config.storage.receipt_retention_days,
+        receipts::DEFAULT_PRUNE_INTERVAL_SECS,
+    );
 
-    // Spawn background task to clean up expired/orphaned upload sessions
-    upload::spawn_cleanup_task(upload_store.clone(), upload::DEFAULT_CLEANUP_INTERVAL_SECS);
+    // Spawn background task to write download counts to disk
+    let downloads = downloads::DownloadCounter::new(config.storage.data_path.clone());
+    downloads::spawn_flush_task(downloads.clone(), downloads::DEFAULT_FLUSH_INTERVAL_SECS);
 
     // Create database update actor
     let (db_actor, db_update_handle) = DbUpdateActor::new(Arc::clone(&storage));
+    let db_actor = db_actor.with_db_options(api::DbOptions::from(&config.storage));
 
     // Spawn actor task
     tokio::spawn(db_actor.run());
 
-    // Rebuild all repository databases on startup
-    rebuild_all_databases(&storage, &db_update_handle).await;
-
     // Spawn background gauge collector
     metrics::spawn_gauge_collector(Arc::clone(&storage));
 
+    // Load allowed users, reloading them in the background if file-backed
+    let allowlist = auth::Allowlist::from_config(config.auth.as_ref());
+    if let Some(auth_config) = &config.auth {
+        allowlist.spawn_reload_task(auth_config.allowed_users_reload_secs);
+    }
+
     // Create shared state
     let state = Arc::new(AppState {
         storage,
         config: config.clone(),
         upload_store,
         db_update: db_update_handle,
-        http_client: reqwest::Client::new(),
+        github: auth::GitHubClient::from_config(config.auth.as_ref()),
+        allowlist,
+        ready: Arc::new(AtomicBool::new(false)),
+        events: events::EventLog::new(config.server.event_log_capacity),
+        limits: admission::ConcurrencyLimits::from_config(&config.server),
+        rate_limiter: admission::RateLimiter::from_config(&config.server),
+        receipts,
+        downloads,
     });
 
+    // Rebuild all repository databases, marking the server ready once done
+    tokio::spawn(warm_up(
+        Arc::clone(&state.storage),
+        state.db_update.clone(),
+        Arc::clone(&state.ready),
+    ));
+
     // Build API routes using utoipa_axum router
     let (api_router, api_doc) = create_api_router(state.clone()).split_for_parts();
 
     // Build repository routes (pacman interface)
     let repo_routes = Router::new()
         .route("/{repo}/os/{arch}/{filename}", get(serve_file))
+        .layer(middleware::from_fn_with_state(
+            state.limits.clone(),
+            admission::per_ip_limit_layer,
+        ))
+        .with_state(state.clone());
+
+    // Build health check route, outside /api and without auth
+    let health_routes = Router::new()
+        .route("/health", get(api::health))
         .with_state(state.clone());
 
     // Build documentation routes
@@ -109,13 +244,24 @@ pub async fn run_service(config_path: Option<&str>) -> Result<(), Box<dyn std::e
     );
 
     // Combine all routes
-    let app = Router::new()
+    let mut app = Router::new()
         .nest("/api", api_router)
         .merge(repo_routes)
+        .merge(health_routes)
         .merge(doc_routes)
         .merge(metrics_routes)
-        .layer(middleware::from_fn(metrics::http_metrics_layer))
-        .layer(CorsLayer::permissive())
+        .layer(middleware::from_fn(metrics::http_metrics_layer));
+
+    if config.server.access_log {
+        app = app.layer(middleware::from_fn(access_log::access_log_layer));
+    }
+
+    let app = app
+        .layer(middleware::from_fn_with_state(
+            state.limits.clone(),
+            admission::concurrency_limit_layer,
+        ))
+        .layer(cors_layer(config.server.cors.as_ref()))
         .layer(TraceLayer::new_for_http());
 
     // Start server
@@ -126,9 +272,21 @@ pub async fn run_service(config_path: Option<&str>) -> Result<(), Box<dyn std::e
     tracing::info!("API documentation available at http://{}/api-docs", addr);
 
     // Run server with graceful shutdown
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal(state.db_update.clone()))
-        .await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal(state.db_update.clone()))
+    .await?;
+
+    if let Err(e) = state.downloads.flush().await {
+        tracing::warn!(error = %e, "Failed to write download counts");
+    }
+
+    let _ = cleanup_shutdown.send(true);
+    if let Err(e) = cleanup_task.await {
+        tracing::warn!(error = %e, "Upload session cleanup task failed");
+    }
 
     Ok(())
 }
@@ -161,8 +319,35 @@ async fn shutdown_signal(db_update: DbUpdateHandle) {
     db_update.shutdown().await;
 }
 
-/// Rebuild all repository databases on startup
-async fn rebuild_all_databases(storage: &Arc<Storage>, db_update: &DbUpdateHandle) {
+/// Rebuild all repository databases, then flag the server as ready once the
+/// db actor has run every rebuild
+pub async fn warm_up(
+    storage: Arc<dyn PackageStore>,
+    db_update: DbUpdateHandle,
+    ready: Arc<AtomicBool>,
+) {
+    let startup = rebuild_all_databases(&storage, &db_update).await;
+
+    // Wait for each startup rebuild to have run, failed or not: a broken
+    // repo or a stream of later writes must not keep the server unready
+    while !startup
+        .iter()
+        .all(|(repo, arch, requested)| db_update.generation(repo, arch).attempted >= *requested)
+    {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+
+    ready.store(true, Ordering::Release);
+    tracing::info!("Startup database rebuild finished, server ready");
+}
+
+/// Rebuild all repository databases on startup, returning the repo/arch and
+/// generation of each rebuild requested
+async fn rebuild_all_databases(
+    storage: &Arc<dyn PackageStore>,
+    db_update: &DbUpdateHandle,
+) -> Vec<(String, String, u64)> {
+    let mut requested = Vec::new();
     tracing::info!("Rebuilding all repository databases on startup");
 
     // List all repos
@@ -170,13 +355,13 @@ async fn rebuild_all_databases(storage: &Arc<Storage>, db_update: &DbUpdateHandl
         Ok(repos) => repos,
         Err(e) => {
             tracing::error!(error = %e, "Failed to list repositories for startup rebuild");
-            return;
+            return requested;
         }
     };
 
     if repos.is_empty() {
         tracing::info!("No repositories found, skipping database rebuild");
-        return;
+        return requested;
     }
 
     // For each repo, get unique architectures and rebuild databases
@@ -189,7 +374,8 @@ async fn rebuild_all_databases(storage: &Arc<Storage>, db_update: &DbUpdateHandl
                         continue;
                     }
                     tracing::info!(repo, arch, "Rebuilding database");
-                    db_update.force_rebuild(&repo, &arch).await;
+                    let generation = db_update.force_rebuild(&repo, &arch).await;
+                    requested.push((repo.clone(), arch, generation));
                 }
             }
             Err(e) => {
@@ -197,4 +383,6 @@ async fn rebuild_all_databases(storage: &Arc<Storage>, db_update: &DbUpdateHandl
             }
         }
     }
+
+    requested
 }
@@ -0,0 +1,99 @@
+//! Cache of the `.PKGINFO` and file list extracted from stored packages
+//!
+//! Extracting them means decompressing the whole package, so db regeneration
+//! keeps what it extracted and reuses it while the package file is
+//! unchanged: same SHA256 in its metadata, same size and mtime on disk.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::SystemTime;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+use crate::error::{Result, ResultIoExt};
+use crate::models::{Package, PkgInfo};
+
+/// What was extracted from a package file, and which file it was
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedPkgInfo {
+    sha256: String,
+    size: u64,
+    modified: SystemTime,
+    pkginfo: PkgInfo,
+    files: Vec<String>,
+}
+
+impl CachedPkgInfo {
+    fn describes(&self, package: &Package, file: &std::fs::Metadata) -> bool {
+        self.sha256 == package.sha256
+            && self.size == file.len()
+            && file
+                .modified()
+                .is_ok_and(|modified| modified == self.modified)
+    }
+}
+
+/// Load the cached pkginfo and file list of `package`, whose stored file has
+/// metadata `file`
+///
+/// Returns `None` if there is no cache, it can't be read, or it was taken
+/// from a different file than the one now stored.
+pub async fn load_pkginfo_cache(
+    path: &Path,
+    package: &Package,
+    file: &std::fs::Metadata,
+) -> Option<(PkgInfo, Vec<String>)> {
+    let json = match fs::read(path).await {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            tracing::warn!(path = %path.display(), error = %e, "Failed to read cached pkginfo");
+            return None;
+        }
+    };
+
+    match serde_json::from_slice::<CachedPkgInfo>(&json) {
+        Ok(cached) if cached.describes(package, file) => Some((cached.pkginfo, cached.files)),
+        Ok(_) => None,
+        Err(e) => {
+            tracing::warn!(
+                path = %path.display(),
+                error = %e,
+                "Ignoring unparseable cached pkginfo"
+            );
+            None
+        }
+    }
+}
+
+/// Cache the pkginfo and file list extracted from `package`, whose stored
+/// file has metadata `file`
+///
+/// Written atomically (temp file + rename), so a crash never leaves a
+/// truncated cache behind.
+pub async fn store_pkginfo_cache(
+    path: &Path,
+    package: &Package,
+    file: &std::fs::Metadata,
+    pkginfo: &PkgInfo,
+    files: &[String],
+) -> Result<()> {
+    let cached = CachedPkgInfo {
+        sha256: package.sha256.clone(),
+        size: file.len(),
+        modified: file.modified().map_io_err(path)?,
+        pkginfo: pkginfo.clone(),
+        files: files.to_vec(),
+    };
+    let json = serde_json::to_vec(&cached).map_err(std::io::Error::other)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await.map_io_err(parent)?;
+    }
+    let tmp_path = path.with_extension("json.tmp");
+    let mut tmp = fs::File::create(&tmp_path).await.map_io_err(&tmp_path)?;
+    tmp.write_all(&json).await.map_io_err(&tmp_path)?;
+    tmp.sync_all().await.map_io_err(&tmp_path)?;
+
+    fs::rename(&tmp_path, path).await.map_io_err(path)
+}
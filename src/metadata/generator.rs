@@ -1,13 +1,16 @@
+use crate::config::DbCompressionFormat;
 use crate::error::{Error, Result, ResultIoExt};
 use crate::models::{Package, PkgInfo};
 use flate2::Compression;
 use flate2::write::GzEncoder;
+use serde::Serialize;
+use std::io::Write;
 use std::path::Path;
 use tar::Builder;
-use tokio::fs;
 
-/// Generate desc file content for a package
-pub fn generate_desc(pkg: &Package, pkginfo: &PkgInfo) -> String {
+/// Generate desc file content for a package, embedding `pgpsig` (its
+/// base64-encoded detached signature) when given
+pub fn generate_desc(pkg: &Package, pkginfo: &PkgInfo, pgpsig: Option<&str>) -> String {
     let mut desc = String::new();
 
     // Required fields
@@ -56,6 +59,12 @@ pub fn generate_desc(pkg: &Package, pkginfo: &PkgInfo) -> String {
     desc.push_str("%SHA256SUM%\n");
     desc.push_str(&format!("{}\n\n", pkg.sha256));
 
+    // Signature
+    if let Some(pgpsig) = pgpsig {
+        desc.push_str("%PGPSIG%\n");
+        desc.push_str(&format!("{}\n\n", pgpsig));
+    }
+
     // URL
     if let Some(ref url) = pkginfo.url {
         desc.push_str("%URL%\n");
@@ -128,29 +137,76 @@ pub fn generate_desc(pkg: &Package, pkginfo: &PkgInfo) -> String {
     desc
 }
 
-/// Generate repository database
+/// Name of a package's directory inside the repo and files databases,
+/// `{name}-{version}`.
+///
+/// Name and version are checked against the characters pacman allows, so a
+/// crafted value (e.g. containing `/` or a newline) can't produce a malformed
+/// tar entry.
+fn db_entry_dir(pkg: &Package) -> Result<String> {
+    fn is_valid(value: &str, extra: &str) -> bool {
+        !value.is_empty()
+            && !value.starts_with(['-', '.'])
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || extra.contains(c))
+    }
+
+    if !is_valid(&pkg.name, "@._+-") || !is_valid(&pkg.version, "._+:-") {
+        return Err(Error::MetadataGeneration {
+            msg: format!(
+                "Invalid characters in db entry name: {:?} version {:?}",
+                pkg.name, pkg.version
+            ),
+        });
+    }
+
+    Ok(format!("{}-{}", pkg.name, pkg.version))
+}
+
+/// Like [`db_entry_dir`], but logs and returns `None` so one bad package is
+/// left out of the database instead of failing the whole archive.
+fn db_entry_dir_or_skip(pkg: &Package) -> Option<String> {
+    db_entry_dir(pkg)
+        .inspect_err(|e| {
+            tracing::error!(
+                package = ?pkg.name,
+                version = ?pkg.version,
+                error = %e,
+                "Skipping package with invalid name in database"
+            );
+        })
+        .ok()
+}
+
+/// Generate repository database, gzip compressed at `level` (0-9) when
+/// `format` is gzip
+///
+/// `signatures` holds each package's base64 signature to embed, if any.
 pub async fn generate_repo_db(
     repo_dir: &Path,
     repo_name: &str,
     packages: &[(Package, PkgInfo)],
+    signatures: &[Option<String>],
+    format: DbCompressionFormat,
+    level: u32,
 ) -> Result<()> {
-    let db_path = repo_dir.join(format!("{}.db.tar.gz", repo_name));
+    let db_path = repo_dir.join(format!("{}.db.tar.{}", repo_name, format.extension()));
     let db_link = repo_dir.join(format!("{}.db", repo_name));
 
     // Clone data needed for blocking task
     let packages = packages.to_vec();
-    let db_path_clone = db_path.clone();
-
-    // Create tar.gz archive in blocking task (CPU-intensive compression)
-    tokio::task::spawn_blocking(move || {
-        let file = std::fs::File::create(&db_path_clone).map_io_err(&db_path_clone)?;
-        let encoder = GzEncoder::new(file, Compression::default());
-        let mut tar = Builder::new(encoder);
+    let signatures = signatures.to_vec();
 
+    // Create the archive in blocking task (CPU-intensive compression)
+    write_archive_atomically(&db_path, format, level, move |tar| {
         // Add each package's desc file
-        for (pkg, pkginfo) in &packages {
-            let desc_content = generate_desc(pkg, pkginfo);
-            let entry_path = format!("{}-{}/desc", pkg.name, pkg.version);
+        for ((pkg, pkginfo), pgpsig) in packages.iter().zip(&signatures) {
+            let Some(entry_dir) = db_entry_dir_or_skip(pkg) else {
+                continue;
+            };
+            let desc_content = generate_desc(pkg, pkginfo, pgpsig.as_deref());
+            let entry_path = format!("{entry_dir}/desc");
 
             let mut header = tar::Header::new_gnu();
             header.set_path(&entry_path)?;
@@ -160,43 +216,12 @@ pub async fn generate_repo_db(
 
             tar.append(&header, desc_content.as_bytes())?;
         }
-
-        tar.finish()?;
-        Ok::<_, crate::error::Error>(())
-    })
-    .await
-    .map_err(|e| std::io::Error::other(format!("Task join error: {}", e)))??;
-
-    // Create symlink
-    if db_link.exists() {
-        fs::remove_file(&db_link).await.map_io_err(&db_link)?;
-    }
-
-    let repo_name = repo_name.to_string();
-    let db_link_clone = db_link.clone();
-    #[cfg(not(unix))]
-    let db_path_for_copy = db_path.clone();
-
-    // Symlink creation in blocking task
-    tokio::task::spawn_blocking(move || {
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::symlink;
-            symlink(format!("{}.db.tar.gz", repo_name), &db_link_clone)
-                .map_io_err(&db_link_clone)?;
-        }
-
-        #[cfg(not(unix))]
-        {
-            // On non-Unix systems, just copy the file
-            std::fs::copy(&db_path_for_copy, &db_link_clone).map_io_err(&db_link_clone)?;
-        }
-        Ok::<_, Error>(())
+        Ok(())
     })
-    .await
-    .map_err(|e| std::io::Error::other(format!("Task join error: {}", e)))??;
+    .await?;
 
-    Ok(())
+    link_archive(&db_path, &db_link).await?;
+    remove_other_format(repo_dir, &format!("{}.db", repo_name), format).await
 }
 
 /// Generate files database (simplified version - just contains filenames for now)
@@ -204,31 +229,40 @@ pub async fn generate_files_db(
     repo_dir: &Path,
     repo_name: &str,
     packages: &[(Package, PkgInfo)],
+    signatures: &[Option<String>],
+    file_lists: &[Vec<String>],
+    format: DbCompressionFormat,
+    level: u32,
 ) -> Result<()> {
-    let files_path = repo_dir.join(format!("{}.files.tar.gz", repo_name));
+    let files_path = repo_dir.join(format!("{}.files.tar.{}", repo_name, format.extension()));
     let files_link = repo_dir.join(format!("{}.files", repo_name));
 
     // Clone data needed for blocking task
     let packages = packages.to_vec();
-    let files_path_clone = files_path.clone();
-
-    // Create tar.gz archive in blocking task (CPU-intensive compression)
-    tokio::task::spawn_blocking(move || {
-        let file = std::fs::File::create(&files_path_clone).map_io_err(&files_path_clone)?;
-        let encoder = GzEncoder::new(file, Compression::default());
-        let mut tar = Builder::new(encoder);
-
-        // Add each package's files entry (simplified - would need full file listing)
-        for (pkg, pkginfo) in &packages {
+    let signatures = signatures.to_vec();
+    let file_lists = file_lists.to_vec();
+
+    // Create the archive in blocking task (CPU-intensive compression)
+    write_archive_atomically(&files_path, format, level, move |tar| {
+        // Add each package's files entry
+        for (((pkg, pkginfo), pgpsig), files) in packages.iter().zip(&signatures).zip(&file_lists) {
+            let Some(entry_dir) = db_entry_dir_or_skip(pkg) else {
+                continue;
+            };
             let mut files_content = String::new();
 
             // Add desc content
-            files_content.push_str(&generate_desc(pkg, pkginfo));
+            files_content.push_str(&generate_desc(pkg, pkginfo, pgpsig.as_deref()));
 
-            // Add placeholder files section
-            files_content.push_str("%FILES%\n\n");
+            // Add the installed file listing
+            files_content.push_str("%FILES%\n");
+            for file in files {
+                files_content.push_str(file);
+                files_content.push('\n');
+            }
+            files_content.push('\n');
 
-            let entry_path = format!("{}-{}/files", pkg.name, pkg.version);
+            let entry_path = format!("{entry_dir}/files");
 
             let mut header = tar::Header::new_gnu();
             header.set_path(&entry_path)?;
@@ -238,41 +272,385 @@ pub async fn generate_files_db(
 
             tar.append(&header, files_content.as_bytes())?;
         }
+        Ok(())
+    })
+    .await?;
+
+    link_archive(&files_path, &files_link).await?;
+    remove_other_format(repo_dir, &format!("{}.files", repo_name), format).await
+}
+
+/// Filename of the JSON index written next to the databases
+pub const JSON_INDEX_FILENAME: &str = "index.json";
+
+/// One package in the JSON index: its stored record plus the commonly used
+/// `.PKGINFO` fields
+#[derive(Serialize)]
+struct JsonIndexEntry<'a> {
+    /// Carries `license`, taken from `.PKGINFO` for records stored before
+    /// licenses were recorded
+    #[serde(flatten)]
+    package: Package,
+    description: Option<&'a str>,
+    url: Option<&'a str>,
+    depends: &'a [String],
+    optdepends: &'a [String],
+    provides: &'a [String],
+    conflicts: &'a [String],
+    replaces: &'a [String],
+    groups: &'a [String],
+}
+
+/// Generate the JSON index, a plain list of the packages in the database for
+/// clients that don't read the pacman format
+pub async fn generate_json_index(repo_dir: &Path, packages: &[(Package, PkgInfo)]) -> Result<()> {
+    let mut entries: Vec<JsonIndexEntry> = packages
+        .iter()
+        .filter(|(pkg, _)| db_entry_dir(pkg).is_ok())
+        .map(|(package, pkginfo)| JsonIndexEntry {
+            package: Package {
+                license: pkginfo.license.clone(),
+                ..package.clone()
+            },
+            description: pkginfo.pkgdesc.as_deref(),
+            url: pkginfo.url.as_deref(),
+            depends: &pkginfo.depends,
+            optdepends: &pkginfo.optdepends,
+            provides: &pkginfo.provides,
+            conflicts: &pkginfo.conflicts,
+            replaces: &pkginfo.replaces,
+            groups: &pkginfo.groups,
+        })
+        .collect();
+    entries.sort_by(|a, b| a.package.name.cmp(&b.package.name));
+
+    let json = serde_json::to_vec_pretty(&entries).map_err(|e| Error::MetadataGeneration {
+        msg: format!("Failed to serialize JSON index: {e}"),
+    })?;
+
+    // Write to a temporary sibling first so clients never see a partial index
+    let path = repo_dir.join(JSON_INDEX_FILENAME);
+    let tmp_path = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp_path, json)
+        .await
+        .map_io_err(&tmp_path)?;
+    tokio::fs::rename(&tmp_path, &path).await.map_io_err(&path)
+}
+
+/// Remove the repository and files databases (archives in either format and
+/// their links) and the JSON index for a repo/arch. Files that are already
+/// gone are ignored.
+pub async fn remove_repo_dbs(repo_dir: &Path, repo_name: &str) -> Result<()> {
+    for name in [
+        format!("{}.db", repo_name),
+        format!("{}.db.tar.gz", repo_name),
+        format!("{}.db.tar.zst", repo_name),
+        format!("{}.files", repo_name),
+        format!("{}.files.tar.gz", repo_name),
+        format!("{}.files.tar.zst", repo_name),
+        JSON_INDEX_FILENAME.to_owned(),
+    ] {
+        let path = repo_dir.join(name);
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).map_io_err(&path),
+        }
+    }
+    Ok(())
+}
+
+/// Compressing writer for a database archive in either format
+enum ArchiveEncoder {
+    Gzip(GzEncoder<std::fs::File>),
+    Zstd(zstd::Encoder<'static, std::fs::File>),
+}
+
+impl ArchiveEncoder {
+    fn new(file: std::fs::File, format: DbCompressionFormat, level: u32) -> std::io::Result<Self> {
+        Ok(match format {
+            DbCompressionFormat::Gzip => Self::Gzip(GzEncoder::new(file, Compression::new(level))),
+            DbCompressionFormat::Zstd => Self::Zstd(zstd::Encoder::new(file, 0)?),
+        })
+    }
+
+    /// Write the compression trailer and hand back the file
+    fn finish(self) -> std::io::Result<std::fs::File> {
+        match self {
+            Self::Gzip(encoder) => encoder.finish(),
+            Self::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl Write for ArchiveEncoder {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Gzip(encoder) => encoder.write(buf),
+            Self::Zstd(encoder) => encoder.write(buf),
+        }
+    }
 
-        tar.finish()?;
-        Ok::<_, crate::error::Error>(())
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Gzip(encoder) => encoder.flush(),
+            Self::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Build a compressed tar archive at `path` without ever exposing a partial
+/// file.
+///
+/// The archive is written to a temporary sibling and renamed into place once
+/// complete, so if generation fails the previous archive keeps being served.
+async fn write_archive_atomically<F>(
+    path: &Path,
+    format: DbCompressionFormat,
+    level: u32,
+    build: F,
+) -> Result<()>
+where
+    F: FnOnce(&mut Builder<ArchiveEncoder>) -> Result<()> + Send + 'static,
+{
+    let path = path.to_path_buf();
+    let tmp_path = path.with_extension(format!("{}.tmp", format.extension()));
+
+    tokio::task::spawn_blocking(move || {
+        let result = (|| {
+            let file = std::fs::File::create(&tmp_path).map_io_err(&tmp_path)?;
+            let encoder = ArchiveEncoder::new(file, format, level).map_io_err(&tmp_path)?;
+            let mut tar = Builder::new(encoder);
+
+            build(&mut tar)?;
+
+            let file = tar.into_inner()?.finish().map_io_err(&tmp_path)?;
+            file.sync_all().map_io_err(&tmp_path)?;
+            std::fs::rename(&tmp_path, &path).map_io_err(&path)
+        })();
+
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp_path);
+        }
+        result
     })
     .await
-    .map_err(|e| std::io::Error::other(format!("Task join error: {}", e)))??;
+    .map_err(|e| std::io::Error::other(format!("Task join error: {}", e)))?
+}
 
-    // Create symlink
-    if files_link.exists() {
-        fs::remove_file(&files_link).await.map_io_err(&files_link)?;
+/// Remove the archive left behind in the format not in use, e.g.
+/// `sw1nn.db.tar.gz` after switching to zstd, so stale databases aren't
+/// served
+async fn remove_other_format(
+    repo_dir: &Path,
+    stem: &str,
+    format: DbCompressionFormat,
+) -> Result<()> {
+    let other = match format {
+        DbCompressionFormat::Gzip => DbCompressionFormat::Zstd,
+        DbCompressionFormat::Zstd => DbCompressionFormat::Gzip,
+    };
+    let path = repo_dir.join(format!("{}.tar.{}", stem, other.extension()));
+    match tokio::fs::remove_file(&path).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).map_io_err(&path),
     }
+}
 
-    let repo_name = repo_name.to_string();
-    let files_link_clone = files_link.clone();
-    #[cfg(not(unix))]
-    let files_path_for_copy = files_path.clone();
+/// Point `link` (e.g. `sw1nn.db`) at the archive at `archive_path`.
+///
+/// On Unix this is a relative symlink, left untouched if it already points at
+/// the archive; elsewhere the archive is copied.
+async fn link_archive(archive_path: &Path, link: &Path) -> Result<()> {
+    let archive_path = archive_path.to_path_buf();
+    let link = link.to_path_buf();
 
-    // Symlink creation in blocking task
     tokio::task::spawn_blocking(move || {
         #[cfg(unix)]
         {
             use std::os::unix::fs::symlink;
-            symlink(format!("{}.files.tar.gz", repo_name), &files_link_clone)
-                .map_io_err(&files_link_clone)?;
+
+            let target = archive_path
+                .file_name()
+                .ok_or_else(|| Error::MetadataGeneration {
+                    msg: format!("Invalid archive path: {}", archive_path.display()),
+                })?;
+
+            if std::fs::read_link(&link).is_ok_and(|existing| existing == Path::new(target)) {
+                return Ok(());
+            }
+            if link.symlink_metadata().is_ok() {
+                std::fs::remove_file(&link).map_io_err(&link)?;
+            }
+            symlink(target, &link).map_io_err(&link)?;
         }
 
         #[cfg(not(unix))]
         {
             // On non-Unix systems, just copy the file
-            std::fs::copy(&files_path_for_copy, &files_link_clone).map_io_err(&files_link_clone)?;
+            std::fs::copy(&archive_path, &link).map_io_err(&link)?;
         }
         Ok::<_, Error>(())
     })
     .await
-    .map_err(|e| std::io::Error::other(format!("Task join error: {}", e)))??;
+    .map_err(|e| std::io::Error::other(format!("Task join error: {}", e)))?
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::io::Read;
+
+    fn pkg(name: &str, version: &str) -> (Package, PkgInfo) {
+        let pkginfo = PkgInfo::parse(&format!(
+            "pkgname = placeholder\npkgver = {version}\narch = x86_64\n"
+        ))
+        .unwrap();
+        let package = Package {
+            name: name.to_owned(),
+            version: version.to_owned(),
+            arch: "x86_64".to_owned(),
+            repo: "sw1nn".to_owned(),
+            filename: format!("{}-{version}-x86_64.pkg.tar.zst", name.trim()),
+            sha256: String::new(),
+            size: 0,
+            created_at: Utc::now(),
+            signed: false,
+            signature_verified: false,
+            license: Vec::new(),
+        };
+        (package, pkginfo)
+    }
+
+    #[test]
+    fn db_entry_dir_accepts_pacman_names() {
+        let (package, _) = pkg("lib32-gcc+plugins@2_x", "1:2.0.r5.g1a2b-1");
+        assert_eq!(
+            db_entry_dir(&package).unwrap(),
+            "lib32-gcc+plugins@2_x-1:2.0.r5.g1a2b-1"
+        );
+    }
+
+    #[test]
+    fn db_entry_dir_rejects_unsafe_names() {
+        for (name, version) in [
+            ("bad\nname", "1.0.0-1"),
+            ("../escape", "1.0.0-1"),
+            ("a/b", "1.0.0-1"),
+            ("-leading", "1.0.0-1"),
+            ("good", "1.0/0-1"),
+            ("good", "1.0.0-1\n"),
+            ("", "1.0.0-1"),
+        ] {
+            let (package, _) = pkg(name, version);
+            assert!(db_entry_dir(&package).is_err(), "{name:?} {version:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn generate_repo_db_skips_package_with_newline_in_name() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let packages = vec![pkg("bad\nname", "1.0.0-1"), pkg("good", "1.0.0-1")];
+
+        generate_repo_db(
+            dir.path(),
+            "sw1nn",
+            &packages,
+            &[None, None],
+            DbCompressionFormat::Gzip,
+            6,
+        )
+        .await
+        .unwrap();
+
+        let file = std::fs::File::open(dir.path().join("sw1nn.db.tar.gz")).unwrap();
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+        let mut paths = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            paths.push(entry.path().unwrap().display().to_string());
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            assert!(!content.contains("bad\nname"));
+        }
+        assert_eq!(paths, vec!["good-1.0.0-1/desc"]);
+    }
+
+    #[tokio::test]
+    async fn generate_repo_db_writes_zstd_archive() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let packages = vec![pkg("good", "1.0.0-1")];
+
+        generate_repo_db(
+            dir.path(),
+            "sw1nn",
+            &packages,
+            &[None],
+            DbCompressionFormat::Zstd,
+            6,
+        )
+        .await
+        .unwrap();
+
+        assert!(!dir.path().join("sw1nn.db.tar.gz").exists());
+        #[cfg(unix)]
+        assert_eq!(
+            std::fs::read_link(dir.path().join("sw1nn.db")).unwrap(),
+            Path::new("sw1nn.db.tar.zst")
+        );
+
+        let file = std::fs::File::open(dir.path().join("sw1nn.db")).unwrap();
+        let mut archive = tar::Archive::new(zstd::Decoder::new(file).unwrap());
+        let mut entries = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().display().to_string();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            entries.push((path, content));
+        }
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, "good-1.0.0-1/desc");
+        assert!(entries[0].1.contains("%NAME%\ngood\n"), "{}", entries[0].1);
+        assert!(entries[0].1.contains("%VERSION%\n1.0.0-1\n"));
+    }
+
+    #[tokio::test]
+    async fn generate_repo_db_compression_level_keeps_contents() {
+        let packages = vec![pkg("good", "1.0.0-1"), pkg("other", "2.0.0-1")];
+
+        let mut archives = Vec::new();
+        for level in [1, 9] {
+            let dir = tempfile::TempDir::new().unwrap();
+            generate_repo_db(
+                dir.path(),
+                "sw1nn",
+                &packages,
+                &[None, None],
+                DbCompressionFormat::Gzip,
+                level,
+            )
+            .await
+            .unwrap();
+
+            let compressed = std::fs::read(dir.path().join("sw1nn.db.tar.gz")).unwrap();
+            assert_eq!(&compressed[..2], [0x1f, 0x8b], "level {level} isn't gzip");
+            let mut tar = Vec::new();
+            flate2::read::GzDecoder::new(&compressed[..])
+                .read_to_end(&mut tar)
+                .unwrap();
+            archives.push(tar);
+        }
+
+        assert_eq!(archives[0], archives[1]);
+        let mut archive = tar::Archive::new(&archives[0][..]);
+        let paths: Vec<String> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+            .collect();
+        assert_eq!(paths, ["good-1.0.0-1/desc", "other-2.0.0-1/desc"]);
+    }
 }
@@ -1,5 +1,12 @@
+pub mod cache;
 pub mod generator;
 pub mod parser;
 
-pub use generator::{generate_files_db, generate_repo_db};
-pub use parser::{calculate_sha256, extract_pkginfo};
+pub use cache::{load_pkginfo_cache, store_pkginfo_cache};
+pub use generator::{
+    JSON_INDEX_FILENAME, generate_files_db, generate_json_index, generate_repo_db, remove_repo_dbs,
+};
+pub use parser::{
+    Compression, Provenance, calculate_sha256, extract_file_list, extract_pkginfo,
+    extract_pkginfo_and_files, extract_provenance, verify_buildinfo, verify_compression,
+};
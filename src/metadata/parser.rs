@@ -5,31 +5,270 @@ use tar::Archive;
 use zstd::stream::read::Decoder;
 
 /// Extract .PKGINFO from a .pkg.tar.zst file
-pub fn extract_pkginfo(package_data: &[u8]) -> Result<PkgInfo> {
+///
+/// If `.PKGINFO` has no `size`, the installed size is computed in the same
+/// pass by summing the regular files the package installs.
+///
+/// A `.PKGINFO` that isn't valid UTF-8 is rejected, unless `lossy_utf8` is
+/// set, in which case invalid sequences are replaced with U+FFFD.
+pub fn extract_pkginfo(package_data: &[u8], lossy_utf8: bool) -> Result<PkgInfo> {
+    scan_package(package_data, lossy_utf8, false).map(|(pkginfo, _)| pkginfo)
+}
+
+/// Extract .PKGINFO and the file listing of a .pkg.tar.zst file in a single
+/// decompression pass. See [`extract_pkginfo`] and [`extract_file_list`].
+pub fn extract_pkginfo_and_files(
+    package_data: &[u8],
+    lossy_utf8: bool,
+) -> Result<(PkgInfo, Vec<String>)> {
+    scan_package(package_data, lossy_utf8, true)
+}
+
+/// List the paths a .pkg.tar.zst installs, sorted, for the `%FILES%` section
+/// of the files database. Package metadata at the archive root (`.PKGINFO`,
+/// `.MTREE`, `.BUILDINFO`, `.INSTALL`, ...) and directory entries are left
+/// out.
+pub fn extract_file_list(package_data: &[u8]) -> Result<Vec<String>> {
+    let mut archive = Archive::new(Decoder::new(package_data)?);
+
+    let mut files = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if let Some(file) = installed_file(&entry)? {
+            files.push(file);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The path an archive entry installs, or `None` for metadata dotfiles and
+/// directories
+fn installed_file<R: Read>(entry: &tar::Entry<R>) -> Result<Option<String>> {
+    let path = entry.path()?;
+    let path = path.to_string_lossy();
+    if path.starts_with('.') || path.ends_with('/') || entry.header().entry_type().is_dir() {
+        return Ok(None);
+    }
+    Ok(Some(path.into_owned()))
+}
+
+fn scan_package(
+    package_data: &[u8],
+    lossy_utf8: bool,
+    list_files: bool,
+) -> Result<(PkgInfo, Vec<String>)> {
     // Decompress zstd
     let decoder = Decoder::new(package_data)?;
 
     // Read tar archive
     let mut archive = Archive::new(decoder);
 
+    let mut pkginfo: Option<PkgInfo> = None;
+    let mut installed_size = 0u64;
+    let mut files = Vec::new();
+
     // Find and read .PKGINFO file
     for entry in archive.entries()? {
         let mut entry = entry?;
         let path = entry.path()?;
 
         if path.to_str() == Some(".PKGINFO") {
-            let mut content = String::new();
-            entry.read_to_string(&mut content)?;
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+            let content = decode_pkginfo(bytes, lossy_utf8)?;
 
-            return PkgInfo::parse(&content).map_err(|e| Error::InvalidPackage {
+            let parsed = PkgInfo::parse(&content).map_err(|e| Error::InvalidPackage {
                 pkgname: format!("Failed to parse .PKGINFO: {}", e),
+            })?;
+            if parsed.size.is_some() && !list_files {
+                return Ok((parsed, files));
+            }
+            pkginfo = Some(parsed);
+            continue;
+        }
+
+        // Dotfiles at the archive root (.BUILDINFO, .MTREE, .INSTALL, ...)
+        // are package metadata, not installed files
+        if let Some(file) = installed_file(&entry)? {
+            if entry.header().entry_type().is_file() {
+                installed_size += entry.size();
+            }
+            if list_files {
+                files.push(file);
+            }
+        }
+    }
+    files.sort();
+
+    match pkginfo {
+        Some(mut pkginfo) => {
+            pkginfo.size.get_or_insert(installed_size);
+            Ok((pkginfo, files))
+        }
+        None => Err(Error::InvalidPackage {
+            pkgname: ".PKGINFO not found in package".to_string(),
+        }),
+    }
+}
+
+fn decode_pkginfo(bytes: Vec<u8>, lossy_utf8: bool) -> Result<String> {
+    match String::from_utf8(bytes) {
+        Ok(content) => Ok(content),
+        Err(e) if lossy_utf8 => {
+            tracing::warn!(
+                offset = e.utf8_error().valid_up_to(),
+                "PKGINFO is not valid UTF-8, replacing invalid sequences"
+            );
+            Ok(String::from_utf8_lossy(e.as_bytes()).into_owned())
+        }
+        // Deliberately not mentioning ".PKGINFO" so the reason survives the
+        // error response sanitizing
+        Err(e) => Err(Error::InvalidPackage {
+            pkgname: format!(
+                "PKGINFO is not valid UTF-8 (invalid byte at offset {})",
+                e.utf8_error().valid_up_to()
+            ),
+        }),
+    }
+}
+
+/// Provenance files shipped inside a package by makepkg
+#[derive(Debug, Default)]
+pub struct Provenance {
+    /// Raw `.BUILDINFO` contents
+    pub buildinfo: Option<Vec<u8>>,
+    /// Raw `.MTREE` contents (gzip-compressed mtree as written by makepkg)
+    pub mtree: Option<Vec<u8>>,
+}
+
+/// Extract .BUILDINFO and .MTREE from a .pkg.tar.zst file
+///
+/// Either entry may be absent; packages built without makepkg often lack them.
+pub fn extract_provenance(package_data: &[u8]) -> Result<Provenance> {
+    let decoder = Decoder::new(package_data)?;
+    let mut archive = Archive::new(decoder);
+    let mut provenance = Provenance::default();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let target = match entry.path()?.to_str() {
+            Some(".BUILDINFO") => &mut provenance.buildinfo,
+            Some(".MTREE") => &mut provenance.mtree,
+            _ => continue,
+        };
+
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+        *target = Some(content);
+
+        if provenance.buildinfo.is_some() && provenance.mtree.is_some() {
+            break;
+        }
+    }
+
+    Ok(provenance)
+}
+
+/// Check that `.BUILDINFO` describes the same package as `.PKGINFO`
+///
+/// Compares pkgname, pkgver and arch. A field missing from `.BUILDINFO` is
+/// not checked.
+pub fn verify_buildinfo(buildinfo: &[u8], pkginfo: &PkgInfo) -> Result<()> {
+    let content = String::from_utf8_lossy(buildinfo);
+
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once(" = ") else {
+            continue;
+        };
+        let (field, expected) = match key.trim() {
+            "pkgname" => ("name", &pkginfo.pkgname),
+            "pkgver" => ("version", &pkginfo.pkgver),
+            "pkgarch" => ("arch", &pkginfo.arch),
+            _ => continue,
+        };
+
+        let value = value.trim();
+        if value != expected {
+            return Err(Error::InvalidPackage {
+                pkgname: format!(
+                    "BUILDINFO {} '{}' does not match package {} '{}'",
+                    key.trim(),
+                    value,
+                    field,
+                    expected
+                ),
             });
         }
     }
 
-    Err(Error::InvalidPackage {
-        pkgname: ".PKGINFO not found in package".to_string(),
-    })
+    Ok(())
+}
+
+/// Compression of a package archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display)]
+pub enum Compression {
+    #[display("zstd")]
+    Zstd,
+    #[display("gzip")]
+    Gzip,
+    #[display("xz")]
+    Xz,
+    #[display("bzip2")]
+    Bzip2,
+}
+
+impl Compression {
+    /// Detect the compression of package data from its magic bytes
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Self::Zstd)
+        } else if data.starts_with(&[0x1f, 0x8b]) {
+            Some(Self::Gzip)
+        } else if data.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Self::Xz)
+        } else if data.starts_with(b"BZh") {
+            Some(Self::Bzip2)
+        } else {
+            None
+        }
+    }
+
+    /// The compression a package filename's suffix promises
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let (_, suffix) = filename.rsplit_once(".pkg.tar.")?;
+        match suffix {
+            "zst" => Some(Self::Zstd),
+            "gz" => Some(Self::Gzip),
+            "xz" => Some(Self::Xz),
+            "bz2" => Some(Self::Bzip2),
+            _ => None,
+        }
+    }
+}
+
+/// Check that package data is compressed the way its filename says
+///
+/// Catches mislabeled artifacts (e.g. gzip data named `.pkg.tar.zst`) with a
+/// clear error instead of an opaque decompression failure.
+pub fn verify_compression(filename: &str, package_data: &[u8]) -> Result<()> {
+    let Some(expected) = Compression::from_filename(filename) else {
+        return Ok(());
+    };
+
+    match Compression::detect(package_data) {
+        Some(actual) if actual == expected => Ok(()),
+        Some(actual) => Err(Error::InvalidPackage {
+            pkgname: format!(
+                "{filename} is named as {expected} compressed but contains {actual} data"
+            ),
+        }),
+        None => Err(Error::InvalidPackage {
+            pkgname: format!(
+                "{filename} is named as {expected} compressed but its compression is not recognised"
+            ),
+        }),
+    }
 }
 
 /// Calculate MD5 checksum
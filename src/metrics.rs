@@ -51,10 +51,18 @@ fn register_descriptions() {
         "sw1nn_pkg_repo_uploads_aborted_total",
         "Total aborted uploads"
     );
+    describe_counter!(
+        "sw1nn_pkg_repo_uploads_failed_total",
+        "Total uploads whose completion failed"
+    );
     describe_counter!(
         "sw1nn_pkg_repo_package_downloads_total",
         "Total package file downloads"
     );
+    describe_counter!(
+        "sw1nn_pkg_repo_bytes_served_total",
+        "Total bytes of repository files (packages, dbs, sidecars) served"
+    );
     describe_counter!(
         "sw1nn_pkg_repo_packages_deleted_total",
         "Total packages deleted"
@@ -67,6 +75,14 @@ fn register_descriptions() {
         "sw1nn_pkg_repo_cleanup_versions_deleted_total",
         "Total package versions deleted by cleanup"
     );
+    describe_counter!(
+        "sw1nn_pkg_repo_db_packages_skipped_total",
+        "Total packages left out of a database rebuild because they could not be read"
+    );
+    describe_counter!(
+        "sw1nn_pkg_repo_requests_shed_total",
+        "Total requests refused with 503 by a concurrency limit"
+    );
 
     // Histograms
     describe_histogram!(
@@ -146,6 +162,10 @@ pub fn record_upload_aborted() {
     counter!("sw1nn_pkg_repo_uploads_aborted_total").increment(1);
 }
 
+pub fn record_upload_failed(repo: &str) {
+    counter!("sw1nn_pkg_repo_uploads_failed_total", "repo" => repo.to_owned()).increment(1);
+}
+
 pub fn record_package_download(repo: &str, arch: &str) {
     counter!(
         "sw1nn_pkg_repo_package_downloads_total",
@@ -155,6 +175,10 @@ pub fn record_package_download(repo: &str, arch: &str) {
     .increment(1);
 }
 
+pub fn record_bytes_served(repo: &str, bytes: u64) {
+    counter!("sw1nn_pkg_repo_bytes_served_total", "repo" => repo.to_owned()).increment(bytes);
+}
+
 pub fn record_package_deleted(repo: &str, count: u64) {
     counter!("sw1nn_pkg_repo_packages_deleted_total", "repo" => repo.to_owned()).increment(count);
 }
@@ -177,6 +201,19 @@ pub fn record_cleanup_versions_deleted(repo: &str, count: u64) {
     .increment(count);
 }
 
+pub fn record_db_packages_skipped(repo: &str, arch: &str, count: u64) {
+    counter!(
+        "sw1nn_pkg_repo_db_packages_skipped_total",
+        "repo" => repo.to_owned(),
+        "arch" => arch.to_owned()
+    )
+    .increment(count);
+}
+
+pub fn record_request_shed(limit: &str) {
+    counter!("sw1nn_pkg_repo_requests_shed_total", "limit" => limit.to_owned()).increment(1);
+}
+
 // -- Histogram helpers --
 
 pub fn record_upload_size(repo: &str, size: u64) {
@@ -213,7 +250,7 @@ async fn dir_size(path: &Path) -> u64 {
 }
 
 /// Collect storage gauge metrics by scanning the data directory.
-pub async fn collect_storage_gauges(storage: &crate::storage::Storage) {
+pub async fn collect_storage_gauges(storage: &dyn crate::storage::PackageStore) {
     let Ok(repos) = storage.list_repos().await else {
         return;
     };
@@ -292,7 +329,7 @@ fn normalise_endpoint(path: &str) -> String {
         return "/api/packages/:name/versions/delete".to_owned();
     }
 
-    // /api/packages/{name}  (DELETE)
+    // /api/packages/{name}  (GET, DELETE, PUT)
     if segments.len() == 4 && segments.get(2) == Some(&"packages") {
         return "/api/packages/:name".to_owned();
     }
@@ -333,16 +370,16 @@ pub async fn http_metrics_layer(request: Request, next: Next) -> Response {
 }
 
 /// Spawn a background task that periodically collects storage gauges.
-pub fn spawn_gauge_collector(storage: std::sync::Arc<crate::storage::Storage>) {
+pub fn spawn_gauge_collector(storage: std::sync::Arc<dyn crate::storage::PackageStore>) {
     tokio::spawn(async move {
         // Short delay to let startup complete
         tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-        collect_storage_gauges(&storage).await;
+        collect_storage_gauges(storage.as_ref()).await;
 
         let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
         loop {
             interval.tick().await;
-            collect_storage_gauges(&storage).await;
+            collect_storage_gauges(storage.as_ref()).await;
         }
     });
 }
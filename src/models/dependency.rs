@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Version comparison in a dependency constraint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum VersionOp {
+    #[serde(rename = ">=")]
+    Ge,
+    #[serde(rename = "<=")]
+    Le,
+    #[serde(rename = "=")]
+    Eq,
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = "<")]
+    Lt,
+}
+
+impl VersionOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VersionOp::Ge => ">=",
+            VersionOp::Le => "<=",
+            VersionOp::Eq => "=",
+            VersionOp::Gt => ">",
+            VersionOp::Lt => "<",
+        }
+    }
+}
+
+impl fmt::Display for VersionOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A dependency as written in `.PKGINFO`, e.g. `glibc>=2.38` or `bash`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Dependency {
+    /// Package (or provided) name
+    pub name: String,
+    /// Version comparison, if the dependency is versioned
+    pub op: Option<VersionOp>,
+    /// Version the comparison applies to
+    pub version: Option<String>,
+}
+
+impl Dependency {
+    /// Whether a package at `version` satisfies this dependency
+    ///
+    /// Follows pacman: a constraint without a pkgrel ignores the candidate's
+    /// pkgrel. Unparseable versions never match a versioned dependency.
+    pub fn satisfied_by(&self, version: &str) -> bool {
+        let (Some(op), Some(required)) = (self.op, &self.version) else {
+            return true;
+        };
+        match (
+            alpm_types::VersionRequirement::from_str(&format!("{op}{required}")),
+            alpm_types::Version::from_str(version),
+        ) {
+            (Ok(requirement), Ok(version)) => requirement.is_satisfied_by(&version),
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Dependency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let Some(op_start) = s.find(['<', '>', '=']) else {
+            if s.is_empty() {
+                return Err("Empty dependency".to_string());
+            }
+            return Ok(Dependency {
+                name: s.to_string(),
+                op: None,
+                version: None,
+            });
+        };
+
+        let (name, rest) = s.split_at(op_start);
+        let (op, version) = [
+            VersionOp::Ge,
+            VersionOp::Le,
+            VersionOp::Eq,
+            VersionOp::Gt,
+            VersionOp::Lt,
+        ]
+        .into_iter()
+        .find_map(|op| rest.strip_prefix(op.as_str()).map(|version| (op, version)))
+        .ok_or_else(|| format!("Invalid version comparison in dependency '{s}'"))?;
+
+        if name.is_empty() {
+            return Err(format!("Missing name in dependency '{s}'"));
+        }
+        if version.is_empty() || version.starts_with(['<', '>', '=']) {
+            return Err(format!("Invalid version in dependency '{s}'"));
+        }
+
+        Ok(Dependency {
+            name: name.to_string(),
+            op: Some(op),
+            version: Some(version.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if let (Some(op), Some(version)) = (self.op, &self.version) {
+            write!(f, "{op}{version}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dep(s: &str) -> Dependency {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_plain_name() {
+        assert_eq!(
+            dep("bash"),
+            Dependency {
+                name: "bash".to_string(),
+                op: None,
+                version: None,
+            }
+        );
+        assert_eq!(dep("lib32-gcc-libs").name, "lib32-gcc-libs");
+    }
+
+    #[test]
+    fn parses_each_operator() {
+        for (raw, op, version) in [
+            ("glibc>=2.38", VersionOp::Ge, "2.38"),
+            ("python<=3.12", VersionOp::Le, "3.12"),
+            ("foo=1:1.0-2", VersionOp::Eq, "1:1.0-2"),
+            ("bar>1.0", VersionOp::Gt, "1.0"),
+            ("python<3.12", VersionOp::Lt, "3.12"),
+        ] {
+            let parsed = dep(raw);
+            assert_eq!(parsed.op, Some(op), "{raw}");
+            assert_eq!(parsed.version.as_deref(), Some(version), "{raw}");
+            // Raw form round-trips unchanged
+            assert_eq!(parsed.to_string(), raw);
+        }
+        assert_eq!(dep("glibc>=2.38").name, "glibc");
+    }
+
+    #[test]
+    fn rejects_malformed_constraints() {
+        for raw in ["", ">=1.0", "foo>=", "foo=>1.0", "foo<>1.0"] {
+            assert!(raw.parse::<Dependency>().is_err(), "{raw:?}");
+        }
+    }
+
+    #[test]
+    fn satisfied_by_follows_pacman_rules() {
+        assert!(dep("bash").satisfied_by("5.2.026-2"));
+
+        assert!(dep("glibc>=2.38").satisfied_by("2.39-1"));
+        assert!(dep("glibc>=2.38").satisfied_by("2.38-1"));
+        assert!(!dep("glibc>=2.38").satisfied_by("2.9-1"));
+
+        // Numeric, not lexicographic, segment comparison
+        assert!(dep("python<3.12").satisfied_by("3.9-1"));
+        assert!(!dep("python<3.12").satisfied_by("3.12.1-1"));
+
+        // No pkgrel in the constraint ignores the candidate's pkgrel
+        assert!(dep("foo=1.0").satisfied_by("1.0-3"));
+        assert!(!dep("foo=1.0-2").satisfied_by("1.0-3"));
+        assert!(dep("foo>1.0-2").satisfied_by("1.0-3"));
+
+        assert!(!dep("foo>=1.0").satisfied_by("not a version"));
+    }
+}
@@ -1,5 +1,7 @@
+pub mod dependency;
 pub mod package;
 pub mod pkginfo;
 
+pub use dependency::{Dependency, VersionOp};
 pub use package::{Package, PackageQuery};
 pub use pkginfo::PkgInfo;
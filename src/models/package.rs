@@ -20,6 +20,15 @@ pub struct Package {
     pub size: u64,
     /// Package creation timestamp
     pub created_at: DateTime<Utc>,
+    /// Whether a detached `.sig` signature is stored with the package
+    #[serde(default)]
+    pub signed: bool,
+    /// Whether the signature was verified against a trusted key at upload
+    #[serde(default)]
+    pub signature_verified: bool,
+    /// Licenses declared in `.PKGINFO`
+    #[serde(default)]
+    pub license: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -48,4 +57,11 @@ pub struct PackageQuery {
     pub repo: Option<String>,
     /// Filter by architecture
     pub arch: Option<String>,
+    /// Filter by declared license (case-insensitive)
+    pub license: Option<String>,
+    /// Maximum number of results (default 100, capped by the server's
+    /// `max_list_results`)
+    pub limit: Option<usize>,
+    /// Number of matching packages to skip (default 0)
+    pub offset: Option<usize>,
 }
@@ -24,6 +24,12 @@ pub struct PkgInfo {
 }
 
 impl PkgInfo {
+    /// Runtime dependencies with any version constraints split out; entries
+    /// that don't parse are skipped
+    pub fn parsed_depends(&self) -> Vec<super::Dependency> {
+        self.depends.iter().filter_map(|d| d.parse().ok()).collect()
+    }
+
     /// Parse .PKGINFO content
     pub fn parse(content: &str) -> Result<Self, String> {
         let mut pkgname = None;
@@ -0,0 +1,250 @@
+//! Per-upload receipts
+//!
+//! Every completed upload leaves a small JSON receipt in
+//! `data/.receipts/{upload_id}.json` recording the session, the resulting
+//! package, the uploading user and when it happened. Receipts are kept
+//! separately from package metadata, so they survive deletes and cleanups,
+//! and are pruned once older than the configured retention.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::fs;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::{Error, Result, ResultIoExt};
+use crate::models::Package;
+use crate::upload::UploadSession;
+
+/// How often expired receipts are pruned: 1 hour
+pub const DEFAULT_PRUNE_INTERVAL_SECS: u64 = 3600;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UploadReceipt {
+    pub upload_id: String,
+    pub user: String,
+    /// Filename the client declared when starting the upload
+    pub filename: String,
+    pub file_size: u64,
+    /// SHA256 the client declared, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    pub repo: String,
+    pub arch: String,
+    pub has_signature: bool,
+    /// When the upload session was started
+    pub started_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+    /// The package the upload resulted in
+    pub package: Package,
+}
+
+impl UploadReceipt {
+    pub fn new(session: &UploadSession, package: &Package, user: &str) -> Self {
+        Self {
+            upload_id: session.upload_id.clone(),
+            user: user.to_string(),
+            filename: session.filename.clone(),
+            file_size: session.file_size,
+            sha256: session.sha256.clone(),
+            repo: session.repo.clone(),
+            arch: session.arch.clone(),
+            has_signature: session.has_signature,
+            started_at: session.created_at,
+            completed_at: Utc::now(),
+            package: package.clone(),
+        }
+    }
+}
+
+/// Receipts stored as JSON files under `data/.receipts/`
+#[derive(Debug, Clone)]
+pub struct ReceiptStore {
+    dir: PathBuf,
+}
+
+impl ReceiptStore {
+    pub fn new(data_path: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: data_path.into().join(".receipts"),
+        }
+    }
+
+    fn receipt_path(&self, upload_id: &str) -> Result<PathBuf> {
+        // Upload IDs are UUIDs; anything else could escape the receipts dir
+        Uuid::parse_str(upload_id).map_err(|_| Error::InvalidPackage {
+            pkgname: format!("Invalid upload ID format: {}", upload_id),
+        })?;
+        Ok(self.dir.join(format!("{}.json", upload_id)))
+    }
+
+    /// Write the receipt, replacing any earlier one for the same upload
+    pub async fn save(&self, receipt: &UploadReceipt) -> Result<()> {
+        let path = self.receipt_path(&receipt.upload_id)?;
+        fs::create_dir_all(&self.dir).await.map_io_err(&self.dir)?;
+
+        let json = serde_json::to_vec_pretty(receipt).map_err(std::io::Error::other)?;
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, json).await.map_io_err(&tmp_path)?;
+        fs::rename(&tmp_path, &path).await.map_io_err(&path)?;
+        Ok(())
+    }
+
+    pub async fn load(&self, upload_id: &str) -> Result<UploadReceipt> {
+        let path = self.receipt_path(upload_id)?;
+        let json = match fs::read(&path).await {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(Error::PackageNotFound {
+                    pkgname: format!("upload receipt {}", upload_id),
+                });
+            }
+            Err(e) => return Err(e).map_io_err(&path),
+        };
+        serde_json::from_slice(&json).map_err(|e| Error::MetadataGeneration {
+            msg: format!("Corrupt upload receipt {}: {}", upload_id, e),
+        })
+    }
+
+    /// Delete receipts completed more than `retention` ago, returning how
+    /// many were removed. Unreadable receipts are judged by file mtime.
+    pub async fn prune(&self, retention: Duration) -> Result<usize> {
+        let cutoff = Utc::now() - retention;
+        let mut entries = match fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e).map_io_err(&self.dir),
+        };
+
+        let mut removed = 0;
+        while let Some(entry) = entries.next_entry().await.map_io_err(&self.dir)? {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+
+            let completed_at = match fs::read(&path).await {
+                Ok(json) => serde_json::from_slice::<UploadReceipt>(&json)
+                    .ok()
+                    .map(|r| r.completed_at),
+                Err(_) => None,
+            };
+            let completed_at = match completed_at {
+                Some(at) => at,
+                None => match entry.metadata().await.and_then(|m| m.modified()) {
+                    Ok(modified) => modified.into(),
+                    Err(_) => continue,
+                },
+            };
+
+            if completed_at < cutoff {
+                fs::remove_file(&path).await.map_io_err(&path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Spawn a background task that periodically prunes receipts older than
+/// `retention_days`.
+pub fn spawn_prune_task(store: ReceiptStore, retention_days: u64, interval_secs: u64) {
+    tokio::spawn(async move {
+        let retention = Duration::days(retention_days as i64);
+        let interval = std::time::Duration::from_secs(interval_secs);
+
+        loop {
+            match store.prune(retention).await {
+                Ok(count) if count > 0 => {
+                    tracing::info!(count, "Pruned expired upload receipts");
+                }
+                Err(e) => {
+                    tracing::error!(error = %e, "Failed to prune upload receipts");
+                }
+                _ => {}
+            }
+
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn test_receipt(completed_at: DateTime<Utc>) -> UploadReceipt {
+        UploadReceipt {
+            upload_id: Uuid::new_v4().to_string(),
+            user: "alice".to_string(),
+            filename: "foo-1.0.0-1-x86_64.pkg.tar.zst".to_string(),
+            file_size: 42,
+            sha256: None,
+            repo: "test".to_string(),
+            arch: "x86_64".to_string(),
+            has_signature: false,
+            started_at: completed_at,
+            completed_at,
+            package: Package {
+                name: "foo".to_string(),
+                version: "1.0.0-1".to_string(),
+                arch: "x86_64".to_string(),
+                repo: "test".to_string(),
+                filename: "foo-1.0.0-1-x86_64.pkg.tar.zst".to_string(),
+                sha256: "abc".to_string(),
+                size: 42,
+                created_at: completed_at,
+                signed: false,
+                signature_verified: false,
+                license: vec![],
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = ReceiptStore::new(temp_dir.path());
+        let receipt = test_receipt(Utc::now());
+
+        store.save(&receipt).await.unwrap();
+        let loaded = store.load(&receipt.upload_id).await.unwrap();
+
+        assert_eq!(loaded.upload_id, receipt.upload_id);
+        assert_eq!(loaded.user, "alice");
+        assert_eq!(loaded.package.name, "foo");
+    }
+
+    #[tokio::test]
+    async fn test_load_rejects_non_uuid_ids() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = ReceiptStore::new(temp_dir.path());
+
+        assert!(matches!(
+            store.load("../config").await,
+            Err(Error::InvalidPackage { .. })
+        ));
+        assert!(matches!(
+            store.load(&Uuid::new_v4().to_string()).await,
+            Err(Error::PackageNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_prune_removes_only_expired_receipts() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = ReceiptStore::new(temp_dir.path());
+        let old = test_receipt(Utc::now() - Duration::days(40));
+        let recent = test_receipt(Utc::now() - Duration::days(1));
+        store.save(&old).await.unwrap();
+        store.save(&recent).await.unwrap();
+
+        let removed = store.prune(Duration::days(30)).await.unwrap();
+
+        assert_eq!(removed, 1);
+        assert!(store.load(&old.upload_id).await.is_err());
+        assert!(store.load(&recent.upload_id).await.is_ok());
+    }
+}
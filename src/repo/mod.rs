@@ -1,14 +1,40 @@
 use axum::{
-    extract::{Path, Request, State},
+    extract::{Path, Query, Request, State},
     http::{StatusCode, header},
     response::{IntoResponse, Response},
 };
+use chrono::Utc;
 use std::sync::Arc;
 use tower::util::ServiceExt;
 use tower_http::services::ServeFile;
 
 use crate::api::AppState;
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::signed_url::{self, SignedUrlParams};
+use crate::storage::PACKAGE_SIDECAR_SUFFIXES;
+
+/// Resolve a requested filename to the package it belongs to
+///
+/// Returns the package filename for a package file or one of its sidecars
+/// (signature, provenance), or `None` if the filename isn't package-related.
+fn package_filename(filename: &str) -> Option<&str> {
+    let pkg_filename = PACKAGE_SIDECAR_SUFFIXES
+        .iter()
+        .find_map(|suffix| filename.strip_suffix(suffix))
+        .unwrap_or(filename);
+
+    pkg_filename
+        .ends_with(".pkg.tar.zst")
+        .then_some(pkg_filename)
+}
+
+/// Split a `{name}-latest-{arch}.pkg.tar.zst` link name into its package
+/// name and arch
+fn latest_link(filename: &str) -> Option<(&str, &str)> {
+    let (rest, arch) = filename.strip_suffix(".pkg.tar.zst")?.rsplit_once('-')?;
+    let name = rest.strip_suffix("-latest")?;
+    (!name.is_empty() && !arch.is_empty()).then_some((name, arch))
+}
 
 /// Serve repository files (packages or database files)
 /// This handles both .pkg.tar.zst files and .db/.files database files
@@ -17,19 +43,46 @@ pub async fn serve_file(
     Path((repo, arch, filename)): Path<(String, String, String)>,
     request: Request,
 ) -> Result<Response> {
+    if state.config.server.require_signed_downloads {
+        let secret = state
+            .config
+            .server
+            .download_signing_secret
+            .as_deref()
+            .ok_or_else(|| Error::Config {
+                msg: "require_signed_downloads needs a download_signing_secret".to_string(),
+            })?;
+        // Parsed only here so a stray query string can't break public
+        // downloads; a malformed one is treated as unsigned
+        let signature = Query::<SignedUrlParams>::try_from_uri(request.uri())
+            .map(|Query(params)| params)
+            .unwrap_or_default();
+        signed_url::verify(secret, request.uri().path(), &signature, Utc::now())?;
+    }
+
+    // Aliases resolve to the stored arch; path validation below still applies
+    let arch = state.config.storage.canonical_arch(&arch).to_owned();
+
     // Check if it's a database file or package file
-    let file_path = if filename.ends_with(".db")
+    let is_db = filename.ends_with(".db")
         || filename.ends_with(".files")
         || filename.ends_with(".db.tar.gz")
         || filename.ends_with(".files.tar.gz")
-    {
-        // Database files are in {repo}/os/{arch}/ for URL compatibility
+        || filename.ends_with(".db.tar.zst")
+        || filename.ends_with(".files.tar.zst")
+        || filename == crate::metadata::JSON_INDEX_FILENAME;
+    // The package being downloaded, to count once it's served
+    let mut counted_package = None;
+    // Whether this is a `{name}-latest-{arch}` link, retargeted on uploads
+    let mut is_latest_link = false;
+
+    let file_path = if is_db {
+        // Database files (and the JSON index) are in {repo}/os/{arch}/ for URL compatibility
         let db_dir = state.storage.db_dir(&repo, &arch)?;
         db_dir.join(&filename)
-    } else if filename.ends_with(".pkg.tar.zst") || filename.ends_with(".pkg.tar.zst.sig") {
-        // Package files are in flat storage
+    } else if let Some(pkg_filename) = package_filename(&filename) {
+        // Package files (and their sidecars) are in flat storage
         // Verify the package exists and arch matches (or is "any")
-        let pkg_filename = filename.trim_end_matches(".sig");
 
         // Check if package metadata exists and arch matches
         let metadata_name = pkg_filename.trim_end_matches(".pkg.tar.zst");
@@ -39,39 +92,90 @@ pub async fn serve_file(
                 if package.arch != arch && package.arch != "any" {
                     return Ok((StatusCode::NOT_FOUND, "File not found").into_response());
                 }
+                // Sidecars aren't downloads of the package
+                if pkg_filename == filename {
+                    counted_package = Some(package);
+                }
                 // Return path to actual file
                 state.storage.package_path(&repo, &filename)?
             }
-            Err(_) => {
+            Err(_) => match latest_link(&filename) {
+                // The link is itself a symlink, which `package_path` would
+                // refuse under `reject_symlinks`
+                Some((name, link_arch)) => {
+                    is_latest_link = true;
+                    state.storage.latest_link_path(&repo, name, link_arch)?
+                }
                 // Package metadata not found, try direct file access for .sig files
                 // or return not found
-                state.storage.package_path(&repo, &filename)?
-            }
+                None => state.storage.package_path(&repo, &filename)?,
+            },
         }
     } else {
         // Unknown file type
         return Ok((StatusCode::NOT_FOUND, "File not found").into_response());
     };
 
-    if !file_path.exists() {
-        return Ok((StatusCode::NOT_FOUND, "File not found").into_response());
+    let metadata = match tokio::fs::metadata(&file_path).await {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return Ok((StatusCode::NOT_FOUND, "File not found").into_response()),
+    };
+
+    // Package files and their sidecars never change once published, while
+    // databases and latest links are rewritten on every update
+    let cache_control = if is_latest_link {
+        "no-cache".to_owned()
+    } else if is_db {
+        match state.config.server.db_cache_max_age_secs {
+            0 => "no-cache".to_owned(),
+            max_age => format!("public, max-age={max_age}"),
+        }
+    } else {
+        format!(
+            "public, max-age={}, immutable",
+            state.config.server.package_cache_max_age_secs
+        )
+    };
+
+    // Pacman refreshes dbs often; let it revalidate instead of re-downloading
+    let etag = weak_etag(&metadata);
+    if request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|value| etag_matches(value, &etag))
+    {
+        let mut response = StatusCode::NOT_MODIFIED.into_response();
+        set_validators(&mut response, &etag, &cache_control);
+        return Ok(response);
     }
 
+    let is_get = request.method() == axum::http::Method::GET;
+
     // Record download metric for package files
     if filename.ends_with(".pkg.tar.zst") && !filename.ends_with(".sig") {
         crate::metrics::record_package_download(&repo, &arch);
     }
 
-    // Determine content type based on extension
-    let content_type = if filename.ends_with(".pkg.tar.zst") {
+    // Determine content type based on extension. Databases are compressed
+    // archives served as opaque files, the same way Arch mirrors do: no
+    // `Content-Encoding`, since pacman asks curl to decode transfer
+    // encodings and would then store a decompressed tar under `.db`. The
+    // `.db`/`.files` links point at archives in the configured format.
+    let content_type = if filename.ends_with(".tar.zst") {
         "application/zstd"
-    } else if filename.ends_with(".tar.gz")
-        || filename.ends_with(".db")
-        || filename.ends_with(".files")
-    {
+    } else if filename.ends_with(".tar.gz") {
         "application/gzip"
+    } else if filename.ends_with(".db") || filename.ends_with(".files") {
+        state.config.storage.db_compression_format.content_type()
     } else if filename.ends_with(".sig") {
         "application/pgp-signature"
+    } else if filename.ends_with(".json") {
+        "application/json"
+    } else if filename.ends_with(".BUILDINFO") {
+        "text/plain; charset=utf-8"
+    } else if filename.ends_with(".MTREE") {
+        // makepkg writes .MTREE gzip-compressed
+        "application/gzip"
     } else {
         "application/octet-stream"
     };
@@ -94,5 +198,57 @@ pub async fn serve_file(
         header::HeaderValue::from_static(content_type),
     );
 
+    set_validators(&mut response, &etag, &cache_control);
+
+    // Whole file or the requested range, as sent
+    if is_get
+        && response.status().is_success()
+        && let Some(length) = response
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok()?.parse().ok())
+    {
+        crate::metrics::record_bytes_served(&repo, length);
+    }
+
+    // Count whole downloads only, so a resumed download isn't counted twice
+    if is_get
+        && response.status() == StatusCode::OK
+        && let Some(package) = counted_package
+        && let Err(e) = state.downloads.record(&repo, &arch, &package).await
+    {
+        tracing::warn!(error = %e, package = %package.filename, "Failed to count download");
+    }
+
     Ok(response)
 }
+
+/// Weak ETag from the file's size and modification time. Databases are
+/// replaced by rename on every update, which changes both.
+fn weak_etag(metadata: &std::fs::Metadata) -> String {
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|mtime| mtime.duration_since(std::time::UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    format!("W/\"{:x}-{:x}\"", metadata.len(), mtime.as_nanos())
+}
+
+/// Whether an `If-None-Match` value matches `etag`, using the weak
+/// comparison RFC 9110 prescribes for it
+fn etag_matches(if_none_match: &header::HeaderValue, etag: &str) -> bool {
+    let Ok(value) = if_none_match.to_str() else {
+        return false;
+    };
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_owned();
+    value.trim() == "*" || value.split(',').any(|tag| opaque(tag) == opaque(etag))
+}
+
+fn set_validators(response: &mut Response, etag: &str, cache_control: &str) {
+    if let Ok(value) = header::HeaderValue::from_str(etag) {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+    if let Ok(value) = header::HeaderValue::from_str(cache_control) {
+        response.headers_mut().insert(header::CACHE_CONTROL, value);
+    }
+}
@@ -0,0 +1,127 @@
+//! Detached package signature verification
+//!
+//! With `verify_signatures`, the `.sig` uploaded with a package is checked
+//! by running `gpgv` against the configured trusted keyring. Only the keys in
+//! that keyring are trusted; gpg's web of trust plays no part.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::error::{Error, Result};
+
+/// Verify the detached signature `sig` over `package` against `keyring`,
+/// returning the fingerprint of the signing key.
+///
+/// A signature that doesn't match, or was made by a key not in the keyring,
+/// is an [`Error::SignatureInvalid`]. Failing to run `gpgv` at all is a
+/// configuration error.
+pub fn verify_detached(package: &[u8], sig: &[u8], keyring: &Path) -> Result<String> {
+    // gpgv needs the signature as a file; the signed data is piped in
+    let sig_path = std::env::temp_dir().join(format!(
+        "sw1nn-pkg-repo-{}.sig",
+        uuid::Uuid::new_v4().simple()
+    ));
+    std::fs::write(&sig_path, sig)?;
+    let output = run_gpgv(package, &sig_path, keyring);
+    let _ = std::fs::remove_file(&sig_path);
+    let output = output?;
+
+    let status = String::from_utf8_lossy(&output.stdout);
+    let fingerprint = status
+        .lines()
+        .find_map(|line| line.strip_prefix("[GNUPG:] VALIDSIG "))
+        .and_then(|rest| rest.split_whitespace().next());
+
+    match fingerprint {
+        Some(fingerprint) if output.status.success() => Ok(fingerprint.to_owned()),
+        _ => {
+            tracing::warn!(
+                keyring = %keyring.display(),
+                stderr = %String::from_utf8_lossy(&output.stderr).trim(),
+                "Package signature verification failed"
+            );
+            Err(Error::SignatureInvalid {
+                reason: failure_reason(&status).to_string(),
+            })
+        }
+    }
+}
+
+fn run_gpgv(package: &[u8], sig_path: &Path, keyring: &Path) -> Result<std::process::Output> {
+    let mut child = Command::new("gpgv")
+        .arg("--keyring")
+        // gpgv looks relative keyring names up in its home directory
+        .arg(std::path::absolute(keyring)?)
+        .args(["--status-fd", "1"])
+        .arg(sig_path)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::Config {
+            msg: format!("Failed to run gpgv for signature verification: {}", e),
+        })?;
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    std::thread::scope(|scope| {
+        // Feed the package from a separate thread so a gpgv that stops
+        // reading early can't deadlock against its full output pipes
+        scope.spawn(move || {
+            // gpgv exits without reading everything for unusable signatures
+            let _ = stdin.write_all(package);
+        });
+        Ok(child.wait_with_output()?)
+    })
+}
+
+/// A client-safe summary of why gpgv rejected the signature
+fn failure_reason(status: &str) -> &'static str {
+    let has = |keyword: &str| {
+        status
+            .lines()
+            .any(|line| line.split_whitespace().nth(1) == Some(keyword))
+    };
+    if has("BADSIG") {
+        "signature does not match the package"
+    } else if has("NO_PUBKEY") {
+        "signed by a key that is not trusted"
+    } else if has("EXPKEYSIG") || has("REVKEYSIG") {
+        "signing key is expired or revoked"
+    } else {
+        "signature could not be verified"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failure_reason_names_the_gpgv_status() {
+        assert_eq!(
+            failure_reason("[GNUPG:] NEWSIG\n[GNUPG:] BADSIG 0123 someone\n"),
+            "signature does not match the package"
+        );
+        assert_eq!(
+            failure_reason("[GNUPG:] ERRSIG 0123 1 8 00 1 9\n[GNUPG:] NO_PUBKEY 0123\n"),
+            "signed by a key that is not trusted"
+        );
+        assert_eq!(failure_reason(""), "signature could not be verified");
+    }
+
+    #[test]
+    fn garbage_signature_is_rejected() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let keyring = dir.path().join("trusted.gpg");
+        std::fs::write(&keyring, b"").unwrap();
+
+        let result = verify_detached(b"package bytes", b"not a signature", &keyring);
+
+        assert!(
+            matches!(result, Err(Error::SignatureInvalid { .. })),
+            "{result:?}"
+        );
+    }
+}
@@ -1,58 +1,85 @@
+use crate::config::CleanupPolicy;
 use crate::error::Result;
 use crate::models::Package;
-use crate::storage::Storage;
-use std::collections::HashMap;
+use crate::storage::PackageStore;
+use crate::version;
 
-/// Package with parsed semver components: (Package, major, minor, patch, pkgrel)
-type PackageWithVersion = (Package, u64, u64, u64, u64);
-/// Semver key: (major, minor, patch)
-type SemverKey = (u64, u64, u64);
+/// Package with its release line: (Package, epoch, major, minor)
+type PackageWithVersion = (Package, u64, u64, u64);
 
-/// Parse semantic version and pkgrel from Arch Linux package version string
+/// Parse the release line of an Arch Linux package version: its epoch and
+/// the leading major and minor numbers of its pkgver
 ///
-/// Format: `[epoch:]major.minor.patch-pkgrel`
-/// Returns: `Some((major, minor, patch, pkgrel))` or `None` if invalid
+/// Format: `[epoch:]major[.minor[...]]-pkgrel`, a missing epoch or minor
+/// being 0. Anything may follow the minor, so git and date versions parse.
+/// Returns: `Some((epoch, major, minor))` or `None` if the pkgver doesn't
+/// start with a number
 ///
 /// Examples:
-/// - "1.5.3-1" → Some((1, 5, 3, 1))
-/// - "2:1.5.3-2" → Some((1, 5, 3, 2))
-/// - "1.5.3-12" → Some((1, 5, 3, 12))
-fn parse_semver_from_pkgver(version_str: &str) -> Option<(u64, u64, u64, u64)> {
-    // Remove epoch if present (strip "N:" prefix)
-    let without_epoch = if let Some(colon_pos) = version_str.find(':') {
-        &version_str[colon_pos + 1..]
-    } else {
-        version_str
-    };
-
-    // Split on last '-' to separate pkgver and pkgrel
-    let last_dash = without_epoch.rfind('-')?;
-    let pkgver = &without_epoch[..last_dash];
-    let pkgrel = &without_epoch[last_dash + 1..];
-
-    // Parse pkgrel as u64
-    let pkgrel_num = pkgrel.parse::<u64>().ok()?;
-
-    // Parse pkgver as semver
-    let semver = semver::Version::parse(pkgver).ok()?;
-
-    Some((semver.major, semver.minor, semver.patch, pkgrel_num))
+/// - "1.5.3-1" → Some((0, 1, 5))
+/// - "2:1.5.3-2" → Some((2, 1, 5))
+/// - "1.0.0.r5.gabcdef-1" → Some((0, 1, 0))
+/// - "20240101-1" → Some((0, 20240101, 0))
+fn parse_release_line(version_str: &str) -> Option<(u64, u64, u64)> {
+    let (epoch, pkgver, _pkgrel) = version::split_version(version_str);
+    let epoch = epoch.parse::<u64>().ok()?;
+
+    let digits = |s: &str| s.bytes().take_while(u8::is_ascii_digit).count();
+    let major_len = digits(pkgver);
+    let major = pkgver[..major_len].parse::<u64>().ok()?;
+    // Only a number directly after the first '.' is a minor version
+    let minor = pkgver[major_len..]
+        .strip_prefix('.')
+        .and_then(|rest| rest[..digits(rest)].parse::<u64>().ok())
+        .unwrap_or(0);
+
+    Some((epoch, major, minor))
 }
 
-/// Clean up old package versions, keeping only:
+/// Clean up old package versions, keeping only those `policy` keeps. By
+/// default:
 /// 1. Current version (newest overall)
 /// 2. Latest of same minor version (excluding current)
 /// 3. Latest of previous minor version
 ///
+/// Epoch is the most significant component, so `2:1.0.0-1` is newer than
+/// `1.9.9-1`, and minor versions are only related within the same epoch.
+///
 /// For packages with same pkgver but different pkgrel (e.g., 1.5.3-1, 1.5.3-2),
 /// only the newest pkgrel is kept.
 ///
 /// Returns list of deleted packages.
 pub async fn cleanup_old_versions(
-    storage: &Storage,
+    storage: &dyn PackageStore,
+    package_name: &str,
+    repo: &str,
+    arch: &str,
+    policy: &CleanupPolicy,
+) -> Result<Vec<Package>> {
+    let to_delete = find_old_versions(storage, package_name, repo, arch, policy).await?;
+
+    for package in &to_delete {
+        storage.delete_package(package).await.inspect_err(|e| {
+            tracing::error!(
+                package = %package.name,
+                version = %package.version,
+                error = %e,
+                "Failed to delete package during cleanup"
+            );
+        })?;
+    }
+
+    Ok(to_delete)
+}
+
+/// Find the package versions [`cleanup_old_versions`] would delete, without
+/// deleting anything.
+pub async fn find_old_versions(
+    storage: &dyn PackageStore,
     package_name: &str,
     repo: &str,
     arch: &str,
+    policy: &CleanupPolicy,
 ) -> Result<Vec<Package>> {
     // List all packages for this repo, filtered by arch
     let all_packages = storage.list_packages_for_arch(repo, arch).await?;
@@ -68,17 +95,17 @@ pub async fn cleanup_old_versions(
         return Ok(Vec::new());
     }
 
-    // Parse versions and filter out non-semver packages
+    // Parse release lines and filter out unversioned packages
     let mut packages_with_versions: Vec<PackageWithVersion> = Vec::new();
 
     for package in packages.iter() {
-        if let Some((major, minor, patch, pkgrel)) = parse_semver_from_pkgver(&package.version) {
-            packages_with_versions.push((package.clone(), major, minor, patch, pkgrel));
+        if let Some((epoch, major, minor)) = parse_release_line(&package.version) {
+            packages_with_versions.push((package.clone(), epoch, major, minor));
         } else {
             tracing::warn!(
                 package = %package.name,
                 version = %package.version,
-                "Skipping package with non-semver version from cleanup"
+                "Skipping package with non-numeric version from cleanup"
             );
         }
     }
@@ -88,114 +115,59 @@ pub async fn cleanup_old_versions(
         return Ok(Vec::new());
     }
 
-    // Deduplicate by pkgver: group by (major, minor, patch), keep only newest pkgrel
-    let mut pkgver_map: HashMap<SemverKey, PackageWithVersion> = HashMap::new();
-
-    for (pkg, major, minor, patch, pkgrel) in packages_with_versions {
-        let key = (major, minor, patch);
-        // Check if we already have this pkgver
-        if let Some((_, _, _, _, existing_pkgrel)) = pkgver_map.get(&key) {
-            // Only insert if the new pkgrel is higher
-            if pkgrel > *existing_pkgrel {
-                pkgver_map.insert(key, (pkg, major, minor, patch, pkgrel));
-            }
-        } else {
-            // First time seeing this pkgver
-            pkgver_map.insert(key, (pkg, major, minor, patch, pkgrel));
-        }
-    }
-
-    // Convert back to vector
-    let mut deduplicated: Vec<PackageWithVersion> = pkgver_map.into_values().collect();
-
-    // If only 1 version after deduplication, nothing to clean up
-    if deduplicated.len() <= 1 {
-        // But we might need to delete old pkgrels
-        let kept_versions: Vec<String> = deduplicated
-            .iter()
-            .map(|(p, _, _, _, _)| p.version.clone())
-            .collect();
-
-        let mut to_delete = Vec::new();
-        for package in packages {
-            if !kept_versions.contains(&package.version) {
-                to_delete.push(package);
-            }
-        }
-
-        for package in &to_delete {
-            storage.delete_package(package).await.inspect_err(|e| {
-                tracing::error!(
-                    package = %package.name,
-                    version = %package.version,
-                    error = %e,
-                    "Failed to delete old pkgrel during cleanup"
-                );
-            })?;
-        }
-
-        return Ok(to_delete);
-    }
-
-    // Sort by version descending (newest first)
-    // Compare tuples (major, minor, patch, pkgrel) in reverse order
-    deduplicated.sort_by(
-        |(_, maj_a, min_a, pat_a, rel_a), (_, maj_b, min_b, pat_b, rel_b)| {
-            (maj_b, min_b, pat_b, rel_b).cmp(&(maj_a, min_a, pat_a, rel_a))
-        },
-    );
-
-    // Identify versions to keep
-    let (current_pkg, current_major, current_minor, _current_patch, _current_pkgrel) =
-        &deduplicated[0];
-
-    let mut versions_to_keep = vec![current_pkg.clone()];
-
-    // Find latest of same minor (excluding current)
-    let same_minor: Vec<&(Package, u64, u64, u64, u64)> = deduplicated
+    // Sort by version descending (newest first), as pacman's vercmp orders
+    // them: epoch first, then pkgver, then pkgrel
+    packages_with_versions.sort_by(|(a, ..), (b, ..)| version::vercmp(&b.version, &a.version));
+
+    // Deduplicate by pkgver: of each [epoch:]pkgver keep only the newest
+    // pkgrel, which sorts first
+    let mut deduplicated = packages_with_versions.clone();
+    deduplicated.dedup_by(|(a, ..), (b, ..)| {
+        version::vercmp(
+            version::strip_pkgrel(&a.version),
+            version::strip_pkgrel(&b.version),
+        )
+        .is_eq()
+    });
+
+    // Identify versions to keep; the current version always is
+    let (_, current_epoch, current_major, current_minor) = &deduplicated[0];
+
+    let mut versions_to_keep: Vec<&Package> = deduplicated
         .iter()
-        .skip(1) // Skip current
-        .filter(|(_, major, minor, _, _)| major == current_major && minor == current_minor)
+        .take(policy.keep_latest.max(1))
+        .map(|(pkg, ..)| pkg)
         .collect();
 
-    if let Some((pkg, _, _, _, _)) = same_minor.first() {
-        versions_to_keep.push((*pkg).clone());
-    }
-
-    // Find latest of previous minor
-    if *current_minor > 0 {
-        let previous_minor = current_minor - 1;
-        let prev_minor: Vec<&(Package, u64, u64, u64, u64)> = deduplicated
+    // Latest versions of the current minor
+    versions_to_keep.extend(
+        deduplicated
             .iter()
-            .filter(|(_, major, minor, _, _)| major == current_major && minor == &previous_minor)
-            .collect();
-
-        if let Some((pkg, _, _, _, _)) = prev_minor.first() {
-            versions_to_keep.push((*pkg).clone());
-        }
-    }
+            .filter(|(_, epoch, major, minor)| {
+                epoch == current_epoch && major == current_major && minor == current_minor
+            })
+            .take(policy.keep_per_minor)
+            .map(|(pkg, ..)| pkg),
+    );
 
-    // Identify packages to delete (all packages not in versions_to_keep)
-    let kept_versions: Vec<String> = versions_to_keep.iter().map(|p| p.version.clone()).collect();
-    let mut to_delete = Vec::new();
+    // Latest of each previous minor
+    for previous_minor in (0..*current_minor).rev().take(policy.keep_previous_minors) {
+        let prev_minor = deduplicated.iter().find(|(_, epoch, major, minor)| {
+            epoch == current_epoch && major == current_major && *minor == previous_minor
+        });
 
-    for package in packages {
-        if !kept_versions.contains(&package.version) {
-            to_delete.push(package);
+        if let Some((pkg, ..)) = prev_minor {
+            versions_to_keep.push(pkg);
         }
     }
 
-    // Delete packages
-    for package in &to_delete {
-        storage.delete_package(package).await.inspect_err(|e| {
-            tracing::error!(
-                package = %package.name,
-                version = %package.version,
-                error = %e,
-                "Failed to delete package during cleanup"
-            );
-        })?;
-    }
+    // Identify packages to delete (all parsed packages not in versions_to_keep)
+    let kept_versions: Vec<String> = versions_to_keep.iter().map(|p| p.version.clone()).collect();
+    let to_delete = packages_with_versions
+        .into_iter()
+        .map(|(package, ..)| package)
+        .filter(|package| !kept_versions.contains(&package.version))
+        .collect();
 
     Ok(to_delete)
 }
@@ -205,33 +177,34 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_parse_semver_basic() -> Result<()> {
-        assert_eq!(parse_semver_from_pkgver("1.5.3-1"), Some((1, 5, 3, 1)));
-        assert_eq!(parse_semver_from_pkgver("2.0.0-1"), Some((2, 0, 0, 1)));
-        assert_eq!(parse_semver_from_pkgver("0.1.0-1"), Some((0, 1, 0, 1)));
+    fn test_parse_release_line_basic() -> Result<()> {
+        assert_eq!(parse_release_line("1.5.3-1"), Some((0, 1, 5)));
+        assert_eq!(parse_release_line("2.0.0-1"), Some((0, 2, 0)));
+        assert_eq!(parse_release_line("0.1.0-1"), Some((0, 0, 1)));
         Ok(())
     }
 
     #[test]
-    fn test_parse_semver_with_epoch() -> Result<()> {
-        assert_eq!(parse_semver_from_pkgver("2:1.5.3-1"), Some((1, 5, 3, 1)));
-        assert_eq!(parse_semver_from_pkgver("1:2.0.0-3"), Some((2, 0, 0, 3)));
+    fn test_parse_release_line_with_epoch() -> Result<()> {
+        assert_eq!(parse_release_line("2:1.5.3-1"), Some((2, 1, 5)));
+        assert_eq!(parse_release_line("1:2.0.0-3"), Some((1, 2, 0)));
+        assert_eq!(parse_release_line("x:2.0.0-3"), None); // Not an epoch
         Ok(())
     }
 
     #[test]
-    fn test_parse_semver_various_pkgrel() -> Result<()> {
-        assert_eq!(parse_semver_from_pkgver("1.5.3-1"), Some((1, 5, 3, 1)));
-        assert_eq!(parse_semver_from_pkgver("1.5.3-2"), Some((1, 5, 3, 2)));
-        assert_eq!(parse_semver_from_pkgver("1.5.3-12"), Some((1, 5, 3, 12)));
+    fn test_parse_release_line_non_semver() -> Result<()> {
+        assert_eq!(parse_release_line("1.0.0.r5.gabcdef-1"), Some((0, 1, 0)));
+        assert_eq!(parse_release_line("20250115-1"), Some((0, 20250115, 0)));
+        assert_eq!(parse_release_line("1.5"), Some((0, 1, 5))); // Missing pkgrel
+        assert_eq!(parse_release_line("2.rc1-1"), Some((0, 2, 0)));
         Ok(())
     }
 
     #[test]
-    fn test_parse_semver_invalid() -> Result<()> {
-        assert_eq!(parse_semver_from_pkgver("invalid"), None);
-        assert_eq!(parse_semver_from_pkgver("1.5"), None); // Missing pkgrel
-        assert_eq!(parse_semver_from_pkgver("20250115"), None); // Date format
+    fn test_parse_release_line_invalid() -> Result<()> {
+        assert_eq!(parse_release_line("invalid"), None);
+        assert_eq!(parse_release_line("r123.abcdef-1"), None);
         Ok(())
     }
 }
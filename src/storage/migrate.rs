@@ -0,0 +1,187 @@
+use crate::error::{Error, Result, ResultIoExt};
+use crate::metadata::{calculate_sha256, extract_pkginfo};
+use crate::models::Package;
+use crate::storage::{FsStore, PACKAGE_SIDECAR_SUFFIXES, PackageStore};
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Directories of a repo that belong to the current layout
+const LAYOUT_DIRS: [&str; 3] = ["os", "packages", "metadata"];
+
+impl FsStore {
+    /// Move packages from the legacy `data/{repo}/{arch}/` layout into the
+    /// current one, returning how many packages were moved.
+    ///
+    /// Packages in a legacy arch directory are invisible to the server. Each
+    /// is stored like an upload (file into `packages/`, metadata written from
+    /// its `.PKGINFO`), its sidecars moved along, and emptied legacy
+    /// directories removed. Databases are left to the startup rebuild.
+    /// Packages that can't be read or would clash with a stored package are
+    /// logged and left in place.
+    pub async fn migrate_legacy_layout(&self) -> Result<usize> {
+        let mut moved = 0;
+        for (repo, arch_dir) in self.legacy_arch_dirs().await? {
+            moved += self.migrate_legacy_arch_dir(&repo, &arch_dir).await?;
+
+            match fs::remove_dir(&arch_dir).await {
+                Ok(()) => tracing::info!(path = %arch_dir.display(), "Removed legacy directory"),
+                Err(e) => tracing::warn!(
+                    path = %arch_dir.display(),
+                    error = %e,
+                    "Legacy directory not empty after migration, leaving it in place"
+                ),
+            }
+        }
+        Ok(moved)
+    }
+
+    /// `(repo, dir)` of every `data/{repo}/{arch}/` holding package files
+    async fn legacy_arch_dirs(&self) -> Result<Vec<(String, PathBuf)>> {
+        let mut dirs = Vec::new();
+        if !self.base_path.exists() {
+            return Ok(dirs);
+        }
+
+        let mut repo_entries = fs::read_dir(&self.base_path)
+            .await
+            .map_io_err(&self.base_path)?;
+        while let Some(repo_entry) = repo_entries
+            .next_entry()
+            .await
+            .map_io_err(&self.base_path)?
+        {
+            let repo = repo_entry.file_name().to_string_lossy().into_owned();
+            if repo.starts_with('.') || !repo_entry.path().is_dir() {
+                continue;
+            }
+
+            let repo_path = repo_entry.path();
+            let mut arch_entries = fs::read_dir(&repo_path).await.map_io_err(&repo_path)?;
+            while let Some(arch_entry) = arch_entries.next_entry().await.map_io_err(&repo_path)? {
+                let name = arch_entry.file_name().to_string_lossy().into_owned();
+                let path = arch_entry.path();
+                if LAYOUT_DIRS.contains(&name.as_str()) || name.starts_with('.') || !path.is_dir() {
+                    continue;
+                }
+                if !package_files(&path).await?.is_empty() {
+                    dirs.push((repo.clone(), path));
+                }
+            }
+        }
+        Ok(dirs)
+    }
+
+    async fn migrate_legacy_arch_dir(&self, repo: &str, arch_dir: &Path) -> Result<usize> {
+        let mut moved = 0;
+        for pkg_path in package_files(arch_dir).await? {
+            match self.migrate_legacy_package(repo, &pkg_path).await {
+                Ok(package) => {
+                    tracing::info!(
+                        src = %pkg_path.display(),
+                        repo,
+                        package = %package.name,
+                        version = %package.version,
+                        arch = %package.arch,
+                        "Migrated legacy package"
+                    );
+                    moved += 1;
+                }
+                Err(e) => tracing::warn!(
+                    src = %pkg_path.display(),
+                    error = %e,
+                    "Could not migrate legacy package, leaving it in place"
+                ),
+            }
+        }
+
+        // Drop metadata the legacy layout may have kept per arch; it was
+        // rewritten from the packages themselves
+        let old_metadata_dir = arch_dir.join("metadata");
+        if old_metadata_dir.is_dir() && package_files(arch_dir).await?.is_empty() {
+            fs::remove_dir_all(&old_metadata_dir)
+                .await
+                .map_io_err(&old_metadata_dir)?;
+        }
+        Ok(moved)
+    }
+
+    async fn migrate_legacy_package(&self, repo: &str, pkg_path: &Path) -> Result<Package> {
+        let filename = pkg_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let lossy_pkginfo = self.lossy_pkginfo;
+        let read_path = pkg_path.to_path_buf();
+        let (pkginfo, sha256, size) = tokio::task::spawn_blocking(move || {
+            let data = std::fs::read(&read_path).map_io_err(&read_path)?;
+            let pkginfo = extract_pkginfo(&data, lossy_pkginfo)?;
+            Ok::<_, Error>((pkginfo, calculate_sha256(&data), data.len() as u64))
+        })
+        .await
+        .map_err(|e| std::io::Error::other(format!("Task join error: {}", e)))??;
+
+        // Keep the file's age rather than stamping every package with the
+        // time of the migration
+        let created_at = fs::metadata(pkg_path)
+            .await
+            .and_then(|meta| meta.modified())
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+
+        let sig_path = sidecar_path(pkg_path, ".sig");
+        let package = Package {
+            name: pkginfo.pkgname,
+            version: pkginfo.pkgver,
+            arch: pkginfo.arch,
+            repo: repo.to_owned(),
+            filename,
+            sha256,
+            size,
+            created_at,
+            signed: sig_path.exists(),
+            signature_verified: false,
+            license: pkginfo.license,
+        };
+
+        self.store_package_from_path(&package, pkg_path).await?;
+        fs::remove_file(pkg_path).await.map_io_err(pkg_path)?;
+
+        for suffix in PACKAGE_SIDECAR_SUFFIXES {
+            let src = sidecar_path(pkg_path, suffix);
+            if !src.exists() {
+                continue;
+            }
+            let dest = self.package_path(repo, &format!("{}{}", package.filename, suffix))?;
+            fs::rename(&src, &dest).await.map_io_err(&dest)?;
+        }
+
+        Ok(package)
+    }
+}
+
+fn sidecar_path(pkg_path: &Path, suffix: &str) -> PathBuf {
+    let mut path = pkg_path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// The `.pkg.tar.zst` files directly inside `dir`, sorted
+async fn package_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut entries = fs::read_dir(dir).await.map_io_err(dir)?;
+    while let Some(entry) = entries.next_entry().await.map_io_err(dir)? {
+        let path = entry.path();
+        if entry
+            .file_name()
+            .to_string_lossy()
+            .ends_with(".pkg.tar.zst")
+            && path.is_file()
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -1,14 +1,43 @@
+use crate::config::{MetadataStore, StorageConfig};
+use crate::db_actor::RepoArchKey;
 use crate::error::{Error, Result, ResultIoExt};
 use crate::models::Package;
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 
 mod cleanup;
-pub use cleanup::cleanup_old_versions;
+mod migrate;
+mod store;
+pub use cleanup::{cleanup_old_versions, find_old_versions};
+pub use store::PackageStore;
+
+/// Suffixes of files stored alongside a package file that share its lifetime
+pub const PACKAGE_SIDECAR_SUFFIXES: [&str; 3] = [".sig", ".BUILDINFO", ".MTREE"];
+
+/// File holding every package's metadata of a repo with the bundled
+/// metadata store
+pub const METADATA_BUNDLE_FILENAME: &str = "metadata.json.zst";
+
+/// Advisory lock file held by the server owning a data directory
+pub const DATA_LOCK_FILENAME: &str = ".lock";
+
+/// Exclusive hold on a data directory, released when dropped (or when the
+/// process exits, however it exits)
+#[derive(Debug)]
+pub struct DataDirLock {
+    _file: std::fs::File,
+}
+
+/// Default maximum length of a single path component, in bytes. Matches the
+/// NAME_MAX of common Linux filesystems.
+pub const DEFAULT_MAX_COMPONENT_LEN: usize = 255;
 
 /// Validate a path component to prevent directory traversal attacks
-fn validate_path_component(component: &str) -> Result<()> {
+fn validate_path_component(component: &str, max_len: usize) -> Result<()> {
     // Reject empty, ".", "..", or components containing path separators
     if component.is_empty() {
         return Err(Error::InvalidPackage {
@@ -34,6 +63,21 @@ fn validate_path_component(component: &str) -> Result<()> {
         });
     }
 
+    if component.chars().any(char::is_control) {
+        return Err(Error::InvalidPackage {
+            pkgname: "Name cannot contain control characters".to_string(),
+        });
+    }
+
+    if component.len() > max_len {
+        return Err(Error::InvalidPackage {
+            pkgname: format!(
+                "Name too long: {} bytes exceeds the maximum of {max_len}",
+                component.len()
+            ),
+        });
+    }
+
     Ok(())
 }
 
@@ -73,90 +117,697 @@ fn validate_path_within_base(base: &Path, path: &Path) -> Result<()> {
     Ok(())
 }
 
-/// Storage layer for managing package files and metadata
+/// Reject symlinks in any existing component of `path` below `base`
+///
+/// Unlike the canonical prefix check this doesn't depend on the rest of the
+/// path existing yet, so a symlinked directory can't be used to place files
+/// outside `base` once the missing components are created.
+fn reject_symlinks_under_base(base: &Path, path: &Path) -> Result<()> {
+    let relative = path.strip_prefix(base).map_err(|_| Error::InvalidPackage {
+        pkgname: "Path traversal detected".to_string(),
+    })?;
+
+    let mut current = base.to_path_buf();
+    for component in relative.components() {
+        current.push(component);
+        match current.symlink_metadata() {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                return Err(Error::InvalidPackage {
+                    pkgname: "Symlink in storage path rejected".to_string(),
+                });
+            }
+            Ok(_) => {}
+            // Nothing below a missing component exists either
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+            Err(e) => return Err(e).map_io_err(&current),
+        }
+    }
+
+    Ok(())
+}
+
+/// Write package metadata atomically (temp file + rename), so a crash never
+/// leaves truncated JSON behind
+async fn write_metadata(meta_path: &Path, package: &Package) -> Result<()> {
+    let metadata_json = serde_json::to_string_pretty(package).map_err(std::io::Error::other)?;
+    let tmp_path = meta_path.with_extension("json.tmp");
+
+    let mut file = fs::File::create(&tmp_path).await.map_io_err(&tmp_path)?;
+    file.write_all(metadata_json.as_bytes())
+        .await
+        .map_io_err(&tmp_path)?;
+    file.sync_all().await.map_io_err(&tmp_path)?;
+
+    fs::rename(&tmp_path, meta_path).await.map_io_err(meta_path)
+}
+
+/// Read a package metadata file, returning `None` (with a warning) if it
+/// doesn't parse
+async fn read_metadata(path: &Path) -> Result<Option<Package>> {
+    let content = fs::read_to_string(path).await.map_io_err(path)?;
+    match serde_json::from_str::<Package>(&content) {
+        Ok(package) => Ok(Some(package)),
+        Err(e) => {
+            tracing::warn!(
+                path = %path.display(),
+                error = %e,
+                "Skipping unparseable package metadata file"
+            );
+            Ok(None)
+        }
+    }
+}
+
+/// Read every parseable per-package metadata file in a metadata directory
+async fn read_metadata_dir(meta_dir: &Path) -> Result<Vec<Package>> {
+    let mut packages = Vec::new();
+    let mut entries = fs::read_dir(meta_dir).await.map_io_err(meta_dir)?;
+
+    while let Some(entry) = entries.next_entry().await.map_io_err(meta_dir)? {
+        let path = entry.path();
+        if path.extension().and_then(|s| s.to_str()) == Some("json")
+            && let Some(package) = read_metadata(&path).await?
+        {
+            packages.push(package);
+        }
+    }
+
+    Ok(packages)
+}
+
+/// Metadata name of a package: its filename without `.pkg.tar.zst`
+fn metadata_name(package: &Package) -> &str {
+    package.filename.trim_end_matches(".pkg.tar.zst")
+}
+
+/// Read the metadata bundle of a metadata directory, keyed by metadata name
+///
+/// Without a bundle the per-package files are read instead, so a repo
+/// switched to the bundled store keeps its packages and is converted on the
+/// next write. Entries that don't parse are skipped with a warning, as
+/// unparseable per-package files are.
+async fn read_metadata_bundle(meta_dir: &Path) -> Result<BTreeMap<String, Package>> {
+    let bundle_path = meta_dir.join(METADATA_BUNDLE_FILENAME);
+
+    let compressed = match fs::read(&bundle_path).await {
+        Ok(compressed) => compressed,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            if !meta_dir.exists() {
+                return Ok(BTreeMap::new());
+            }
+            return Ok(read_metadata_dir(meta_dir)
+                .await?
+                .into_iter()
+                .map(|p| (metadata_name(&p).to_owned(), p))
+                .collect());
+        }
+        Err(e) => return Err(e).map_io_err(&bundle_path),
+    };
+
+    let entries: BTreeMap<String, serde_json::Value> = tokio::task::spawn_blocking(move || {
+        let json = zstd::decode_all(compressed.as_slice())?;
+        serde_json::from_slice(&json).map_err(std::io::Error::other)
+    })
+    .await
+    .map_err(|e| std::io::Error::other(format!("Task join error: {e}")))?
+    .map_io_err(&bundle_path)?;
+
+    Ok(entries
+        .into_iter()
+        .filter_map(|(name, entry)| match serde_json::from_value(entry) {
+            Ok(package) => Some((name, package)),
+            Err(e) => {
+                tracing::warn!(
+                    path = %bundle_path.display(),
+                    package = %name,
+                    error = %e,
+                    "Skipping unparseable package metadata in bundle"
+                );
+                None
+            }
+        })
+        .collect())
+}
+
+/// Write a metadata bundle atomically (temp file + rename)
+async fn write_metadata_bundle(
+    meta_dir: &Path,
+    packages: Arc<BTreeMap<String, Package>>,
+) -> Result<()> {
+    let bundle_path = meta_dir.join(METADATA_BUNDLE_FILENAME);
+    let tmp_path = bundle_path.with_extension("zst.tmp");
+
+    let compressed = tokio::task::spawn_blocking(move || {
+        let json = serde_json::to_vec(&*packages).map_err(std::io::Error::other)?;
+        zstd::encode_all(json.as_slice(), 0)
+    })
+    .await
+    .map_err(|e| std::io::Error::other(format!("Task join error: {e}")))?
+    .map_io_err(&bundle_path)?;
+
+    let mut file = fs::File::create(&tmp_path).await.map_io_err(&tmp_path)?;
+    file.write_all(&compressed).await.map_io_err(&tmp_path)?;
+    file.sync_all().await.map_io_err(&tmp_path)?;
+
+    fs::rename(&tmp_path, &bundle_path)
+        .await
+        .map_io_err(&bundle_path)
+}
+
+/// Relative target of a package's pool symlink, as seen from its pool directory
+#[cfg(unix)]
+fn pool_link_target(package: &Package) -> PathBuf {
+    Path::new("../..")
+        .join(&package.repo)
+        .join("packages")
+        .join(&package.filename)
+}
+
+/// [`PackageStore`] keeping package files and metadata on the local
+/// filesystem
 ///
 /// Flat storage structure (arch is metadata, not directory):
 ///   data/{repo}/packages/{package-file}
 ///   data/{repo}/packages/{package-file}.sig
 ///   data/{repo}/metadata/{package-name}.json
-///   data/{repo}/os/{arch}/{repo}.db.tar.gz  (databases for URL compatibility)
-pub struct Storage {
+///     (or data/{repo}/metadata/metadata.json.zst with the bundled metadata store)
+///   data/{repo}/pkginfo/{package-name}.json  (cached .PKGINFO and file list)
+///   data/{repo}/os/{arch}/{repo}.db.tar.gz  (databases for URL compatibility;
+///     .tar.zst with the zstd db_compression_format)
+///   data/.pool/{sha256[..2]}/{package-file} -> ../../{repo}/packages/{package-file}
+///     (only with `maintain_pool`)
+///   data/{repo}/packages/{name}-latest-{arch}.pkg.tar.zst -> {package-file}
+///     (only with `maintain_latest_symlink`)
+pub struct FsStore {
     base_path: PathBuf,
+    max_component_len: usize,
+    maintain_pool: bool,
+    maintain_latest_symlink: bool,
+    reject_duplicate_content: bool,
+    reject_symlinks: bool,
+    lossy_pkginfo: bool,
+    metadata_store: MetadataStore,
+    db_locks: Mutex<HashMap<RepoArchKey, Arc<tokio::sync::Mutex<()>>>>,
+    bundle_locks: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+    /// Parsed metadata bundles by repo, replaced on every bundle write
+    bundle_cache: Mutex<HashMap<String, Arc<BTreeMap<String, Package>>>>,
 }
 
-impl Storage {
+impl FsStore {
     pub fn new(base_path: impl Into<PathBuf>) -> Self {
         Self {
             base_path: base_path.into(),
+            max_component_len: DEFAULT_MAX_COMPONENT_LEN,
+            maintain_pool: false,
+            maintain_latest_symlink: false,
+            reject_duplicate_content: false,
+            reject_symlinks: false,
+            lossy_pkginfo: false,
+            metadata_store: MetadataStore::PerPackage,
+            db_locks: Mutex::default(),
+            bundle_locks: Mutex::default(),
+            bundle_cache: Mutex::default(),
         }
     }
 
-    /// Get the packages directory for a repo
-    pub fn packages_dir(&self, repo: &str) -> Result<PathBuf> {
-        validate_path_component(repo)?;
+    /// Create storage using the data path and limits from config
+    pub fn from_config(config: &StorageConfig) -> Self {
+        Self {
+            base_path: config.data_path.clone(),
+            max_component_len: config.max_filename_length,
+            maintain_pool: config.maintain_pool,
+            maintain_latest_symlink: config.maintain_latest_symlink,
+            reject_duplicate_content: config.reject_duplicate_content,
+            reject_symlinks: config.reject_symlinks,
+            lossy_pkginfo: config.lossy_pkginfo,
+            metadata_store: config.metadata_store,
+            db_locks: Mutex::default(),
+            bundle_locks: Mutex::default(),
+            bundle_cache: Mutex::default(),
+        }
+    }
 
-        let path = self.base_path.join(repo).join("packages");
+    /// Check that the data directory is usable before serving: it must exist
+    /// (or be created when `create` is set), be a directory and be writable
+    pub async fn preflight(&self, create: bool) -> Result<()> {
+        let base = &self.base_path;
+
+        match fs::metadata(base).await {
+            Ok(meta) if !meta.is_dir() => {
+                return Err(Error::Config {
+                    msg: format!("data_path {} is not a directory", base.display()),
+                });
+            }
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && create => {
+                fs::create_dir_all(base).await.map_err(|e| Error::Config {
+                    msg: format!("Failed to create data_path {}: {}", base.display(), e),
+                })?;
+            }
+            Err(e) => {
+                return Err(Error::Config {
+                    msg: format!("data_path {} is not accessible: {}", base.display(), e),
+                });
+            }
+        }
+
+        // Unique per call, as health checks probe concurrently
+        let probe = base.join(format!(".write-probe-{}", uuid::Uuid::new_v4()));
+        fs::write(&probe, b"").await.map_err(|e| Error::Config {
+            msg: format!("data_path {} is not writable: {}", base.display(), e),
+        })?;
+        fs::remove_file(&probe).await.map_io_err(&probe)?;
+
+        Ok(())
+    }
+
+    /// Take the advisory lock on `data/.lock`, failing if another process
+    /// (e.g. a second server instance) already holds it. Two servers sharing
+    /// a data directory would race each other's db regenerations.
+    pub fn lock_data_dir(&self) -> Result<DataDirLock> {
+        let path = self.base_path.join(DATA_LOCK_FILENAME);
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_io_err(&path)?;
+
+        match file.try_lock() {
+            Ok(()) => Ok(DataDirLock { _file: file }),
+            Err(std::fs::TryLockError::WouldBlock) => Err(Error::Config {
+                msg: format!(
+                    "data_path {} is in use by another instance (lock held on {})",
+                    self.base_path.display(),
+                    path.display()
+                ),
+            }),
+            Err(std::fs::TryLockError::Error(e)) => Err(e).map_io_err(&path),
+        }
+    }
+
+    /// Check that `path` stays within the base directory, and with
+    /// `reject_symlinks` that no component below it is a symlink
+    fn validate_within_base(&self, path: &Path) -> Result<()> {
+        validate_path_within_base(&self.base_path, path)?;
+        if self.reject_symlinks {
+            reject_symlinks_under_base(&self.base_path, path)?;
+        }
+        Ok(())
+    }
+
+    /// Use the given metadata store instead of one file per package
+    pub fn with_metadata_store(mut self, metadata_store: MetadataStore) -> Self {
+        self.metadata_store = metadata_store;
+        self
+    }
+
+    fn bundle_lock(&self, repo: &str) -> Arc<tokio::sync::Mutex<()>> {
+        Arc::clone(
+            self.bundle_locks
+                .lock()
+                .expect("bundle lock map poisoned")
+                .entry(repo.to_owned())
+                .or_default(),
+        )
+    }
+
+    fn cached_bundle(&self, repo: &str) -> Option<Arc<BTreeMap<String, Package>>> {
+        self.bundle_cache
+            .lock()
+            .expect("bundle cache poisoned")
+            .get(repo)
+            .cloned()
+    }
+
+    /// The metadata bundle of a repo, read from disk only when it isn't
+    /// cached yet
+    async fn metadata_bundle(&self, repo: &str) -> Result<Arc<BTreeMap<String, Package>>> {
+        if let Some(packages) = self.cached_bundle(repo) {
+            return Ok(packages);
+        }
+
+        // Under the bundle lock, so a concurrent write can't be overwritten
+        // in the cache by what was read before it
+        let lock = self.bundle_lock(repo);
+        let _guard = lock.lock().await;
+        if let Some(packages) = self.cached_bundle(repo) {
+            return Ok(packages);
+        }
+
+        let packages = Arc::new(read_metadata_bundle(&self.metadata_dir(repo)?).await?);
+        self.bundle_cache
+            .lock()
+            .expect("bundle cache poisoned")
+            .insert(repo.to_owned(), Arc::clone(&packages));
+        Ok(packages)
+    }
+
+    /// Read-modify-write the metadata bundle of a repo, serialized per repo
+    /// so concurrent stores and deletes don't lose each other's changes
+    async fn update_metadata_bundle(
+        &self,
+        repo: &str,
+        update: impl FnOnce(&mut BTreeMap<String, Package>),
+    ) -> Result<()> {
+        let meta_dir = self.metadata_dir(repo)?;
+        let lock = self.bundle_lock(repo);
+        let _guard = lock.lock().await;
+
+        let mut packages = read_metadata_bundle(&meta_dir).await?;
+        update(&mut packages);
+
+        // Dropped first so a failed write leaves nothing stale behind
+        self.bundle_cache
+            .lock()
+            .expect("bundle cache poisoned")
+            .remove(repo);
+        let packages = Arc::new(packages);
+        write_metadata_bundle(&meta_dir, Arc::clone(&packages)).await?;
+        self.bundle_cache
+            .lock()
+            .expect("bundle cache poisoned")
+            .insert(repo.to_owned(), packages);
+        Ok(())
+    }
+
+    /// Save a package's metadata in the configured metadata store
+    ///
+    /// If metadata for the same file already exists, its `created_at` is
+    /// kept and only the other fields are replaced, so rewriting metadata
+    /// during maintenance doesn't reset the package's history.
+    async fn save_metadata(&self, package: &Package) -> Result<()> {
+        match self.metadata_store {
+            MetadataStore::PerPackage => {
+                let meta_path = self.metadata_path(&package.repo, metadata_name(package))?;
+                let mut package = package.clone();
+                if meta_path.exists()
+                    && let Some(existing) = read_metadata(&meta_path).await?
+                {
+                    package.created_at = existing.created_at;
+                }
+                write_metadata(&meta_path, &package).await
+            }
+            MetadataStore::Bundled => {
+                self.update_metadata_bundle(&package.repo, |packages| {
+                    let mut package = package.clone();
+                    if let Some(existing) = packages.get(metadata_name(&package)) {
+                        package.created_at = existing.created_at;
+                    }
+                    packages.insert(metadata_name(&package).to_owned(), package);
+                })
+                .await
+            }
+        }
+    }
+
+    /// Link a stored package into the pool, if `maintain_pool` is enabled
+    async fn link_into_pool(&self, package: &Package) -> Result<()> {
+        if !self.maintain_pool {
+            return Ok(());
+        }
+        let Some(pool_path) = self.pool_path(package)? else {
+            tracing::warn!(
+                package = %package.filename,
+                "Package has no SHA256, not linking into pool"
+            );
+            return Ok(());
+        };
+
+        if let Some(parent) = pool_path.parent() {
+            fs::create_dir_all(parent).await.map_io_err(parent)?;
+        }
+
+        // An identical file stored in another repo already owns the link
+        if fs::symlink_metadata(&pool_path).await.is_ok() {
+            return Ok(());
+        }
 
+        #[cfg(unix)]
+        fs::symlink(pool_link_target(package), &pool_path)
+            .await
+            .map_io_err(&pool_path)?;
+
+        #[cfg(not(unix))]
+        fs::hard_link(
+            self.package_path(&package.repo, &package.filename)?,
+            &pool_path,
+        )
+        .await
+        .map_io_err(&pool_path)?;
+
+        Ok(())
+    }
+
+    /// Point the `latest` link for a package name/arch at its newest stored
+    /// version, or remove it once none is left. No-op unless
+    /// `maintain_latest_symlink` is enabled.
+    ///
+    /// On Unix this is a relative symlink; elsewhere the package is copied.
+    async fn update_latest_link(&self, repo: &str, name: &str, arch: &str) -> Result<()> {
+        if !self.maintain_latest_symlink {
+            return Ok(());
+        }
+        let link = self.latest_link_path(repo, name, arch)?;
+
+        let newest = self
+            .list_packages(repo)
+            .await?
+            .into_iter()
+            .filter(|p| p.name == name && p.arch == arch)
+            .max_by(|a, b| crate::version::vercmp(&a.version, &b.version));
+
+        let Some(newest) = newest else {
+            if fs::symlink_metadata(&link).await.is_ok() {
+                fs::remove_file(&link).await.map_io_err(&link)?;
+            }
+            return Ok(());
+        };
+
+        #[cfg(unix)]
+        {
+            let target = Path::new(&newest.filename);
+            if fs::read_link(&link).await.is_ok_and(|t| t == target) {
+                return Ok(());
+            }
+
+            // Swap the link in with a rename so it never dangles mid-update
+            let tmp_link = link.with_extension("zst.tmp");
+            let _ = fs::remove_file(&tmp_link).await;
+            fs::symlink(target, &tmp_link).await.map_io_err(&tmp_link)?;
+            fs::rename(&tmp_link, &link).await.map_io_err(&link)?;
+        }
+
+        #[cfg(not(unix))]
+        fs::copy(self.package_path(repo, &newest.filename)?, &link)
+            .await
+            .map_io_err(&link)?;
+
+        Ok(())
+    }
+
+    /// Remove a package's pool link, if it points at this package's file
+    async fn unlink_from_pool(&self, package: &Package) -> Result<()> {
+        let Some(pool_path) = self.pool_path(package)? else {
+            return Ok(());
+        };
+
+        #[cfg(unix)]
+        let owned = fs::read_link(&pool_path)
+            .await
+            .is_ok_and(|target| target == pool_link_target(package));
+
+        #[cfg(not(unix))]
+        let owned = pool_path.exists();
+
+        if owned {
+            fs::remove_file(&pool_path).await.map_io_err(&pool_path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Warn about (or, with `reject_duplicate_content`, reject) a package
+    /// whose SHA256 matches another stored version of the same name
+    async fn check_duplicate_content(&self, package: &Package) -> Result<()> {
+        if package.sha256.is_empty() {
+            return Ok(());
+        }
+
+        let duplicate = self
+            .list_packages(&package.repo)
+            .await?
+            .into_iter()
+            .find(|p| {
+                p.name == package.name
+                    && p.filename != package.filename
+                    && p.sha256 == package.sha256
+            });
+
+        let Some(existing) = duplicate else {
+            return Ok(());
+        };
+
+        tracing::warn!(
+            package = %package.filename,
+            existing = %existing.filename,
+            repo = %package.repo,
+            sha256 = %package.sha256,
+            "Package content is identical to an existing package"
+        );
+
+        if self.reject_duplicate_content {
+            return Err(Error::DuplicateContent {
+                pkgname: package.filename.clone(),
+                existing: existing.filename,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Read every package in a repo's metadata directory from the
+    /// configured store
+    async fn read_metadata_store(&self, repo: &str, meta_dir: &Path) -> Result<Vec<Package>> {
+        match self.metadata_store {
+            MetadataStore::PerPackage => read_metadata_dir(meta_dir).await,
+            MetadataStore::Bundled => Ok(self
+                .metadata_bundle(repo)
+                .await?
+                .values()
+                .cloned()
+                .collect()),
+        }
+    }
+}
+
+#[async_trait]
+impl PackageStore for FsStore {
+    async fn check_writable(&self) -> Result<()> {
+        self.preflight(false).await
+    }
+
+    async fn lock_db(&self, repo: &str, arch: &str) -> tokio::sync::OwnedMutexGuard<()> {
+        let lock = Arc::clone(
+            self.db_locks
+                .lock()
+                .expect("db lock map poisoned")
+                .entry(RepoArchKey::new(repo, arch))
+                .or_default(),
+        );
+        lock.lock_owned().await
+    }
+
+    /// Pool symlinks live at `data/.pool/{sha256[..2]}/{filename}`
+    fn pool_path(&self, package: &Package) -> Result<Option<PathBuf>> {
+        let Some(prefix) = package.sha256.get(..2) else {
+            return Ok(None);
+        };
+        validate_path_component(prefix, self.max_component_len)?;
+        validate_path_component(&package.filename, self.max_component_len)?;
+
+        let path = self
+            .base_path
+            .join(".pool")
+            .join(prefix)
+            .join(&package.filename);
+
+        // The pool entry itself is a symlink; only its directories are checked
         validate_path_within_base(&self.base_path, &path)?;
+        if let Some(parent) = path.parent() {
+            self.validate_within_base(parent)?;
+        }
+
+        Ok(Some(path))
+    }
+
+    /// `data/{repo}/packages/{name}-latest-{arch}.pkg.tar.zst`
+    fn latest_link_path(&self, repo: &str, name: &str, arch: &str) -> Result<PathBuf> {
+        let link_name = format!("{name}-latest-{arch}.pkg.tar.zst");
+        validate_path_component(&link_name, self.max_component_len)?;
+
+        let packages_dir = self.packages_dir(repo)?;
+        let path = packages_dir.join(link_name);
+
+        // The link itself is a symlink; only its directory is checked
+        validate_path_within_base(&self.base_path, &path)?;
+        self.validate_within_base(&packages_dir)?;
+
+        Ok(path)
+    }
+
+    fn packages_dir(&self, repo: &str) -> Result<PathBuf> {
+        validate_path_component(repo, self.max_component_len)?;
+
+        let path = self.base_path.join(repo).join("packages");
+
+        self.validate_within_base(&path)?;
 
         Ok(path)
     }
 
-    /// Get the metadata directory for a repo
-    pub fn metadata_dir(&self, repo: &str) -> Result<PathBuf> {
-        validate_path_component(repo)?;
+    fn metadata_dir(&self, repo: &str) -> Result<PathBuf> {
+        validate_path_component(repo, self.max_component_len)?;
 
         let path = self.base_path.join(repo).join("metadata");
 
-        validate_path_within_base(&self.base_path, &path)?;
+        self.validate_within_base(&path)?;
 
         Ok(path)
     }
 
-    /// Get the path for a package file (flat structure, no arch in path)
-    pub fn package_path(&self, repo: &str, filename: &str) -> Result<PathBuf> {
-        validate_path_component(repo)?;
-        validate_path_component(filename)?;
+    fn package_path(&self, repo: &str, filename: &str) -> Result<PathBuf> {
+        validate_path_component(repo, self.max_component_len)?;
+        validate_path_component(filename, self.max_component_len)?;
 
         let path = self.packages_dir(repo)?.join(filename);
 
-        validate_path_within_base(&self.base_path, &path)?;
+        self.validate_within_base(&path)?;
 
         Ok(path)
     }
 
-    /// Get the path for package metadata (flat structure, no arch in path)
-    pub fn metadata_path(&self, repo: &str, package_name: &str) -> Result<PathBuf> {
-        validate_path_component(repo)?;
-        validate_path_component(package_name)?;
+    fn metadata_path(&self, repo: &str, package_name: &str) -> Result<PathBuf> {
+        validate_path_component(repo, self.max_component_len)?;
+        validate_path_component(package_name, self.max_component_len)?;
 
         let path = self
             .metadata_dir(repo)?
             .join(format!("{package_name}.json"));
 
-        validate_path_within_base(&self.base_path, &path)?;
+        self.validate_within_base(&path)?;
+
+        Ok(path)
+    }
+
+    fn pkginfo_cache_path(&self, repo: &str, package_name: &str) -> Result<PathBuf> {
+        validate_path_component(repo, self.max_component_len)?;
+        validate_path_component(package_name, self.max_component_len)?;
+
+        let path = self
+            .base_path
+            .join(repo)
+            .join("pkginfo")
+            .join(format!("{package_name}.json"));
+
+        self.validate_within_base(&path)?;
 
         Ok(path)
     }
 
-    /// Get the directory path for database files (keeps arch for URL compatibility)
-    /// This is where .db and .files archives are stored
-    pub fn db_dir(&self, repo: &str, arch: &str) -> Result<PathBuf> {
-        validate_path_component(repo)?;
-        validate_path_component(arch)?;
+    fn db_dir(&self, repo: &str, arch: &str) -> Result<PathBuf> {
+        validate_path_component(repo, self.max_component_len)?;
+        validate_path_component(arch, self.max_component_len)?;
 
         let path = self.base_path.join(repo).join("os").join(arch);
 
-        validate_path_within_base(&self.base_path, &path)?;
+        self.validate_within_base(&path)?;
 
         Ok(path)
     }
 
-    /// Store a package file and its metadata
-    ///
     /// Uses atomic file creation to prevent TOCTOU race conditions.
-    /// If the package already exists, returns PackageAlreadyExists error.
-    pub async fn store_package(&self, package: &Package, data: &[u8]) -> Result<()> {
+    async fn store_package(&self, package: &Package, data: &[u8]) -> Result<()> {
         let pkg_path = self.package_path(&package.repo, &package.filename)?;
         let metadata_filename = package.filename.trim_end_matches(".pkg.tar.zst");
         let meta_path = self.metadata_path(&package.repo, metadata_filename)?;
@@ -197,19 +848,17 @@ impl Storage {
         file.sync_all().await.map_io_err(&pkg_path)?;
 
         // Write metadata
-        let metadata_json = serde_json::to_string_pretty(package).map_err(std::io::Error::other)?;
-        fs::write(&meta_path, metadata_json)
-            .await
-            .map_io_err(&meta_path)?;
+        self.save_metadata(package).await?;
+
+        self.link_into_pool(package).await?;
+        self.update_latest_link(&package.repo, &package.name, &package.arch)
+            .await?;
 
         Ok(())
     }
 
-    /// Store a package file from a source path (avoids loading into memory)
-    ///
-    /// Uses atomic file creation to prevent TOCTOU race conditions.
-    /// If the package already exists, returns PackageAlreadyExists error.
-    pub async fn store_package_from_path(
+    /// Checks for duplicate content before copying, as configured.
+    async fn store_package_from_path(
         &self,
         package: &Package,
         source_path: &std::path::Path,
@@ -233,6 +882,8 @@ impl Storage {
             });
         }
 
+        self.check_duplicate_content(package).await?;
+
         // Copy file to destination
         // Using copy instead of rename to work across filesystems
         tokio::fs::copy(source_path, &pkg_path)
@@ -240,18 +891,42 @@ impl Storage {
             .map_io_err(&pkg_path)?;
 
         // Write metadata
-        let metadata_json = serde_json::to_string_pretty(package).map_err(std::io::Error::other)?;
-        fs::write(&meta_path, metadata_json)
-            .await
-            .map_io_err(&meta_path)?;
+        self.save_metadata(package).await?;
+
+        self.link_into_pool(package).await?;
+        self.update_latest_link(&package.repo, &package.name, &package.arch)
+            .await?;
 
         Ok(())
     }
 
-    /// Load package metadata by filename
-    pub async fn load_package(&self, repo: &str, package_name: &str) -> Result<Package> {
+    async fn reindex_package(&self, package: &Package) -> Result<()> {
+        if !self
+            .package_exists(&package.repo, &package.filename)
+            .await?
+        {
+            return Err(Error::PackageNotFound {
+                pkgname: package.filename.clone(),
+            });
+        }
+
+        self.save_metadata(package).await
+    }
+
+    async fn load_package(&self, repo: &str, package_name: &str) -> Result<Package> {
         let meta_path = self.metadata_path(repo, package_name)?;
 
+        if self.metadata_store == MetadataStore::Bundled {
+            return self
+                .metadata_bundle(repo)
+                .await?
+                .get(package_name)
+                .cloned()
+                .ok_or_else(|| Error::PackageNotFound {
+                    pkgname: package_name.to_string(),
+                });
+        }
+
         if !meta_path.exists() {
             return Err(Error::PackageNotFound {
                 pkgname: package_name.to_string(),
@@ -266,61 +941,17 @@ impl Storage {
         Ok(package)
     }
 
-    /// Find a package by filename, checking if arch matches or is "any"
-    pub async fn find_package_for_arch(
-        &self,
-        repo: &str,
-        arch: &str,
-        filename: &str,
-    ) -> Result<Package> {
-        let metadata_filename = filename.trim_end_matches(".pkg.tar.zst");
-        let package = self.load_package(repo, metadata_filename).await?;
-
-        // Package arch must match requested arch, or be "any"
-        if package.arch != arch && package.arch != "any" {
-            return Err(Error::PackageNotFound {
-                pkgname: filename.to_string(),
-            });
-        }
-
-        Ok(package)
-    }
-
-    /// List all packages in a repo
-    pub async fn list_packages(&self, repo: &str) -> Result<Vec<Package>> {
+    async fn list_packages(&self, repo: &str) -> Result<Vec<Package>> {
         let meta_dir = self.metadata_dir(repo)?;
 
         if !meta_dir.exists() {
             return Ok(Vec::new());
         }
 
-        let mut packages = Vec::new();
-        let mut entries = fs::read_dir(&meta_dir).await.map_io_err(&meta_dir)?;
-
-        while let Some(entry) = entries.next_entry().await.map_io_err(&meta_dir)? {
-            let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) == Some("json") {
-                let content = fs::read_to_string(&path).await.map_io_err(&path)?;
-                if let Ok(package) = serde_json::from_str::<Package>(&content) {
-                    packages.push(package);
-                }
-            }
-        }
-
-        Ok(packages)
-    }
-
-    /// List packages filtered by architecture (includes "any" packages)
-    pub async fn list_packages_for_arch(&self, repo: &str, arch: &str) -> Result<Vec<Package>> {
-        let packages = self.list_packages(repo).await?;
-        Ok(packages
-            .into_iter()
-            .filter(|p| p.arch == arch || p.arch == "any")
-            .collect())
+        self.read_metadata_store(repo, &meta_dir).await
     }
 
-    /// List all packages across all repos
-    pub async fn list_all_packages(&self) -> Result<Vec<Package>> {
+    async fn list_all_packages(&self) -> Result<Vec<Package>> {
         let mut all_packages = Vec::new();
 
         // Check if base path exists
@@ -349,25 +980,17 @@ impl Storage {
             }
 
             // Read all packages in this repo
-            let mut meta_entries = fs::read_dir(&meta_dir).await.map_io_err(&meta_dir)?;
-            while let Some(meta_entry) = meta_entries.next_entry().await.map_io_err(&meta_dir)? {
-                let path = meta_entry.path();
-                if path.extension().and_then(|s| s.to_str()) == Some("json") {
-                    let content = fs::read_to_string(&path).await.map_io_err(&path)?;
-                    if let Ok(mut package) = serde_json::from_str::<Package>(&content) {
-                        // Ensure repo field is set correctly
-                        package.repo = repo_name.clone();
-                        all_packages.push(package);
-                    }
-                }
+            for mut package in self.read_metadata_store(&repo_name, &meta_dir).await? {
+                // Ensure repo field is set correctly
+                package.repo = repo_name.clone();
+                all_packages.push(package);
             }
         }
 
         Ok(all_packages)
     }
 
-    /// List all repos that exist in storage
-    pub async fn list_repos(&self) -> Result<Vec<String>> {
+    async fn list_repos(&self) -> Result<Vec<String>> {
         let mut repos = Vec::new();
 
         if !self.base_path.exists() {
@@ -397,17 +1020,7 @@ impl Storage {
         Ok(repos)
     }
 
-    /// Get unique architectures from packages in a repo
-    pub async fn list_archs_in_repo(&self, repo: &str) -> Result<Vec<String>> {
-        let packages = self.list_packages(repo).await?;
-        let mut archs: Vec<String> = packages.into_iter().map(|p| p.arch).collect();
-        archs.sort();
-        archs.dedup();
-        Ok(archs)
-    }
-
-    /// Delete a package and its metadata
-    pub async fn delete_package(&self, package: &Package) -> Result<()> {
+    async fn delete_package(&self, package: &Package) -> Result<()> {
         let pkg_path = self.package_path(&package.repo, &package.filename)?;
         let metadata_filename = package.filename.trim_end_matches(".pkg.tar.zst");
         let meta_path = self.metadata_path(&package.repo, metadata_filename)?;
@@ -418,21 +1031,492 @@ impl Storage {
         }
 
         // Delete metadata
-        if meta_path.exists() {
-            fs::remove_file(&meta_path).await.map_io_err(&meta_path)?;
+        match self.metadata_store {
+            MetadataStore::PerPackage => {
+                if meta_path.exists() {
+                    fs::remove_file(&meta_path).await.map_io_err(&meta_path)?;
+                }
+            }
+            MetadataStore::Bundled => {
+                self.update_metadata_bundle(&package.repo, |packages| {
+                    packages.remove(metadata_filename);
+                })
+                .await?;
+            }
         }
 
-        // Delete signature file if present
-        let sig_path = PathBuf::from(format!("{}.sig", pkg_path.display()));
-        if sig_path.exists() {
-            fs::remove_file(&sig_path).await.map_io_err(&sig_path)?;
+        // Delete signature and provenance sidecars if present
+        for suffix in PACKAGE_SIDECAR_SUFFIXES {
+            let sidecar_path = PathBuf::from(format!("{}{suffix}", pkg_path.display()));
+            if sidecar_path.exists() {
+                fs::remove_file(&sidecar_path)
+                    .await
+                    .map_io_err(&sidecar_path)?;
+            }
         }
 
+        let cache_path = self.pkginfo_cache_path(&package.repo, metadata_filename)?;
+        if cache_path.exists() {
+            fs::remove_file(&cache_path).await.map_io_err(&cache_path)?;
+        }
+
+        // Delete pool link regardless of the current setting, in case it was
+        // created while the pool was enabled
+        self.unlink_from_pool(package).await?;
+        self.update_latest_link(&package.repo, &package.name, &package.arch)
+            .await?;
+
         Ok(())
     }
 
-    /// Check if a package file exists
-    pub async fn package_exists(&self, repo: &str, filename: &str) -> Result<bool> {
+    async fn package_exists(&self, repo: &str, filename: &str) -> Result<bool> {
         Ok(self.package_path(repo, filename)?.exists())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_path_component_accepts_package_filename() {
+        assert!(
+            validate_path_component("foo-1.0.0-1-x86_64.pkg.tar.zst", DEFAULT_MAX_COMPONENT_LEN)
+                .is_ok()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_repo_dir_rejected_when_configured() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let base = temp_dir.path().join("data");
+        let outside = temp_dir.path().join("outside");
+        std::fs::create_dir_all(&base).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, base.join("evil")).unwrap();
+        // Points outside at a directory that doesn't exist (yet)
+        std::os::unix::fs::symlink(outside.join("later"), base.join("dangling")).unwrap();
+
+        let config = StorageConfig {
+            data_path: base.clone(),
+            reject_symlinks: true,
+            ..StorageConfig::default()
+        };
+        let storage = FsStore::from_config(&config);
+        for repo in ["evil", "dangling"] {
+            assert!(storage.package_path(repo, "foo.pkg.tar.zst").is_err());
+            assert!(storage.db_dir(repo, "x86_64").is_err());
+        }
+        let err = storage.metadata_dir("dangling").unwrap_err();
+        assert!(err.to_string().contains("Symlink"), "{err}");
+
+        // Regular repos are unaffected
+        assert!(storage.package_path("sw1nn", "foo.pkg.tar.zst").is_ok());
+
+        // The canonical prefix check alone can't see through a dangling link
+        let storage = FsStore::new(&base);
+        assert!(storage.metadata_dir("dangling").is_ok());
+    }
+
+    #[tokio::test]
+    async fn preflight_creates_missing_data_path() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let base = temp_dir.path().join("data");
+
+        let err = FsStore::new(&base).preflight(false).await.unwrap_err();
+        assert!(matches!(err, Error::Config { .. }), "{err}");
+
+        FsStore::new(&base).preflight(true).await.unwrap();
+        assert!(base.is_dir());
+        assert_eq!(std::fs::read_dir(&base).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn preflight_rejects_unusable_data_path() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        // A file where the directory should be
+        let file = temp_dir.path().join("data");
+        std::fs::write(&file, b"").unwrap();
+        let err = FsStore::new(&file).preflight(true).await.unwrap_err();
+        assert!(err.to_string().contains("not a directory"), "{err}");
+
+        // Below a file, so it can't be created either
+        let err = FsStore::new(file.join("data"))
+            .preflight(true)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Config { .. }), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn preflight_rejects_read_only_data_path() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let base = temp_dir.path().join("data");
+        std::fs::create_dir(&base).unwrap();
+        std::fs::set_permissions(&base, std::fs::Permissions::from_mode(0o555)).unwrap();
+
+        // Permission bits don't apply to root; nothing to check there
+        if std::fs::write(base.join("probe"), b"").is_ok() {
+            return;
+        }
+
+        let err = FsStore::new(&base).preflight(true).await.unwrap_err();
+        assert!(err.to_string().contains("not writable"), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn data_dir_lock_is_exclusive_until_dropped() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let first = FsStore::new(temp_dir.path());
+        let second = FsStore::new(temp_dir.path());
+
+        let lock = first.lock_data_dir().unwrap();
+        let err = second.lock_data_dir().unwrap_err();
+        assert!(
+            err.to_string().contains("in use by another instance"),
+            "{err}"
+        );
+
+        drop(lock);
+        assert!(second.lock_data_dir().is_ok());
+    }
+
+    #[test]
+    fn validate_path_component_rejects_over_long_name() {
+        let name = "a".repeat(DEFAULT_MAX_COMPONENT_LEN + 1);
+        let err = validate_path_component(&name, DEFAULT_MAX_COMPONENT_LEN).unwrap_err();
+        assert!(err.to_string().contains("too long"), "{err}");
+
+        let name = "a".repeat(DEFAULT_MAX_COMPONENT_LEN);
+        assert!(validate_path_component(&name, DEFAULT_MAX_COMPONENT_LEN).is_ok());
+    }
+
+    #[test]
+    fn validate_path_component_honours_configured_limit() {
+        assert!(validate_path_component("abcdef", 5).is_err());
+        assert!(validate_path_component("abcde", 5).is_ok());
+    }
+
+    /// Captures formatted tracing output for assertions
+    #[derive(Clone, Default)]
+    struct LogCapture(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogCapture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn test_package(name: &str) -> Package {
+        Package {
+            name: name.to_owned(),
+            version: "1.0.0-1".to_owned(),
+            arch: "x86_64".to_owned(),
+            repo: "sw1nn".to_owned(),
+            filename: format!("{name}-1.0.0-1-x86_64.pkg.tar.zst"),
+            sha256: String::new(),
+            size: 4,
+            created_at: chrono::Utc::now(),
+            signed: false,
+            signature_verified: false,
+            license: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn store_package_leaves_no_temp_metadata() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = FsStore::new(temp_dir.path());
+
+        storage
+            .store_package(&test_package("foo"), b"data")
+            .await
+            .unwrap();
+
+        let meta_dir = storage.metadata_dir("sw1nn").unwrap();
+        let names: Vec<String> = std::fs::read_dir(&meta_dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, vec!["foo-1.0.0-1-x86_64.json"]);
+    }
+
+    async fn assert_metadata_round_trips(storage: &dyn PackageStore) {
+        for name in ["foo", "bar"] {
+            storage
+                .store_package(&test_package(name), b"data")
+                .await
+                .unwrap();
+        }
+
+        let loaded = storage
+            .load_package("sw1nn", "foo-1.0.0-1-x86_64")
+            .await
+            .unwrap();
+        assert_eq!(loaded.filename, "foo-1.0.0-1-x86_64.pkg.tar.zst");
+
+        let mut names: Vec<String> = storage
+            .list_packages("sw1nn")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["bar", "foo"]);
+
+        storage.delete_package(&test_package("foo")).await.unwrap();
+        let remaining = storage.list_all_packages().await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].name, "bar");
+        assert!(matches!(
+            storage.load_package("sw1nn", "foo-1.0.0-1-x86_64").await,
+            Err(Error::PackageNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn fs_store_works_as_trait_object() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage: Arc<dyn PackageStore> = Arc::new(FsStore::new(temp_dir.path()));
+
+        assert_metadata_round_trips(storage.as_ref()).await;
+
+        let bar = test_package("bar");
+        assert!(
+            storage
+                .package_exists("sw1nn", &bar.filename)
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            storage
+                .find_package_for_arch("sw1nn", "x86_64", &bar.filename)
+                .await
+                .unwrap()
+                .name,
+            "bar"
+        );
+        assert_eq!(
+            storage.list_archs_in_repo("sw1nn").await.unwrap(),
+            ["x86_64"]
+        );
+        assert!(matches!(
+            storage.package_path("sw1nn", "../escape").unwrap_err(),
+            Error::InvalidPackage { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn reindex_preserves_created_at() {
+        for metadata_store in [MetadataStore::PerPackage, MetadataStore::Bundled] {
+            let temp_dir = tempfile::TempDir::new().unwrap();
+            let storage = FsStore::new(temp_dir.path()).with_metadata_store(metadata_store);
+            let mut package = test_package("foo");
+            package.created_at = chrono::DateTime::from_timestamp(1_600_000_000, 0).unwrap();
+            storage.store_package(&package, b"data").await.unwrap();
+
+            let reindexed = Package {
+                sha256: "ab".repeat(32),
+                size: 5,
+                ..test_package("foo")
+            };
+            storage.reindex_package(&reindexed).await.unwrap();
+
+            let loaded = storage
+                .load_package("sw1nn", "foo-1.0.0-1-x86_64")
+                .await
+                .unwrap();
+            assert_eq!(loaded.created_at, package.created_at, "{metadata_store:?}");
+            assert_eq!(loaded.sha256, reindexed.sha256);
+            assert_eq!(loaded.size, 5);
+        }
+    }
+
+    #[tokio::test]
+    async fn reindex_requires_stored_package() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = FsStore::new(temp_dir.path());
+
+        let result = storage.reindex_package(&test_package("foo")).await;
+        assert!(matches!(result, Err(Error::PackageNotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn per_package_metadata_round_trips() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = FsStore::new(temp_dir.path());
+
+        assert_metadata_round_trips(&storage).await;
+    }
+
+    #[tokio::test]
+    async fn bundled_metadata_round_trips() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = FsStore::new(temp_dir.path()).with_metadata_store(MetadataStore::Bundled);
+
+        assert_metadata_round_trips(&storage).await;
+
+        let meta_dir = storage.metadata_dir("sw1nn").unwrap();
+        let names: Vec<String> = std::fs::read_dir(&meta_dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, vec![METADATA_BUNDLE_FILENAME]);
+    }
+
+    #[tokio::test]
+    async fn bundled_metadata_picks_up_per_package_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        FsStore::new(temp_dir.path())
+            .store_package(&test_package("foo"), b"data")
+            .await
+            .unwrap();
+
+        let storage = FsStore::new(temp_dir.path()).with_metadata_store(MetadataStore::Bundled);
+        storage
+            .store_package(&test_package("bar"), b"data")
+            .await
+            .unwrap();
+
+        let packages = storage.list_packages("sw1nn").await.unwrap();
+        assert_eq!(packages.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn bundled_metadata_is_cached_until_written() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = FsStore::new(temp_dir.path()).with_metadata_store(MetadataStore::Bundled);
+        storage
+            .store_package(&test_package("foo"), b"data")
+            .await
+            .unwrap();
+
+        // Served from memory: the bundle on disk isn't read again
+        let bundle_path = storage
+            .metadata_dir("sw1nn")
+            .unwrap()
+            .join(METADATA_BUNDLE_FILENAME);
+        let bundle = std::fs::read(&bundle_path).unwrap();
+        std::fs::write(&bundle_path, b"garbage").unwrap();
+        storage
+            .load_package("sw1nn", "foo-1.0.0-1-x86_64")
+            .await
+            .unwrap();
+        std::fs::write(&bundle_path, bundle).unwrap();
+
+        // Writes are visible straight away
+        storage
+            .store_package(&test_package("bar"), b"data")
+            .await
+            .unwrap();
+        assert_eq!(storage.list_packages("sw1nn").await.unwrap().len(), 2);
+        storage.delete_package(&test_package("foo")).await.unwrap();
+        assert!(matches!(
+            storage.load_package("sw1nn", "foo-1.0.0-1-x86_64").await,
+            Err(Error::PackageNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn corrupt_bundle_entry_is_skipped() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = FsStore::new(temp_dir.path());
+        storage
+            .store_package(&test_package("good"), b"data")
+            .await
+            .unwrap();
+
+        let meta_dir = storage.metadata_dir("sw1nn").unwrap();
+        let json = serde_json::json!({
+            "good-1.0.0-1-x86_64": test_package("good"),
+            "bad-1.0.0-1-x86_64": {"name": "bad"},
+        });
+        let compressed = zstd::encode_all(json.to_string().as_bytes(), 0).unwrap();
+        std::fs::write(meta_dir.join(METADATA_BUNDLE_FILENAME), compressed).unwrap();
+
+        let storage = FsStore::new(temp_dir.path()).with_metadata_store(MetadataStore::Bundled);
+        let packages = storage.list_packages("sw1nn").await.unwrap();
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].name, "good");
+    }
+
+    #[tokio::test]
+    async fn corrupt_metadata_is_skipped_with_warning() {
+        let capture = LogCapture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = FsStore::new(temp_dir.path());
+        storage
+            .store_package(&test_package("good"), b"data")
+            .await
+            .unwrap();
+
+        // Simulate a metadata write truncated by a crash
+        let corrupt_path = storage
+            .metadata_path("sw1nn", "bad-1.0.0-1-x86_64")
+            .unwrap();
+        std::fs::write(&corrupt_path, r#"{"name": "bad", "vers"#).unwrap();
+
+        let packages = storage.list_packages("sw1nn").await.unwrap();
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].name, "good");
+
+        let all_packages = storage.list_all_packages().await.unwrap();
+        assert_eq!(all_packages.len(), 1);
+
+        let logs = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("WARN"), "{logs}");
+        assert!(
+            logs.contains("Skipping unparseable package metadata file"),
+            "{logs}"
+        );
+        assert!(logs.contains("bad-1.0.0-1-x86_64.json"), "{logs}");
+    }
+
+    #[test]
+    fn validate_path_component_rejects_control_characters() {
+        for name in ["foo\nbar", "foo\tbar", "foo\x1bbar", "foo\x7fbar"] {
+            let err = validate_path_component(name, DEFAULT_MAX_COMPONENT_LEN).unwrap_err();
+            assert!(err.to_string().contains("control characters"), "{err}");
+        }
+    }
+
+    #[tokio::test]
+    async fn lock_db_serializes_per_repo_arch() {
+        let storage = FsStore::new("/nonexistent");
+
+        let guard = storage.lock_db("sw1nn", "x86_64").await;
+
+        // Another repo/arch is independent
+        drop(storage.lock_db("sw1nn", "aarch64").await);
+
+        // The same repo/arch waits for the first holder
+        let second = tokio::time::timeout(
+            std::time::Duration::from_millis(50),
+            storage.lock_db("sw1nn", "x86_64"),
+        )
+        .await;
+        assert!(second.is_err());
+
+        drop(guard);
+        drop(storage.lock_db("sw1nn", "x86_64").await);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::error::{Error, Result};
+use crate::models::Package;
+use async_trait::async_trait;
+use std::path::PathBuf;
+
+/// Storage backend for package files, their metadata and the repository
+/// databases
+///
+/// [`FsStore`](super::FsStore) keeps everything under a local data
+/// directory. Other backends (e.g. S3-compatible object storage) implement
+/// this trait; the server only holds an `Arc<dyn PackageStore>`.
+///
+/// The path methods locate files the server reads and writes directly:
+/// served downloads and generated databases. A remote backend maps them to
+/// a local staging area. They are also where a backend rejects names that
+/// would escape its storage.
+#[async_trait]
+pub trait PackageStore: Send + Sync {
+    /// Check that the storage is there and new files can be written to it,
+    /// by actually writing one rather than trusting permissions
+    async fn check_writable(&self) -> Result<()>;
+
+    /// Take the regeneration lock for a repo/arch database
+    ///
+    /// Held for the whole of a regeneration so two runs for the same
+    /// repo/arch never race on the archive temp files and links, whichever
+    /// code path started them. Different repo/archs don't block each other.
+    async fn lock_db(&self, repo: &str, arch: &str) -> tokio::sync::OwnedMutexGuard<()>;
+
+    /// Get the pool path for a package, or `None` if the package has no
+    /// usable SHA256
+    fn pool_path(&self, package: &Package) -> Result<Option<PathBuf>>;
+
+    /// Get the path of the link to the newest version of a package name/arch
+    fn latest_link_path(&self, repo: &str, name: &str, arch: &str) -> Result<PathBuf>;
+
+    /// Get the packages directory for a repo
+    fn packages_dir(&self, repo: &str) -> Result<PathBuf>;
+
+    /// Get the metadata directory for a repo
+    fn metadata_dir(&self, repo: &str) -> Result<PathBuf>;
+
+    /// Get the path for a package file (flat structure, no arch in path)
+    fn package_path(&self, repo: &str, filename: &str) -> Result<PathBuf>;
+
+    /// Get the path for package metadata (flat structure, no arch in path)
+    fn metadata_path(&self, repo: &str, package_name: &str) -> Result<PathBuf>;
+
+    /// Get the path of the cached `.PKGINFO` and file list of a package
+    fn pkginfo_cache_path(&self, repo: &str, package_name: &str) -> Result<PathBuf>;
+
+    /// Get the directory for a repo/arch's database files (keeps arch for
+    /// URL compatibility)
+    fn db_dir(&self, repo: &str, arch: &str) -> Result<PathBuf>;
+
+    /// Store a package file and its metadata
+    ///
+    /// If the package already exists, returns PackageAlreadyExists error.
+    async fn store_package(&self, package: &Package, data: &[u8]) -> Result<()>;
+
+    /// Store a package file from a source path (avoids loading into memory)
+    ///
+    /// If the package already exists, returns PackageAlreadyExists error.
+    async fn store_package_from_path(
+        &self,
+        package: &Package,
+        source_path: &std::path::Path,
+    ) -> Result<()>;
+
+    /// Rewrite the metadata of an already stored package, e.g. after
+    /// recomputing its checksum and size from the stored file
+    ///
+    /// The original `created_at` is preserved.
+    async fn reindex_package(&self, package: &Package) -> Result<()>;
+
+    /// Load package metadata by filename
+    async fn load_package(&self, repo: &str, package_name: &str) -> Result<Package>;
+
+    /// List all packages in a repo
+    async fn list_packages(&self, repo: &str) -> Result<Vec<Package>>;
+
+    /// List all packages across all repos
+    async fn list_all_packages(&self) -> Result<Vec<Package>>;
+
+    /// List all repos that exist in storage
+    async fn list_repos(&self) -> Result<Vec<String>>;
+
+    /// Delete a package, its metadata, its sidecars and its cached pkginfo
+    async fn delete_package(&self, package: &Package) -> Result<()>;
+
+    /// Check if a package file exists
+    async fn package_exists(&self, repo: &str, filename: &str) -> Result<bool>;
+
+    /// Find a package by filename, checking if arch matches or is "any"
+    async fn find_package_for_arch(
+        &self,
+        repo: &str,
+        arch: &str,
+        filename: &str,
+    ) -> Result<Package> {
+        let metadata_filename = filename.trim_end_matches(".pkg.tar.zst");
+        let package = self.load_package(repo, metadata_filename).await?;
+
+        // Package arch must match requested arch, or be "any"
+        if package.arch != arch && package.arch != "any" {
+            return Err(Error::PackageNotFound {
+                pkgname: filename.to_string(),
+            });
+        }
+
+        Ok(package)
+    }
+
+    /// List packages filtered by architecture (includes "any" packages)
+    async fn list_packages_for_arch(&self, repo: &str, arch: &str) -> Result<Vec<Package>> {
+        let packages = self.list_packages(repo).await?;
+        Ok(packages
+            .into_iter()
+            .filter(|p| p.arch == arch || p.arch == "any")
+            .collect())
+    }
+
+    /// Get unique architectures from packages in a repo
+    async fn list_archs_in_repo(&self, repo: &str) -> Result<Vec<String>> {
+        let packages = self.list_packages(repo).await?;
+        let mut archs: Vec<String> = packages.into_iter().map(|p| p.arch).collect();
+        archs.sort();
+        archs.dedup();
+        Ok(archs)
+    }
+}
@@ -33,6 +33,10 @@ pub struct UploadSession {
     pub expires_at: DateTime<Utc>,
     #[serde(skip)]
     pub uploaded_chunks: HashSet<u32>,
+    /// Byte ranges received so far by a `Content-Range` upload, as sorted,
+    /// merged half-open `(start, end)` pairs. `None` for chunked uploads.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub received_ranges: Option<Vec<(u64, u64)>>,
 }
 
 impl UploadSession {
@@ -46,15 +50,68 @@ impl UploadSession {
     }
 
     pub fn is_complete(&self) -> bool {
+        if let Some(ranges) = &self.received_ranges {
+            return ranges.as_slice() == [(0, self.file_size)];
+        }
+
         self.uploaded_chunks.len() == self.total_chunks as usize
             && (1..=self.total_chunks).all(|n| self.uploaded_chunks.contains(&n))
     }
 
+    /// Record `[start, end)` as received, merging it with overlapping or
+    /// adjacent ranges. Turns the session into a `Content-Range` upload.
+    pub fn record_range(&mut self, start: u64, end: u64) {
+        let ranges = self.received_ranges.get_or_insert_with(Vec::new);
+        ranges.push((start, end));
+        ranges.sort_unstable();
+
+        let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+        for &(start, end) in ranges.iter() {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        *ranges = merged;
+    }
+
+    /// Number of distinct bytes received by a `Content-Range` upload
+    pub fn received_bytes(&self) -> u64 {
+        self.received_ranges
+            .iter()
+            .flatten()
+            .map(|(start, end)| end - start)
+            .sum()
+    }
+
     pub fn missing_chunks(&self) -> Vec<u32> {
         (1..=self.total_chunks)
             .filter(|n| !self.uploaded_chunks.contains(n))
             .collect()
     }
+
+    /// Exact size in bytes that chunk `chunk_number` must have.
+    ///
+    /// Chunks are numbered `1..=total_chunks`, where `total_chunks` is
+    /// `ceil(file_size / chunk_size)`. Every chunk but the last is exactly
+    /// `chunk_size` bytes; the last holds the remaining
+    /// `file_size - (total_chunks - 1) * chunk_size` bytes, which is a full
+    /// `chunk_size` when the file size is an exact multiple, and the whole file
+    /// when it is smaller than `chunk_size`.
+    ///
+    /// Returns `None` if `chunk_number` is out of range.
+    pub fn expected_chunk_size(&self, chunk_number: u32) -> Option<usize> {
+        if chunk_number < 1 || chunk_number > self.total_chunks {
+            return None;
+        }
+
+        if chunk_number < self.total_chunks {
+            Some(self.chunk_size)
+        } else {
+            let preceding = u64::from(self.total_chunks - 1) * self.chunk_size as u64;
+            Some((self.file_size - preceding) as usize)
+        }
+    }
 }
 
 // Typestate marker types for required fields
@@ -221,7 +278,7 @@ impl UploadSessionBuilder<HasFilename, HasFileSize, HasRepo, HasArch> {
     /// This method is only available when all required fields have been set.
     pub fn build(self) -> UploadSession {
         let file_size = self.file_size.expect("file_size is required");
-        let total_chunks = ((file_size as f64) / (self.chunk_size as f64)).ceil() as u32;
+        let total_chunks = file_size.div_ceil(self.chunk_size as u64) as u32;
         let now = Utc::now();
         let expires_at = now + Duration::seconds(self.expiration_secs);
 
@@ -238,36 +295,106 @@ impl UploadSessionBuilder<HasFilename, HasFileSize, HasRepo, HasArch> {
             created_at: now,
             expires_at,
             uploaded_chunks: HashSet::new(),
+            received_ranges: None,
         }
     }
 }
 
-/// In-memory storage for upload sessions
+/// Upload sessions, held in memory and persisted under `.uploads/` so they
+/// can be rehydrated after a restart
 #[derive(Clone)]
 pub struct UploadSessionStore {
     sessions: Arc<RwLock<std::collections::HashMap<String, UploadSession>>>,
+    /// Per-key locks serializing `Content-Range` writes to a session, and the
+    /// lookup or creation of the session for a filename
+    range_locks: Arc<std::sync::Mutex<std::collections::HashMap<String, RangeLock>>>,
     base_path: PathBuf,
+    max_inflight_bytes: Option<u64>,
 }
 
+type RangeLock = Arc<tokio::sync::Mutex<()>>;
+
 impl UploadSessionStore {
     pub fn new(base_path: PathBuf) -> Self {
         Self {
             sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+            range_locks: Arc::default(),
             base_path,
+            max_inflight_bytes: None,
         }
     }
 
+    /// Limit the combined `file_size` of all active sessions; new sessions
+    /// that would exceed it are refused
+    pub fn with_max_inflight_bytes(mut self, max: Option<u64>) -> Self {
+        self.max_inflight_bytes = max;
+        self
+    }
+
     /// Get the number of active upload sessions
     pub async fn session_count(&self) -> usize {
         self.sessions.read().await.len()
     }
 
+    /// Total `file_size` of all unexpired sessions
+    pub async fn inflight_bytes(&self) -> u64 {
+        self.sessions
+            .read()
+            .await
+            .values()
+            .filter(|s| !s.is_expired())
+            .map(|s| s.file_size)
+            .sum()
+    }
+
     /// Create a new upload session
+    ///
+    /// Fails with [`Error::InsufficientStorage`] if the session would take
+    /// the active sessions past the in-flight byte limit.
     pub async fn create_session(&self, session: UploadSession) -> Result<UploadSession> {
         let upload_id = session.upload_id.clone();
 
+        // Admit and register the session under one lock so concurrent
+        // initiates can't overshoot the limit together. Deleting the session
+        // (complete, abort or expiry) releases its bytes; expired sessions
+        // cleanup hasn't swept yet don't count.
+        {
+            let mut sessions = self.sessions.write().await;
+            if let Some(max) = self.max_inflight_bytes {
+                let inflight: u64 = sessions
+                    .values()
+                    .filter(|s| !s.is_expired())
+                    .map(|s| s.file_size)
+                    .sum();
+                if inflight.saturating_add(session.file_size) > max {
+                    return Err(Error::InsufficientStorage {
+                        msg: format!(
+                            "Upload of {} would exceed the in-flight upload limit of {} ({} in progress)",
+                            byte_unit::Byte::from_u64(session.file_size),
+                            byte_unit::Byte::from_u64(max),
+                            byte_unit::Byte::from_u64(inflight),
+                        ),
+                    });
+                }
+            }
+            sessions.insert(upload_id.clone(), session.clone());
+            crate::metrics::set_upload_sessions_active(sessions.len());
+        }
+
+        if let Err(e) = self.write_session_files(&session).await {
+            let mut sessions = self.sessions.write().await;
+            sessions.remove(&upload_id);
+            crate::metrics::set_upload_sessions_active(sessions.len());
+            return Err(e);
+        }
+
+        Ok(session)
+    }
+
+    /// Create the on-disk directories and metadata for a new session
+    async fn write_session_files(&self, session: &UploadSession) -> Result<()> {
         // Create upload directory
-        let upload_dir = self.upload_dir(&upload_id)?;
+        let upload_dir = self.upload_dir(&session.upload_id)?;
         fs::create_dir_all(&upload_dir)
             .await
             .map_io_err(&upload_dir)?;
@@ -280,18 +407,10 @@ impl UploadSessionStore {
 
         // Save session metadata
         let metadata_path = upload_dir.join("metadata.json");
-        let metadata_json =
-            serde_json::to_string_pretty(&session).map_err(std::io::Error::other)?;
+        let metadata_json = serde_json::to_string_pretty(session).map_err(std::io::Error::other)?;
         fs::write(&metadata_path, metadata_json)
             .await
-            .map_io_err(&metadata_path)?;
-
-        // Store in memory
-        let mut sessions = self.sessions.write().await;
-        sessions.insert(upload_id.clone(), session.clone());
-        crate::metrics::set_upload_sessions_active(sessions.len());
-
-        Ok(session)
+            .map_io_err(&metadata_path)
     }
 
     /// Get an upload session by ID
@@ -393,47 +512,32 @@ impl UploadSessionStore {
         // Validate chunk exists in session
         let mut session = self.get_session(upload_id).await?;
 
-        if chunk_number < 1 || chunk_number > session.total_chunks {
+        let Some(expected_size) = session.expected_chunk_size(chunk_number) else {
             return Err(Error::InvalidPackage {
                 pkgname: format!(
                     "Chunk number {} out of range (1-{})",
                     chunk_number, session.total_chunks
                 ),
             });
-        }
+        };
 
-        // Validate chunk size
-        if chunk_number < session.total_chunks {
-            // All chunks except the last must be exactly chunk_size
-            if data.len() != session.chunk_size {
-                return Err(Error::InvalidPackage {
-                    pkgname: format!(
-                        "Chunk {} size mismatch: expected {}, got {}",
-                        chunk_number,
-                        session.chunk_size,
-                        data.len()
-                    ),
-                });
-            }
-        } else {
-            // Last chunk can be smaller
-            let expected_last_chunk_size = (session.file_size as usize) % session.chunk_size;
-            let expected_size = if expected_last_chunk_size == 0 {
-                session.chunk_size
+        // All chunks except the last must be exactly chunk_size; the last
+        // holds whatever remains of the file
+        if data.len() != expected_size {
+            let which = if chunk_number == session.total_chunks {
+                "Final chunk"
             } else {
-                expected_last_chunk_size
+                "Chunk"
             };
-
-            if data.len() != expected_size {
-                return Err(Error::InvalidPackage {
-                    pkgname: format!(
-                        "Final chunk {} size mismatch: expected {}, got {}",
-                        chunk_number,
-                        expected_size,
-                        data.len()
-                    ),
-                });
-            }
+            return Err(Error::InvalidPackage {
+                pkgname: format!(
+                    "{} {} size mismatch: expected {}, got {}",
+                    which,
+                    chunk_number,
+                    expected_size,
+                    data.len()
+                ),
+            });
         }
 
         // Write chunk to disk
@@ -454,6 +558,127 @@ impl UploadSessionStore {
         Ok(checksum)
     }
 
+    /// Get path to the file a `Content-Range` upload is written into
+    pub fn range_upload_path(&self, upload_id: &str) -> Result<PathBuf> {
+        let upload_dir = self.upload_dir(upload_id)?;
+        Ok(upload_dir.join("upload.part"))
+    }
+
+    fn range_lock(&self, key: &str) -> RangeLock {
+        Arc::clone(
+            self.range_locks
+                .lock()
+                .expect("range lock map poisoned")
+                .entry(key.to_owned())
+                .or_default(),
+        )
+    }
+
+    /// Forget the lock for `key` once nobody holds or waits on it
+    fn release_range_lock(&self, key: &str) {
+        let mut locks = self.range_locks.lock().expect("range lock map poisoned");
+        if locks
+            .get(key)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            locks.remove(key);
+        }
+    }
+
+    /// Find the unexpired `Content-Range` upload in progress for a filename,
+    /// or create one with `new_session` if there is none. Concurrent calls
+    /// for one filename end up sharing a single session.
+    pub async fn find_or_create_range_session(
+        &self,
+        repo: &str,
+        filename: &str,
+        new_session: impl FnOnce() -> Result<UploadSession>,
+    ) -> Result<UploadSession> {
+        let key = format!("{repo}/{filename}");
+        let lock = self.range_lock(&key);
+        let result = {
+            let _guard = lock.lock().await;
+            match self.find_range_session(repo, filename).await {
+                Some(session) => Ok(session),
+                None => match new_session() {
+                    Ok(session) => self.create_session(session).await,
+                    Err(e) => Err(e),
+                },
+            }
+        };
+        drop(lock);
+        self.release_range_lock(&key);
+        result
+    }
+
+    /// Find the unexpired `Content-Range` upload in progress for a filename
+    pub async fn find_range_session(&self, repo: &str, filename: &str) -> Option<UploadSession> {
+        let sessions = self.sessions.read().await;
+        sessions
+            .values()
+            .find(|s| {
+                s.received_ranges.is_some()
+                    && s.repo == repo
+                    && s.filename == filename
+                    && !s.is_expired()
+            })
+            .cloned()
+    }
+
+    /// Write `data` at byte offset `start` of a `Content-Range` upload and
+    /// record the range as received. Writes to one session run one at a time
+    /// so none loses another's recorded range.
+    pub async fn store_range(
+        &self,
+        upload_id: &str,
+        start: u64,
+        data: &[u8],
+    ) -> Result<UploadSession> {
+        let lock = self.range_lock(upload_id);
+        let result = {
+            let _guard = lock.lock().await;
+            self.write_range(upload_id, start, data).await
+        };
+        drop(lock);
+        self.release_range_lock(upload_id);
+        result
+    }
+
+    async fn write_range(&self, upload_id: &str, start: u64, data: &[u8]) -> Result<UploadSession> {
+        use tokio::io::AsyncSeekExt;
+
+        let mut session = self.get_session(upload_id).await?;
+
+        let end = start + data.len() as u64;
+        if end > session.file_size {
+            return Err(Error::InvalidPackage {
+                pkgname: format!(
+                    "Range {}-{} exceeds upload size {}",
+                    start, end, session.file_size
+                ),
+            });
+        }
+
+        let part_path = self.range_upload_path(upload_id)?;
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&part_path)
+            .await
+            .map_io_err(&part_path)?;
+        file.seek(std::io::SeekFrom::Start(start))
+            .await
+            .map_io_err(&part_path)?;
+        file.write_all(data).await.map_io_err(&part_path)?;
+        file.sync_all().await.map_io_err(&part_path)?;
+
+        session.record_range(start, end);
+        self.update_session(session.clone()).await?;
+
+        Ok(session)
+    }
+
     /// Store signature file
     pub async fn store_signature(&self, upload_id: &str, data: &[u8]) -> Result<String> {
         let sig_path = self.signature_path(upload_id)?;
@@ -550,7 +775,10 @@ impl UploadSessionStore {
         }
     }
 
-    /// Clean up expired sessions
+    /// Clean up expired sessions, returning their upload IDs
+    ///
+    /// Besides the sessions in memory, this sweeps `.uploads/` for session
+    /// directories no loaded session owns whose metadata has expired.
     pub async fn cleanup_expired(&self) -> Result<Vec<String>> {
         let mut expired = Vec::new();
 
@@ -562,6 +790,8 @@ impl UploadSessionStore {
         }
         drop(sessions);
 
+        expired.extend(self.expired_unloaded_sessions().await?);
+
         for upload_id in &expired {
             if let Err(e) = self.delete_session(upload_id).await {
                 tracing::warn!(upload_id, error = %e, "Failed to cleanup expired session");
@@ -571,9 +801,115 @@ impl UploadSessionStore {
         Ok(expired)
     }
 
-    /// Purge all upload directories on disk.
-    /// Called on startup since sessions don't survive restarts.
-    pub async fn purge_all(&self) -> Result<u32> {
+    /// Upload IDs of session directories on disk that aren't loaded and
+    /// whose `metadata.json` says they have expired
+    async fn expired_unloaded_sessions(&self) -> Result<Vec<String>> {
+        let uploads_dir = self.base_path.join(".uploads");
+        let mut entries = match fs::read_dir(&uploads_dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).map_io_err(&uploads_dir),
+        };
+
+        let mut expired = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_io_err(&uploads_dir)? {
+            let upload_id = entry.file_name().to_string_lossy().into_owned();
+            let Ok(upload_dir) = self.upload_dir(&upload_id) else {
+                continue;
+            };
+            if self.sessions.read().await.contains_key(&upload_id) {
+                continue;
+            }
+
+            let session = fs::read(upload_dir.join("metadata.json"))
+                .await
+                .ok()
+                .and_then(|json| serde_json::from_slice::<UploadSession>(&json).ok());
+            if session.is_some_and(|session| session.is_expired()) {
+                expired.push(upload_id);
+            }
+        }
+        Ok(expired)
+    }
+
+    /// Load the sessions a previous run left in `.uploads/`, so their
+    /// uploads can be resumed, returning how many were loaded.
+    ///
+    /// Each session's received chunks are rebuilt from its `chunks/`
+    /// directory; a chunk of the wrong size (e.g. cut short by a crash) is
+    /// left out and has to be re-sent. Expired sessions are deleted instead
+    /// of loaded. Directories without a readable `metadata.json` are left
+    /// for [`purge_orphans`](Self::purge_orphans).
+    pub async fn rehydrate(&self) -> Result<usize> {
+        let uploads_dir = self.base_path.join(".uploads");
+        if !uploads_dir.exists() {
+            return Ok(0);
+        }
+
+        let mut loaded = 0;
+        let mut entries = fs::read_dir(&uploads_dir).await.map_io_err(&uploads_dir)?;
+        while let Some(entry) = entries.next_entry().await.map_io_err(&uploads_dir)? {
+            let upload_id = entry.file_name().to_string_lossy().into_owned();
+            let Ok(upload_dir) = self.upload_dir(&upload_id) else {
+                continue;
+            };
+
+            let metadata_path = upload_dir.join("metadata.json");
+            let session = fs::read(&metadata_path)
+                .await
+                .ok()
+                .and_then(|json| serde_json::from_slice::<UploadSession>(&json).ok());
+            let session = match session {
+                Some(session) if session.upload_id == upload_id => session,
+                _ => {
+                    tracing::warn!(upload_id, "Upload directory has no usable session metadata");
+                    continue;
+                }
+            };
+
+            if session.is_expired() {
+                fs::remove_dir_all(&upload_dir)
+                    .await
+                    .map_io_err(&upload_dir)?;
+                tracing::debug!(upload_id, "Removed expired upload session");
+                continue;
+            }
+
+            let session = self.with_stored_chunks(session).await?;
+            tracing::debug!(
+                upload_id,
+                filename = %session.filename,
+                chunks = session.uploaded_chunks.len(),
+                total_chunks = session.total_chunks,
+                "Rehydrated upload session"
+            );
+            let mut sessions = self.sessions.write().await;
+            sessions.insert(upload_id, session);
+            crate::metrics::set_upload_sessions_active(sessions.len());
+            loaded += 1;
+        }
+
+        Ok(loaded)
+    }
+
+    /// Fill in `uploaded_chunks` from the chunk files on disk
+    async fn with_stored_chunks(&self, mut session: UploadSession) -> Result<UploadSession> {
+        for chunk_number in 1..=session.total_chunks {
+            let chunk_path = self.chunk_path(&session.upload_id, chunk_number)?;
+            let Ok(metadata) = fs::metadata(&chunk_path).await else {
+                continue;
+            };
+            let expected = session.expected_chunk_size(chunk_number).map(|n| n as u64);
+            if Some(metadata.len()) == expected {
+                session.uploaded_chunks.insert(chunk_number);
+            }
+        }
+        Ok(session)
+    }
+
+    /// Remove upload directories on disk that don't belong to a loaded
+    /// session. Called on startup, after [`rehydrate`](Self::rehydrate).
+    pub async fn purge_orphans(&self) -> Result<u32> {
         let uploads_dir = self.base_path.join(".uploads");
 
         if !uploads_dir.exists() {
@@ -594,6 +930,9 @@ impl UploadSessionStore {
                 .file_name()
                 .and_then(|n| n.to_str())
                 .unwrap_or("<invalid>");
+            if self.sessions.read().await.contains_key(dir_name) {
+                continue;
+            }
 
             if let Err(e) = fs::remove_dir_all(&path).await {
                 tracing::warn!(
@@ -614,13 +953,18 @@ impl UploadSessionStore {
 /// Default cleanup interval: 1 hour
 pub const DEFAULT_CLEANUP_INTERVAL_SECS: u64 = 3600;
 
-/// Spawn a background task that periodically cleans up expired upload sessions.
-pub fn spawn_cleanup_task(store: UploadSessionStore, interval_secs: u64) {
+/// Spawn a background task that periodically cleans up expired upload
+/// sessions. The task finishes once `shutdown` is set (or its sender dropped).
+pub fn spawn_cleanup_task(
+    store: UploadSessionStore,
+    interval_secs: u64,
+    mut shutdown: tokio::sync::watch::Receiver<bool>,
+) -> tokio::task::JoinHandle<()> {
     tokio::spawn(async move {
         let interval = std::time::Duration::from_secs(interval_secs);
 
-        // Purge all stale sessions on startup (sessions don't survive restarts)
-        match store.purge_all().await {
+        // Purge upload directories no rehydrated session owns
+        match store.purge_orphans().await {
             Ok(count) if count > 0 => {
                 tracing::info!(count, "Purged stale upload directories on startup");
             }
@@ -631,9 +975,12 @@ pub fn spawn_cleanup_task(store: UploadSessionStore, interval_secs: u64) {
         }
 
         loop {
-            tokio::time::sleep(interval).await;
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = shutdown.wait_for(|stop| *stop) => break,
+            }
 
-            // Clean up expired in-memory sessions
+            // Clean up expired sessions, in memory and on disk
             match store.cleanup_expired().await {
                 Ok(expired) if !expired.is_empty() => {
                     tracing::info!(count = expired.len(), "Cleaned up expired upload sessions");
@@ -644,5 +991,6 @@ pub fn spawn_cleanup_task(store: UploadSessionStore, interval_secs: u64) {
                 _ => {}
             }
         }
-    });
+        tracing::debug!("Upload session cleanup task stopped");
+    })
 }
@@ -0,0 +1,299 @@
+//! Arch Linux package version comparison
+//!
+//! [`vercmp`] orders versions the way pacman's `vercmp` does, so anything
+//! pacman would accept as a version (`1.0.0.r5.gabcdef-1`, `20240101-1`,
+//! `2:1.0-1`) can be compared, not just semver.
+
+use std::cmp::Ordering;
+
+/// Split a `[epoch:]pkgver[-pkgrel]` version into its epoch (`"0"` if
+/// absent), pkgver and pkgrel
+///
+/// As in libalpm, only an all-digit prefix before the first `:` is an epoch,
+/// and the pkgrel is everything after the last `-`.
+pub fn split_version(version: &str) -> (&str, &str, Option<&str>) {
+    let digits = version.bytes().take_while(u8::is_ascii_digit).count();
+    let (epoch, rest) = match version[digits..].strip_prefix(':') {
+        Some(rest) if digits > 0 => (&version[..digits], rest),
+        Some(rest) => ("0", rest),
+        None => ("0", version),
+    };
+
+    match rest.rsplit_once('-') {
+        Some((pkgver, pkgrel)) => (epoch, pkgver, Some(pkgrel)),
+        None => (epoch, rest, None),
+    }
+}
+
+/// The version without its pkgrel, e.g. `2:1.0.0` for `2:1.0.0-3`
+pub fn strip_pkgrel(version: &str) -> &str {
+    match split_version(version) {
+        (_, _, Some(pkgrel)) => &version[..version.len() - pkgrel.len() - 1],
+        (_, _, None) => version,
+    }
+}
+
+/// Compare two package versions like pacman's `vercmp`
+///
+/// Epochs are compared first, then pkgvers, then pkgrels, the latter only
+/// when both versions have one: `1.5` and `1.5-1` are the same version.
+pub fn vercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+
+    let (epoch_a, pkgver_a, pkgrel_a) = split_version(a);
+    let (epoch_b, pkgver_b, pkgrel_b) = split_version(b);
+
+    rpmvercmp(epoch_a, epoch_b)
+        .then_with(|| rpmvercmp(pkgver_a, pkgver_b))
+        .then_with(|| match (pkgrel_a, pkgrel_b) {
+            (Some(pkgrel_a), Some(pkgrel_b)) => rpmvercmp(pkgrel_a, pkgrel_b),
+            _ => Ordering::Equal,
+        })
+}
+
+/// Segment-wise comparison of one version component, as libalpm's
+/// `rpmvercmp`
+///
+/// Runs of digits compare numerically, runs of letters lexically, and a
+/// numeric segment is always newer than an alpha one. Separators only
+/// matter by count. A trailing alpha segment is older than nothing at all
+/// (`1.0a` < `1.0`), anything else trailing is newer (`1.0.1` > `1.0`).
+fn rpmvercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+
+    let mut one = a.as_bytes();
+    let mut two = b.as_bytes();
+
+    while !one.is_empty() && !two.is_empty() {
+        let sep_one = one
+            .iter()
+            .take_while(|c| !c.is_ascii_alphanumeric())
+            .count();
+        let sep_two = two
+            .iter()
+            .take_while(|c| !c.is_ascii_alphanumeric())
+            .count();
+        one = &one[sep_one..];
+        two = &two[sep_two..];
+
+        if one.is_empty() || two.is_empty() {
+            break;
+        }
+
+        // Differing separator runs settle it: more separators is newer
+        if sep_one != sep_two {
+            return sep_one.cmp(&sep_two);
+        }
+
+        // Take the same kind of segment (numeric or alpha) from both sides
+        let is_num = one[0].is_ascii_digit();
+        let same_kind = |c: &u8| {
+            if is_num {
+                c.is_ascii_digit()
+            } else {
+                c.is_ascii_alphabetic()
+            }
+        };
+        let (seg_one, rest_one) = one.split_at(one.iter().take_while(|c| same_kind(c)).count());
+        let (seg_two, rest_two) = two.split_at(two.iter().take_while(|c| same_kind(c)).count());
+
+        // The segments are of different kinds; numeric is newer
+        if seg_two.is_empty() {
+            return if is_num {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            };
+        }
+
+        let ordering = if is_num {
+            let strip = |seg: &[u8]| -> usize { seg.iter().take_while(|&&c| c == b'0').count() };
+            let seg_one = &seg_one[strip(seg_one)..];
+            let seg_two = &seg_two[strip(seg_two)..];
+            seg_one
+                .len()
+                .cmp(&seg_two.len())
+                .then_with(|| seg_one.cmp(seg_two))
+        } else {
+            seg_one.cmp(seg_two)
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+
+        one = rest_one;
+        two = rest_two;
+    }
+
+    if one.is_empty() && two.is_empty() {
+        return Ordering::Equal;
+    }
+
+    // A remaining alpha segment never beats running out
+    let alpha = |s: &[u8]| s.first().is_some_and(u8::is_ascii_alphabetic);
+    if (one.is_empty() && !alpha(two)) || alpha(one) {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    }
+}
+
+/// Whether a version spec contains semver range operators rather than
+/// naming an exact version
+fn is_range(spec: &str) -> bool {
+    spec.contains(['^', '~', '>', '<', '=', '*', ','])
+}
+
+/// Parse the pkgver of an Arch Linux version as semver, ignoring epoch and
+/// pkgrel
+fn parse_semver(version: &str) -> Option<semver::Version> {
+    let (_, pkgver, _) = split_version(version);
+    semver::Version::parse(pkgver).ok()
+}
+
+/// Whether `version` satisfies a version spec
+///
+/// A spec is either an exact version, matched with [`vercmp`] (so `1.5.3`
+/// matches every pkgrel of it), or a semver range like `^1.0.0` or
+/// `>=1.0.0, <2.0.0`. Ranges only apply to versions whose pkgver is semver:
+/// for other versions `None` is returned, as they can't be evaluated. A spec
+/// with range operators that doesn't parse as a range is taken as exact.
+pub fn matches_constraint(version: &str, spec: &str) -> Option<bool> {
+    if is_range(spec)
+        && let Ok(range) = semver::VersionReq::parse(spec)
+    {
+        return parse_semver(version).map(|version| range.matches(&version));
+    }
+
+    Some(vercmp(version, spec).is_eq())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test vectors from pacman's `test/util/vercmptest.sh`
+    #[test]
+    fn test_vercmp_matches_pacman() {
+        let cases = [
+            // all similar length, no pkgrel
+            ("1.5.0", "1.5.0", 0),
+            ("1.5.1", "1.5.0", 1),
+            // mixed length
+            ("1.5.1", "1.5", 1),
+            // with pkgrel, simple
+            ("1.5.0-1", "1.5.0-1", 0),
+            ("1.5.0-1", "1.5.0-2", -1),
+            ("1.5.0-1", "1.5.1-1", -1),
+            ("1.5.0-2", "1.5.1-1", -1),
+            // with pkgrel, mixed lengths
+            ("1.5-1", "1.5.1-1", -1),
+            ("1.5-2", "1.5.1-1", -1),
+            ("1.5-2", "1.5.1-2", -1),
+            // mixed pkgrel inclusion
+            ("1.5", "1.5-1", 0),
+            ("1.5-1", "1.5", 0),
+            ("1.1-1", "1.1", 0),
+            ("1.0-1", "1.1", -1),
+            ("1.1-1", "1.0", 1),
+            // alphanumeric versions
+            ("1.5b-1", "1.5-1", -1),
+            ("1.5b", "1.5", -1),
+            ("1.5b-1", "1.5", -1),
+            ("1.5b", "1.5.1", -1),
+            // from the manpage
+            ("1.0a", "1.0alpha", -1),
+            ("1.0alpha", "1.0b", -1),
+            ("1.0b", "1.0beta", -1),
+            ("1.0beta", "1.0rc", -1),
+            ("1.0rc", "1.0", -1),
+            ("1.0a", "1.0", -1),
+            ("1.0.1", "1.0", 1),
+            // alpha-dotted versions
+            ("1.5.a", "1.5", 1),
+            ("1.5.b", "1.5.a", 1),
+            ("1.5.1", "1.5.b", 1),
+            // alpha dots and dashes
+            ("1.5.b-1", "1.5.b", 0),
+            ("1.5-1", "1.5.b", -1),
+            // same/similar content, differing separators
+            ("2.0", "2_0", 0),
+            ("2.0_a", "2_0.a", 0),
+            ("2.0a", "2.0.a", -1),
+            ("2___a", "2_a", 1),
+            // epoch included version comparisons
+            ("0:1.0", "0:1.0", 0),
+            ("0:1.0", "0:1.1", -1),
+            ("1:1.0", "0:1.0", 1),
+            ("1:1.0", "0:1.1", 1),
+            ("1:1.0", "2:1.1", -1),
+            // epoch + sometimes present pkgrel
+            ("1:1.0", "0:1.0-1", 1),
+            ("1:1.0-1", "0:1.1-1", 1),
+            // epoch included on one version
+            ("0:1.0", "1.0", 0),
+            ("0:1.1", "1.0", 1),
+            ("0:1.1", "1.1", 0),
+            ("1:1.0", "1.0", 1),
+            ("1:1.1", "1.1", 1),
+            ("1.1", "1:1.1", -1),
+            // pkgrel dotted versions
+            ("1.0-1.5", "1.0-1.5", 0),
+            ("1.0-1.5", "1.0-1.6", -1),
+            ("1.0-2.1", "1.0-1.6", 1),
+            // leading zeros
+            ("1.010", "1.10", 0),
+            ("1.002", "1.1", 1),
+        ];
+
+        for (a, b, expected) in cases {
+            let expected = expected.cmp(&0);
+            assert_eq!(vercmp(a, b), expected, "vercmp({a}, {b})");
+            assert_eq!(vercmp(b, a), expected.reverse(), "vercmp({b}, {a})");
+        }
+    }
+
+    #[test]
+    fn test_vercmp_orders_non_semver_versions() {
+        assert_eq!(
+            vercmp("1.0.0.r5.gabcdef-1", "1.0.0.r12.g123456-1"),
+            Ordering::Less
+        );
+        assert_eq!(vercmp("20240101-1", "20231231-2"), Ordering::Greater);
+        // Epoch outranks a higher pkgver
+        assert_eq!(vercmp("2:1.0.0-1", "1.9.9-1"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_split_version() {
+        assert_eq!(split_version("1.5.3-1"), ("0", "1.5.3", Some("1")));
+        assert_eq!(split_version("2:1.5.3-2"), ("2", "1.5.3", Some("2")));
+        assert_eq!(split_version("1.5.3"), ("0", "1.5.3", None));
+        assert_eq!(split_version("x:1.5"), ("0", "x:1.5", None));
+        assert_eq!(strip_pkgrel("2:1.5.3-2"), "2:1.5.3");
+        assert_eq!(strip_pkgrel("1.5.3"), "1.5.3");
+    }
+
+    #[test]
+    fn test_matches_constraint() {
+        assert_eq!(matches_constraint("1.5.3-1", "1.5.3-1"), Some(true));
+        assert_eq!(matches_constraint("1.5.3-2", "1.5.3-1"), Some(false));
+        // Without a pkgrel, every pkgrel matches
+        assert_eq!(matches_constraint("1.5.3-2", "1.5.3"), Some(true));
+        assert_eq!(matches_constraint("20240101-1", "20240101-1"), Some(true));
+
+        assert_eq!(matches_constraint("1.5.3-1", "^1.0.0"), Some(true));
+        assert_eq!(matches_constraint("2.0.0-1", "^1.0.0"), Some(false));
+        assert_eq!(
+            matches_constraint("1:1.5.3-1", ">=1.0.0, <2.0.0"),
+            Some(true)
+        );
+        // Ranges can't be evaluated against non-semver pkgvers
+        assert_eq!(matches_constraint("20240101-1", "<2.0.0"), None);
+        assert_eq!(matches_constraint("1.0.0.r5.gabcdef-1", "^1.0.0"), None);
+    }
+}
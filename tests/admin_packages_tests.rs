@@ -0,0 +1,67 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{
+    body_json, create_test_package, send, setup_test_app_with_storage, upload_package,
+    wait_for_db_entries,
+};
+
+#[tokio::test]
+async fn missing_package_file_is_flagged() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    for name in ["kept", "vanished"] {
+        let data = create_test_package(name, "1.0.0-1", "x86_64");
+        let (status, _) =
+            upload_package(&app, &format!("{name}-1.0.0-1-x86_64.pkg.tar.zst"), &data).await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+    wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+
+    let path = storage
+        .package_path("sw1nn", "vanished-1.0.0-1-x86_64.pkg.tar.zst")
+        .unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let response = send(&app, "GET", "/api/admin/packages").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_json(response).await;
+    assert_eq!(body["total"], 2);
+    let entries = body["packages"].as_array().unwrap();
+    assert_eq!(entries[0]["package"]["name"], "kept");
+    assert_eq!(entries[0]["file_present"], true);
+    assert_eq!(entries[0]["signed"], false);
+    assert_eq!(entries[1]["package"]["name"], "vanished");
+    assert_eq!(entries[1]["file_present"], false);
+
+    let response = send(&app, "GET", "/api/admin/packages?file_present=false").await;
+    let body = body_json(response).await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["packages"][0]["package"]["name"], "vanished");
+}
+
+#[tokio::test]
+async fn only_newest_version_is_in_current_db() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    for version in ["1.0.0-1", "2.0.0-1"] {
+        let data = create_test_package("paged", version, "x86_64");
+        let (status, _) =
+            upload_package(&app, &format!("paged-{version}-x86_64.pkg.tar.zst"), &data).await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+    wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+    // Let the db actor apply the second upload's regeneration
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+    let response = send(&app, "GET", "/api/admin/packages?in_current_db=true").await;
+    let body = body_json(response).await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["packages"][0]["package"]["version"], "2.0.0-1");
+
+    let response = send(&app, "GET", "/api/admin/packages?offset=1&limit=1").await;
+    let body = body_json(response).await;
+    assert_eq!(body["total"], 2);
+    assert_eq!(body["offset"], 1);
+    let entries = body["packages"].as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["package"]["version"], "2.0.0-1");
+}
@@ -1,6 +1,11 @@
 use axum::body::Body;
 use axum::http::{Request, StatusCode};
 use serde_json::json;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use sw1nn_pkg_repo::auth::GitHubClient;
+use sw1nn_pkg_repo::config::{ApiKeyScope, GitHubApiConfig};
+use sw1nn_pkg_repo::error::Error;
 use tower::util::ServiceExt;
 
 mod common;
@@ -12,8 +17,12 @@ fn test_auth_config() -> sw1nn_pkg_repo::config::AuthConfig {
     sw1nn_pkg_repo::config::AuthConfig {
         github_client_id: "test-client-id".to_string(),
         allowed_users: vec!["testuser".to_string()],
+        allowed_users_file: None,
+        allowed_users_reload_secs: 60,
         jwt_secret: TEST_JWT_SECRET.to_string(),
         jwt_expiration_secs: 3600,
+        github: Default::default(),
+        api_keys: vec![],
     }
 }
 
@@ -179,6 +188,251 @@ async fn test_write_endpoint_succeeds_with_valid_token() -> Result<(), Box<dyn s
     Ok(())
 }
 
+async fn initiate_upload_as(app: &axum::Router, token: &str) -> StatusCode {
+    let request_body = json!({
+        "filename": "test-pkg-1.0.0-x86_64.pkg.tar.zst",
+        "size": 1048576,
+        "chunk_size": 1048576,
+        "has_signature": false
+    });
+
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/packages/upload/initiate")
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {token}"))
+                .body(Body::from(serde_json::to_vec(&request_body).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+async fn refresh_with(app: &axum::Router, token: &str) -> axum::response::Response {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/auth/refresh")
+                .header("Authorization", format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_refresh_issues_new_token_of_same_type() {
+    let auth = test_auth_config();
+    let app = setup_test_app_with_auth(auth.clone()).await;
+    let token = create_test_token("testuser");
+    let old = sw1nn_pkg_repo::auth::validate_jwt(&auth, &token).unwrap();
+
+    let response = refresh_with(&app, &token).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = common::body_json(response).await;
+    assert_eq!(body["username"], "testuser");
+
+    let refreshed = sw1nn_pkg_repo::auth::validate_jwt(&auth, body["token"].as_str().unwrap())
+        .expect("refreshed token is valid");
+    assert_eq!(refreshed.sub, "testuser");
+    assert_eq!(refreshed.token_type, old.token_type);
+    assert_eq!(body["expires_at"], refreshed.exp);
+    assert!(refreshed.exp >= old.exp);
+
+    assert_eq!(
+        initiate_upload_as(&app, body["token"].as_str().unwrap()).await,
+        StatusCode::CREATED
+    );
+}
+
+#[tokio::test]
+async fn test_refresh_rejects_expired_token() {
+    let auth = test_auth_config();
+    let app = setup_test_app_with_auth(auth.clone()).await;
+
+    // Expired well past the validation leeway
+    let mut expired_auth = auth;
+    expired_auth.jwt_expiration_secs = -3600;
+    let expired = sw1nn_pkg_repo::auth::create_jwt(&expired_auth, "testuser", "user").unwrap();
+
+    let response = refresh_with(&app, &expired).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/auth/refresh")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_refresh_rechecks_allowlist() {
+    let app = setup_test_app_with_auth(test_auth_config()).await;
+    let token = create_test_token("removed-user");
+
+    let response = refresh_with(&app, &token).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+const CI_API_KEY: &str = "ci-key-0123456789abcdef0123456789abcdef";
+
+fn api_key(username: &str, key: &str) -> sw1nn_pkg_repo::config::ApiKey {
+    use sha2::Digest;
+    sw1nn_pkg_repo::config::ApiKey {
+        username: username.to_string(),
+        key_sha256: format!("{:x}", sha2::Sha256::digest(key.as_bytes())),
+        scope: ApiKeyScope::Write,
+    }
+}
+
+async fn initiate_upload_with_key(app: &axum::Router, key: &str) -> StatusCode {
+    let request_body = json!({
+        "filename": "test-pkg-1.0.0-x86_64.pkg.tar.zst",
+        "size": 1048576,
+        "chunk_size": 1048576,
+        "has_signature": false
+    });
+
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/packages/upload/initiate")
+                .header("Content-Type", "application/json")
+                .header("X-API-Key", key)
+                .body(Body::from(serde_json::to_vec(&request_body).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+async fn test_write_endpoint_succeeds_with_api_key() {
+    let mut auth = test_auth_config();
+    auth.api_keys = vec![api_key("testuser", CI_API_KEY)];
+    let app = setup_test_app_with_auth(auth).await;
+
+    assert_eq!(
+        initiate_upload_with_key(&app, CI_API_KEY).await,
+        StatusCode::CREATED
+    );
+}
+
+#[tokio::test]
+async fn test_unknown_api_key_returns_401() {
+    let mut auth = test_auth_config();
+    auth.api_keys = vec![api_key("testuser", CI_API_KEY)];
+    let app = setup_test_app_with_auth(auth).await;
+
+    assert_eq!(
+        initiate_upload_with_key(&app, "not-the-ci-key").await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        initiate_upload_with_key(&app, "").await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn test_api_key_rejected_once_revoked() {
+    // No keys configured: a key that used to be valid is unknown
+    let app = setup_test_app_with_auth(test_auth_config()).await;
+
+    assert_eq!(
+        initiate_upload_with_key(&app, CI_API_KEY).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn test_read_only_api_key_cannot_write() {
+    let mut auth = test_auth_config();
+    let mut key = api_key("testuser", CI_API_KEY);
+    key.scope = ApiKeyScope::ReadOnly;
+    auth.api_keys = vec![key];
+    let app = setup_test_app_with_auth(auth).await;
+
+    assert_eq!(
+        initiate_upload_with_key(&app, CI_API_KEY).await,
+        StatusCode::FORBIDDEN
+    );
+
+    // Authenticated reads still work
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/admin/events")
+                .header("X-API-Key", CI_API_KEY)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_api_key_of_disallowed_user_returns_403() {
+    let mut auth = test_auth_config();
+    auth.api_keys = vec![api_key("someone-else", CI_API_KEY)];
+    let app = setup_test_app_with_auth(auth).await;
+
+    assert_eq!(
+        initiate_upload_with_key(&app, CI_API_KEY).await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn test_allowed_users_file_is_reloaded() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::TempDir::new()?;
+    let users_file = dir.path().join("allowed_users");
+    std::fs::write(&users_file, "# maintainers\ntestuser\n")?;
+
+    let mut auth = test_auth_config();
+    auth.allowed_users = Vec::new();
+    auth.allowed_users_file = Some(users_file.clone());
+    auth.allowed_users_reload_secs = 1;
+    let app = setup_test_app_with_auth(auth.clone()).await;
+
+    let token = sw1nn_pkg_repo::auth::create_jwt(&auth, "newuser", "admin")?;
+    assert_eq!(
+        initiate_upload_as(&app, &token).await,
+        StatusCode::FORBIDDEN
+    );
+
+    std::fs::write(&users_file, "# maintainers\ntestuser\nnewuser\n")?;
+
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+    loop {
+        let status = initiate_upload_as(&app, &token).await;
+        if status == StatusCode::CREATED {
+            break;
+        }
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "allowlist was not reloaded in time"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_delete_endpoint_requires_auth() -> Result<(), Box<dyn std::error::Error>> {
     let app = setup_test_app_with_auth(test_auth_config()).await;
@@ -197,6 +451,35 @@ async fn test_delete_endpoint_requires_auth() -> Result<(), Box<dyn std::error::
     Ok(())
 }
 
+#[tokio::test]
+async fn test_delete_all_endpoint_requires_auth() -> Result<(), Box<dyn std::error::Error>> {
+    let app = setup_test_app_with_auth(test_auth_config()).await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/api/packages/nonexistent/all")
+                .body(Body::empty())?,
+        )
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_health_endpoint_bypasses_auth() -> Result<(), Box<dyn std::error::Error>> {
+    let app = setup_test_app_with_auth(test_auth_config()).await;
+
+    let response = app
+        .oneshot(Request::builder().uri("/health").body(Body::empty())?)
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_rebuild_endpoint_requires_auth() -> Result<(), Box<dyn std::error::Error>> {
     let app = setup_test_app_with_auth(test_auth_config()).await;
@@ -240,3 +523,211 @@ fn test_validate_jwt_with_wrong_secret() {
     let result = sw1nn_pkg_repo::auth::validate_jwt(&wrong_auth, &token);
     assert!(result.is_err());
 }
+
+// -- GitHub client retries and circuit breaker --
+
+/// Serve `router` on a local port, returning its base URL
+async fn spawn_mock_github(router: axum::Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    format!("http://{addr}")
+}
+
+fn mock_github_config(base_url: &str) -> GitHubApiConfig {
+    GitHubApiConfig {
+        url: base_url.to_owned(),
+        api_url: base_url.to_owned(),
+        max_retries: 3,
+        retry_backoff_ms: 1,
+        breaker_threshold: 5,
+        breaker_cooldown_secs: 60,
+    }
+}
+
+/// Mock `GET /user` failing with 502 `failures` times before answering
+async fn flaky_user_endpoint(failures: usize) -> (String, Arc<AtomicUsize>) {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&hits);
+    let router = axum::Router::new().route(
+        "/user",
+        axum::routing::get(move || {
+            let counter = Arc::clone(&counter);
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                    (StatusCode::BAD_GATEWAY, axum::Json(json!({})))
+                } else {
+                    (StatusCode::OK, axum::Json(json!({"login": "testuser"})))
+                }
+            }
+        }),
+    );
+    (spawn_mock_github(router).await, hits)
+}
+
+#[tokio::test]
+async fn github_user_lookup_retries_transient_errors() {
+    let (base_url, hits) = flaky_user_endpoint(2).await;
+    let client = GitHubClient::new(mock_github_config(&base_url));
+
+    let user = client.get_github_user("token").await.unwrap();
+
+    assert_eq!(user.login, "testuser");
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn github_user_lookup_gives_up_after_max_retries() {
+    let (base_url, hits) = flaky_user_endpoint(usize::MAX).await;
+    let client = GitHubClient::new(mock_github_config(&base_url));
+
+    let result = client.get_github_user("token").await;
+
+    assert!(matches!(result, Err(Error::GitHubApi { .. })));
+    assert_eq!(hits.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn github_circuit_breaker_fails_fast() {
+    let (base_url, hits) = flaky_user_endpoint(usize::MAX).await;
+    let client = GitHubClient::new(GitHubApiConfig {
+        max_retries: 0,
+        breaker_threshold: 2,
+        ..mock_github_config(&base_url)
+    });
+
+    for _ in 0..2 {
+        let result = client.get_github_user("token").await;
+        assert!(matches!(result, Err(Error::GitHubApi { .. })));
+    }
+
+    let result = client.get_github_user("token").await;
+    assert!(matches!(result, Err(Error::GitHubUnavailable { .. })));
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn device_token_passes_on_slow_down_interval() {
+    let router = axum::Router::new().route(
+        "/login/oauth/access_token",
+        axum::routing::post(|| async { axum::Json(json!({"error": "slow_down", "interval": 10})) }),
+    );
+    let base_url = spawn_mock_github(router).await;
+    let mut auth = test_auth_config();
+    auth.github = mock_github_config(&base_url);
+    let app = setup_test_app_with_auth(auth).await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/auth/device/token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(json!({"device_code": "abc"}).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let body = common::body_json(response).await;
+    assert_eq!(body["status"], "pending");
+    assert_eq!(body["interval"], 10);
+}
+
+/// Captures formatted tracing output
+#[derive(Clone, Default)]
+struct LogCapture(Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for LogCapture {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_upload_logs_user_and_filename() {
+    let capture = LogCapture::default();
+    let writer = capture.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(move || writer.clone())
+        .with_ansi(false)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let app = setup_test_app_with_auth(test_auth_config()).await;
+    let token = create_test_token("testuser");
+    let filename = "log-pkg-1.0.0-1-x86_64.pkg.tar.zst";
+    let data = common::create_test_package("log-pkg", "1.0.0-1", "x86_64");
+
+    let authed = |method: &str, uri: &str, content_type: &str, body: Vec<u8>| {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Content-Type", content_type)
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    let init = json!({
+        "filename": filename,
+        "size": data.len(),
+        "chunk_size": data.len(),
+        "has_signature": false
+    });
+    let response = app
+        .clone()
+        .oneshot(authed(
+            "POST",
+            "/api/packages/upload/initiate",
+            "application/json",
+            serde_json::to_vec(&init).unwrap(),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let upload_id = common::body_json(response).await["upload_id"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    let response = app
+        .clone()
+        .oneshot(authed(
+            "POST",
+            &format!("/api/packages/upload/{upload_id}/chunks/1"),
+            "application/octet-stream",
+            data,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let checksum = common::body_json(response).await["checksum"].clone();
+
+    let complete = json!({"chunks": [{"chunk_number": 1, "checksum": checksum}]});
+    let response = app
+        .clone()
+        .oneshot(authed(
+            "POST",
+            &format!("/api/packages/upload/{upload_id}/complete"),
+            "application/json",
+            serde_json::to_vec(&complete).unwrap(),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let logs = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+    let uploaded = logs
+        .lines()
+        .find(|line| line.contains("Package uploaded"))
+        .unwrap_or_else(|| panic!("no upload event in {logs}"));
+    assert!(uploaded.contains("user=\"testuser\""), "{uploaded}");
+    assert!(uploaded.contains(filename), "{uploaded}");
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{seed_package, send_json, setup_test_app_with_config, test_config};
+use serde_json::json;
+
+#[tokio::test]
+async fn batch_info_returns_latest_and_null_for_missing() {
+    let (app, storage) = setup_test_app_with_config(test_config()).await;
+    seed_package(&storage, "sw1nn", "alpha", "1.0.0-1", "x86_64").await;
+    seed_package(&storage, "sw1nn", "alpha", "1.2.0-1", "x86_64").await;
+    seed_package(&storage, "sw1nn", "beta", "0.3.0-1", "any").await;
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/api/packages/batch-info",
+        &json!({"names": ["alpha", "beta", "missing"], "arch": "x86_64"}),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["alpha"]["version"], "1.2.0-1");
+    assert_eq!(body["beta"]["version"], "0.3.0-1");
+    assert_eq!(body["beta"]["arch"], "any");
+    assert!(body["missing"].is_null());
+    assert_eq!(body.as_object().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn batch_info_rejects_oversized_batch() {
+    let mut config = test_config();
+    config.server.max_batch_size = 2;
+    let (app, _storage) = setup_test_app_with_config(config).await;
+
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/packages/batch-info",
+        &json!({"names": ["a", "b", "c"]}),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
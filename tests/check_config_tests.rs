@@ -0,0 +1,66 @@
+use std::process::Command;
+
+fn check_config(contents: &str) -> std::process::Output {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, contents).unwrap();
+
+    Command::new(env!("CARGO_BIN_EXE_sw1nn-pkg-repod"))
+        .arg("--check-config")
+        .arg("--config")
+        .arg(&path)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn check_config_fails_on_invalid_config() {
+    let output = check_config(
+        r#"
+[server]
+port = 3000
+
+[storage]
+data_path = "/nonexistent"
+
+[auth]
+github_client_id = "client"
+allowed_users = ["someone"]
+jwt_secret = "too-short"
+"#,
+    );
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("jwt_secret"), "{stderr}");
+}
+
+#[test]
+fn check_config_prints_settings_with_secrets_redacted() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let secret = "a-secret-that-is-at-least-32-characters-long";
+    let output = check_config(&format!(
+        r#"
+[server]
+port = 3123
+
+[storage]
+data_path = "{}"
+
+[auth]
+github_client_id = "client"
+allowed_users = ["someone"]
+jwt_secret = "{secret}"
+"#,
+        dir.path().join("not-created").display()
+    ));
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("port: 3123"), "{stdout}");
+    assert!(!stdout.contains(secret), "{stdout}");
+    assert!(stdout.contains("<redacted>"), "{stdout}");
+
+    // The data dir is left alone
+    assert!(!dir.path().join("not-created").exists());
+}
@@ -4,7 +4,13 @@ use serde_json::json;
 use tower::util::ServiceExt;
 
 mod common;
-use common::{create_test_package, setup_test_app};
+
+use common::{
+    body_json, create_test_package, send, send_json, setup_test_app, setup_test_app_with_config,
+    setup_test_app_with_storage, test_config, upload_package, wait_for_db_entries,
+};
+use std::collections::HashSet;
+use sw1nn_pkg_repo::upload::{UploadSession, UploadSessionStore};
 
 #[tokio::test]
 async fn test_chunked_upload_initiate() {
@@ -71,6 +77,7 @@ async fn test_chunked_upload_invalid_filename() {
         .unwrap();
     let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
     assert!(error["error"].as_str().unwrap().contains(".pkg.tar.zst"));
+    assert_eq!(error["code"], "invalid_package");
 }
 
 #[tokio::test]
@@ -98,6 +105,52 @@ async fn test_chunked_upload_size_too_large() {
     assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
 }
 
+#[tokio::test]
+async fn test_chunked_upload_checks_arch_allowlist() {
+    let app = setup_test_app().await;
+    let initiate = |arch: &str| {
+        json!({
+            "filename": format!("test-pkg-1.0.0-1-{arch}.pkg.tar.zst"),
+            "size": 1024,
+            "arch": arch,
+            "has_signature": false
+        })
+    };
+
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &initiate("aarch64"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, error) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &initiate("riscv64"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error["code"], "invalid_package");
+    assert!(error["error"].as_str().unwrap().contains("riscv64"));
+
+    // An empty allowlist allows any arch
+    let mut config = test_config();
+    config.storage.allowed_archs.clear();
+    let (app, _storage) = setup_test_app_with_config(config).await;
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &initiate("riscv64"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
 #[tokio::test]
 async fn test_chunked_upload_complete_workflow() {
     let app = setup_test_app().await;
@@ -588,3 +641,1068 @@ async fn test_chunked_upload_concurrent_sessions() {
     assert_eq!(response1.status(), StatusCode::OK);
     assert_eq!(response2.status(), StatusCode::OK);
 }
+
+#[tokio::test]
+async fn test_legacy_upload_returns_gone() {
+    let app = setup_test_app().await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/packages")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::GONE);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let response_json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(
+        response_json["error"]
+            .as_str()
+            .unwrap()
+            .contains("/api/packages/upload/initiate")
+    );
+}
+
+#[tokio::test]
+async fn test_reupload_identical_package_is_unchanged() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    let data = create_test_package("same-pkg", "1.0.0-1", "x86_64");
+    let filename = "same-pkg-1.0.0-1-x86_64.pkg.tar.zst";
+
+    let (status, first) = upload_package(&app, filename, &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // Wait for the upload's db regeneration, then remove the db so any
+    // further regeneration is observable
+    wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+    let db_dir = storage.db_dir("sw1nn", "x86_64").unwrap();
+    std::fs::remove_file(db_dir.join("sw1nn.db")).unwrap();
+
+    let (status, second) = upload_package(&app, filename, &data).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(second["sha256"], first["sha256"]);
+    assert_eq!(second["created_at"], first["created_at"]);
+
+    // Well past the test actor's 100ms debounce
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    assert!(!db_dir.join("sw1nn.db").exists());
+}
+
+/// Adding a signature to a stored unsigned package isn't a no-op; the
+/// signature must not be dropped silently
+#[tokio::test]
+async fn test_reupload_adding_signature_conflicts() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    let data = create_test_package("same-pkg", "1.0.0-1", "x86_64");
+    let filename = "same-pkg-1.0.0-1-x86_64.pkg.tar.zst";
+
+    let (status, _) = upload_package(&app, filename, &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, _) =
+        common::upload_package_with_signature(&app, filename, &data, Some(b"signature")).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let stored = storage
+        .load_package("sw1nn", "same-pkg-1.0.0-1-x86_64")
+        .await
+        .unwrap();
+    assert!(!stored.signed);
+}
+
+#[tokio::test]
+async fn test_reupload_different_bytes_conflicts() {
+    let app = setup_test_app().await;
+    let filename = "same-pkg-1.0.0-1-x86_64.pkg.tar.zst";
+
+    let data = create_test_package("same-pkg", "1.0.0-1", "x86_64");
+    let (status, _) = upload_package(&app, filename, &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let mut changed = create_test_package("same-pkg", "1.0.0-1", "x86_64");
+    changed.extend_from_slice(&create_test_package("other", "1.0.0-1", "x86_64"));
+    let (status, body) = upload_package(&app, filename, &changed).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "package_already_exists");
+    assert_eq!(
+        body["error"],
+        "Package already exists: same-pkg-1.0.0-1-x86_64.pkg.tar.zst"
+    );
+}
+
+fn boundary_session(file_size: u64, chunk_size: usize) -> UploadSession {
+    UploadSession::builder()
+        .filename("boundary-1.0.0-1-x86_64.pkg.tar.zst")
+        .file_size(file_size)
+        .repo("sw1nn")
+        .arch("x86_64")
+        .chunk_size(chunk_size)
+        .build()
+}
+
+#[test]
+fn test_chunk_size_contract_at_boundaries() {
+    // (file_size, chunk_size, total_chunks, final chunk size)
+    let cases = [
+        (1024, 256, 4, 256), // exact multiple: last chunk is full
+        (1025, 256, 5, 1),   // one byte over: last chunk is a single byte
+        (1023, 256, 4, 255), // one byte under
+        (100, 256, 1, 100),  // file smaller than chunk: one short chunk
+        (256, 256, 1, 256),  // file equal to chunk
+        (1, 1, 1, 1),        // smallest possible upload
+    ];
+
+    for (file_size, chunk_size, total_chunks, last_size) in cases {
+        let session = boundary_session(file_size, chunk_size);
+        let case = format!("file_size={file_size} chunk_size={chunk_size}");
+
+        assert_eq!(session.total_chunks, total_chunks, "{case}");
+        assert_eq!(session.expected_chunk_size(0), None, "{case}");
+        assert_eq!(
+            session.expected_chunk_size(total_chunks + 1),
+            None,
+            "{case}"
+        );
+        for n in 1..total_chunks {
+            assert_eq!(session.expected_chunk_size(n), Some(chunk_size), "{case}");
+        }
+        assert_eq!(
+            session.expected_chunk_size(total_chunks),
+            Some(last_size),
+            "{case}"
+        );
+
+        // Chunk sizes always add up to the file size
+        let sum: u64 = (1..=total_chunks)
+            .map(|n| session.expected_chunk_size(n).unwrap() as u64)
+            .sum();
+        assert_eq!(sum, file_size, "{case}");
+    }
+}
+
+#[tokio::test]
+async fn test_store_chunk_enforces_final_chunk_size() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let store = UploadSessionStore::new(temp_dir.path().to_path_buf());
+
+    // (file_size, chunk_size, final chunk accepted, final chunk rejected)
+    let cases = [
+        (1024, 256, 256, 255), // exact multiple: short final chunk rejected
+        (1025, 256, 1, 256),   // one byte over: full final chunk rejected
+        (100, 256, 100, 256),  // file smaller than chunk: padded chunk rejected
+        (100, 256, 100, 99),
+    ];
+
+    for (file_size, chunk_size, good, bad) in cases {
+        let session = store
+            .create_session(boundary_session(file_size, chunk_size))
+            .await
+            .unwrap();
+        let last = session.total_chunks;
+        let case = format!("file_size={file_size} chunk_size={chunk_size}");
+
+        assert!(
+            store
+                .store_chunk(&session.upload_id, last, &vec![0u8; bad])
+                .await
+                .is_err(),
+            "{case}: {bad}-byte final chunk should be rejected"
+        );
+        assert!(
+            store
+                .store_chunk(&session.upload_id, last, &vec![0u8; good])
+                .await
+                .is_ok(),
+            "{case}: {good}-byte final chunk should be accepted"
+        );
+        assert!(
+            store
+                .store_chunk(&session.upload_id, last + 1, &vec![0u8; good])
+                .await
+                .is_err(),
+            "{case}: chunk past the end should be rejected"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_chunk_size_larger_than_file_is_single_chunk() {
+    let app = setup_test_app().await;
+    let package_data = create_test_package("small-pkg", "1.0.0-1", "x86_64");
+
+    // No chunk_size: the 1 MiB default exceeds this tiny package
+    let (status, init) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &json!({
+            "filename": "small-pkg-1.0.0-1-x86_64.pkg.tar.zst",
+            "size": package_data.len(),
+            "has_signature": false
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(init["total_chunks"], 1);
+}
+
+#[tokio::test]
+async fn test_custom_session_expiration() {
+    let app = setup_test_app().await;
+    let package_data = create_test_package("quick-pkg", "1.0.0-1", "x86_64");
+
+    let before = chrono::Utc::now();
+    let (status, init) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &json!({
+            "filename": "quick-pkg-1.0.0-1-x86_64.pkg.tar.zst",
+            "size": package_data.len(),
+            "has_signature": false,
+            "expiration_secs": 1
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let expires_at =
+        chrono::DateTime::parse_from_rfc3339(init["expires_at"].as_str().unwrap()).unwrap();
+    assert!(expires_at <= before + chrono::Duration::seconds(2));
+
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+
+    let upload_id = init["upload_id"].as_str().unwrap();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/packages/upload/{upload_id}/chunks/1"))
+                .header("Content-Type", "application/octet-stream")
+                .body(Body::from(package_data))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_session_expiration_over_ceiling_rejected() {
+    let app = setup_test_app().await;
+
+    for expiration_secs in [0, 604801] {
+        let (status, body) = send_json(
+            &app,
+            "POST",
+            "/api/packages/upload/initiate",
+            &json!({
+                "filename": "quick-pkg-1.0.0-1-x86_64.pkg.tar.zst",
+                "size": 1024,
+                "has_signature": false,
+                "expiration_secs": expiration_secs
+            }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{expiration_secs}");
+        assert!(
+            body["error"]
+                .as_str()
+                .unwrap()
+                .contains("Invalid expiration"),
+            "{body}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_default_expiration_capped_by_ceiling() {
+    let mut config = test_config();
+    config.server.max_upload_expiration_secs = 3600;
+    let (app, _storage) = setup_test_app_with_config(config).await;
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &json!({
+            "filename": "quick-pkg-1.0.0-1-x86_64.pkg.tar.zst",
+            "size": 1024,
+            "has_signature": false
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let expires_at: chrono::DateTime<chrono::Utc> =
+        body["expires_at"].as_str().unwrap().parse().unwrap();
+    assert!(expires_at <= chrono::Utc::now() + chrono::Duration::seconds(3600));
+}
+
+#[tokio::test]
+async fn test_get_upload_session_details() {
+    let app = setup_test_app().await;
+
+    let (status, init) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &json!({
+            "filename": "stuck-pkg-1.0.0-1-x86_64.pkg.tar.zst",
+            "size": 2500,
+            "chunk_size": 1000,
+            "has_signature": true
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let upload_id = init["upload_id"].as_str().unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/packages/upload/{upload_id}/chunks/2"))
+                .header("Content-Type", "application/octet-stream")
+                .body(Body::from(vec![0u8; 1000]))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = send(&app, "GET", &format!("/api/packages/upload/{upload_id}")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let details = body_json(response).await;
+    assert_eq!(details["upload_id"], upload_id);
+    assert_eq!(details["filename"], "stuck-pkg-1.0.0-1-x86_64.pkg.tar.zst");
+    assert_eq!(details["size"], 2500);
+    assert_eq!(details["repo"], "sw1nn");
+    assert_eq!(details["arch"], "x86_64");
+    assert_eq!(details["chunk_size"], 1000);
+    assert_eq!(details["total_chunks"], 3);
+    assert_eq!(details["uploaded_chunks"], json!([2]));
+    assert_eq!(details["has_signature"], true);
+    assert_eq!(details["expires_at"], init["expires_at"]);
+    assert!(details["created_at"].is_string());
+    assert_eq!(details["expired"], false);
+
+    let response = send(
+        &app,
+        "GET",
+        &format!("/api/packages/upload/{}", uuid::Uuid::new_v4()),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_upload_session_details_report_expiry() {
+    let app = setup_test_app().await;
+
+    let (status, init) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &json!({
+            "filename": "late-pkg-1.0.0-1-x86_64.pkg.tar.zst",
+            "size": 1000,
+            "has_signature": false,
+            "expiration_secs": 1
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let upload_id = init["upload_id"].as_str().unwrap();
+
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+
+    let response = send(&app, "GET", &format!("/api/packages/upload/{upload_id}")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let details = body_json(response).await;
+    assert_eq!(details["upload_id"], upload_id);
+    assert_eq!(details["expired"], true);
+}
+
+async fn put_range(
+    app: &axum::Router,
+    filename: &str,
+    data: &[u8],
+    start: usize,
+    end: usize,
+) -> axum::response::Response {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/packages/{filename}"))
+                .header("Content-Type", "application/octet-stream")
+                .header(
+                    "Content-Range",
+                    format!("bytes {}-{}/{}", start, end - 1, data.len()),
+                )
+                .body(Body::from(data[start..end].to_vec()))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_content_range_put_upload() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    let package_data = create_test_package("range-pkg", "1.0.0-1", "x86_64");
+    let filename = "range-pkg-1.0.0-1-x86_64.pkg.tar.zst";
+    let mid = package_data.len() / 2;
+
+    // Second half first: stored, but nothing contiguous from the start yet
+    let response = put_range(&app, filename, &package_data, mid, package_data.len()).await;
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert!(response.headers().get("range").is_none());
+    let body = body_json(response).await;
+    assert_eq!(body["received_bytes"], package_data.len() - mid);
+    assert_eq!(body["size"], package_data.len());
+
+    // First half completes the file
+    let response = put_range(&app, filename, &package_data, 0, mid).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let package = body_json(response).await;
+    assert_eq!(package["name"], "range-pkg");
+    assert_eq!(package["size"], package_data.len());
+
+    let stored = storage.package_path("sw1nn", filename).unwrap();
+    assert_eq!(std::fs::read(stored).unwrap(), package_data);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_content_range_puts_share_a_session() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    let package_data = create_test_package("range-pkg", "1.0.0-1", "x86_64");
+    let filename = "range-pkg-1.0.0-1-x86_64.pkg.tar.zst";
+    let pieces = 8;
+    let piece = package_data.len().div_ceil(pieces);
+
+    // Everything but the first piece at once, all racing to create the session
+    let puts = (1..pieces).map(|i| {
+        let app = app.clone();
+        let data = package_data.clone();
+        tokio::spawn(async move {
+            let end = ((i + 1) * piece).min(data.len());
+            let response = put_range(&app, filename, &data, i * piece, end).await;
+            assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+            body_json(response).await["upload_id"]
+                .as_str()
+                .unwrap()
+                .to_owned()
+        })
+    });
+    let upload_ids: HashSet<String> = futures_util::future::join_all(puts)
+        .await
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(upload_ids.len(), 1);
+
+    // Every range was recorded, so the first piece completes the file
+    let response = put_range(&app, filename, &package_data, 0, piece).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let stored = storage.package_path("sw1nn", filename).unwrap();
+    assert_eq!(std::fs::read(stored).unwrap(), package_data);
+}
+
+#[tokio::test]
+async fn test_content_range_put_reports_received_prefix() {
+    let app = setup_test_app().await;
+    let package_data = create_test_package("range-pkg", "1.0.0-1", "x86_64");
+    let filename = "range-pkg-1.0.0-1-x86_64.pkg.tar.zst";
+
+    let response = put_range(&app, filename, &package_data, 0, 100).await;
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.headers()["range"], "bytes=0-99");
+
+    // Missing header and mismatched body length are rejected
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/api/packages/{filename}"),
+        &json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/packages/{filename}"))
+                .header(
+                    "Content-Range",
+                    format!("bytes 0-99/{}", package_data.len()),
+                )
+                .body(Body::from(vec![0u8; 10]))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+/// `max_payload_size` caps both the declared size of an upload and the size
+/// of any single request body, replacing axum's 2 MB default body limit.
+#[tokio::test]
+async fn test_configured_max_payload_size_enforced() {
+    const MIB: usize = 1024 * 1024;
+    let mut config = common::test_config();
+    config.server.max_payload_size = byte_unit::Byte::from_u64(10 * MIB as u64);
+    let (app, _storage) = common::setup_test_app_with_config(config).await;
+
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &json!({
+            "filename": "big-1.0.0-1-x86_64.pkg.tar.zst",
+            "size": 11 * MIB,
+            "has_signature": false
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+    // A body over 2 MB but within the limit is accepted
+    let data = vec![0u8; 4 * MIB];
+    let filename = "mid-1.0.0-1-x86_64.pkg.tar.zst";
+    let response = put_range(&app, filename, &data, 0, 3 * MIB).await;
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+
+    // One past the limit is not, whatever the declared total
+    let data = vec![0u8; 10 * MIB + 1];
+    let response = put_range(&app, filename, &data, 0, data.len()).await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn test_expired_sessions_dont_count_toward_inflight_limit() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let store =
+        UploadSessionStore::new(temp_dir.path().to_path_buf()).with_max_inflight_bytes(Some(3000));
+    let session = |name: &str, expiration_secs| {
+        UploadSession::builder()
+            .filename(format!("{name}-1.0.0-1-x86_64.pkg.tar.zst"))
+            .file_size(2000)
+            .repo("sw1nn")
+            .arch("x86_64")
+            .expiration_secs(expiration_secs)
+            .build()
+    };
+
+    // Expired, but not yet swept by the cleanup task
+    store.create_session(session("stale", -1)).await.unwrap();
+    assert_eq!(store.session_count().await, 1);
+    assert_eq!(store.inflight_bytes().await, 0);
+
+    store.create_session(session("fresh", 3600)).await.unwrap();
+    assert_eq!(store.inflight_bytes().await, 2000);
+    assert!(store.create_session(session("extra", 3600)).await.is_err());
+}
+
+#[tokio::test]
+async fn test_initiate_past_inflight_limit_rejected() {
+    let mut config = common::test_config();
+    config.server.max_total_inflight_bytes = Some(byte_unit::Byte::from_u64(3000));
+    let (app, _storage) = common::setup_test_app_with_config(config).await;
+
+    let initiate = |name: &str, size: u64| {
+        json!({
+            "filename": format!("{name}-1.0.0-1-x86_64.pkg.tar.zst"),
+            "size": size,
+            "has_signature": false
+        })
+    };
+
+    let (status, first) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &initiate("first", 2000),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // 2000 + 1500 > 3000
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &initiate("second", 1500),
+    )
+    .await;
+    assert_eq!(status, StatusCode::INSUFFICIENT_STORAGE);
+    assert!(
+        body["error"].as_str().unwrap().contains("in-flight"),
+        "{body}"
+    );
+
+    // Still fits alongside the first
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &initiate("third", 1000),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // Aborting releases the first session's bytes
+    let upload_id = first["upload_id"].as_str().unwrap();
+    let response = send(&app, "DELETE", &format!("/api/packages/upload/{upload_id}")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &initiate("second", 1500),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_upload_rejects_compression_not_matching_extension() {
+    use std::io::Write;
+
+    let app = setup_test_app().await;
+    let zstd_data = create_test_package("mislabeled", "1.0.0-1", "x86_64");
+    let tar = zstd::decode_all(zstd_data.as_slice()).unwrap();
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&tar).unwrap();
+    let gzip_data = encoder.finish().unwrap();
+
+    let (status, body) =
+        upload_package(&app, "mislabeled-1.0.0-1-x86_64.pkg.tar.zst", &gzip_data).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let error = body["error"].as_str().unwrap();
+    assert!(error.contains("zstd"), "{error}");
+    assert!(error.contains("gzip"), "{error}");
+}
+
+#[tokio::test]
+async fn test_initiate_for_identical_package_skips_session() {
+    let config = common::test_config();
+    let uploads_dir = config.storage.data_path.join(".uploads");
+    let (app, _storage) = common::setup_test_app_with_config(config).await;
+    let data = create_test_package("present", "1.0.0-1", "x86_64");
+    let filename = "present-1.0.0-1-x86_64.pkg.tar.zst";
+
+    let (status, stored) = upload_package(&app, filename, &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let sessions_before = std::fs::read_dir(&uploads_dir).unwrap().count();
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &json!({
+            "filename": filename,
+            "size": data.len(),
+            "sha256": stored["sha256"],
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["name"], "present");
+    assert_eq!(body["sha256"], stored["sha256"]);
+    assert!(body.get("upload_id").is_none());
+    assert_eq!(
+        std::fs::read_dir(&uploads_dir).unwrap().count(),
+        sessions_before
+    );
+
+    // A different hash, or adding a signature, still gets a session
+    for request in [
+        json!({"filename": filename, "size": data.len(), "sha256": "0".repeat(64)}),
+        json!({
+            "filename": filename,
+            "size": data.len(),
+            "sha256": stored["sha256"],
+            "has_signature": true,
+        }),
+    ] {
+        let (status, body) =
+            send_json(&app, "POST", "/api/packages/upload/initiate", &request).await;
+        assert_eq!(status, StatusCode::CREATED, "{request}");
+        assert!(body["upload_id"].is_string());
+    }
+}
+
+/// An empty hash never matches a stored package recorded without one
+#[tokio::test]
+async fn test_initiate_with_empty_hash_gets_a_session() {
+    let (app, storage) = common::setup_test_app_with_config(common::test_config()).await;
+    let (data, filename) =
+        common::seed_package(&storage, "sw1nn", "nohash", "1.0.0-1", "x86_64").await;
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &json!({"filename": filename, "size": data.len(), "sha256": ""}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert!(body["upload_id"].is_string());
+}
+
+#[tokio::test]
+async fn test_upload_rejects_non_utf8_pkginfo() {
+    let pkginfo = b"pkgname = mojibake\npkgver = 1.0.0-1\narch = x86_64\npkgdesc = caf\xe9\n";
+    let data = common::compress_tar(&[(".PKGINFO", pkginfo)]);
+    let filename = "mojibake-1.0.0-1-x86_64.pkg.tar.zst";
+
+    let app = setup_test_app().await;
+    let (status, body) = upload_package(&app, filename, &data).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        body["error"]
+            .as_str()
+            .unwrap()
+            .contains("PKGINFO is not valid UTF-8"),
+        "{body}"
+    );
+
+    let mut config = common::test_config();
+    config.storage.lossy_pkginfo = true;
+    let (app, _storage) = common::setup_test_app_with_config(config).await;
+    let (status, body) = upload_package(&app, filename, &data).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    assert_eq!(body["name"], "mojibake");
+}
+
+/// Initiate a single-chunk upload and send its chunk, returning the upload
+/// ID and the body for completing it.
+async fn stage_upload(
+    app: &axum::Router,
+    filename: &str,
+    data: &[u8],
+) -> (String, serde_json::Value) {
+    let (status, init) = send_json(
+        app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &json!({"filename": filename, "size": data.len(), "chunk_size": data.len()}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let upload_id = init["upload_id"].as_str().unwrap().to_owned();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/packages/upload/{upload_id}/chunks/1"))
+                .header("Content-Type", "application/octet-stream")
+                .body(Body::from(data.to_vec()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let chunk = body_json(response).await;
+
+    let complete = json!({"chunks": [{"chunk_number": 1, "checksum": chunk["checksum"]}]});
+    (upload_id, complete)
+}
+
+#[tokio::test]
+async fn test_failed_completion_can_be_retried() {
+    let app = setup_test_app().await;
+    let filename = "retried-1.0.0-1-x86_64.pkg.tar.zst";
+
+    let data = create_test_package("retried", "1.0.0-1", "x86_64");
+    let (status, _) = upload_package(&app, filename, &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let mut changed = create_test_package("retried", "1.0.0-1", "x86_64");
+    changed.extend_from_slice(&create_test_package("other", "1.0.0-1", "x86_64"));
+    let (upload_id, complete) = stage_upload(&app, filename, &changed).await;
+    let complete_uri = format!("/api/packages/upload/{upload_id}/complete");
+
+    let (status, _) = send_json(&app, "POST", &complete_uri, &complete).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // The session survives the failure
+    let response = send(&app, "GET", &format!("/api/packages/upload/{upload_id}")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Once the conflict is gone, completing again succeeds without re-sending
+    let response = send(
+        &app,
+        "DELETE",
+        "/api/packages/retried-1.0.0-1-x86_64?repo=sw1nn",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let (status, body) = send_json(&app, "POST", &complete_uri, &complete).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    assert_eq!(body["name"], "retried");
+}
+
+#[tokio::test]
+async fn test_invalid_package_discards_session() {
+    let filename = "garbage-1.0.0-1-x86_64.pkg.tar.zst";
+    let data = zstd::encode_all(&b"not a tar archive"[..], 0).unwrap();
+
+    let app = setup_test_app().await;
+    let (upload_id, complete) = stage_upload(&app, filename, &data).await;
+    let complete_uri = format!("/api/packages/upload/{upload_id}/complete");
+    let (status, _) = send_json(&app, "POST", &complete_uri, &complete).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let response = send(&app, "GET", &format!("/api/packages/upload/{upload_id}")).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Unless configured to keep it
+    let mut config = common::test_config();
+    config.server.keep_invalid_uploads = true;
+    let (app, _storage) = common::setup_test_app_with_config(config).await;
+    let (upload_id, complete) = stage_upload(&app, filename, &data).await;
+    let complete_uri = format!("/api/packages/upload/{upload_id}/complete");
+    let (status, _) = send_json(&app, "POST", &complete_uri, &complete).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let response = send(&app, "GET", &format!("/api/packages/upload/{upload_id}")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_rehydrated_session_is_resumable() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+
+    let store = UploadSessionStore::new(temp_dir.path().to_path_buf());
+    let session = store
+        .create_session(boundary_session(1024, 256))
+        .await
+        .unwrap();
+    let upload_id = session.upload_id.clone();
+    store.store_chunk(&upload_id, 1, &[1u8; 256]).await.unwrap();
+    store.store_chunk(&upload_id, 2, &[2u8; 256]).await.unwrap();
+    // A chunk cut short by a crash mid-write doesn't count as received
+    std::fs::write(store.chunk_path(&upload_id, 3).unwrap(), [3u8; 100]).unwrap();
+    let expired = store
+        .create_session(
+            UploadSession::builder()
+                .filename("expired-1.0.0-1-x86_64.pkg.tar.zst")
+                .file_size(10)
+                .repo("sw1nn")
+                .arch("x86_64")
+                .expiration_secs(-1)
+                .build(),
+        )
+        .await
+        .unwrap();
+    drop(store);
+
+    let store = UploadSessionStore::new(temp_dir.path().to_path_buf());
+    assert_eq!(store.rehydrate().await.unwrap(), 1);
+
+    let session = store.get_session(&upload_id).await.unwrap();
+    assert_eq!(session.missing_chunks(), vec![3, 4]);
+    assert!(store.get_session(&expired.upload_id).await.is_err());
+    assert!(!store.upload_dir(&expired.upload_id).unwrap().exists());
+
+    // The upload picks up where it left off
+    store.store_chunk(&upload_id, 3, &[3u8; 256]).await.unwrap();
+    store.store_chunk(&upload_id, 4, &[4u8; 256]).await.unwrap();
+    let assembled = store.assemble_chunks(&upload_id).await.unwrap();
+    let data = std::fs::read(assembled).unwrap();
+    assert_eq!(data.len(), 1024);
+    assert_eq!(&data[256..260], &[2u8; 4]);
+
+    // Rehydrated sessions survive the startup purge
+    assert_eq!(store.purge_orphans().await.unwrap(), 0);
+    assert!(store.upload_dir(&upload_id).unwrap().exists());
+}
+
+fn short_lived_session(expiration_secs: i64) -> UploadSession {
+    UploadSession::builder()
+        .filename("shortlived-1.0.0-1-x86_64.pkg.tar.zst")
+        .file_size(10)
+        .repo("sw1nn")
+        .arch("x86_64")
+        .expiration_secs(expiration_secs)
+        .build()
+}
+
+#[tokio::test]
+async fn test_cleanup_expired_removes_session_dirs() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let store = UploadSessionStore::new(temp_dir.path().to_path_buf());
+    let loaded = store.create_session(short_lived_session(1)).await.unwrap();
+    let live = store
+        .create_session(short_lived_session(3600))
+        .await
+        .unwrap();
+
+    // A stale session left on disk that this store never loaded
+    let other = UploadSessionStore::new(temp_dir.path().to_path_buf());
+    let unloaded = other.create_session(short_lived_session(1)).await.unwrap();
+
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let mut expired = store.cleanup_expired().await.unwrap();
+    expired.sort();
+
+    let mut expected = vec![loaded.upload_id.clone(), unloaded.upload_id.clone()];
+    expected.sort();
+    assert_eq!(expired, expected);
+    assert!(!store.upload_dir(&loaded.upload_id).unwrap().exists());
+    assert!(!store.upload_dir(&unloaded.upload_id).unwrap().exists());
+    assert!(store.upload_dir(&live.upload_id).unwrap().exists());
+    assert_eq!(store.session_count().await, 1);
+}
+
+#[tokio::test]
+async fn test_cleanup_task_stops_on_shutdown() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let store = UploadSessionStore::new(temp_dir.path().to_path_buf());
+    let (shutdown, shutdown_rx) = tokio::sync::watch::channel(false);
+
+    let task = sw1nn_pkg_repo::upload::spawn_cleanup_task(store, 3600, shutdown_rx);
+    shutdown.send(true).unwrap();
+
+    tokio::time::timeout(std::time::Duration::from_secs(5), task)
+        .await
+        .expect("cleanup task should stop on shutdown")
+        .unwrap();
+}
+
+async fn post_chunk(app: &axum::Router, upload_id: &str, chunk: u32, len: usize) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/packages/upload/{upload_id}/chunks/{chunk}"))
+                .header("Content-Type", "application/octet-stream")
+                .body(Body::from(vec![0u8; len]))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_upload_status_reports_missing_chunks() {
+    let app = setup_test_app().await;
+
+    let (status, init) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &json!({
+            "filename": "resume-pkg-1.0.0-1-x86_64.pkg.tar.zst",
+            "size": 4000,
+            "chunk_size": 1000,
+            "has_signature": false
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let upload_id = init["upload_id"].as_str().unwrap();
+
+    post_chunk(&app, upload_id, 3, 1000).await;
+    post_chunk(&app, upload_id, 1, 1000).await;
+
+    let response = send(
+        &app,
+        "GET",
+        &format!("/api/packages/upload/{upload_id}/status"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let status = body_json(response).await;
+    assert_eq!(status["upload_id"], upload_id);
+    assert_eq!(status["size"], 4000);
+    assert_eq!(status["chunk_size"], 1000);
+    assert_eq!(status["total_chunks"], 4);
+    assert_eq!(status["uploaded_chunks"], json!([1, 3]));
+    assert_eq!(status["missing_chunks"], json!([2, 4]));
+    assert_eq!(status["expires_at"], init["expires_at"]);
+
+    let response = send(
+        &app,
+        "GET",
+        &format!("/api/packages/upload/{}/status", uuid::Uuid::new_v4()),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_upload_status_of_expired_session_is_gone() {
+    let app = setup_test_app().await;
+
+    let (status, init) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &json!({
+            "filename": "late-pkg-1.0.0-1-x86_64.pkg.tar.zst",
+            "size": 1000,
+            "has_signature": false,
+            "expiration_secs": 1
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let upload_id = init["upload_id"].as_str().unwrap();
+
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+
+    let response = send(
+        &app,
+        "GET",
+        &format!("/api/packages/upload/{upload_id}/status"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::GONE);
+}
+
+#[tokio::test]
+async fn test_filename_must_match_pkginfo() {
+    let data = create_test_package("bar", "1.0.0-1", "x86_64");
+
+    let (app, storage) = setup_test_app_with_storage().await;
+    let (status, error) = upload_package(&app, "foo-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error["code"], "invalid_package");
+    assert!(
+        error["error"]
+            .as_str()
+            .unwrap()
+            .contains("bar-1.0.0-1-x86_64.pkg.tar.zst"),
+        "{error}"
+    );
+    assert!(storage.list_packages("sw1nn").await.unwrap().is_empty());
+
+    // Only logged when verification is off; stored under the .PKGINFO name
+    let mut config = common::test_config();
+    config.storage.verify_filename = false;
+    let (app, _storage) = common::setup_test_app_with_config(config).await;
+    let (status, package) = upload_package(&app, "foo-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED, "{package}");
+    assert_eq!(package["name"], "bar");
+    assert_eq!(package["filename"], "bar-1.0.0-1-x86_64.pkg.tar.zst");
+}
@@ -0,0 +1,242 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{
+    seed_package, send_json, setup_test_app_with_config, setup_test_app_with_storage, test_config,
+};
+use serde_json::json;
+use std::sync::Arc;
+use sw1nn_pkg_repo::config::CleanupPolicy;
+use sw1nn_pkg_repo::storage::{PackageStore, cleanup_old_versions};
+
+/// Seed four versions of which the retention policy deletes only `1.0.0-1`.
+async fn seed_versions(storage: &Arc<dyn PackageStore>, repo: &str, name: &str, arch: &str) {
+    for version in ["1.0.0-1", "1.1.0-1", "1.2.0-1", "1.2.1-1"] {
+        seed_package(storage, repo, name, version, arch).await;
+    }
+}
+
+#[tokio::test]
+async fn cleanup_all_covers_every_repo_and_arch() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    seed_versions(&storage, "sw1nn", "foo", "x86_64").await;
+    seed_versions(&storage, "sw1nn", "bar", "aarch64").await;
+    seed_versions(&storage, "other", "baz", "x86_64").await;
+
+    let (status, body) = send_json(&app, "POST", "/api/admin/cleanup-all", &json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["dry_run"], false);
+    assert_eq!(body["total"]["packages_processed"], 3);
+    assert_eq!(body["total"]["versions_deleted"], 3);
+
+    let mut breakdown: Vec<(String, String, u64)> = body["repos"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| {
+            (
+                r["repo"].as_str().unwrap().to_owned(),
+                r["arch"].as_str().unwrap().to_owned(),
+                r["versions_deleted"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    breakdown.sort();
+    assert_eq!(
+        breakdown,
+        vec![
+            ("other".to_owned(), "x86_64".to_owned(), 1),
+            ("sw1nn".to_owned(), "aarch64".to_owned(), 1),
+            ("sw1nn".to_owned(), "x86_64".to_owned(), 1),
+        ]
+    );
+
+    assert_eq!(storage.list_packages("sw1nn").await.unwrap().len(), 6);
+    assert_eq!(storage.list_packages("other").await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn cleanup_all_dry_run_deletes_nothing() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    seed_versions(&storage, "sw1nn", "foo", "x86_64").await;
+    seed_versions(&storage, "other", "baz", "aarch64").await;
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/api/admin/cleanup-all",
+        &json!({"dry_run": true}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["dry_run"], true);
+    assert_eq!(body["total"]["versions_deleted"], 2);
+    assert_eq!(
+        body["total"]["details"][0]["deleted_versions"],
+        json!(["1.0.0-1"])
+    );
+
+    assert_eq!(storage.list_packages("sw1nn").await.unwrap().len(), 4);
+    assert_eq!(storage.list_packages("other").await.unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn cleanup_all_counts_any_packages_once() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    seed_versions(&storage, "sw1nn", "font", "any").await;
+    seed_package(&storage, "sw1nn", "tool", "1.0.0-1", "x86_64").await;
+    seed_package(&storage, "sw1nn", "armtool", "1.0.0-1", "aarch64").await;
+
+    for dry_run in [true, false] {
+        let (status, body) = send_json(
+            &app,
+            "POST",
+            "/api/admin/cleanup-all",
+            &json!({"dry_run": dry_run}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        // font, tool and armtool, though nothing of the last two is deleted
+        assert_eq!(body["total"]["packages_processed"], 3, "{body}");
+        assert_eq!(body["total"]["versions_deleted"], 1, "{body}");
+        assert_eq!(body["total"]["details"].as_array().unwrap().len(), 1);
+    }
+
+    assert_eq!(storage.list_packages("sw1nn").await.unwrap().len(), 5);
+}
+
+#[tokio::test]
+async fn cleanup_ranks_epoch_above_pkgver() {
+    let (_app, storage) = setup_test_app_with_storage().await;
+    for version in ["1.8.0-1", "1.9.0-1", "1.9.9-1", "2:1.0.0-1"] {
+        seed_package(&storage, "sw1nn", "foo", version, "x86_64").await;
+    }
+
+    let policy = CleanupPolicy::default();
+    let deleted = cleanup_old_versions(storage.as_ref(), "foo", "sw1nn", "x86_64", &policy)
+        .await
+        .unwrap();
+    let mut deleted: Vec<String> = deleted.into_iter().map(|p| p.version).collect();
+    deleted.sort();
+
+    // 2:1.0.0-1 is current despite its lower pkgver, and no other version
+    // shares its epoch and minor
+    assert_eq!(deleted, ["1.8.0-1", "1.9.0-1", "1.9.9-1"]);
+    let remaining: Vec<String> = storage
+        .list_packages("sw1nn")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|p| p.version)
+        .collect();
+    assert_eq!(remaining, ["2:1.0.0-1"]);
+}
+
+#[tokio::test]
+async fn cleanup_keeps_epoch_versions_of_distinct_pkgver() {
+    let (_app, storage) = setup_test_app_with_storage().await;
+    // Same pkgver in different epochs isn't a pkgrel duplicate
+    for version in ["1.0.0-1", "1:1.0.0-1"] {
+        seed_package(&storage, "sw1nn", "foo", version, "x86_64").await;
+    }
+
+    let policy = CleanupPolicy::default();
+    let deleted = cleanup_old_versions(storage.as_ref(), "foo", "sw1nn", "x86_64", &policy)
+        .await
+        .unwrap();
+    let deleted: Vec<String> = deleted.into_iter().map(|p| p.version).collect();
+    assert_eq!(deleted, ["1.0.0-1"]);
+}
+
+#[tokio::test]
+async fn cleanup_orders_git_versions_with_vercmp() {
+    let (_app, storage) = setup_test_app_with_storage().await;
+    for version in [
+        "1.0.0.r3.g0a1b2c-1",
+        "1.0.0.r12.g3d4e5f-1",
+        "1.0.0.r5.g6a7b8c-1",
+        "r2.gdeadbe-1",
+    ] {
+        seed_package(&storage, "sw1nn", "foo-git", version, "x86_64").await;
+    }
+
+    let policy = CleanupPolicy::default();
+    let deleted = cleanup_old_versions(storage.as_ref(), "foo-git", "sw1nn", "x86_64", &policy)
+        .await
+        .unwrap();
+    let deleted: Vec<String> = deleted.into_iter().map(|p| p.version).collect();
+
+    // r12 is current and r5 the latest of the same minor; a pkgver without
+    // a leading number is left alone
+    assert_eq!(deleted, ["1.0.0.r3.g0a1b2c-1"]);
+}
+
+#[tokio::test]
+async fn cleanup_keep_latest_ignores_minor_grouping() {
+    let (_app, storage) = setup_test_app_with_storage().await;
+    for version in [
+        "1.0.0-1", "1.1.0-1", "1.2.0-1", "1.3.0-1", "1.3.1-1", "1.4.0-1", "2.0.0-1", "2.0.0-2",
+    ] {
+        seed_package(&storage, "sw1nn", "foo", version, "x86_64").await;
+    }
+
+    let policy = CleanupPolicy {
+        keep_latest: 5,
+        keep_per_minor: 0,
+        keep_previous_minors: 0,
+    };
+    let deleted = cleanup_old_versions(storage.as_ref(), "foo", "sw1nn", "x86_64", &policy)
+        .await
+        .unwrap();
+    let mut deleted: Vec<String> = deleted.into_iter().map(|p| p.version).collect();
+    deleted.sort();
+
+    // Superseded pkgrels still go; the five newest pkgvers stay across
+    // minors and majors
+    assert_eq!(deleted, ["1.0.0-1", "1.1.0-1", "2.0.0-1"]);
+}
+
+#[tokio::test]
+async fn cleanup_endpoint_takes_policy_from_request() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    for version in ["1.0.0-1", "1.1.0-1", "1.2.0-1", "1.2.1-1"] {
+        seed_package(&storage, "sw1nn", "foo", version, "x86_64").await;
+    }
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/api/packages/cleanup",
+        &json!({ "policy": { "keep_latest": 3 } }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    // Unspecified fields keep their defaults, so 1.2.0-1 is also kept as
+    // the latest other version of the current minor
+    assert_eq!(body["details"][0]["deleted_versions"], json!(["1.0.0-1"]));
+    assert_eq!(storage.list_packages("sw1nn").await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn cleanup_policy_from_config_applies_by_default() {
+    let mut config = test_config();
+    config.storage.cleanup_policy = CleanupPolicy {
+        keep_latest: 1,
+        keep_per_minor: 1,
+        keep_previous_minors: 0,
+    };
+    let (app, storage) = setup_test_app_with_config(config).await;
+    seed_versions(&storage, "sw1nn", "foo", "x86_64").await;
+
+    let (status, body) = send_json(&app, "POST", "/api/packages/cleanup", &json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["versions_deleted"], 3);
+    let remaining: Vec<String> = storage
+        .list_packages("sw1nn")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|p| p.version)
+        .collect();
+    assert_eq!(remaining, ["1.2.1-1"]);
+}
@@ -3,18 +3,27 @@
 #![allow(dead_code)]
 
 use axum::Router;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::response::Response;
 use std::io::Write;
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 use std::time::Duration;
-use sw1nn_pkg_repo::api::{AppState, create_api_router};
+use sw1nn_pkg_repo::admission::{self, ConcurrencyLimits, RateLimiter};
+use sw1nn_pkg_repo::api::{AppState, DbOptions, create_api_router};
+use sw1nn_pkg_repo::auth::{Allowlist, GitHubClient};
 use sw1nn_pkg_repo::config::Config;
 use sw1nn_pkg_repo::db_actor::DbUpdateActor;
+use sw1nn_pkg_repo::downloads::DownloadCounter;
+use sw1nn_pkg_repo::events::EventLog;
+use sw1nn_pkg_repo::receipts::ReceiptStore;
 use sw1nn_pkg_repo::repo::serve_file;
-use sw1nn_pkg_repo::storage::Storage;
+use sw1nn_pkg_repo::storage::{FsStore, PackageStore};
 use sw1nn_pkg_repo::upload::UploadSessionStore;
 use tar::{Builder, Header};
 use tempfile::TempDir;
-use tower_http::cors::CorsLayer;
+use tower::util::ServiceExt;
 use tower_http::trace::TraceLayer;
 use utoipa_rapidoc::RapiDoc;
 use zstd::stream::write::Encoder;
@@ -24,9 +33,8 @@ pub async fn setup_test_app() -> Router {
     router
 }
 
-/// Build the test app and also return the backing [`Storage`] so tests can seed
-/// packages directly without going through the upload API.
-pub async fn setup_test_app_with_storage() -> (Router, Arc<Storage>) {
+/// Build a default test config rooted in a fresh temporary data directory.
+pub fn test_config() -> Config {
     // Create temporary directory for test data
     let temp_dir = TempDir::new().unwrap();
     let temp_path = temp_dir.path().to_path_buf();
@@ -35,25 +43,65 @@ pub async fn setup_test_app_with_storage() -> (Router, Arc<Storage>) {
     std::mem::forget(temp_dir);
 
     let mut config = Config::default();
-    config.storage.data_path = temp_path.clone();
+    config.storage.data_path = temp_path;
     config.storage.auto_cleanup_enabled = false; // Disable auto-cleanup for tests
+    config
+}
+
+/// Build the test app and also return the backing [`PackageStore`] so tests can seed
+/// packages directly without going through the upload API.
+pub async fn setup_test_app_with_storage() -> (Router, Arc<dyn PackageStore>) {
+    setup_test_app_with_config(test_config()).await
+}
+
+pub async fn setup_test_app_with_auth(auth: sw1nn_pkg_repo::config::AuthConfig) -> Router {
+    let mut config = test_config();
+    config.auth = Some(auth);
+    let (router, _storage) = setup_test_app_with_config(config).await;
+    router
+}
+
+/// Build the test app from an explicit config (see [`test_config`]).
+pub async fn setup_test_app_with_config(config: Config) -> (Router, Arc<dyn PackageStore>) {
+    setup_test_app_with_readiness(config, Arc::new(AtomicBool::new(true))).await
+}
 
-    let storage = Arc::new(Storage::new(&config.storage.data_path));
-    let upload_store = UploadSessionStore::new(temp_path);
+/// Build the test app with a readiness flag the test controls, for exercising
+/// warm-up behaviour.
+pub async fn setup_test_app_with_readiness(
+    config: Config,
+    ready: Arc<AtomicBool>,
+) -> (Router, Arc<dyn PackageStore>) {
+    let storage: Arc<dyn PackageStore> = Arc::new(FsStore::from_config(&config.storage));
+    let upload_store = UploadSessionStore::new(config.storage.data_path.clone())
+        .with_max_inflight_bytes(config.server.max_total_inflight_bytes.map(|b| b.as_u64()));
 
     // Create database update actor with short debounce for tests
     let (db_actor, db_update_handle) =
         DbUpdateActor::with_debounce(Arc::clone(&storage), Duration::from_millis(100));
+    let db_actor = db_actor.with_db_options(DbOptions::from(&config.storage));
 
     // Spawn actor task (will run for duration of test)
     tokio::spawn(db_actor.run());
 
+    let allowlist = Allowlist::from_config(config.auth.as_ref());
+    if let Some(auth_config) = &config.auth {
+        allowlist.spawn_reload_task(auth_config.allowed_users_reload_secs);
+    }
+
     let state = Arc::new(AppState {
         storage: Arc::clone(&storage),
         config: config.clone(),
         upload_store,
         db_update: db_update_handle,
-        http_client: reqwest::Client::new(),
+        github: GitHubClient::from_config(config.auth.as_ref()),
+        allowlist,
+        ready,
+        events: EventLog::new(config.server.event_log_capacity),
+        limits: ConcurrencyLimits::from_config(&config.server),
+        rate_limiter: RateLimiter::from_config(&config.server),
+        receipts: ReceiptStore::new(config.storage.data_path.clone()),
+        downloads: DownloadCounter::new(config.storage.data_path.clone()),
     });
 
     // Build API routes
@@ -65,6 +113,15 @@ pub async fn setup_test_app_with_storage() -> (Router, Arc<Storage>) {
             "/{repo}/os/{arch}/{filename}",
             axum::routing::get(serve_file),
         )
+        .layer(axum::middleware::from_fn_with_state(
+            state.limits.clone(),
+            admission::per_ip_limit_layer,
+        ))
+        .with_state(state.clone());
+
+    // Build health check route
+    let health_routes = Router::new()
+        .route("/health", axum::routing::get(sw1nn_pkg_repo::api::health))
         .with_state(state.clone());
 
     // Build documentation routes
@@ -75,76 +132,58 @@ pub async fn setup_test_app_with_storage() -> (Router, Arc<Storage>) {
     let router = Router::new()
         .nest("/api", api_router)
         .merge(repo_routes)
+        .merge(health_routes)
         .merge(doc_routes)
-        .layer(CorsLayer::permissive())
+        .layer(axum::middleware::from_fn_with_state(
+            state.limits.clone(),
+            admission::concurrency_limit_layer,
+        ))
+        .layer(sw1nn_pkg_repo::cors_layer(config.server.cors.as_ref()))
         .layer(TraceLayer::new_for_http());
 
     (router, storage)
 }
 
-pub async fn setup_test_app_with_auth(auth: sw1nn_pkg_repo::config::AuthConfig) -> Router {
-    let temp_dir = TempDir::new().unwrap();
-    let temp_path = temp_dir.path().to_path_buf();
-    std::mem::forget(temp_dir);
-
-    let mut config = Config::default();
-    config.storage.data_path = temp_path.clone();
-    config.storage.auto_cleanup_enabled = false;
-    config.auth = Some(auth);
-
-    let storage = Arc::new(Storage::new(&config.storage.data_path));
-    let upload_store = UploadSessionStore::new(temp_path);
-
-    let (db_actor, db_update_handle) =
-        DbUpdateActor::with_debounce(Arc::clone(&storage), Duration::from_millis(100));
-    tokio::spawn(db_actor.run());
-
-    let state = Arc::new(AppState {
-        storage,
-        config: config.clone(),
-        upload_store,
-        db_update: db_update_handle,
-        http_client: reqwest::Client::new(),
-    });
-
-    let (api_router, api_doc) = create_api_router(state.clone()).split_for_parts();
-    let repo_routes = Router::new()
-        .route(
-            "/{repo}/os/{arch}/{filename}",
-            axum::routing::get(serve_file),
-        )
-        .with_state(state.clone());
-    let doc_routes = Router::new()
-        .merge(RapiDoc::with_openapi("/api-docs/openapi.json", api_doc).path("/api-docs"));
-
-    Router::new()
-        .nest("/api", api_router)
-        .merge(repo_routes)
-        .merge(doc_routes)
-        .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
-}
-
 /// Create a test package with the given name, version, and architecture
 pub fn create_test_package(pkgname: &str, pkgver: &str, arch: &str) -> Vec<u8> {
+    create_test_package_with_entries(pkgname, pkgver, arch, &[])
+}
+
+/// Create a test package that additionally contains the given archive entries
+/// (path, contents) after the `.PKGINFO`.
+pub fn create_test_package_with_entries(
+    pkgname: &str,
+    pkgver: &str,
+    arch: &str,
+    entries: &[(&str, &[u8])],
+) -> Vec<u8> {
     // Create .PKGINFO content
     let pkginfo_content = format!(
         "pkgname = {}\npkgver = {}\narch = {}\n",
         pkgname, pkgver, arch
     );
 
+    let mut all_entries: Vec<(&str, &[u8])> = vec![(".PKGINFO", pkginfo_content.as_bytes())];
+    all_entries.extend_from_slice(entries);
+
+    compress_tar(&all_entries)
+}
+
+/// Build a zstd-compressed tar archive from (path, contents) entries.
+pub fn compress_tar(entries: &[(&str, &[u8])]) -> Vec<u8> {
     // Create a tar archive in memory
     let mut tar_data = Vec::new();
     {
         let mut tar = Builder::new(&mut tar_data);
 
-        // Add .PKGINFO file
-        let mut header = Header::new_gnu();
-        header.set_path(".PKGINFO").unwrap();
-        header.set_size(pkginfo_content.len() as u64);
-        header.set_mode(0o644);
-        header.set_cksum();
-        tar.append(&header, pkginfo_content.as_bytes()).unwrap();
+        for (path, contents) in entries {
+            let mut header = Header::new_gnu();
+            header.set_path(path).unwrap();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append(&header, *contents).unwrap();
+        }
 
         tar.finish().unwrap();
     }
@@ -160,10 +199,147 @@ pub fn create_test_package(pkgname: &str, pkgver: &str, arch: &str) -> Vec<u8> {
     compressed
 }
 
+/// Upload raw package bytes through the chunked upload API in a single chunk,
+/// returning the completion status and JSON body.
+pub async fn upload_package(
+    app: &Router,
+    filename: &str,
+    data: &[u8],
+) -> (StatusCode, serde_json::Value) {
+    upload_package_with_signature(app, filename, data, None).await
+}
+
+/// Like [`upload_package`], also uploading `signature` as the detached
+/// signature when given.
+pub async fn upload_package_with_signature(
+    app: &Router,
+    filename: &str,
+    data: &[u8],
+    signature: Option<&[u8]>,
+) -> (StatusCode, serde_json::Value) {
+    upload_package_to_repo(app, None, filename, data, signature).await
+}
+
+/// Like [`upload_package_with_signature`], into `repo` instead of the
+/// default repo when given.
+pub async fn upload_package_to_repo(
+    app: &Router,
+    repo: Option<&str>,
+    filename: &str,
+    data: &[u8],
+    signature: Option<&[u8]>,
+) -> (StatusCode, serde_json::Value) {
+    let init_body = serde_json::json!({
+        "filename": filename,
+        "size": data.len(),
+        "chunk_size": data.len(),
+        "repo": repo,
+        "has_signature": signature.is_some()
+    });
+    let (status, init) = send_json(app, "POST", "/api/packages/upload/initiate", &init_body).await;
+    if status != StatusCode::CREATED {
+        return (status, init);
+    }
+    let upload_id = init["upload_id"].as_str().unwrap().to_owned();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/packages/upload/{upload_id}/chunks/1"))
+                .header("Content-Type", "application/octet-stream")
+                .body(Body::from(data.to_vec()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let chunk = body_json(response).await;
+
+    if let Some(signature) = signature {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/api/packages/upload/{upload_id}/signature"))
+                    .header("Content-Type", "application/octet-stream")
+                    .body(Body::from(signature.to_vec()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let complete_body = serde_json::json!({
+        "chunks": [{"chunk_number": 1, "checksum": chunk["checksum"]}]
+    });
+    send_json(
+        app,
+        "POST",
+        &format!("/api/packages/upload/{upload_id}/complete"),
+        &complete_body,
+    )
+    .await
+}
+
+/// Send a JSON request and return the status and parsed JSON body
+/// (`Value::Null` for an empty or non-JSON body).
+pub async fn send_json(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    body: &serde_json::Value,
+) -> (StatusCode, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("Content-Type", "application/json")
+                .body(Body::from(serde_json::to_vec(body).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    (status, body_json(response).await)
+}
+
+/// Send a bodyless request and return the raw response.
+pub async fn send(app: &Router, method: &str, uri: &str) -> Response {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+/// Collect a response body into bytes.
+pub async fn body_bytes(response: Response) -> Vec<u8> {
+    axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap()
+        .to_vec()
+}
+
+/// Collect a response body and parse it as JSON (`Value::Null` if not JSON).
+pub async fn body_json(response: Response) -> serde_json::Value {
+    serde_json::from_slice(&body_bytes(response).await).unwrap_or(serde_json::Value::Null)
+}
+
 /// Seed a package (file + metadata) directly into storage and return both the
 /// raw package bytes and the filename it was stored under.
 pub async fn seed_package(
-    storage: &Storage,
+    storage: &Arc<dyn PackageStore>,
     repo: &str,
     name: &str,
     version: &str,
@@ -182,7 +358,46 @@ pub async fn seed_package(
         sha256: String::new(),
         size: data.len() as u64,
         created_at: chrono::Utc::now(),
+        signed: false,
+        signature_verified: false,
+        license: Vec::new(),
     };
     storage.store_package(&package, &data).await.unwrap();
     (data, filename)
 }
+
+/// Wait (up to ~5s) for the db actor to publish `{repo}.db` for the given
+/// repo/arch, then return the `pkgname-pkgver` directory entries it contains.
+pub async fn wait_for_db_entries(
+    storage: &Arc<dyn PackageStore>,
+    repo: &str,
+    arch: &str,
+) -> Vec<String> {
+    use flate2::read::GzDecoder;
+
+    let db_link = storage
+        .db_dir(repo, arch)
+        .unwrap()
+        .join(format!("{repo}.db"));
+    for _ in 0..50 {
+        if db_link.exists() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let file = std::fs::File::open(&db_link).expect("database was not generated");
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    let mut entries: Vec<String> = archive
+        .entries()
+        .unwrap()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            let path = entry.path().unwrap().into_owned();
+            path.parent().unwrap().to_string_lossy().into_owned()
+        })
+        .collect();
+    entries.sort();
+    entries.dedup();
+    entries
+}
@@ -0,0 +1,238 @@
+mod common;
+
+use axum::body::{Body, Bytes};
+use axum::extract::ConnectInfo;
+use axum::http::{Request, StatusCode};
+use common::{body_bytes, seed_package, send, setup_test_app_with_config, test_config};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tower::util::ServiceExt;
+
+const INITIATE_BODY: &str = r#"{"filename": "held-1.0.0-1-x86_64.pkg.tar.zst", "size": 10, "chunk_size": 10, "has_signature": false}"#;
+
+/// Initiate-upload request from `client`, plus a sender that releases its
+/// body. Until then the request stays in flight.
+fn held_initiate_request(client: &str) -> (Request<Body>, oneshot::Sender<()>) {
+    let (release, released) = oneshot::channel::<()>();
+    let body = futures_util::stream::once(async move {
+        let _ = released.await;
+        Ok::<_, std::io::Error>(Bytes::from_static(INITIATE_BODY.as_bytes()))
+    });
+    let request = initiate_request(client, Body::from_stream(body));
+    (request, release)
+}
+
+fn initiate_request(client: &str, body: Body) -> Request<Body> {
+    let mut request = Request::builder()
+        .method("POST")
+        .uri("/api/packages/upload/initiate")
+        .header("Content-Type", "application/json")
+        .body(body)
+        .unwrap();
+    let addr: SocketAddr = client.parse().unwrap();
+    request.extensions_mut().insert(ConnectInfo(addr));
+    request
+}
+
+#[tokio::test]
+async fn requests_over_global_cap_get_503() {
+    let mut config = test_config();
+    config.server.max_concurrent_requests = Some(1);
+    let (app, _storage) = setup_test_app_with_config(config).await;
+
+    let (request, release) = held_initiate_request("10.0.0.1:1000");
+    let in_flight = tokio::spawn(app.clone().oneshot(request));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let response = send(&app, "GET", "/api/packages").await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(response.headers().contains_key("retry-after"));
+
+    release.send(()).unwrap();
+    let response = in_flight.await.unwrap().unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    // The slot is held until the response body is sent
+    body_bytes(response).await;
+
+    let response = send(&app, "GET", "/api/packages").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn writes_over_per_ip_cap_get_503() {
+    let mut config = test_config();
+    config.server.max_concurrent_requests_per_ip = Some(1);
+    let (app, _storage) = setup_test_app_with_config(config).await;
+
+    let (request, release) = held_initiate_request("10.0.0.1:1000");
+    let in_flight = tokio::spawn(app.clone().oneshot(request));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // Same client: over its cap
+    let response = app
+        .clone()
+        .oneshot(initiate_request("10.0.0.1:1001", Body::from(INITIATE_BODY)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    // Another client is unaffected
+    let response = app
+        .clone()
+        .oneshot(initiate_request("10.0.0.2:1000", Body::from(INITIATE_BODY)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    release.send(()).unwrap();
+    let response = in_flight.await.unwrap().unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn writes_over_rate_limit_get_429() {
+    let mut config = test_config();
+    config.server.upload_rate_limit = Some(2);
+    let (app, _storage) = setup_test_app_with_config(config).await;
+
+    for _ in 0..2 {
+        let response = app
+            .clone()
+            .oneshot(initiate_request("10.0.0.1:1000", Body::from(INITIATE_BODY)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    let response = app
+        .clone()
+        .oneshot(initiate_request("10.0.0.1:1001", Body::from(INITIATE_BODY)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()["retry-after"], "30");
+
+    // Another client has its own allowance
+    let response = app
+        .clone()
+        .oneshot(initiate_request("10.0.0.2:1000", Body::from(INITIATE_BODY)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // Reads and health checks aren't limited
+    for uri in ["/api/packages", "/health"] {
+        let mut request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let addr: SocketAddr = "10.0.0.1:1002".parse().unwrap();
+        request.extensions_mut().insert(ConnectInfo(addr));
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn read_only_posts_and_auth_skip_rate_limit() {
+    let mut config = test_config();
+    config.server.upload_rate_limit = Some(1);
+    let (app, _storage) = setup_test_app_with_config(config).await;
+
+    let response = app
+        .clone()
+        .oneshot(initiate_request("10.0.0.1:1000", Body::from(INITIATE_BODY)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // The bucket is empty, but batch-info and device-flow polling still pass
+    let requests = [
+        ("/api/packages/batch-info", r#"{"names": ["held"]}"#),
+        ("/api/auth/device/token", r#"{"device_code": "abc"}"#),
+        ("/api/auth/refresh", r#"{"refresh_token": "abc"}"#),
+    ];
+    for _ in 0..3 {
+        for (uri, body) in requests {
+            let mut request = Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("Content-Type", "application/json")
+                .body(Body::from(body))
+                .unwrap();
+            let addr: SocketAddr = "10.0.0.1:1001".parse().unwrap();
+            request.extensions_mut().insert(ConnectInfo(addr));
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS, "{uri}");
+        }
+    }
+
+    let response = app
+        .clone()
+        .oneshot(initiate_request("10.0.0.1:1002", Body::from(INITIATE_BODY)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+fn download_request(client: &str, filename: &str) -> Request<Body> {
+    let mut request = Request::builder()
+        .uri(format!("/sw1nn/os/x86_64/{filename}"))
+        .body(Body::empty())
+        .unwrap();
+    let addr: SocketAddr = client.parse().unwrap();
+    request.extensions_mut().insert(ConnectInfo(addr));
+    request
+}
+
+#[tokio::test]
+async fn downloads_hold_their_slot_until_the_body_is_sent() {
+    let mut config = test_config();
+    config.server.max_concurrent_requests = Some(1);
+    let (app, storage) = setup_test_app_with_config(config).await;
+    let (_, filename) = seed_package(&storage, "sw1nn", "dlpkg", "1.0.0-1", "x86_64").await;
+
+    // The handler has returned, but the file body hasn't been streamed yet
+    let download = app
+        .clone()
+        .oneshot(download_request("10.0.0.1:1000", &filename))
+        .await
+        .unwrap();
+    assert_eq!(download.status(), StatusCode::OK);
+
+    let response = send(&app, "GET", "/api/packages").await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    body_bytes(download).await;
+    let response = send(&app, "GET", "/api/packages").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn downloads_count_toward_per_ip_cap_until_sent() {
+    let mut config = test_config();
+    config.server.max_concurrent_requests_per_ip = Some(1);
+    let (app, storage) = setup_test_app_with_config(config).await;
+    let (_, filename) = seed_package(&storage, "sw1nn", "dlpkg", "1.0.0-1", "x86_64").await;
+
+    let download = app
+        .clone()
+        .oneshot(download_request("10.0.0.1:1000", &filename))
+        .await
+        .unwrap();
+    assert_eq!(download.status(), StatusCode::OK);
+
+    let response = app
+        .clone()
+        .oneshot(download_request("10.0.0.1:1001", &filename))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    // An abandoned download frees its slot too
+    drop(download);
+    let response = app
+        .clone()
+        .oneshot(download_request("10.0.0.1:1002", &filename))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use common::{setup_test_app_with_config, test_config};
+use sw1nn_pkg_repo::config::CorsConfig;
+use tower::util::ServiceExt;
+
+#[tokio::test]
+async fn configured_origin_is_allowed_and_others_are_not() {
+    let mut config = test_config();
+    config.server.cors = Some(CorsConfig {
+        allowed_origins: vec!["https://repo-ui.example.com".to_string()],
+        allowed_methods: vec!["GET".to_string()],
+        allow_credentials: false,
+    });
+    let (app, _storage) = setup_test_app_with_config(config).await;
+
+    let request = |origin: &str| {
+        Request::builder()
+            .uri("/api/packages")
+            .header("Origin", origin)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(request("https://repo-ui.example.com"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["access-control-allow-origin"],
+        "https://repo-ui.example.com"
+    );
+
+    let response = app
+        .clone()
+        .oneshot(request("https://evil.example.com"))
+        .await
+        .unwrap();
+    assert!(
+        !response
+            .headers()
+            .contains_key("access-control-allow-origin")
+    );
+}
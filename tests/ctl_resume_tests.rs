@@ -0,0 +1,148 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use serde_json::json;
+use std::process::Output;
+use tokio::process::Command;
+use tower::util::ServiceExt;
+
+mod common;
+use common::{create_test_package, send, send_json, setup_test_app};
+
+/// Serve the test app on a local port, returning its base URL
+async fn spawn_app() -> (axum::Router, String) {
+    let app = setup_test_app().await;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let served = app.clone();
+    tokio::spawn(async move { axum::serve(listener, served).await.unwrap() });
+    (app, format!("http://{addr}"))
+}
+
+/// Run `sw1nn-pkg-ctl` against `base_url` with no stored login token,
+/// logging errors to stdout
+async fn run_ctl(base_url: &str, args: &[&str]) -> Output {
+    let home = tempfile::TempDir::new().unwrap();
+    Command::new(env!("CARGO_BIN_EXE_sw1nn-pkg-ctl"))
+        .args(["--color", "never"])
+        .args(args)
+        .env("SW1NN_REPO_URL", base_url)
+        .env("RUST_LOG", "sw1nn_pkg_ctl=error")
+        .env("HOME", home.path())
+        .env("XDG_CONFIG_HOME", home.path())
+        .output()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_resume_sends_only_missing_chunks() {
+    let (app, base_url) = spawn_app().await;
+
+    let filename = "resume-pkg-1.0.0-1-x86_64.pkg.tar.zst";
+    let data = create_test_package("resume-pkg", "1.0.0-1", "x86_64");
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join(filename);
+    std::fs::write(&path, &data).unwrap();
+
+    let chunk_size = data.len().div_ceil(3);
+    let sha256 = {
+        use sha2::Digest;
+        format!("{:x}", sha2::Sha256::digest(&data))
+    };
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/api/packages/upload/initiate",
+        &json!({
+            "filename": filename,
+            "size": data.len(),
+            "sha256": sha256,
+            "chunk_size": chunk_size,
+            "has_signature": false
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let upload_id = body["upload_id"].as_str().unwrap().to_owned();
+
+    // Simulate an interrupted upload that got the first chunk through
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/packages/upload/{upload_id}/chunks/1"))
+                .header("Content-Type", "application/octet-stream")
+                .body(Body::from(data[..chunk_size].to_vec()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let output = run_ctl(
+        &base_url,
+        &["upload", "--resume", &upload_id, path.to_str().unwrap()],
+    )
+    .await;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let response = send(&app, "GET", "/api/packages/resume-pkg").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_resume_unknown_session_fails_clearly() {
+    let (_app, base_url) = spawn_app().await;
+
+    let filename = "resume-pkg-1.0.0-1-x86_64.pkg.tar.zst";
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join(filename);
+    std::fs::write(
+        &path,
+        create_test_package("resume-pkg", "1.0.0-1", "x86_64"),
+    )
+    .unwrap();
+
+    let output = run_ctl(
+        &base_url,
+        &[
+            "upload",
+            "--resume",
+            "no-such-upload",
+            path.to_str().unwrap(),
+        ],
+    )
+    .await;
+    assert!(!output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("not found"), "{stdout}");
+    assert!(stdout.contains("without --resume"), "{stdout}");
+}
+
+#[tokio::test]
+async fn test_reupload_of_identical_package_succeeds() {
+    let (app, base_url) = spawn_app().await;
+
+    let filename = "same-pkg-1.0.0-1-x86_64.pkg.tar.zst";
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join(filename);
+    std::fs::write(&path, create_test_package("same-pkg", "1.0.0-1", "x86_64")).unwrap();
+
+    for _ in 0..2 {
+        let output = run_ctl(&base_url, &["upload", path.to_str().unwrap()]).await;
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+    }
+
+    let response = send(&app, "GET", "/api/packages/same-pkg").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
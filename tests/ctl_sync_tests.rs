@@ -0,0 +1,93 @@
+use axum::http::StatusCode;
+use std::process::Output;
+use tokio::process::Command;
+
+mod common;
+use common::{body_json, create_test_package, send, setup_test_app, upload_package};
+
+/// Serve a fresh test app on a local port, returning its base URL
+async fn spawn_app() -> (axum::Router, String) {
+    let app = setup_test_app().await;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let served = app.clone();
+    tokio::spawn(async move { axum::serve(listener, served).await.unwrap() });
+    (app, format!("http://{addr}"))
+}
+
+/// Run `sw1nn-pkg-ctl` against `base_url` with no stored login token
+async fn run_ctl(base_url: &str, args: &[&str]) -> Output {
+    let home = tempfile::TempDir::new().unwrap();
+    Command::new(env!("CARGO_BIN_EXE_sw1nn-pkg-ctl"))
+        .args(["--color", "never"])
+        .args(args)
+        .env("SW1NN_REPO_URL", base_url)
+        .env("RUST_LOG", "sw1nn_pkg_ctl=error")
+        .env("HOME", home.path())
+        .env("XDG_CONFIG_HOME", home.path())
+        .output()
+        .await
+        .unwrap()
+}
+
+async fn package_versions(app: &axum::Router, name: &str) -> Vec<String> {
+    let response = send(app, "GET", &format!("/api/packages/{name}")).await;
+    if response.status() == StatusCode::NOT_FOUND {
+        return vec![];
+    }
+    let body = body_json(response).await;
+    let mut versions: Vec<String> = body
+        .as_array()
+        .map(|packages| {
+            packages
+                .iter()
+                .map(|p| p["version"].as_str().unwrap().to_owned())
+                .collect()
+        })
+        .unwrap_or_else(|| vec![body["version"].as_str().unwrap().to_owned()]);
+    versions.sort();
+    versions
+}
+
+#[tokio::test]
+async fn test_sync_uploads_missing_packages() {
+    let (upstream, upstream_url) = spawn_app().await;
+    let (local, local_url) = spawn_app().await;
+
+    for version in ["1.0.0-1", "1.1.0-1"] {
+        let (status, _) = upload_package(
+            &upstream,
+            &format!("sync-pkg-{version}-x86_64.pkg.tar.zst"),
+            &create_test_package("sync-pkg", version, "x86_64"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+    let (status, _) = upload_package(
+        &local,
+        "sync-pkg-1.0.0-1-x86_64.pkg.tar.zst",
+        &create_test_package("sync-pkg", "1.0.0-1", "x86_64"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let output = run_ctl(&local_url, &["sync", &upstream_url, "--dry-run"]).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("sync-pkg 1.1.0-1"), "{stdout}");
+    assert!(!stdout.contains("sync-pkg 1.0.0-1"), "{stdout}");
+    assert_eq!(package_versions(&local, "sync-pkg").await, ["1.0.0-1"]);
+
+    let output = run_ctl(&local_url, &["sync", &upstream_url]).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert_eq!(
+        package_versions(&local, "sync-pkg").await,
+        ["1.0.0-1", "1.1.0-1"]
+    );
+
+    let output = run_ctl(&local_url, &["sync", &upstream_url]).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("Already in sync"), "{stdout}");
+}
@@ -6,7 +6,10 @@ use tower::Service;
 use tower::util::ServiceExt;
 
 mod common;
-use common::{create_test_package, setup_test_app};
+use common::{
+    body_json, create_test_package, send, setup_test_app, setup_test_app_with_storage,
+    upload_package, wait_for_db_entries,
+};
 use sw1nn_pkg_repo::models::Package;
 
 /// Helper to upload a package to the test repo
@@ -157,7 +160,8 @@ async fn test_delete_specific_version() {
     let body = axum::body::to_bytes(response.into_body(), usize::MAX)
         .await
         .unwrap();
-    let packages: Vec<Package> = serde_json::from_slice(&body).unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let packages: Vec<Package> = serde_json::from_value(body["items"].clone()).unwrap();
 
     assert_eq!(packages.len(), 1);
     assert_eq!(packages[0].version, "1.1.0-1");
@@ -211,7 +215,8 @@ async fn test_delete_multiple_versions() {
     let body = axum::body::to_bytes(response.into_body(), usize::MAX)
         .await
         .unwrap();
-    let packages: Vec<Package> = serde_json::from_slice(&body).unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let packages: Vec<Package> = serde_json::from_value(body["items"].clone()).unwrap();
 
     assert_eq!(packages.len(), 1);
     assert_eq!(packages[0].version, "1.2.0-1");
@@ -265,7 +270,8 @@ async fn test_delete_semver_range() {
     let body = axum::body::to_bytes(response.into_body(), usize::MAX)
         .await
         .unwrap();
-    let packages: Vec<Package> = serde_json::from_slice(&body).unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let packages: Vec<Package> = serde_json::from_value(body["items"].clone()).unwrap();
 
     assert_eq!(packages.len(), 1);
     assert_eq!(packages[0].version, "2.0.0-1");
@@ -320,7 +326,8 @@ async fn test_delete_mixed_exact_and_range() {
     let body = axum::body::to_bytes(response.into_body(), usize::MAX)
         .await
         .unwrap();
-    let packages: Vec<Package> = serde_json::from_slice(&body).unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let packages: Vec<Package> = serde_json::from_value(body["items"].clone()).unwrap();
 
     assert_eq!(packages.len(), 1);
     assert_eq!(packages[0].version, "1.1.0-1");
@@ -369,3 +376,113 @@ async fn test_delete_nonexistent_version() {
 
     assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
+
+#[tokio::test]
+async fn test_range_skips_non_semver_versions_with_warning() {
+    let mut app = setup_test_app().await;
+
+    upload_test_package(&mut app, "dated-pkg", "1.0.0-1", "x86_64").await;
+    upload_test_package(&mut app, "dated-pkg", "20250115-1", "x86_64").await;
+
+    let delete = |versions: serde_json::Value| {
+        Request::builder()
+            .method("POST")
+            .uri("/api/packages/dated-pkg/versions/delete")
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                serde_json::to_vec(&json!({ "versions": versions })).unwrap(),
+            ))
+            .unwrap()
+    };
+
+    // The range deletes the semver version and reports the dated one
+    let response = app
+        .clone()
+        .oneshot(delete(json!(["<2.0.0"])))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let response_json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(response_json["deleted_versions"], json!(["1.0.0-1"]));
+    assert_eq!(response_json["unevaluated_versions"], json!(["20250115-1"]));
+
+    // A range alone can't reach the dated version
+    let response = app
+        .clone()
+        .oneshot(delete(json!([">=0.0.0"])))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(String::from_utf8_lossy(&body).contains("20250115-1"));
+
+    // An exact spec still deletes it, without a warning
+    let response = app
+        .oneshot(delete(json!(["20250115-1", ">=0.0.0"])))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let response_json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(response_json["deleted_versions"], json!(["20250115-1"]));
+    assert!(response_json.get("unevaluated_versions").is_none());
+}
+
+#[tokio::test]
+async fn test_delete_all_versions_removes_package_from_db() {
+    let (app, storage) = setup_test_app_with_storage().await;
+
+    for (name, version) in [
+        ("retired", "1.0.0-1"),
+        ("retired", "1.1.0-1"),
+        ("retired", "2.0.0-1"),
+        ("keeper", "1.0.0-1"),
+    ] {
+        let data = create_test_package(name, version, "x86_64");
+        let (status, _) =
+            upload_package(&app, &format!("{name}-{version}-x86_64.pkg.tar.zst"), &data).await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+
+    let response = send(&app, "DELETE", "/api/packages/retired/all?arch=x86_64").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_json(response).await;
+    assert_eq!(body["deleted_count"], 3);
+    assert_eq!(
+        body["deleted_versions"],
+        json!(["1.0.0-1", "1.1.0-1", "2.0.0-1"])
+    );
+
+    let remaining: Vec<String> = storage
+        .list_packages("sw1nn")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|p| p.name)
+        .collect();
+    assert_eq!(remaining, ["keeper"]);
+
+    // Wait for the regeneration queued by the delete
+    for _ in 0..50 {
+        let response = send(&app, "GET", "/api/repos/sw1nn/os/x86_64/db-status").await;
+        if body_json(response).await["current"] == true {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(
+        wait_for_db_entries(&storage, "sw1nn", "x86_64").await,
+        vec!["keeper-1.0.0-1"]
+    );
+
+    // Nothing left to delete
+    let response = send(&app, "DELETE", "/api/packages/retired/all?arch=x86_64").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{create_test_package, setup_test_app_with_config, test_config, upload_package};
+use sw1nn_pkg_repo::config::Config;
+use sw1nn_pkg_repo::error::Error;
+use sw1nn_pkg_repo::models::Package;
+
+/// Upload a package, then store a byte-identical copy of it under a second
+/// filename (as a rebuild with a bumped pkgrel but identical content would be)
+async fn store_identical_copy(config: Config) -> (sw1nn_pkg_repo::error::Result<()>, Package) {
+    let (app, storage) = setup_test_app_with_config(config).await;
+
+    let data = create_test_package("duppkg", "1.0.0-1", "x86_64");
+    let (status, body) = upload_package(&app, "duppkg-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let original: Package = serde_json::from_value(body).unwrap();
+
+    let source = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(source.path(), &data).unwrap();
+
+    let copy = Package {
+        version: "1.0.0-2".to_string(),
+        filename: "duppkg-1.0.0-2-x86_64.pkg.tar.zst".to_string(),
+        ..original
+    };
+    let result = storage.store_package_from_path(&copy, source.path()).await;
+    (result, copy)
+}
+
+#[tokio::test]
+async fn duplicate_content_is_stored_by_default() {
+    let (result, _copy) = store_identical_copy(test_config()).await;
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn duplicate_content_rejected_when_configured() {
+    let mut config = test_config();
+    config.storage.reject_duplicate_content = true;
+
+    let (result, copy) = store_identical_copy(config).await;
+    match result {
+        Err(Error::DuplicateContent { pkgname, existing }) => {
+            assert_eq!(pkgname, copy.filename);
+            assert_eq!(existing, "duppkg-1.0.0-1-x86_64.pkg.tar.zst");
+        }
+        other => panic!("expected DuplicateContent, got {other:?}"),
+    }
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{body_json, create_test_package, send, setup_test_app, upload_package};
+
+#[tokio::test]
+async fn upload_and_delete_are_recorded() {
+    let app = setup_test_app().await;
+    let data = create_test_package("evented", "1.0.0-1", "x86_64");
+
+    let (status, _) = upload_package(&app, "evented-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let response = send(
+        &app,
+        "DELETE",
+        "/api/packages/evented-1.0.0-1-x86_64?repo=sw1nn",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = send(&app, "GET", "/api/admin/events").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let events = body_json(response).await;
+    let events = events.as_array().unwrap();
+    assert_eq!(events.len(), 2);
+
+    assert_eq!(events[0]["kind"], "upload");
+    assert_eq!(events[0]["package"], "evented");
+    assert_eq!(events[0]["version"], "1.0.0-1");
+    assert_eq!(events[0]["repo"], "sw1nn");
+    assert_eq!(events[0]["user"], "<anonymous>");
+    assert_eq!(events[1]["kind"], "delete");
+
+    let response = send(&app, "GET", "/api/admin/events?limit=1").await;
+    let events = body_json(response).await;
+    assert_eq!(events.as_array().unwrap().len(), 1);
+    assert_eq!(events[0]["kind"], "delete");
+}
+
+#[tokio::test]
+async fn failed_upload_is_recorded() {
+    let app = setup_test_app().await;
+
+    let (status, _) =
+        upload_package(&app, "broken-1.0.0-1-x86_64.pkg.tar.zst", b"not a package").await;
+    assert!(!status.is_success());
+
+    let response = send(&app, "GET", "/api/admin/events").await;
+    let events = body_json(response).await;
+    assert_eq!(events[0]["kind"], "upload_failed");
+    assert!(
+        events[0]["detail"]
+            .as_str()
+            .unwrap()
+            .starts_with("broken-1.0.0-1-x86_64.pkg.tar.zst")
+    );
+}
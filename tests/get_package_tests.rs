@@ -0,0 +1,77 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{body_json, compress_tar, create_test_package, send, setup_test_app, upload_package};
+use serde_json::json;
+
+#[tokio::test]
+async fn get_package_by_exact_version() {
+    let app = setup_test_app().await;
+    for version in ["1.0.0-1", "1.1.0-1"] {
+        let data = create_test_package("getpkg", version, "x86_64");
+        let (status, _) =
+            upload_package(&app, &format!("getpkg-{version}-x86_64.pkg.tar.zst"), &data).await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+
+    let response = send(&app, "GET", "/api/packages/getpkg?version=1.0.0-1").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let package = body_json(response).await;
+    assert_eq!(package["name"], "getpkg");
+    assert_eq!(package["version"], "1.0.0-1");
+    assert_eq!(package["filename"], "getpkg-1.0.0-1-x86_64.pkg.tar.zst");
+
+    // Without a version every stored version comes back, oldest first
+    let response = send(&app, "GET", "/api/packages/getpkg").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let versions: Vec<_> = body_json(response)
+        .await
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["version"].as_str().unwrap().to_owned())
+        .collect();
+    assert_eq!(versions, ["1.0.0-1", "1.1.0-1"]);
+}
+
+#[tokio::test]
+async fn get_package_single_match_and_missing() {
+    let app = setup_test_app().await;
+    let data = create_test_package("onlypkg", "2.0.0-1", "x86_64");
+    upload_package(&app, "onlypkg-2.0.0-1-x86_64.pkg.tar.zst", &data).await;
+
+    let response = send(&app, "GET", "/api/packages/onlypkg?arch=x86_64").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_json(response).await["version"], "2.0.0-1");
+
+    for uri in [
+        "/api/packages/onlypkg?version=9.9.9-1",
+        "/api/packages/missing",
+        "/api/packages/onlypkg?repo=other",
+    ] {
+        let response = send(&app, "GET", uri).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn get_package_includes_parsed_depends() {
+    let app = setup_test_app().await;
+    let pkginfo = "pkgname = deppkg\npkgver = 1.0.0-1\narch = x86_64\n\
+                   depend = glibc>=2.38\ndepend = bash\n";
+    let data = compress_tar(&[(".PKGINFO", pkginfo.as_bytes())]);
+    let (status, _) = upload_package(&app, "deppkg-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let response = send(&app, "GET", "/api/packages/deppkg").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let package = body_json(response).await;
+    assert_eq!(package["name"], "deppkg");
+    assert_eq!(
+        package["depends"],
+        json!([
+            {"name": "glibc", "op": ">=", "version": "2.38"},
+            {"name": "bash", "op": null, "version": null},
+        ])
+    );
+}
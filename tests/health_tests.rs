@@ -0,0 +1,68 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{body_json, send, setup_test_app_with_config, test_config};
+
+#[tokio::test]
+async fn health_ok_when_data_path_writable() {
+    let config = test_config();
+    let data_path = config.storage.data_path.clone();
+    let (app, _storage) = setup_test_app_with_config(config).await;
+
+    let response = send(&app, "GET", "/health").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_json(response).await;
+    assert_eq!(body["status"], "ok");
+    assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    assert!(body.get("error").is_none(), "{body}");
+
+    // The probe file is cleaned up
+    assert_eq!(std::fs::read_dir(&data_path).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn health_unavailable_when_data_path_missing() {
+    let mut config = test_config();
+    config.storage.data_path = config.storage.data_path.join("missing");
+    let (app, _storage) = setup_test_app_with_config(config).await;
+
+    let response = send(&app, "GET", "/health").await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body = body_json(response).await;
+    assert_eq!(body["status"], "unavailable");
+    assert!(
+        body["error"].as_str().unwrap().contains("not accessible"),
+        "{body}"
+    );
+}
+
+#[tokio::test]
+async fn health_unavailable_when_data_path_not_a_directory() {
+    let mut config = test_config();
+    let file = config.storage.data_path.join("data");
+    std::fs::write(&file, b"").unwrap();
+    config.storage.data_path = file;
+    let (app, _storage) = setup_test_app_with_config(config).await;
+
+    let response = send(&app, "GET", "/health").await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body = body_json(response).await;
+    assert!(
+        body["error"].as_str().unwrap().contains("not a directory"),
+        "{body}"
+    );
+}
+
+#[tokio::test]
+async fn health_is_documented_in_openapi() {
+    let (app, _storage) = setup_test_app_with_config(test_config()).await;
+
+    let response = send(&app, "GET", "/api-docs/openapi.json").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let doc = body_json(response).await;
+    assert!(doc["paths"]["/health"]["get"].is_object(), "{doc}");
+    assert!(
+        doc["components"]["schemas"]["HealthResponse"].is_object(),
+        "{doc}"
+    );
+}
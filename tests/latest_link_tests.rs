@@ -0,0 +1,107 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{
+    body_bytes, create_test_package, send, setup_test_app_with_config, test_config, upload_package,
+};
+use sw1nn_pkg_repo::models::Package;
+
+#[tokio::test]
+async fn latest_link_retargets_on_upload_and_delete() {
+    let mut config = test_config();
+    config.storage.maintain_latest_symlink = true;
+    let (app, storage) = setup_test_app_with_config(config).await;
+    let link = storage
+        .latest_link_path("sw1nn", "linkpkg", "x86_64")
+        .unwrap();
+
+    let old_data = create_test_package("linkpkg", "1.0.0-1", "x86_64");
+    let (status, old) = upload_package(&app, "linkpkg-1.0.0-1-x86_64.pkg.tar.zst", &old_data).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let old: Package = serde_json::from_value(old).unwrap();
+    assert_eq!(
+        std::fs::read_link(&link).unwrap(),
+        std::path::Path::new("linkpkg-1.0.0-1-x86_64.pkg.tar.zst")
+    );
+
+    let new_data = create_test_package("linkpkg", "1.1.0-1", "x86_64");
+    let (status, new) = upload_package(&app, "linkpkg-1.1.0-1-x86_64.pkg.tar.zst", &new_data).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let new: Package = serde_json::from_value(new).unwrap();
+    assert_eq!(
+        std::fs::read_link(&link).unwrap(),
+        std::path::Path::new("linkpkg-1.1.0-1-x86_64.pkg.tar.zst")
+    );
+
+    // The stable URL serves the newest build
+    let response = send(
+        &app,
+        "GET",
+        "/sw1nn/os/x86_64/linkpkg-latest-x86_64.pkg.tar.zst",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_bytes(response).await, new_data);
+
+    // Deleting the newest falls back to the previous version
+    storage.delete_package(&new).await.unwrap();
+    assert_eq!(
+        std::fs::read_link(&link).unwrap(),
+        std::path::Path::new("linkpkg-1.0.0-1-x86_64.pkg.tar.zst")
+    );
+
+    // And the link goes away with the last version
+    storage.delete_package(&old).await.unwrap();
+    assert!(link.symlink_metadata().is_err());
+}
+
+#[tokio::test]
+async fn latest_link_not_maintained_by_default() {
+    let (app, storage) = setup_test_app_with_config(test_config()).await;
+
+    let data = create_test_package("linkpkg", "1.0.0-1", "x86_64");
+    let (status, _) = upload_package(&app, "linkpkg-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let link = storage
+        .latest_link_path("sw1nn", "linkpkg", "x86_64")
+        .unwrap();
+    assert!(link.symlink_metadata().is_err());
+}
+
+#[tokio::test]
+async fn latest_link_served_uncached_with_reject_symlinks() {
+    let mut config = test_config();
+    config.storage.maintain_latest_symlink = true;
+    config.storage.reject_symlinks = true;
+    let (app, _storage) = setup_test_app_with_config(config).await;
+
+    let data = create_test_package("linkpkg", "1.0.0-1", "x86_64");
+    let (status, _) = upload_package(&app, "linkpkg-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let response = send(
+        &app,
+        "GET",
+        "/sw1nn/os/x86_64/linkpkg-latest-x86_64.pkg.tar.zst",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["cache-control"], "no-cache");
+    assert_eq!(body_bytes(response).await, data);
+
+    // The versioned file stays immutable
+    let response = send(
+        &app,
+        "GET",
+        "/sw1nn/os/x86_64/linkpkg-1.0.0-1-x86_64.pkg.tar.zst",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        response.headers()["cache-control"]
+            .to_str()
+            .unwrap()
+            .contains("immutable")
+    );
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use axum::http::{StatusCode, header};
+use common::{body_bytes, body_json, seed_package, send, setup_test_app_with_storage};
+
+#[tokio::test]
+async fn latest_version_returns_newest_as_plain_text() {
+    let (app, storage) = setup_test_app_with_storage().await;
+
+    for version in ["1.2.0-1", "1.10.0-1", "1.9.3-2", "0.15.0.r166.gae5dbc9-1"] {
+        seed_package(&storage, "sw1nn", "verpkg", version, "x86_64").await;
+    }
+    seed_package(&storage, "sw1nn", "otherpkg", "9.9.9-1", "x86_64").await;
+
+    let response = send(&app, "GET", "/api/packages/verpkg/latest-version").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/plain; charset=utf-8"
+    );
+    assert_eq!(body_bytes(response).await, "1.10.0-1".as_bytes());
+}
+
+#[tokio::test]
+async fn latest_version_honors_arch_filter() {
+    let (app, storage) = setup_test_app_with_storage().await;
+
+    seed_package(&storage, "sw1nn", "verpkg", "1.0.0-1", "x86_64").await;
+    seed_package(&storage, "sw1nn", "verpkg", "2.0.0-1", "aarch64").await;
+
+    let response = send(
+        &app,
+        "GET",
+        "/api/packages/verpkg/latest-version?arch=x86_64",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_bytes(response).await, "1.0.0-1".as_bytes());
+}
+
+#[tokio::test]
+async fn latest_version_unknown_package_is_404() {
+    let (app, _storage) = setup_test_app_with_storage().await;
+
+    let response = send(&app, "GET", "/api/packages/missing/latest-version").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn latest_returns_newest_package_by_vercmp() {
+    let (app, storage) = setup_test_app_with_storage().await;
+
+    for version in ["1.0.0-1", "2.0.0-1", "1.0.0-2"] {
+        seed_package(&storage, "sw1nn", "verpkg", version, "x86_64").await;
+    }
+
+    let response = send(&app, "GET", "/api/packages/verpkg/latest?arch=x86_64").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let package = body_json(response).await;
+    assert_eq!(package["name"], "verpkg");
+    assert_eq!(package["version"], "2.0.0-1");
+
+    let response = send(&app, "GET", "/api/packages/missing/latest").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,273 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{
+    body_json, compress_tar, seed_package, send, setup_test_app, setup_test_app_with_config,
+    test_config, upload_package,
+};
+use sw1nn_pkg_repo::api::{DEFAULT_LIST_LIMIT, backfill_licenses};
+use sw1nn_pkg_repo::models::Package;
+
+const CAP: usize = 5;
+
+async fn setup_with_packages(count: usize) -> axum::Router {
+    let mut config = test_config();
+    config.server.max_list_results = CAP;
+    let (app, storage) = setup_test_app_with_config(config).await;
+
+    for i in 0..count {
+        seed_package(
+            &storage,
+            "sw1nn",
+            &format!("pkg{i:02}"),
+            "1.0.0-1",
+            "x86_64",
+        )
+        .await;
+    }
+    app
+}
+
+fn names(body: &serde_json::Value) -> Vec<&str> {
+    body["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["name"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn list_pages_through_packages() {
+    let app = setup_with_packages(8).await;
+
+    // First page
+    let response = send(&app, "GET", "/api/packages?limit=3").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_json(response).await;
+    assert_eq!(body["total"], 8);
+    assert_eq!(names(&body), ["pkg00", "pkg01", "pkg02"]);
+
+    // Middle page
+    let response = send(&app, "GET", "/api/packages?limit=3&offset=3").await;
+    let body = body_json(response).await;
+    assert_eq!(body["total"], 8);
+    assert_eq!(names(&body), ["pkg03", "pkg04", "pkg05"]);
+
+    // Past the end
+    let response = send(&app, "GET", "/api/packages?limit=3&offset=20").await;
+    let body = body_json(response).await;
+    assert_eq!(body["total"], 8);
+    assert!(names(&body).is_empty());
+}
+
+#[tokio::test]
+async fn list_limit_is_capped() {
+    let app = setup_with_packages(8).await;
+
+    let response = send(&app, "GET", "/api/packages?limit=100").await;
+    let body = body_json(response).await;
+    assert_eq!(body["total"], 8);
+    assert_eq!(names(&body), ["pkg00", "pkg01", "pkg02", "pkg03", "pkg04"]);
+
+    // The default page size is capped too
+    let response = send(&app, "GET", "/api/packages").await;
+    assert_eq!(names(&body_json(response).await).len(), CAP);
+}
+
+#[tokio::test]
+async fn list_default_page_size() {
+    let (app, storage) = setup_test_app_with_config(test_config()).await;
+    for i in 0..DEFAULT_LIST_LIMIT + 5 {
+        seed_package(
+            &storage,
+            "sw1nn",
+            &format!("pkg{i:03}"),
+            "1.0.0-1",
+            "x86_64",
+        )
+        .await;
+    }
+
+    let response = send(&app, "GET", "/api/packages").await;
+    let body = body_json(response).await;
+    assert_eq!(body["total"], DEFAULT_LIST_LIMIT + 5);
+    assert_eq!(names(&body).len(), DEFAULT_LIST_LIMIT);
+}
+
+/// Packages spread over two repos and two arches
+async fn setup_repo_arch_matrix() -> axum::Router {
+    let (app, storage) = setup_test_app_with_config(test_config()).await;
+    for repo in ["sw1nn", "extra"] {
+        for arch in ["x86_64", "aarch64"] {
+            seed_package(&storage, repo, &format!("{repo}-{arch}"), "1.0.0-1", arch).await;
+        }
+    }
+    app
+}
+
+fn repo_arch_pairs(body: &serde_json::Value) -> Vec<(String, String)> {
+    let mut pairs: Vec<(String, String)> = body["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| {
+            (
+                p["repo"].as_str().unwrap().to_owned(),
+                p["arch"].as_str().unwrap().to_owned(),
+            )
+        })
+        .collect();
+    pairs.sort();
+    pairs
+}
+
+#[tokio::test]
+async fn list_repo_only_spans_all_arches() {
+    let app = setup_repo_arch_matrix().await;
+
+    let response = send(&app, "GET", "/api/packages?repo=extra").await;
+    assert_eq!(
+        repo_arch_pairs(&body_json(response).await),
+        vec![
+            ("extra".to_owned(), "aarch64".to_owned()),
+            ("extra".to_owned(), "x86_64".to_owned()),
+        ]
+    );
+}
+
+/// `any` packages are listed under every concrete arch, once each, while
+/// `arch=any` lists only them.
+#[tokio::test]
+async fn list_arch_includes_any_packages() {
+    let (app, storage) = setup_test_app_with_config(test_config()).await;
+    seed_package(&storage, "sw1nn", "font", "1.0.0-1", "any").await;
+    seed_package(&storage, "sw1nn", "tool", "1.0.0-1", "x86_64").await;
+    seed_package(&storage, "sw1nn", "armtool", "1.0.0-1", "aarch64").await;
+
+    let response = send(&app, "GET", "/api/packages?repo=sw1nn&arch=x86_64").await;
+    let body = body_json(response).await;
+    assert_eq!(body["total"], 2);
+    assert_eq!(names(&body), ["font", "tool"]);
+
+    let response = send(&app, "GET", "/api/packages?arch=any").await;
+    assert_eq!(names(&body_json(response).await), ["font"]);
+}
+
+/// Giving both repo and arch lists what it did before either became
+/// optional: that repo's packages for the arch, "any" ones included
+#[tokio::test]
+async fn list_repo_and_arch_keeps_any_packages() {
+    let (app, storage) = setup_test_app_with_config(test_config()).await;
+    for repo in ["sw1nn", "extra"] {
+        seed_package(&storage, repo, &format!("{repo}-any"), "1.0.0-1", "any").await;
+        for arch in ["x86_64", "aarch64"] {
+            seed_package(&storage, repo, &format!("{repo}-{arch}"), "1.0.0-1", arch).await;
+        }
+    }
+
+    let response = send(&app, "GET", "/api/packages?repo=sw1nn&arch=aarch64").await;
+    let body = body_json(response).await;
+    assert_eq!(names(&body), ["sw1nn-aarch64", "sw1nn-any"]);
+    assert_eq!(
+        repo_arch_pairs(&body),
+        vec![
+            ("sw1nn".to_owned(), "aarch64".to_owned()),
+            ("sw1nn".to_owned(), "any".to_owned()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn list_arch_only_spans_all_repos() {
+    let app = setup_repo_arch_matrix().await;
+
+    let response = send(&app, "GET", "/api/packages?arch=aarch64").await;
+    assert_eq!(
+        repo_arch_pairs(&body_json(response).await),
+        vec![
+            ("extra".to_owned(), "aarch64".to_owned()),
+            ("sw1nn".to_owned(), "aarch64".to_owned()),
+        ]
+    );
+}
+
+async fn upload_licensed(app: &axum::Router, name: &str, licenses: &[&str]) {
+    let mut pkginfo = format!("pkgname = {name}\npkgver = 1.0.0-1\narch = x86_64\n");
+    for license in licenses {
+        pkginfo.push_str(&format!("license = {license}\n"));
+    }
+    let data = compress_tar(&[(".PKGINFO", pkginfo.as_bytes())]);
+    let (status, _) =
+        upload_package(app, &format!("{name}-1.0.0-1-x86_64.pkg.tar.zst"), &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+async fn names_with_license(app: &axum::Router, license: &str) -> Vec<String> {
+    let response = send(app, "GET", &format!("/api/packages?license={license}")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    names(&body_json(response).await)
+        .into_iter()
+        .map(str::to_owned)
+        .collect()
+}
+
+#[tokio::test]
+async fn list_filters_by_license() {
+    let app = setup_test_app().await;
+    upload_licensed(&app, "mitpkg", &["MIT"]).await;
+    upload_licensed(&app, "gplpkg", &["GPL-3.0-or-later"]).await;
+    upload_licensed(&app, "dualpkg", &["MIT", "Apache-2.0"]).await;
+
+    assert_eq!(names_with_license(&app, "MIT").await, ["dualpkg", "mitpkg"]);
+    assert_eq!(
+        names_with_license(&app, "gpl-3.0-or-later").await,
+        ["gplpkg"]
+    );
+    assert!(names_with_license(&app, "BSD-3-Clause").await.is_empty());
+
+    // Composes with the other filters
+    let response = send(&app, "GET", "/api/packages?license=MIT&name=dual").await;
+    let body = &body_json(response).await["items"];
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_eq!(body[0]["license"], serde_json::json!(["MIT", "Apache-2.0"]));
+}
+
+#[tokio::test]
+async fn license_backfill_covers_packages_stored_without_license() {
+    let (app, storage) = setup_test_app_with_config(test_config()).await;
+
+    // Stored before licenses were recorded: only the .PKGINFO has it
+    let pkginfo = "pkgname = oldpkg\npkgver = 1.0.0-1\narch = x86_64\nlicense = MIT\n";
+    let data = compress_tar(&[(".PKGINFO", pkginfo.as_bytes())]);
+    let package = Package {
+        name: "oldpkg".to_owned(),
+        version: "1.0.0-1".to_owned(),
+        arch: "x86_64".to_owned(),
+        repo: "sw1nn".to_owned(),
+        filename: "oldpkg-1.0.0-1-x86_64.pkg.tar.zst".to_owned(),
+        sha256: String::new(),
+        size: data.len() as u64,
+        created_at: chrono::Utc::now(),
+        signed: false,
+        signature_verified: false,
+        license: Vec::new(),
+    };
+    storage.store_package(&package, &data).await.unwrap();
+    seed_package(&storage, "sw1nn", "nolicense", "1.0.0-1", "x86_64").await;
+
+    // Listing reads metadata only, and never writes it
+    assert!(names_with_license(&app, "MIT").await.is_empty());
+
+    // The startup backfill records it from the .PKGINFO
+    assert_eq!(backfill_licenses(storage.as_ref(), false).await.unwrap(), 1);
+    assert_eq!(names_with_license(&app, "MIT").await, ["oldpkg"]);
+    let stored = storage
+        .load_package("sw1nn", "oldpkg-1.0.0-1-x86_64")
+        .await
+        .unwrap();
+    assert_eq!(stored.license, ["MIT"]);
+
+    // Nothing left to do on the next start
+    assert_eq!(backfill_licenses(storage.as_ref(), false).await.unwrap(), 0);
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{body_json, create_test_package, send, setup_test_app, upload_package};
+use sha2::Digest;
+
+async fn get_manifest(app: &axum::Router) -> serde_json::Value {
+    let response = send(app, "GET", "/api/repos/sw1nn/os/x86_64/manifest").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    body_json(response).await
+}
+
+#[tokio::test]
+async fn manifest_checksum_is_stable_and_tracks_packages() {
+    let app = setup_test_app().await;
+
+    let data = create_test_package("zeta", "1.0.0-1", "x86_64");
+    let (status, _) = upload_package(&app, "zeta-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let first = get_manifest(&app).await;
+
+    let response = send(&app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    assert_eq!(get_manifest(&app).await, first);
+
+    let data = create_test_package("alpha", "1.0.0-1", "any");
+    let (status, _) = upload_package(&app, "alpha-1.0.0-1-any.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let second = get_manifest(&app).await;
+    assert_ne!(second["checksum"], first["checksum"]);
+
+    // Entries are sorted by filename and the checksum covers exactly them
+    let entries = second["entries"].as_array().unwrap();
+    let filenames: Vec<_> = entries
+        .iter()
+        .map(|e| e["filename"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        filenames,
+        [
+            "alpha-1.0.0-1-any.pkg.tar.zst",
+            "zeta-1.0.0-1-x86_64.pkg.tar.zst"
+        ]
+    );
+    let lines: String = entries
+        .iter()
+        .map(|e| {
+            format!(
+                "{} {} {}\n",
+                e["filename"].as_str().unwrap(),
+                e["sha256"].as_str().unwrap(),
+                e["size"]
+            )
+        })
+        .collect();
+    let expected = format!("{:x}", sha2::Sha256::digest(lines.as_bytes()));
+    assert_eq!(second["checksum"], expected);
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{create_test_package, send, setup_test_app, upload_package};
+use metrics_exporter_prometheus::PrometheusHandle;
+use std::sync::OnceLock;
+
+/// The Prometheus recorder is process-global, so it's installed once
+fn metrics_handle() -> &'static PrometheusHandle {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+    HANDLE.get_or_init(sw1nn_pkg_repo::metrics::install_recorder)
+}
+
+/// Value of the series `name{labels}` in the rendered metrics, 0 if absent
+fn counter_value(series: &str) -> u64 {
+    metrics_handle()
+        .render()
+        .lines()
+        .find_map(|line| line.strip_prefix(series)?.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+#[tokio::test]
+async fn upload_download_and_failure_are_counted() {
+    metrics_handle();
+    let app = setup_test_app().await;
+    let uploaded = r#"sw1nn_pkg_repo_uploads_completed_total{repo="sw1nn"}"#;
+    let failed = r#"sw1nn_pkg_repo_uploads_failed_total{repo="sw1nn"}"#;
+    let served = r#"sw1nn_pkg_repo_bytes_served_total{repo="sw1nn"}"#;
+    let (uploaded_before, failed_before, served_before) = (
+        counter_value(uploaded),
+        counter_value(failed),
+        counter_value(served),
+    );
+
+    let data = create_test_package("metered", "1.0.0-1", "x86_64");
+    let (status, _) = upload_package(&app, "metered-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(counter_value(uploaded), uploaded_before + 1);
+
+    let response = send(
+        &app,
+        "GET",
+        "/sw1nn/os/x86_64/metered-1.0.0-1-x86_64.pkg.tar.zst",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(counter_value(served), served_before + data.len() as u64);
+
+    let (status, _) = upload_package(&app, "broken-1.0.0-1-x86_64.pkg.tar.zst", b"garbage").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(counter_value(failed), failed_before + 1);
+}
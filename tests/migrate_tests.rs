@@ -0,0 +1,47 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{body_json, create_test_package, send, setup_test_app_with_config, test_config};
+use sw1nn_pkg_repo::storage::{FsStore, PackageStore};
+
+#[tokio::test]
+async fn legacy_layout_packages_are_migrated_and_listable() {
+    let config = test_config();
+    let data_path = config.storage.data_path.clone();
+
+    // data/{repo}/{arch}/ without the os/ segment or packages/ dir
+    let legacy_dir = data_path.join("sw1nn").join("x86_64");
+    std::fs::create_dir_all(&legacy_dir).unwrap();
+    let filename = "oldie-1.0.0-1-x86_64.pkg.tar.zst";
+    std::fs::write(
+        legacy_dir.join(filename),
+        create_test_package("oldie", "1.0.0-1", "x86_64"),
+    )
+    .unwrap();
+    std::fs::write(legacy_dir.join(format!("{filename}.sig")), b"signature").unwrap();
+
+    let storage = FsStore::from_config(&config.storage);
+    assert_eq!(storage.migrate_legacy_layout().await.unwrap(), 1);
+
+    let package_path = storage.package_path("sw1nn", filename).unwrap();
+    assert!(package_path.exists());
+    assert!(package_path.with_extension("zst.sig").exists());
+    assert!(!legacy_dir.exists());
+
+    let package = storage
+        .load_package("sw1nn", "oldie-1.0.0-1-x86_64")
+        .await
+        .unwrap();
+    assert_eq!(package.version, "1.0.0-1");
+    assert!(package.signed);
+
+    // Nothing left to do on the next start
+    assert_eq!(storage.migrate_legacy_layout().await.unwrap(), 0);
+
+    let (app, _storage) = setup_test_app_with_config(config).await;
+    let response = send(&app, "GET", "/api/packages?name=oldie").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let packages = &body_json(response).await["items"];
+    assert_eq!(packages.as_array().unwrap().len(), 1);
+    assert_eq!(packages[0]["filename"], filename);
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{
+    create_test_package, setup_test_app_with_config, setup_test_app_with_storage, test_config,
+    upload_package,
+};
+use sw1nn_pkg_repo::models::Package;
+
+#[tokio::test]
+async fn pool_link_created_on_store_and_removed_on_delete() {
+    let mut config = test_config();
+    config.storage.maintain_pool = true;
+    let data_path = config.storage.data_path.clone();
+    let (app, storage) = setup_test_app_with_config(config).await;
+
+    let data = create_test_package("poolpkg", "1.0.0-1", "x86_64");
+    let filename = "poolpkg-1.0.0-1-x86_64.pkg.tar.zst";
+    let (status, body) = upload_package(&app, filename, &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let package: Package = serde_json::from_value(body).unwrap();
+
+    let pool_path = data_path
+        .join(".pool")
+        .join(&package.sha256[..2])
+        .join(filename);
+    assert_eq!(
+        storage.pool_path(&package).unwrap(),
+        Some(pool_path.clone())
+    );
+    assert!(pool_path.symlink_metadata().unwrap().is_symlink());
+    // The link resolves to the stored package file
+    assert_eq!(std::fs::read(&pool_path).unwrap(), data);
+
+    storage.delete_package(&package).await.unwrap();
+    assert!(pool_path.symlink_metadata().is_err());
+}
+
+#[tokio::test]
+async fn pool_not_maintained_by_default() {
+    let (app, storage) = setup_test_app_with_storage().await;
+
+    let data = create_test_package("poolpkg", "1.0.0-1", "x86_64");
+    let (status, body) = upload_package(&app, "poolpkg-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let package: Package = serde_json::from_value(body).unwrap();
+
+    let pool_path = storage.pool_path(&package).unwrap().unwrap();
+    assert!(pool_path.symlink_metadata().is_err());
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use common::{body_json, seed_package, send, setup_test_app_with_storage};
+use serde_json::json;
+use tower::util::ServiceExt;
+
+#[tokio::test]
+async fn downloads_are_counted_per_package() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    let (_, popular) = seed_package(&storage, "sw1nn", "popular", "1.0.0-1", "x86_64").await;
+    let (_, other) = seed_package(&storage, "sw1nn", "other", "2.0.0-1", "x86_64").await;
+
+    for filename in [&popular, &popular, &other] {
+        let response = send(&app, "GET", &format!("/sw1nn/os/x86_64/{filename}")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let response = send(
+        &app,
+        "GET",
+        "/api/packages/stats/popular?repo=sw1nn&arch=x86_64",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        body_json(response).await,
+        json!([
+            {"name": "popular", "version": "1.0.0-1", "downloads": 2},
+            {"name": "other", "version": "2.0.0-1", "downloads": 1}
+        ])
+    );
+
+    let response = send(
+        &app,
+        "GET",
+        "/api/packages/stats/popular?repo=sw1nn&arch=x86_64&limit=1",
+    )
+    .await;
+    assert_eq!(body_json(response).await.as_array().unwrap().len(), 1);
+}
+
+/// Signatures, databases, HEAD and partial requests aren't downloads.
+#[tokio::test]
+async fn only_whole_package_downloads_are_counted() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    let (_, filename) = seed_package(&storage, "sw1nn", "quiet", "1.0.0-1", "x86_64").await;
+    std::fs::write(
+        storage
+            .package_path("sw1nn", &format!("{filename}.sig"))
+            .unwrap(),
+        b"sig",
+    )
+    .unwrap();
+
+    send(&app, "GET", &format!("/sw1nn/os/x86_64/{filename}.sig")).await;
+    send(&app, "GET", "/sw1nn/os/x86_64/sw1nn.db").await;
+    send(&app, "HEAD", &format!("/sw1nn/os/x86_64/{filename}")).await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/sw1nn/os/x86_64/{filename}"))
+                .header(header::RANGE, "bytes=0-3")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+
+    let response = send(
+        &app,
+        "GET",
+        "/api/packages/stats/popular?repo=sw1nn&arch=x86_64",
+    )
+    .await;
+    assert_eq!(body_json(response).await, json!([]));
+}
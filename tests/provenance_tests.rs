@@ -0,0 +1,106 @@
+mod common;
+
+use axum::http::{StatusCode, header};
+use common::{
+    body_bytes, create_test_package_with_entries, send, setup_test_app_with_config, test_config,
+    upload_package,
+};
+
+const BUILDINFO: &[u8] = b"format = 2\npkgname = provpkg\npkgver = 1.0.0-1\npkgarch = x86_64\n";
+const MTREE: &[u8] = b"\x1f\x8b\x08\x00fake-gzipped-mtree";
+
+/// With `extract_provenance` enabled, `.BUILDINFO` and `.MTREE` are stored as
+/// sidecars at upload and served next to the package.
+#[tokio::test]
+async fn provenance_sidecars_are_stored_and_served() {
+    let mut config = test_config();
+    config.storage.extract_provenance = true;
+    let (app, _storage) = setup_test_app_with_config(config).await;
+
+    let data = create_test_package_with_entries(
+        "provpkg",
+        "1.0.0-1",
+        "x86_64",
+        &[(".BUILDINFO", BUILDINFO), (".MTREE", MTREE)],
+    );
+    let filename = "provpkg-1.0.0-1-x86_64.pkg.tar.zst";
+    let (status, _) = upload_package(&app, filename, &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let response = send(
+        &app,
+        "GET",
+        &format!("/sw1nn/os/x86_64/{filename}.BUILDINFO"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/plain; charset=utf-8"
+    );
+    assert_eq!(body_bytes(response).await, BUILDINFO);
+
+    let response = send(&app, "GET", &format!("/sw1nn/os/x86_64/{filename}.MTREE")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/gzip");
+    assert_eq!(body_bytes(response).await, MTREE);
+}
+
+/// Without the flag, no sidecars are written.
+#[tokio::test]
+async fn provenance_sidecars_not_stored_by_default() {
+    let (app, _storage) = setup_test_app_with_config(test_config()).await;
+
+    let data = create_test_package_with_entries(
+        "provpkg",
+        "1.0.0-1",
+        "x86_64",
+        &[(".BUILDINFO", BUILDINFO)],
+    );
+    let filename = "provpkg-1.0.0-1-x86_64.pkg.tar.zst";
+    let (status, _) = upload_package(&app, filename, &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let response = send(
+        &app,
+        "GET",
+        &format!("/sw1nn/os/x86_64/{filename}.BUILDINFO"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// With `strict_provenance`, a `.BUILDINFO` claiming a different arch than
+/// `.PKGINFO` gets the upload rejected.
+#[tokio::test]
+async fn strict_provenance_rejects_mismatched_buildinfo() {
+    let mut config = test_config();
+    config.storage.extract_provenance = true;
+    config.storage.strict_provenance = true;
+    let (app, _storage) = setup_test_app_with_config(config).await;
+
+    let buildinfo = b"format = 2\npkgname = provpkg\npkgver = 1.0.0-1\npkgarch = aarch64\n";
+    let data = create_test_package_with_entries(
+        "provpkg",
+        "1.0.0-1",
+        "x86_64",
+        &[(".BUILDINFO", buildinfo)],
+    );
+    let filename = "provpkg-1.0.0-1-x86_64.pkg.tar.zst";
+    let (status, body) = upload_package(&app, filename, &data).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body["error"],
+        "Invalid package: BUILDINFO pkgarch 'aarch64' does not match package arch 'x86_64'"
+    );
+
+    // A consistent .BUILDINFO passes
+    let data = create_test_package_with_entries(
+        "provpkg",
+        "1.0.0-1",
+        "x86_64",
+        &[(".BUILDINFO", BUILDINFO)],
+    );
+    let (status, _) = upload_package(&app, filename, &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
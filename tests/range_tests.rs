@@ -97,3 +97,69 @@ async fn unsatisfiable_range_returns_416() {
 
     assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
 }
+
+/// A bounded `bytes=start-end` range returns exactly that inclusive slice.
+#[tokio::test]
+async fn bounded_range_returns_exact_slice() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    let (data, filename) = seed_package(&storage, "sw1nn", "rangepkg", "1.0.0-1", "x86_64").await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/sw1nn/os/x86_64/{filename}"))
+                .header(header::RANGE, "bytes=10-19")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        response
+            .headers()
+            .get(header::CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok()),
+        Some(format!("bytes 10-19/{}", data.len()).as_str())
+    );
+    assert_eq!(
+        response
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok()),
+        Some("10")
+    );
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(body.as_ref(), &data[10..20]);
+}
+
+/// A Range header that isn't a valid byte range is rejected with 416.
+#[tokio::test]
+async fn malformed_range_returns_416() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    let (_, filename) = seed_package(&storage, "sw1nn", "rangepkg", "1.0.0-1", "x86_64").await;
+
+    for range in ["bytes=abc-def", "bytes=20-10"] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/sw1nn/os/x86_64/{filename}"))
+                    .header(header::RANGE, range)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.status(),
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            "{range}"
+        );
+    }
+}
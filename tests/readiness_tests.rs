@@ -0,0 +1,84 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{
+    create_test_package, seed_package, send, setup_test_app_with_readiness, test_config,
+    upload_package,
+};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[tokio::test]
+async fn ready_reports_warm_up_state() {
+    let ready = Arc::new(AtomicBool::new(false));
+    let (app, _storage) = setup_test_app_with_readiness(test_config(), Arc::clone(&ready)).await;
+
+    let response = send(&app, "GET", "/api/ready").await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    ready.store(true, Ordering::Release);
+    let response = send(&app, "GET", "/api/ready").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn writes_rejected_until_ready() {
+    let mut config = test_config();
+    config.server.reject_writes_until_ready = true;
+    let ready = Arc::new(AtomicBool::new(false));
+    let (app, _storage) = setup_test_app_with_readiness(config, Arc::clone(&ready)).await;
+    let data = create_test_package("warmup", "1.0.0-1", "x86_64");
+
+    let (status, _) = upload_package(&app, "warmup-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+    // Reads are still served during warm-up
+    let response = send(&app, "GET", "/api/packages").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    ready.store(true, Ordering::Release);
+    let (status, _) = upload_package(&app, "warmup-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+/// Without `reject_writes_until_ready`, warm-up only affects `/api/ready`.
+#[tokio::test]
+async fn writes_allowed_during_warm_up_by_default() {
+    let ready = Arc::new(AtomicBool::new(false));
+    let (app, _storage) = setup_test_app_with_readiness(test_config(), ready).await;
+    let data = create_test_package("warmup", "1.0.0-1", "x86_64");
+
+    let (status, _) = upload_package(&app, "warmup-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+/// A repo whose db can't be rebuilt still lets the server become ready.
+#[tokio::test]
+async fn warm_up_finishes_when_a_rebuild_fails() {
+    use std::time::Duration;
+    use sw1nn_pkg_repo::db_actor::DbUpdateActor;
+    use sw1nn_pkg_repo::storage::{FsStore, PackageStore};
+
+    let config = test_config();
+    let storage: Arc<dyn PackageStore> = Arc::new(FsStore::from_config(&config.storage));
+    seed_package(&storage, "good", "okpkg", "1.0.0-1", "x86_64").await;
+    seed_package(&storage, "broken", "badpkg", "1.0.0-1", "x86_64").await;
+    // A file where the db directory should go fails its rebuild
+    std::fs::write(config.storage.data_path.join("broken").join("os"), b"").unwrap();
+
+    let (db_actor, db_update) =
+        DbUpdateActor::with_debounce(Arc::clone(&storage), Duration::from_millis(100));
+    tokio::spawn(db_actor.run());
+
+    let ready = Arc::new(AtomicBool::new(false));
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        sw1nn_pkg_repo::warm_up(Arc::clone(&storage), db_update.clone(), Arc::clone(&ready)),
+    )
+    .await
+    .expect("warm-up should finish despite the failed rebuild");
+
+    assert!(ready.load(Ordering::Acquire));
+    assert!(!db_update.generation("broken", "x86_64").is_current());
+    assert!(db_update.generation("good", "x86_64").is_current());
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use common::{body_json, create_test_package, send, send_json, setup_test_app};
+use tower::util::ServiceExt;
+
+#[tokio::test]
+async fn completed_upload_writes_retrievable_receipt() {
+    let app = setup_test_app().await;
+    let data = create_test_package("receipted", "1.0.0-1", "x86_64");
+
+    let init_body = serde_json::json!({
+        "filename": "receipted-1.0.0-1-x86_64.pkg.tar.zst",
+        "size": data.len(),
+        "chunk_size": data.len()
+    });
+    let (status, init) = send_json(&app, "POST", "/api/packages/upload/initiate", &init_body).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let upload_id = init["upload_id"].as_str().unwrap().to_owned();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/packages/upload/{upload_id}/chunks/1"))
+                .header("Content-Type", "application/octet-stream")
+                .body(Body::from(data.clone()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let chunk = body_json(response).await;
+
+    let complete_body = serde_json::json!({
+        "chunks": [{"chunk_number": 1, "checksum": chunk["checksum"]}]
+    });
+    let (status, package) = send_json(
+        &app,
+        "POST",
+        &format!("/api/packages/upload/{upload_id}/complete"),
+        &complete_body,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let response = send(&app, "GET", &format!("/api/admin/receipts/{upload_id}")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let receipt = body_json(response).await;
+    assert_eq!(receipt["upload_id"], upload_id);
+    assert_eq!(receipt["user"], "<anonymous>");
+    assert_eq!(receipt["filename"], "receipted-1.0.0-1-x86_64.pkg.tar.zst");
+    assert_eq!(receipt["file_size"], data.len());
+    assert_eq!(receipt["repo"], "sw1nn");
+    assert_eq!(receipt["package"]["name"], "receipted");
+    assert_eq!(receipt["package"]["sha256"], package["sha256"]);
+    assert!(receipt["completed_at"].is_string());
+
+    // The receipt outlives the package it describes
+    let response = send(
+        &app,
+        "DELETE",
+        "/api/packages/receipted-1.0.0-1-x86_64?repo=sw1nn",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = send(&app, "GET", &format!("/api/admin/receipts/{upload_id}")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn unknown_receipt_is_not_found() {
+    let app = setup_test_app().await;
+
+    let response = send(
+        &app,
+        "GET",
+        "/api/admin/receipts/00000000-0000-0000-0000-000000000000",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = send(&app, "GET", "/api/admin/receipts/not-a-uuid").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
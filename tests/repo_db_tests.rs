@@ -0,0 +1,655 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use common::{
+    body_bytes, body_json, create_test_package, create_test_package_with_entries, seed_package,
+    send, setup_test_app_with_config, setup_test_app_with_storage, test_config, upload_package,
+    wait_for_db_entries,
+};
+use tower::util::ServiceExt;
+
+/// When a name exists both as an `any` package and an arch-specific one, the
+/// arch db must carry a single entry: the higher version, whichever arch it is.
+#[tokio::test]
+async fn arch_db_keeps_highest_version_across_any_and_arch() {
+    let (app, storage) = setup_test_app_with_storage().await;
+
+    seed_package(&storage, "sw1nn", "mixed", "1.0.0-1", "any").await;
+    seed_package(&storage, "sw1nn", "mixed", "2.0.0-1", "x86_64").await;
+
+    let response = send(&app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    let entries = wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+    assert_eq!(entries, vec!["mixed-2.0.0-1"]);
+}
+
+/// The reverse case: a newer `any` build supersedes an older arch build.
+#[tokio::test]
+async fn arch_db_prefers_newer_any_package() {
+    let (app, storage) = setup_test_app_with_storage().await;
+
+    seed_package(&storage, "sw1nn", "mixed", "1.0.0-1", "x86_64").await;
+    seed_package(&storage, "sw1nn", "mixed", "2.0.0-1", "any").await;
+
+    let response = send(&app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    let entries = wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+    assert_eq!(entries, vec!["mixed-2.0.0-1"]);
+}
+
+/// The db is a gzip payload served as-is: pacman-compatible `Content-Type`,
+/// and no `Content-Encoding` even when the client advertises gzip support.
+#[tokio::test]
+async fn db_served_as_opaque_gzip() {
+    let (app, storage) = setup_test_app_with_storage().await;
+
+    seed_package(&storage, "sw1nn", "dbpkg", "1.0.0-1", "x86_64").await;
+    let response = send(&app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/sw1nn/os/x86_64/sw1nn.db")
+                .header(header::ACCEPT_ENCODING, "deflate, gzip")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/gzip");
+    assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+    assert_eq!(&body_bytes(response).await[..2], &[0x1f, 0x8b]);
+}
+
+/// A corrupt package is left out of the db instead of failing the rebuild.
+#[tokio::test]
+async fn corrupt_package_is_skipped_in_db() {
+    use sw1nn_pkg_repo::models::Package;
+
+    let (app, storage) = setup_test_app_with_storage().await;
+
+    seed_package(&storage, "sw1nn", "good", "1.0.0-1", "x86_64").await;
+    let corrupt = Package {
+        name: "corrupt".to_owned(),
+        version: "1.0.0-1".to_owned(),
+        arch: "x86_64".to_owned(),
+        repo: "sw1nn".to_owned(),
+        filename: "corrupt-1.0.0-1-x86_64.pkg.tar.zst".to_owned(),
+        sha256: String::new(),
+        size: 16,
+        created_at: chrono::Utc::now(),
+        signed: false,
+        signature_verified: false,
+        license: Vec::new(),
+    };
+    storage
+        .store_package(&corrupt, b"not a zstd stream")
+        .await
+        .unwrap();
+
+    let response = send(&app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    let entries = wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+    assert_eq!(entries, vec!["good-1.0.0-1"]);
+}
+
+/// Delete the only package in sw1nn/x86_64 and force a rebuild, returning the
+/// path of the `sw1nn.db` link once the rebuild has replaced the db.
+async fn delete_last_package_and_rebuild(remove_empty_db: bool) -> std::path::PathBuf {
+    let mut config = test_config();
+    config.storage.remove_empty_db = remove_empty_db;
+    let (app, storage) = setup_test_app_with_config(config).await;
+
+    seed_package(&storage, "sw1nn", "lonely", "1.0.0-1", "x86_64").await;
+    let response = send(&app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    assert_eq!(
+        wait_for_db_entries(&storage, "sw1nn", "x86_64").await,
+        vec!["lonely-1.0.0-1"]
+    );
+
+    let package = storage
+        .load_package("sw1nn", "lonely-1.0.0-1-x86_64")
+        .await
+        .unwrap();
+    storage.delete_package(&package).await.unwrap();
+
+    let db_dir = storage.db_dir("sw1nn", "x86_64").unwrap();
+    let db_link = db_dir.join("sw1nn.db");
+    let before = std::fs::metadata(&db_link).unwrap().modified().unwrap();
+
+    let response = send(&app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    for _ in 0..50 {
+        match std::fs::metadata(&db_link) {
+            Ok(meta) if meta.modified().unwrap() > before => break,
+            Err(_) => break,
+            _ => tokio::time::sleep(std::time::Duration::from_millis(100)).await,
+        }
+    }
+    db_link
+}
+
+/// Deleting the last package leaves a valid, empty db behind the usual link.
+#[tokio::test]
+async fn emptied_repo_gets_valid_empty_db() {
+    let db_link = delete_last_package_and_rebuild(false).await;
+
+    assert_eq!(
+        std::fs::read_link(&db_link).unwrap(),
+        std::path::Path::new("sw1nn.db.tar.gz")
+    );
+    let file = std::fs::File::open(&db_link).unwrap();
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    assert_eq!(archive.entries().unwrap().count(), 0);
+}
+
+/// With `remove_empty_db`, the db files are removed instead.
+#[tokio::test]
+async fn emptied_repo_db_removed_when_configured() {
+    let db_link = delete_last_package_and_rebuild(true).await;
+    let db_dir = db_link.parent().unwrap();
+
+    for name in [
+        "sw1nn.db",
+        "sw1nn.db.tar.gz",
+        "sw1nn.files",
+        "sw1nn.files.tar.gz",
+    ] {
+        assert!(
+            db_dir.join(name).symlink_metadata().is_err(),
+            "{name} should be removed"
+        );
+    }
+}
+
+/// With `async_db_update`, a completed upload is answered with 202 while the
+/// db update is queued, and db-status reports when the db has caught up.
+#[tokio::test]
+async fn async_db_update_reports_pending_until_applied() {
+    let mut config = test_config();
+    config.storage.async_db_update = true;
+    let (app, storage) = setup_test_app_with_config(config).await;
+
+    let data = create_test_package("queued", "1.0.0-1", "x86_64");
+    let (status, body) = upload_package(&app, "queued-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(body["name"], "queued");
+    assert_eq!(body["db_update_pending"], true);
+
+    let mut status = serde_json::Value::Null;
+    for _ in 0..50 {
+        let response = send(&app, "GET", "/api/repos/sw1nn/os/x86_64/db-status").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        status = body_json(response).await;
+        if status["current"] == true {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(status["current"], true, "{status}");
+    assert_eq!(status["requested_generation"], 1);
+    assert_eq!(status["applied_generation"], 1);
+
+    assert_eq!(
+        wait_for_db_entries(&storage, "sw1nn", "x86_64").await,
+        vec!["queued-1.0.0-1"]
+    );
+}
+
+/// Uploads arriving within the debounce window are folded into a single db
+/// regeneration.
+#[tokio::test]
+async fn rapid_uploads_share_one_regeneration() {
+    let (app, storage) = setup_test_app_with_storage().await;
+
+    for name in ["burst-a", "burst-b"] {
+        let data = create_test_package(name, "1.0.0-1", "x86_64");
+        let (status, _) =
+            upload_package(&app, &format!("{name}-1.0.0-1-x86_64.pkg.tar.zst"), &data).await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+
+    let mut status = serde_json::Value::Null;
+    for _ in 0..50 {
+        let response = send(&app, "GET", "/api/repos/sw1nn/os/x86_64/db-status").await;
+        status = body_json(response).await;
+        if status["current"] == true {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(status["current"], true, "{status}");
+    assert_eq!(status["requested_generation"], 2);
+    assert_eq!(status["regenerations"], 1, "{status}");
+
+    assert_eq!(
+        wait_for_db_entries(&storage, "sw1nn", "x86_64").await,
+        vec!["burst-a-1.0.0-1", "burst-b-1.0.0-1"]
+    );
+}
+
+/// A stored signature that isn't valid must not end up embedded in the db,
+/// where pacman would reject the package because of it.
+#[tokio::test]
+async fn invalid_signature_not_embedded_in_db() {
+    use std::io::Read;
+
+    let (app, storage) = setup_test_app_with_storage().await;
+    let (_, filename) = seed_package(&storage, "sw1nn", "sigpkg", "1.0.0-1", "x86_64").await;
+    let sig_path = storage
+        .package_path("sw1nn", &format!("{filename}.sig"))
+        .unwrap();
+    std::fs::write(&sig_path, b"not a signature").unwrap();
+
+    let response = send(&app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+
+    let db_path = storage.db_dir("sw1nn", "x86_64").unwrap().join("sw1nn.db");
+    let file = std::fs::File::open(db_path).unwrap();
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+    let mut desc = String::new();
+    entry.read_to_string(&mut desc).unwrap();
+
+    assert!(desc.contains("%NAME%\nsigpkg\n"), "{desc}");
+    assert!(!desc.contains("%PGPSIG%"), "{desc}");
+
+    // The detached signature is still served as-is
+    let response = send(&app, "GET", &format!("/sw1nn/os/x86_64/{filename}.sig")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+/// A signature verified on upload is embedded in the db, base64-encoded.
+#[tokio::test]
+async fn verified_signature_embedded_in_db() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    let data = create_test_package("sigpkg", "1.0.0-1", "x86_64");
+    let package = sw1nn_pkg_repo::models::Package {
+        name: "sigpkg".to_owned(),
+        version: "1.0.0-1".to_owned(),
+        arch: "x86_64".to_owned(),
+        repo: "sw1nn".to_owned(),
+        filename: "sigpkg-1.0.0-1-x86_64.pkg.tar.zst".to_owned(),
+        sha256: String::new(),
+        size: data.len() as u64,
+        created_at: chrono::Utc::now(),
+        signed: true,
+        signature_verified: true,
+        license: Vec::new(),
+    };
+    storage.store_package(&package, &data).await.unwrap();
+    let sig_path = storage
+        .package_path("sw1nn", &format!("{}.sig", package.filename))
+        .unwrap();
+    std::fs::write(&sig_path, b"signature").unwrap();
+
+    rebuild_and_wait(&app).await;
+
+    let desc = first_db_desc(&storage);
+    assert!(desc.contains("%PGPSIG%\nc2lnbmF0dXJl\n"), "{desc}");
+}
+
+/// With `generate_json_index`, db regeneration writes `index.json` next to the
+/// db listing the same packages, and `serve_file` serves it.
+#[tokio::test]
+async fn json_index_matches_db_packages() {
+    let mut config = test_config();
+    config.storage.generate_json_index = true;
+    let (app, storage) = setup_test_app_with_config(config).await;
+
+    seed_package(&storage, "sw1nn", "alpha", "1.0.0-1", "x86_64").await;
+    seed_package(&storage, "sw1nn", "alpha", "1.1.0-1", "x86_64").await;
+    seed_package(&storage, "sw1nn", "beta", "2.0.0-1", "any").await;
+
+    let response = send(&app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let db_entries = wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+
+    // The index is written after the dbs
+    let index_path = storage
+        .db_dir("sw1nn", "x86_64")
+        .unwrap()
+        .join("index.json");
+    for _ in 0..50 {
+        if index_path.exists() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+
+    let response = send(&app, "GET", "/sw1nn/os/x86_64/index.json").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+    let index = body_json(response).await;
+
+    let index_entries: Vec<String> = index
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| {
+            format!(
+                "{}-{}",
+                p["name"].as_str().unwrap(),
+                p["version"].as_str().unwrap()
+            )
+        })
+        .collect();
+    assert_eq!(index_entries, db_entries);
+    assert_eq!(index[0]["arch"], "x86_64");
+    assert_eq!(index[1]["arch"], "any");
+    assert!(index[0]["depends"].is_array());
+}
+
+/// Without the flag no index is written.
+#[tokio::test]
+async fn json_index_not_generated_by_default() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    seed_package(&storage, "sw1nn", "alpha", "1.0.0-1", "x86_64").await;
+
+    send(&app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+
+    let response = send(&app, "GET", "/sw1nn/os/x86_64/index.json").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// Simultaneous deletes on one repo/arch must leave a db listing exactly the
+/// remaining packages.
+#[tokio::test]
+async fn concurrent_deletes_leave_consistent_db() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    for i in 0..6 {
+        seed_package(&storage, "sw1nn", &format!("pkg{i}"), "1.0.0-1", "x86_64").await;
+    }
+
+    let deletes = (0..4).map(|i| {
+        let app = app.clone();
+        tokio::spawn(async move {
+            send(
+                &app,
+                "DELETE",
+                &format!("/api/packages/pkg{i}-1.0.0-1-x86_64?repo=sw1nn"),
+            )
+            .await
+        })
+    });
+    for delete in deletes.collect::<Vec<_>>() {
+        assert_eq!(delete.await.unwrap().status(), StatusCode::NO_CONTENT);
+    }
+
+    for _ in 0..50 {
+        let status =
+            body_json(send(&app, "GET", "/api/repos/sw1nn/os/x86_64/db-status").await).await;
+        if status["current"] == true {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+
+    let entries = wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+    assert_eq!(entries, vec!["pkg4-1.0.0-1", "pkg5-1.0.0-1"]);
+
+    // No temporary archives left behind
+    let db_dir = storage.db_dir("sw1nn", "x86_64").unwrap();
+    let leftovers: Vec<_> = std::fs::read_dir(db_dir)
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().ends_with(".tmp"))
+        .collect();
+    assert!(leftovers.is_empty(), "{leftovers:?}");
+}
+
+/// A `.PKGINFO` without `size` gets `%ISIZE%` computed from the files in the
+/// package, leaving out the package metadata files.
+#[tokio::test]
+async fn isize_computed_when_pkginfo_lacks_size() {
+    use std::io::Read;
+    use sw1nn_pkg_repo::models::Package;
+
+    let (app, storage) = setup_test_app_with_storage().await;
+
+    let data = create_test_package_with_entries(
+        "sizeless",
+        "1.0.0-1",
+        "x86_64",
+        &[
+            ("usr/bin/tool", &[0u8; 1000]),
+            ("usr/share/doc/sizeless/README", &[0u8; 234]),
+            (".BUILDINFO", b"pkgname = sizeless\n"),
+        ],
+    );
+    let package = Package {
+        name: "sizeless".to_owned(),
+        version: "1.0.0-1".to_owned(),
+        arch: "x86_64".to_owned(),
+        repo: "sw1nn".to_owned(),
+        filename: "sizeless-1.0.0-1-x86_64.pkg.tar.zst".to_owned(),
+        sha256: String::new(),
+        size: data.len() as u64,
+        created_at: chrono::Utc::now(),
+        signed: false,
+        signature_verified: false,
+        license: Vec::new(),
+    };
+    storage.store_package(&package, &data).await.unwrap();
+
+    send(&app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+
+    let db_path = storage.db_dir("sw1nn", "x86_64").unwrap().join("sw1nn.db");
+    let file = std::fs::File::open(db_path).unwrap();
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+    let mut desc = String::new();
+    entry.read_to_string(&mut desc).unwrap();
+
+    assert!(desc.contains("%ISIZE%\n1234\n"), "{desc}");
+}
+
+/// The files db lists every file a package installs, without its metadata
+/// files or directory entries.
+#[tokio::test]
+async fn files_db_lists_package_files() {
+    use std::io::Read;
+
+    let (app, storage) = setup_test_app_with_storage().await;
+
+    let data = create_test_package_with_entries(
+        "listed",
+        "1.0.0-1",
+        "x86_64",
+        &[
+            ("usr/bin/", b""),
+            ("usr/bin/listed", b"#!/bin/sh\n"),
+            ("usr/share/doc/listed/README", b"docs"),
+            ("etc/listed.conf", b"key = value\n"),
+            (".BUILDINFO", b"pkgname = listed\n"),
+            (".MTREE", b""),
+        ],
+    );
+    let (status, _) = upload_package(&app, "listed-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+    wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+
+    let files_path = storage
+        .db_dir("sw1nn", "x86_64")
+        .unwrap()
+        .join("sw1nn.files");
+    let file = std::fs::File::open(files_path).unwrap();
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+    assert_eq!(
+        entry.path().unwrap().display().to_string(),
+        "listed-1.0.0-1/files"
+    );
+    let mut content = String::new();
+    entry.read_to_string(&mut content).unwrap();
+
+    assert!(
+        content
+            .ends_with("%FILES%\netc/listed.conf\nusr/bin/listed\nusr/share/doc/listed/README\n\n"),
+        "{content}"
+    );
+}
+
+/// With the zstd db format, `{repo}.db` links to a zstd archive that is
+/// served as such.
+#[tokio::test]
+async fn zstd_db_is_generated_and_served() {
+    let mut config = test_config();
+    config.storage.db_compression_format = sw1nn_pkg_repo::config::DbCompressionFormat::Zstd;
+    let (app, storage) = setup_test_app_with_config(config).await;
+
+    let data = create_test_package("squeezed", "1.0.0-1", "x86_64");
+    let (status, _) = upload_package(&app, "squeezed-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let db_dir = storage.db_dir("sw1nn", "x86_64").unwrap();
+    for _ in 0..50 {
+        if db_dir.join("sw1nn.db").exists() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(
+        std::fs::read_link(db_dir.join("sw1nn.db")).unwrap(),
+        std::path::Path::new("sw1nn.db.tar.zst")
+    );
+    assert!(!db_dir.join("sw1nn.db.tar.gz").exists());
+
+    let response = send(&app, "GET", "/sw1nn/os/x86_64/sw1nn.db").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/zstd");
+    let bytes = body_bytes(response).await;
+    let mut archive = tar::Archive::new(zstd::Decoder::new(&bytes[..]).unwrap());
+    let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+    assert_eq!(
+        entry.path().unwrap().to_str().unwrap(),
+        "squeezed-1.0.0-1/desc"
+    );
+    let mut desc = String::new();
+    std::io::Read::read_to_string(&mut entry, &mut desc).unwrap();
+    assert!(desc.contains("%NAME%\nsqueezed\n"), "{desc}");
+}
+
+/// The rebuild endpoint regenerates a db whose files have gone missing and
+/// reports the queued rebuild; unknown or invalid repos are rejected.
+#[tokio::test]
+async fn rebuild_endpoint_recreates_missing_db() {
+    let (app, storage) = setup_test_app_with_storage().await;
+
+    seed_package(&storage, "sw1nn", "rebuilt", "1.0.0-1", "x86_64").await;
+    let response = send(&app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+
+    let db_dir = storage.db_dir("sw1nn", "x86_64").unwrap();
+    for entry in std::fs::read_dir(&db_dir).unwrap() {
+        std::fs::remove_file(entry.unwrap().path()).unwrap();
+    }
+
+    let response = send(&app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let body = body_json(response).await;
+    assert_eq!(body["repo"], "sw1nn");
+    assert_eq!(body["arch"], "x86_64");
+    assert_eq!(body["status"], "queued");
+
+    let entries = wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+    assert_eq!(entries, vec!["rebuilt-1.0.0-1"]);
+
+    let response = send(&app, "POST", "/api/repos/nosuchrepo/os/x86_64/rebuild").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let long_repo = "r".repeat(300);
+    let response = send(
+        &app,
+        "POST",
+        &format!("/api/repos/{long_repo}/os/x86_64/rebuild"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+/// Force a rebuild of sw1nn/x86_64 and wait until db-status reports it applied.
+async fn rebuild_and_wait(app: &axum::Router) {
+    let response = send(app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    let mut status = serde_json::Value::Null;
+    for _ in 0..50 {
+        status = body_json(send(app, "GET", "/api/repos/sw1nn/os/x86_64/db-status").await).await;
+        if status["current"] == true {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(status["current"], true, "{status}");
+}
+
+/// The `desc` of the first entry in the sw1nn/x86_64 db.
+fn first_db_desc(storage: &std::sync::Arc<dyn sw1nn_pkg_repo::storage::PackageStore>) -> String {
+    use std::io::Read;
+
+    let db_path = storage.db_dir("sw1nn", "x86_64").unwrap().join("sw1nn.db");
+    let file = std::fs::File::open(db_path).unwrap();
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+    let mut desc = String::new();
+    entry.read_to_string(&mut desc).unwrap();
+    desc
+}
+
+/// A rebuild takes an unchanged package's pkginfo from the cache instead of
+/// extracting it again, recreates a missing cache, and deleting the package
+/// removes its cache.
+#[tokio::test]
+async fn pkginfo_cache_reused_until_removed() {
+    let (app, storage) = setup_test_app_with_storage().await;
+
+    let data = create_test_package("cached", "1.0.0-1", "x86_64");
+    let (status, _) = upload_package(&app, "cached-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+    rebuild_and_wait(&app).await;
+
+    let cache_path = storage
+        .pkginfo_cache_path("sw1nn", "cached-1.0.0-1-x86_64")
+        .unwrap();
+    assert!(cache_path.exists());
+    assert!(!first_db_desc(&storage).contains("%DESC%"));
+
+    // Only a rebuild that reads the cache can see this description
+    let mut cached: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&cache_path).unwrap()).unwrap();
+    cached["pkginfo"]["pkgdesc"] = "from the cache".into();
+    std::fs::write(&cache_path, serde_json::to_vec(&cached).unwrap()).unwrap();
+
+    rebuild_and_wait(&app).await;
+    assert!(
+        first_db_desc(&storage).contains("%DESC%\nfrom the cache\n"),
+        "{}",
+        first_db_desc(&storage)
+    );
+
+    std::fs::remove_file(&cache_path).unwrap();
+    rebuild_and_wait(&app).await;
+    assert!(cache_path.exists());
+    let desc = first_db_desc(&storage);
+    assert!(desc.contains("%NAME%\ncached\n"), "{desc}");
+    assert!(!desc.contains("%DESC%"), "{desc}");
+
+    let response = send(&app, "DELETE", "/api/packages/cached/all").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!cache_path.exists());
+}
@@ -0,0 +1,273 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{
+    body_bytes, body_json, seed_package, send, setup_test_app_with_config,
+    setup_test_app_with_storage, test_config, wait_for_db_entries,
+};
+
+/// `any` packages live in flat storage, so they're downloadable through every
+/// concrete arch URL without being folded into that arch's db.
+#[tokio::test]
+async fn any_package_served_via_concrete_arch_url() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    let (data, filename) = seed_package(&storage, "sw1nn", "anypkg", "1.0.0-1", "any").await;
+
+    for arch in ["x86_64", "aarch64", "any"] {
+        let response = send(&app, "GET", &format!("/sw1nn/os/{arch}/{filename}")).await;
+        assert_eq!(response.status(), StatusCode::OK, "arch {arch}");
+        assert_eq!(body_bytes(response).await, data, "arch {arch}");
+    }
+}
+
+/// The fallback only applies to `any`: an arch-specific package isn't served
+/// under a different arch.
+#[tokio::test]
+async fn arch_package_not_served_via_other_arch_url() {
+    let (app, storage) = setup_test_app_with_storage().await;
+    let (_, filename) = seed_package(&storage, "sw1nn", "archpkg", "1.0.0-1", "x86_64").await;
+
+    let response = send(&app, "GET", &format!("/sw1nn/os/aarch64/{filename}")).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// Path validation still applies to the package lookup.
+#[tokio::test]
+async fn traversal_in_package_filename_rejected() {
+    let (app, _storage) = setup_test_app_with_storage().await;
+
+    let response = send(
+        &app,
+        "GET",
+        "/sw1nn/os/x86_64/..%2F..%2Fetc%2Fpasswd.pkg.tar.zst",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+/// An aliased arch resolves to the canonical arch's packages and db.
+#[tokio::test]
+async fn aliased_arch_served_from_canonical_arch() {
+    let mut config = test_config();
+    config
+        .storage
+        .arch_aliases
+        .insert("armv7h".to_string(), "armv7l".to_string());
+    let (app, storage) = setup_test_app_with_config(config).await;
+    let (data, filename) = seed_package(&storage, "sw1nn", "armpkg", "1.0.0-1", "armv7l").await;
+
+    let response = send(&app, "GET", &format!("/sw1nn/os/armv7h/{filename}")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_bytes(response).await, data);
+
+    let response = send(&app, "POST", "/api/repos/sw1nn/os/armv7h/rebuild").await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    assert_eq!(body_json(response).await["arch"], "armv7l");
+    wait_for_db_entries(&storage, "sw1nn", "armv7l").await;
+    assert!(!storage.db_dir("sw1nn", "armv7h").unwrap().exists());
+
+    let response = send(&app, "GET", "/api/repos/sw1nn/os/armv7h/db-status").await;
+    let status = body_json(response).await;
+    assert_eq!(status["arch"], "armv7l");
+    assert!(
+        status["requested_generation"].as_u64().unwrap() > 0,
+        "{status}"
+    );
+
+    let response = send(&app, "GET", "/sw1nn/os/armv7h/sw1nn.db").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = send(&app, "GET", "/api/packages?arch=armv7h").await;
+    let body = &body_json(response).await["items"];
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_eq!(body[0]["arch"], "armv7l");
+
+    // Aliases don't open a way around path validation
+    let response = send(
+        &app,
+        "GET",
+        "/sw1nn/os/armv7h/..%2F..%2Fetc%2Fpasswd.pkg.tar.zst",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+/// Package files are cached as immutable; dbs must be revalidated.
+#[tokio::test]
+async fn cache_control_differs_for_packages_and_dbs() {
+    use axum::http::header::CACHE_CONTROL;
+
+    let (app, storage) = setup_test_app_with_storage().await;
+    let (_, filename) = seed_package(&storage, "sw1nn", "cachepkg", "1.0.0-1", "x86_64").await;
+
+    let response = send(&app, "GET", &format!("/sw1nn/os/x86_64/{filename}")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[CACHE_CONTROL],
+        "public, max-age=31536000, immutable"
+    );
+
+    send(&app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+    let response = send(&app, "GET", "/sw1nn/os/x86_64/sw1nn.db").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CACHE_CONTROL], "no-cache");
+}
+
+/// Both TTLs are configurable.
+#[tokio::test]
+async fn cache_control_uses_configured_ttls() {
+    use axum::http::header::CACHE_CONTROL;
+
+    let mut config = test_config();
+    config.server.package_cache_max_age_secs = 3600;
+    config.server.db_cache_max_age_secs = 30;
+    let (app, storage) = setup_test_app_with_config(config).await;
+    let (_, filename) = seed_package(&storage, "sw1nn", "cachepkg", "1.0.0-1", "x86_64").await;
+
+    let response = send(&app, "GET", &format!("/sw1nn/os/x86_64/{filename}")).await;
+    assert_eq!(
+        response.headers()[CACHE_CONTROL],
+        "public, max-age=3600, immutable"
+    );
+
+    send(&app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+    let response = send(&app, "GET", "/sw1nn/os/x86_64/sw1nn.db").await;
+    assert_eq!(response.headers()[CACHE_CONTROL], "public, max-age=30");
+}
+
+/// Packages are streamed from disk; a multi-MiB package arrives intact with a
+/// `Content-Length` pacman can show progress against.
+#[tokio::test]
+async fn large_package_streamed_intact_with_content_length() {
+    use sw1nn_pkg_repo::models::Package;
+
+    let (app, storage) = setup_test_app_with_storage().await;
+
+    // Incompressible content so the package itself spans many read buffers
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let payload: Vec<u8> = (0..4 * 1024 * 1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect();
+    let data = common::create_test_package_with_entries(
+        "bigpkg",
+        "1.0.0-1",
+        "x86_64",
+        &[("usr/share/bigpkg/blob", &payload)],
+    );
+    let package = Package {
+        name: "bigpkg".to_owned(),
+        version: "1.0.0-1".to_owned(),
+        arch: "x86_64".to_owned(),
+        repo: "sw1nn".to_owned(),
+        filename: "bigpkg-1.0.0-1-x86_64.pkg.tar.zst".to_owned(),
+        sha256: String::new(),
+        size: data.len() as u64,
+        created_at: chrono::Utc::now(),
+        signed: false,
+        signature_verified: false,
+        license: Vec::new(),
+    };
+    storage.store_package(&package, &data).await.unwrap();
+
+    let response = send(
+        &app,
+        "GET",
+        "/sw1nn/os/x86_64/bigpkg-1.0.0-1-x86_64.pkg.tar.zst",
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response
+            .headers()
+            .get(axum::http::header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok()),
+        Some(data.len().to_string().as_str())
+    );
+    assert_eq!(
+        response
+            .headers()
+            .get(axum::http::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok()),
+        Some("application/zstd")
+    );
+    assert!(data.len() > 4 * 1024 * 1024);
+    assert_eq!(body_bytes(response).await, data);
+}
+
+async fn conditional_get(app: &axum::Router, uri: &str, etag: &str) -> axum::response::Response {
+    use tower::util::ServiceExt;
+
+    app.clone()
+        .oneshot(
+            axum::http::Request::builder()
+                .uri(uri)
+                .header(axum::http::header::IF_NONE_MATCH, etag)
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+/// A db fetched with its ETag in `If-None-Match` is answered 304 until the
+/// db changes.
+#[tokio::test]
+async fn db_etag_revalidates_with_304() {
+    use axum::http::header::ETAG;
+
+    let (app, storage) = setup_test_app_with_storage().await;
+    seed_package(&storage, "sw1nn", "etagpkg", "1.0.0-1", "x86_64").await;
+    send(&app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    wait_for_db_entries(&storage, "sw1nn", "x86_64").await;
+
+    let response = send(&app, "GET", "/sw1nn/os/x86_64/sw1nn.db").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let etag = response.headers()[ETAG].to_str().unwrap().to_owned();
+    assert!(etag.starts_with("W/\""), "{etag}");
+
+    let response = conditional_get(&app, "/sw1nn/os/x86_64/sw1nn.db", &etag).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()[ETAG], etag.as_str());
+    assert!(body_bytes(response).await.is_empty());
+
+    // A rewritten db no longer matches
+    seed_package(&storage, "sw1nn", "etagpkg2", "1.0.0-1", "x86_64").await;
+    send(&app, "POST", "/api/repos/sw1nn/os/x86_64/rebuild").await;
+    for _ in 0..50 {
+        if wait_for_db_entries(&storage, "sw1nn", "x86_64").await.len() == 2 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    let response = conditional_get(&app, "/sw1nn/os/x86_64/sw1nn.db", &etag).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(response.headers()[ETAG], etag.as_str());
+}
+
+/// Package files carry an ETag too, and a non-matching one is served in full.
+#[tokio::test]
+async fn package_etag_revalidates_with_304() {
+    use axum::http::header::ETAG;
+
+    let (app, storage) = setup_test_app_with_storage().await;
+    let (data, filename) = seed_package(&storage, "sw1nn", "etagpkg", "1.0.0-1", "x86_64").await;
+    let uri = format!("/sw1nn/os/x86_64/{filename}");
+
+    let response = send(&app, "GET", &uri).await;
+    let etag = response.headers()[ETAG].to_str().unwrap().to_owned();
+
+    let response = conditional_get(&app, &uri, &format!("\"other\", {etag}")).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    let response = conditional_get(&app, &uri, "W/\"other\"").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_bytes(response).await, data);
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{
+    body_json, create_test_package, send, setup_test_app, setup_test_app_with_config, test_config,
+    upload_package, upload_package_to_repo, upload_package_with_signature,
+};
+
+#[tokio::test]
+async fn signed_flag_reflects_uploaded_signature() {
+    let app = setup_test_app().await;
+
+    let data = create_test_package("signedpkg", "1.0.0-1", "x86_64");
+    let (status, package) = upload_package_with_signature(
+        &app,
+        "signedpkg-1.0.0-1-x86_64.pkg.tar.zst",
+        &data,
+        Some(b"fake signature"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(package["signed"], true);
+    assert_eq!(package["signature_verified"], false);
+
+    let data = create_test_package("plainpkg", "1.0.0-1", "x86_64");
+    let (status, package) =
+        upload_package(&app, "plainpkg-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(package["signed"], false);
+
+    // The flags are persisted and show up when listing
+    let response = send(&app, "GET", "/api/packages").await;
+    let packages = &body_json(response).await["items"];
+    let signed = |name: &str| {
+        packages
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|p| p["name"] == name)
+            .unwrap()["signed"]
+            .clone()
+    };
+    assert_eq!(signed("signedpkg"), true);
+    assert_eq!(signed("plainpkg"), false);
+}
+
+/// Config verifying signatures against a keyring holding no keys, so no
+/// signature can pass
+fn verifying_config() -> sw1nn_pkg_repo::config::Config {
+    let mut config = test_config();
+    let keyring = config.storage.data_path.join("trusted.gpg");
+    std::fs::write(&keyring, b"").unwrap();
+    config.storage.verify_signatures = true;
+    config.storage.trusted_keyring = Some(keyring);
+    config
+}
+
+fn pending_uploads(config: &sw1nn_pkg_repo::config::Config) -> usize {
+    std::fs::read_dir(config.storage.data_path.join(".uploads"))
+        .map(|entries| entries.count())
+        .unwrap_or(0)
+}
+
+#[tokio::test]
+async fn bad_signature_rejected_when_verifying() {
+    let config = verifying_config();
+    let (app, storage) = setup_test_app_with_config(config.clone()).await;
+
+    let data = create_test_package("badsig", "1.0.0-1", "x86_64");
+    let (status, body) = upload_package_with_signature(
+        &app,
+        "badsig-1.0.0-1-x86_64.pkg.tar.zst",
+        &data,
+        Some(b"fake signature"),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        body["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid signature"),
+        "{body}"
+    );
+    assert!(storage.list_packages("sw1nn").await.unwrap().is_empty());
+    // Session and assembled file are gone
+    assert_eq!(pending_uploads(&config), 0);
+}
+
+#[tokio::test]
+async fn unsigned_package_rejected_when_verifying() {
+    let config = verifying_config();
+    let (app, storage) = setup_test_app_with_config(config.clone()).await;
+
+    let data = create_test_package("nosig", "1.0.0-1", "x86_64");
+    let (status, body) = upload_package(&app, "nosig-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        body["error"].as_str().unwrap().contains("no signature"),
+        "{body}"
+    );
+    assert!(storage.list_packages("sw1nn").await.unwrap().is_empty());
+    assert_eq!(pending_uploads(&config), 0);
+}
+
+/// Config requiring signatures in the "strict" repo only, against a keyring
+/// holding no keys, while "lax" keeps the default of not verifying
+fn per_repo_config() -> sw1nn_pkg_repo::config::Config {
+    let mut config = test_config();
+    let keyring = config.storage.data_path.join("strict.gpg");
+    std::fs::write(&keyring, b"").unwrap();
+    config.storage.repos.insert(
+        "strict".to_string(),
+        sw1nn_pkg_repo::config::RepoPolicy {
+            require_signature: true,
+            keyring_path: Some(keyring),
+        },
+    );
+    config
+}
+
+#[tokio::test]
+async fn strict_repo_rejects_unsigned_package() {
+    let config = per_repo_config();
+    let (app, storage) = setup_test_app_with_config(config.clone()).await;
+
+    let data = create_test_package("strictpkg", "1.0.0-1", "x86_64");
+    let (status, body) = upload_package_to_repo(
+        &app,
+        Some("strict"),
+        "strictpkg-1.0.0-1-x86_64.pkg.tar.zst",
+        &data,
+        None,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        body["error"].as_str().unwrap().contains("no signature"),
+        "{body}"
+    );
+    assert!(storage.list_packages("strict").await.unwrap().is_empty());
+    assert_eq!(pending_uploads(&config), 0);
+}
+
+#[tokio::test]
+async fn lax_repo_accepts_unsigned_package() {
+    let (app, storage) = setup_test_app_with_config(per_repo_config()).await;
+
+    let data = create_test_package("laxpkg", "1.0.0-1", "x86_64");
+    let (status, package) = upload_package_to_repo(
+        &app,
+        Some("lax"),
+        "laxpkg-1.0.0-1-x86_64.pkg.tar.zst",
+        &data,
+        None,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::CREATED, "{package}");
+    assert_eq!(package["repo"], "lax");
+    assert_eq!(package["signed"], false);
+    assert_eq!(storage.list_packages("lax").await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn strict_repo_checks_signature_against_its_keyring() {
+    let (app, storage) = setup_test_app_with_config(per_repo_config()).await;
+
+    let data = create_test_package("strictsig", "1.0.0-1", "x86_64");
+    let (status, body) = upload_package_to_repo(
+        &app,
+        Some("strict"),
+        "strictsig-1.0.0-1-x86_64.pkg.tar.zst",
+        &data,
+        Some(b"fake signature"),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        body["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid signature"),
+        "{body}"
+    );
+    assert!(storage.list_packages("strict").await.unwrap().is_empty());
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use common::{
+    create_test_package, send, setup_test_app, setup_test_app_with_config, test_config,
+    upload_package,
+};
+use sw1nn_pkg_repo::signed_url::{sign, signed_path};
+
+const SECRET: &str = "0123456789abcdef0123456789abcdef";
+const PATH: &str = "/sw1nn/os/x86_64/private-1.0.0-1-x86_64.pkg.tar.zst";
+
+async fn setup_signed_app() -> axum::Router {
+    let mut config = test_config();
+    config.server.require_signed_downloads = true;
+    config.server.download_signing_secret = Some(SECRET.to_string());
+    let (app, _storage) = setup_test_app_with_config(config).await;
+
+    let data = create_test_package("private", "1.0.0-1", "x86_64");
+    let (status, _) = upload_package(&app, "private-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+    app
+}
+
+#[tokio::test]
+async fn valid_signed_url_is_served() {
+    let app = setup_signed_app().await;
+
+    let url = signed_path(SECRET, PATH, Utc::now() + Duration::minutes(5));
+    let response = send(&app, "GET", &url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn unsigned_request_is_forbidden() {
+    let app = setup_signed_app().await;
+
+    let response = send(&app, "GET", PATH).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn malformed_signature_query_is_forbidden() {
+    let app = setup_signed_app().await;
+
+    let response = send(&app, "GET", &format!("{PATH}?exp=soon&sig=zz")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn public_download_ignores_signature_query() {
+    let app = setup_test_app().await;
+    let data = create_test_package("private", "1.0.0-1", "x86_64");
+    let (status, _) = upload_package(&app, "private-1.0.0-1-x86_64.pkg.tar.zst", &data).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // A malformed exp= must not turn a public download into a 400
+    let response = send(&app, "GET", &format!("{PATH}?exp=soon&sig=zz")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn expired_signed_url_is_forbidden() {
+    let app = setup_signed_app().await;
+
+    let url = signed_path(SECRET, PATH, Utc::now() - Duration::seconds(1));
+    let response = send(&app, "GET", &url).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn tampered_signed_url_is_forbidden() {
+    let app = setup_signed_app().await;
+    let exp = (Utc::now() + Duration::minutes(5)).timestamp();
+    let sig = sign(SECRET, PATH, exp);
+
+    // Extending the expiry invalidates the signature
+    let url = format!("{PATH}?exp={}&sig={sig}", exp + 3600);
+    let response = send(&app, "GET", &url).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // As does reusing it for another file
+    let url = format!("/sw1nn/os/x86_64/sw1nn.db?exp={exp}&sig={sig}");
+    let response = send(&app, "GET", &url).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}